@@ -117,6 +117,16 @@ export namespace Compiler {
   export type Output = {
     dot: string
   };
+  export type AnnotatedEdge = {
+    from: string,
+    action: string,
+    to: string
+  };
+  export type Annotation = {
+    dot: string,
+    unmatched: Compiler.AnnotatedEdge[],
+    missing: Compiler.AnnotatedEdge[]
+  };
 }
 export namespace GCL {
   export type Determinism =
@@ -129,10 +139,19 @@ export namespace GCL {
   };
   export type TargetKind =
     | "Variable"
-    | "Array";
-  export const TARGET_KIND: TargetKind[] = ["Variable", "Array"];
+    | "Array"
+    | "BoolVariable"
+    | "BoolArray";
+  export const TARGET_KIND: TargetKind[] = ["Variable", "Array", "BoolVariable", "BoolArray"];
   export type Variable = string;
   export type Array = string;
+  export type WorklistKind =
+    | "FiFo"
+    | "LiFo"
+    | "RoundRobin"
+    | "ReversePostOrder"
+    | "Scc";
+  export const WORKLIST_KIND: WorklistKind[] = ["FiFo", "LiFo", "RoundRobin", "ReversePostOrder", "Scc"];
 }
 export namespace Interpreter {
   export type Input = {
@@ -146,17 +165,32 @@ export namespace Interpreter {
     final_node: string,
     dot: string,
     trace: Interpreter.Step[],
-    termination: Interpreter.TerminationState
+    termination: Interpreter.TerminationState,
+    assertion: (Interpreter.FailedAssertion | null)
   };
   export type InterpreterMemory = {
     variables: Record<GCL.Variable, number>,
-    arrays: Record<GCL.Array, number[]>
+    arrays: Record<GCL.Array, number[]>,
+    bools?: Record<GCL.Variable, boolean>,
+    bool_arrays?: Record<GCL.Array, boolean[]>
   };
   export type TerminationState =
     | "Running"
     | "Stuck"
-    | "Terminated";
-  export const TERMINATION_STATE: TerminationState[] = ["Running", "Stuck", "Terminated"];
+    | "Terminated"
+    | "AssertionFailed";
+  export const TERMINATION_STATE: TerminationState[] = ["Running", "Stuck", "Terminated", "AssertionFailed"];
+  export type FailedAssertion = {
+    step: number,
+    command: string,
+    location: ([number, number] | null),
+    failure: Interpreter.Failure,
+    message: string
+  };
+  export type Failure =
+    | "Assertion"
+    | "CallDepthExceeded";
+  export const FAILURE: Failure[] = ["Assertion", "CallDepthExceeded"];
   export type Step = {
     action: string,
     node: string,
@@ -222,11 +256,14 @@ export namespace SignAnalysis {
     initial_node: string,
     final_node: string,
     nodes: Record<string, SignAnalysis.SignMemory[]>,
-    dot: string
+    dot: string,
+    semantic_calls: Record<GCL.WorklistKind, number>
   };
   export type SignMemory = {
     variables: Record<GCL.Variable, SignAnalysis.Sign>,
-    arrays: Record<GCL.Array, SignAnalysis.Sign[]>
+    arrays: Record<GCL.Array, SignAnalysis.Sign[]>,
+    bools?: Record<GCL.Variable, boolean>,
+    bool_arrays?: Record<GCL.Array, boolean[]>
   };
   export type Sign =
     | "Positive"
@@ -234,6 +271,107 @@ export namespace SignAnalysis {
     | "Negative";
   export const SIGN: Sign[] = ["Positive", "Zero", "Negative"];
 }
+export namespace IntervalAnalysis {
+  export type Input = {
+    commands: string,
+    determinism: GCL.Determinism,
+    assignment: IntervalAnalysis.IntervalMemory
+  };
+  export type Output = {
+    initial_node: string,
+    final_node: string,
+    nodes: Record<string, IntervalAnalysis.IntervalMemory | null>,
+    dot: string,
+    semantic_calls: Record<GCL.WorklistKind, number>
+  };
+  export type IntervalMemory = {
+    variables: Record<GCL.Variable, IntervalAnalysis.Interval>,
+    arrays: Record<GCL.Array, IntervalAnalysis.Interval>,
+    bools?: Record<GCL.Variable, boolean[]>,
+    bool_arrays?: Record<GCL.Array, boolean[]>
+  };
+  export type Interval = {
+    lower: number | null,
+    upper: number | null
+  };
+}
+export namespace Dataflow {
+  export type Input = {
+    commands: string,
+    determinism: GCL.Determinism
+  };
+}
+export namespace ReachingDefinitions {
+  export type Output = {
+    initial_node: string,
+    final_node: string,
+    nodes: Record<string, ReachingDefinitions.Definition[]>,
+    dot: string
+  };
+  export type Definition = {
+    target: string,
+    from: string | null,
+    to: string
+  };
+}
+export namespace LiveVariables {
+  export type Output = {
+    initial_node: string,
+    final_node: string,
+    nodes: Record<string, string[]>,
+    dot: string
+  };
+}
+export namespace AvailableExpressions {
+  export type Output = {
+    initial_node: string,
+    final_node: string,
+    nodes: Record<string, string[]>,
+    dot: string
+  };
+}
+export namespace VeryBusyExpressions {
+  export type Output = {
+    initial_node: string,
+    final_node: string,
+    nodes: Record<string, string[]>,
+    dot: string
+  };
+}
+export namespace Optimizer {
+  export type Input = {
+    commands: string
+  };
+  export type Edge = {
+    from: string,
+    action: string,
+    to: string
+  };
+  export type Output = {
+    edges: Optimizer.Edge[],
+    dot: string
+  };
+}
+export namespace Reachability {
+  export type Input = {
+    commands: string,
+    determinism: GCL.Determinism,
+    assignment: Interpreter.InterpreterMemory
+  };
+  export type Configuration = {
+    node: string,
+    memory: Interpreter.InterpreterMemory
+  };
+  export type Output = {
+    complete: boolean,
+    configurations: number,
+    terminated: Reachability.Configuration[],
+    stuck: Reachability.Configuration[],
+    failed: Reachability.Configuration[],
+    may_not_terminate: boolean,
+    dot: string
+  };
+}
 export namespace ce_core {
   export type ValidationResult =
     | { "type": "Correct" }
@@ -245,12 +383,19 @@ export namespace ce_shell {
   export type Envs =
     | { "analysis": "Calculator", "io": { input: Calculator.Input, output: Calculator.Output, meta: void, annotation: void } }
     | { "analysis": "Parser", "io": { input: Parser.Input, output: Parser.Output, meta: void, annotation: void } }
-    | { "analysis": "Compiler", "io": { input: Compiler.Input, output: Compiler.Output, meta: void, annotation: void } }
+    | { "analysis": "Compiler", "io": { input: Compiler.Input, output: Compiler.Output, meta: void, annotation: Compiler.Annotation } }
     | { "analysis": "Interpreter", "io": { input: Interpreter.Input, output: Interpreter.Output, meta: GCL.TargetDef[], annotation: void } }
     | { "analysis": "BiGCL", "io": { input: BiGCL.Input, output: BiGCL.Output, meta: void, annotation: void } }
     | { "analysis": "RiscV", "io": { input: RiscV.Input, output: RiscV.Output, meta: void, annotation: RiscV.Annotation } }
     | { "analysis": "Security", "io": { input: SecurityAnalysis.Input, output: SecurityAnalysis.Output, meta: SecurityAnalysis.Meta, annotation: void } }
-    | { "analysis": "Sign", "io": { input: SignAnalysis.Input, output: SignAnalysis.Output, meta: GCL.TargetDef[], annotation: void } };
+    | { "analysis": "Sign", "io": { input: SignAnalysis.Input, output: SignAnalysis.Output, meta: GCL.TargetDef[], annotation: void } }
+    | { "analysis": "Interval", "io": { input: IntervalAnalysis.Input, output: IntervalAnalysis.Output, meta: GCL.TargetDef[], annotation: void } }
+    | { "analysis": "ReachingDefinitions", "io": { input: Dataflow.Input, output: ReachingDefinitions.Output, meta: void, annotation: void } }
+    | { "analysis": "LiveVariables", "io": { input: Dataflow.Input, output: LiveVariables.Output, meta: void, annotation: void } }
+    | { "analysis": "AvailableExpressions", "io": { input: Dataflow.Input, output: AvailableExpressions.Output, meta: void, annotation: void } }
+    | { "analysis": "VeryBusyExpressions", "io": { input: Dataflow.Input, output: VeryBusyExpressions.Output, meta: void, annotation: void } }
+    | { "analysis": "Optimizer", "io": { input: Optimizer.Input, output: Optimizer.Output, meta: void, annotation: void } }
+    | { "analysis": "Reachability", "io": { input: Reachability.Input, output: Reachability.Output, meta: GCL.TargetDef[], annotation: void } };
   export type Analysis =
     | "Calculator"
     | "Parser"
@@ -259,8 +404,15 @@ export namespace ce_shell {
     | "BiGCL"
     | "RiscV"
     | "Security"
-    | "Sign";
-  export const ANALYSIS: Analysis[] = ["Calculator", "Parser", "Compiler", "Interpreter", "BiGCL", "RiscV", "Security", "Sign"];
+    | "Sign"
+    | "Interval"
+    | "ReachingDefinitions"
+    | "LiveVariables"
+    | "AvailableExpressions"
+    | "VeryBusyExpressions"
+    | "Optimizer"
+    | "Reachability";
+  export const ANALYSIS: Analysis[] = ["Calculator", "Parser", "Compiler", "Interpreter", "BiGCL", "RiscV", "Security", "Sign", "Interval", "ReachingDefinitions", "LiveVariables", "AvailableExpressions", "VeryBusyExpressions", "Optimizer", "Reachability"];
   export namespace io {
     export type Input = {
       analysis: ce_shell.Analysis,
@@ -390,6 +542,11 @@ export namespace inspectify {
     export type AnalysisExecution = {
       id: driver.job.JobId
     };
+    export type Minimization = {
+      input: ce_shell.io.Input,
+      validation: ce_core.ValidationResult,
+      runs: number
+    };
     export type CompilationStatus = {
       id: (driver.job.JobId | null),
       state: driver.job.JobState,
@@ -421,6 +578,54 @@ export namespace inspectify {
       validation: (ce_core.ValidationResult | null),
       annotation: (ce_shell.io.Annotation | null)
     };
+    export type DebugStart = {
+      commands: string,
+      determinism: GCL.Determinism,
+      assignment: Interpreter.InterpreterMemory
+    };
+    export type DebugResponse = {
+      state: (inspectify.endpoints.DebugState | null),
+      error: (string | null)
+    };
+    export type DebugState = {
+      session: string,
+      node: string,
+      memory: Interpreter.InterpreterMemory,
+      trace: Interpreter.Step[],
+      termination: Interpreter.TerminationState,
+      choices: inspectify.endpoints.DebugChoice[],
+      watches: inspectify.endpoints.DebugWatch[],
+      breakpoint: (number | null),
+      stop: (string | null),
+      dot: string
+    };
+    export type DebugChoice = {
+      action: string,
+      node: string
+    };
+    export type DebugWatch = {
+      expression: string,
+      value: (string | null),
+      error: (string | null)
+    };
+    export type DebugCommandParams = {
+      session: string,
+      command: inspectify.endpoints.DebugCommand
+    };
+    export type DebugCommand =
+      | { "type": "Inspect" }
+      | { "type": "Step", "value": { choice: number } }
+      | { "type": "StepBack" }
+      | { "type": "Continue" }
+      | { "type": "ReverseContinue" }
+      | { "type": "RunTo", "value": { node: string } }
+      | { "type": "Restart" }
+      | { "type": "SetBreakpoints", "value": { breakpoints: inspectify.endpoints.DebugBreakpoint[] } }
+      | { "type": "SetWatches", "value": { watches: string[] } };
+    export type DebugBreakpoint = {
+      node: (string | null),
+      condition: (string | null)
+    };
   }
 }
 export const api = {
@@ -431,4 +636,8 @@ export const api = {
     jobsCancel: request<driver.job.JobId, void>("json", "POST", "/jobs/cancel", "none"),
     analysis: request<ce_shell.io.Input, (inspectify.endpoints.AnalysisExecution | null)>("json", "POST", "/analysis", "json"),
     reference: request<ce_shell.io.Input, inspectify.endpoints.ReferenceExecution>("json", "POST", "/reference", "json"),
+    minimize: request<ce_shell.io.Input, (inspectify.endpoints.Minimization | null)>("json", "POST", "/minimize", "json"),
+    debugStart: request<inspectify.endpoints.DebugStart, inspectify.endpoints.DebugResponse>("json", "POST", "/debug/start", "json"),
+    debugCommand: request<inspectify.endpoints.DebugCommandParams, inspectify.endpoints.DebugResponse>("json", "POST", "/debug/command", "json"),
+    debugStop: request<string, void>("json", "POST", "/debug/stop", "none"),
 };
@@ -49,7 +49,7 @@ monaco.languages.setMonarchTokensProvider(GCL_LANGUAGE_ID, {
     { token: 'delimiter.angle', open: '<', close: '>' },
   ],
 
-  keywords: ['if', 'fi', 'do', 'od'],
+  keywords: ['if', 'fi', 'do', 'od', 'skip', 'proc', 'in', 'out', 'min', 'max', 'len', 'count'],
   operators: [
     '-',
     ',',
@@ -0,0 +1,79 @@
+//! Coverage-guided generation of inputs.
+//!
+//! The coverage of an input is the behaviour of the reference implementation
+//! it exercises, written as features such as the kinds of actions executed or
+//! the combinations of signs found, as given by [`Env::coverage`]. Rather than
+//! taking the first input generated, a few candidates are generated and the
+//! one covering the most features not seen before is picked, such that a set
+//! of inputs tests more distinct cases.
+
+use std::{cmp::Reverse, collections::BTreeSet};
+
+use rand::Rng;
+
+use crate::{Env, Generate};
+
+/// The number of inputs generated to pick each coverage-guided input from.
+pub const CANDIDATES: usize = 8;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Coverage {
+    features: BTreeSet<String>,
+}
+
+impl Coverage {
+    /// Adds `feature`, returning whether it was not covered already.
+    pub fn insert(&mut self, feature: impl Into<String>) -> bool {
+        self.features.insert(feature.into())
+    }
+    pub fn extend(&mut self, other: Coverage) {
+        self.features.extend(other.features);
+    }
+    pub fn len(&self) -> usize {
+        self.features.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty()
+    }
+    pub fn contains(&self, feature: &str) -> bool {
+        self.features.contains(feature)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.features.iter().map(|f| f.as_str())
+    }
+    /// The number of features covered which are not in `seen`.
+    pub fn unseen(&self, seen: &Coverage) -> usize {
+        self.features.difference(&seen.features).count()
+    }
+}
+
+impl<S: Into<String>> FromIterator<S> for Coverage {
+    fn from_iter<T: IntoIterator<Item = S>>(iter: T) -> Self {
+        Coverage {
+            features: iter.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// The coverage of the reference implementation on `input`, which is empty if
+/// it fails to run.
+pub fn reference_coverage<E: Env>(input: &E::Input) -> Coverage {
+    E::run(input)
+        .map(|output| E::coverage(input, &output))
+        .unwrap_or_default()
+}
+
+/// Generates [`CANDIDATES`] inputs and picks the first of those covering the
+/// most features not in `seen`, which its features are then added to.
+pub fn gn_guided<E: Env, R: Rng>(seen: &mut Coverage, rng: &mut R) -> E::Input {
+    let (input, coverage) = (0..CANDIDATES)
+        .map(|_| {
+            let input = E::Input::gn(&mut (), rng);
+            let coverage = reference_coverage::<E>(&input);
+            (input, coverage)
+        })
+        .min_by_key(|(_, coverage)| Reverse(coverage.unseen(seen)))
+        .expect("there is at least one candidate");
+    seen.extend(coverage);
+    input
+}
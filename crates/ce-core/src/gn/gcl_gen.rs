@@ -1,5 +1,8 @@
+use std::ops::RangeInclusive;
+
 use gcl::ast::{
-    AExpr, AOp, Array, BExpr, Command, Commands, Guard, LogicOp, RelOp, Target, Variable,
+    AExpr, AOp, Array, BExpr, Command, CommandKind, Commands, Guard, LogicOp, RelOp, Target,
+    Variable,
 };
 use rand::{Rng, seq::IndexedRandom};
 
@@ -12,6 +15,12 @@ pub struct Context {
     pub no_loops: bool,
     pub no_division: bool,
     pub no_unary_minus: bool,
+    /// Whether targets may be array elements, with arrays named by the
+    /// uppercase of [`names`](Self::names)
+    pub arrays: bool,
+    /// The lengths of arrays in generated initial memories, which indices are
+    /// biased to stay within
+    pub array_lengths: RangeInclusive<usize>,
     pub names: Vec<String>,
 }
 
@@ -26,6 +35,8 @@ impl Default for Context {
             no_loops: Default::default(),
             no_division: Default::default(),
             no_unary_minus: Default::default(),
+            arrays: Default::default(),
+            array_lengths: 5..=10,
             names: ["a", "b", "c", "d"].map(Into::into).to_vec(),
         }
     }
@@ -40,6 +51,8 @@ impl Context {
             no_loops: false,
             no_division: false,
             no_unary_minus: false,
+            arrays: false,
+            array_lengths: 5..=10,
             names: ["a", "b", "c", "d"].map(Into::into).to_vec(),
         }
     }
@@ -57,8 +70,29 @@ impl Context {
         self
     }
 
+    /// A length for an array in an initial memory.
+    pub fn array_length<R: Rng>(&self, rng: &mut R) -> usize {
+        rng.random_range(self.array_lengths.clone())
+    }
+
     fn use_array(&self) -> bool {
-        false
+        self.arrays
+    }
+
+    /// An index which is mostly a number within the shortest array length,
+    /// and otherwise any expression, which may be out of bounds.
+    fn index<R: Rng>(&mut self, rng: &mut R) -> AExpr {
+        let in_bounds = *self.array_lengths.start() as i32;
+        self.sample(
+            rng,
+            vec![
+                (
+                    if in_bounds == 0 { 0.0 } else { 0.7 },
+                    Box::new(move |_, rng| AExpr::number(rng.random_range(0..in_bounds))),
+                ),
+                (0.3, Box::new(|cx, rng| AExpr::gn(cx, rng))),
+            ],
+        )
     }
 
     fn reference<R: Rng>(&mut self, rng: &mut R) -> Target<Box<AExpr>> {
@@ -76,7 +110,7 @@ impl Context {
                     Box::new(|cx, rng| {
                         Target::Array(
                             Array(cx.names.choose(rng).cloned().unwrap().to_uppercase()),
-                            Box::new(AExpr::gn(cx, rng)),
+                            Box::new(cx.index(rng)),
                         )
                     }),
                 ),
@@ -146,18 +180,18 @@ impl Generate for Commands {
 //                     .variables
 //                     .iter()
 //                     .map(|(v, s)| {
-//                         let v = AExpr::Reference(v.clone().into());
+//                         let v = AExpr::reference(v.clone().into());
 //                         let op = match s {
 //                             Sign::Positive => RelOp::Gt,
 //                             Sign::Zero => RelOp::Eq,
 //                             Sign::Negative => RelOp::Lt,
 //                         };
-//                         BExpr::Rel(v, op, AExpr::Number(0))
+//                         BExpr::rel(v, op, AExpr::number(0))
 //                     })
 //                     .reduce(|a, b| BExpr::logic(a, LogicOp::And, b))
 //             })
 //             .reduce(|a, b| BExpr::logic(a, LogicOp::Or, b))
-//             .unwrap_or(BExpr::Bool(true))
+//             .unwrap_or(BExpr::bool(true))
 //     }
 // }
 
@@ -172,13 +206,16 @@ impl Generate for Command {
                 (
                     1.0,
                     Box::new(|cx, rng| {
-                        Command::Assignment(Target::gn(cx, rng), AExpr::gn(cx, rng))
+                        CommandKind::Assignment(Target::gn(cx, rng), AExpr::gn(cx, rng)).into()
                     }),
                 ),
-                (0.6, Box::new(|cx, rng| Command::If(cx.many(1, 10, rng)))),
+                (
+                    0.6,
+                    Box::new(|cx, rng| CommandKind::If(cx.many(1, 10, rng)).into()),
+                ),
                 (
                     if cx.no_loops { 0.0 } else { 0.3 },
-                    Box::new(|cx, rng| Command::Loop(cx.many(1, 10, rng))),
+                    Box::new(|cx, rng| CommandKind::Loop(cx.many(1, 10, rng)).into()),
                 ),
             ],
         )
@@ -199,7 +236,7 @@ impl Generate for Guard {
     fn gn<R: Rng>(cx: &mut Self::Context, rng: &mut R) -> Self {
         cx.recursion_limit = 5;
         cx.negation_limit = 3;
-        Guard(BExpr::gn(cx, rng), Commands::gn(cx, rng))
+        Guard::new(BExpr::gn(cx, rng), Commands::gn(cx, rng))
     }
 }
 
@@ -211,11 +248,11 @@ impl Generate for AExpr {
             vec![
                 (
                     0.4,
-                    Box::new(|_, rng| AExpr::Number(rng.random_range(-100..=100))),
+                    Box::new(|_, rng| AExpr::number(rng.random_range(-100..=100))),
                 ),
                 (
                     if cx.names.is_empty() { 0.0 } else { 0.8 },
-                    Box::new(|cx, rng| AExpr::Reference(cx.reference(rng))),
+                    Box::new(|cx, rng| AExpr::reference(cx.reference(rng))),
                 ),
                 (
                     if cx.recursion_limit == 0 || cx.fuel == 0 {
@@ -260,12 +297,12 @@ impl Generate for BExpr {
         cx.sample(
             rng,
             vec![
-                (0.2, Box::new(|_cx, rng| BExpr::Bool(rng.random()))),
+                (0.2, Box::new(|_cx, rng| BExpr::bool(rng.random()))),
                 (
                     if cx.recursion_limit == 0 { 0.0 } else { 0.7 },
                     Box::new(|cx, rng| {
                         cx.recursion_limit = cx.recursion_limit.checked_sub(1).unwrap_or_default();
-                        BExpr::Rel(AExpr::gn(cx, rng), RelOp::gn(cx, rng), AExpr::gn(cx, rng))
+                        BExpr::rel(AExpr::gn(cx, rng), RelOp::gn(cx, rng), AExpr::gn(cx, rng))
                     }),
                 ),
                 (
@@ -279,7 +316,7 @@ impl Generate for BExpr {
                     if cx.negation_limit == 0 { 0.0 } else { 0.4 },
                     Box::new(|cx, rng| {
                         cx.negation_limit = cx.negation_limit.checked_sub(1).unwrap_or_default();
-                        BExpr::Not(Box::new(BExpr::gn(cx, rng)))
+                        BExpr::not(BExpr::gn(cx, rng))
                     }),
                 ),
             ],
@@ -319,3 +356,32 @@ impl Generate for LogicOp {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use gcl::ast::AExprKind;
+    use rand::{SeedableRng, rngs::SmallRng};
+
+    use super::*;
+
+    #[test]
+    fn indices_mostly_fall_within_array_lengths() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut cx = Context {
+            arrays: true,
+            ..Default::default()
+        };
+        let samples = 1000;
+        let within = (0..samples)
+            .filter(|_| {
+                let len = cx.array_length(&mut rng);
+                assert!(cx.array_lengths.contains(&len));
+                match cx.index(&mut rng).kind {
+                    AExprKind::Number(n) => (0..len as i32).contains(&n),
+                    _ => false,
+                }
+            })
+            .count();
+        assert!(within > samples * 6 / 10, "{within} of {samples}");
+    }
+}
@@ -1,11 +1,15 @@
+pub mod coverage;
 pub mod gn;
+pub mod shrink;
 
 use std::sync::Arc;
 
+pub use coverage::Coverage;
 pub use gn::Generate;
 use itertools::Either;
 pub use rand;
 use serde::{Deserialize, Serialize};
+pub use shrink::Shrink;
 
 #[derive(Debug, Clone, thiserror::Error)]
 pub enum EnvError {
@@ -54,6 +58,17 @@ impl EnvError {
     }
 }
 
+/// Programs which do not check against their inputs are reported with the
+/// errors of the check as the message.
+impl From<gcl::typeck::CheckErrors> for EnvError {
+    fn from(err: gcl::typeck::CheckErrors) -> Self {
+        EnvError::InvalidInputForProgram {
+            message: err.to_string(),
+            source: None,
+        }
+    }
+}
+
 pub type Result<T, E = EnvError> = std::result::Result<T, E>;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -92,6 +107,7 @@ impl<'a, E: Env> Results<'a, E> {
 
 pub trait Env: Default + std::fmt::Debug + Clone + PartialEq {
     type Input: Generate<Context = ()>
+        + Shrink
         + Serialize
         + for<'a> Deserialize<'a>
         + tapi::Tapi
@@ -133,6 +149,12 @@ pub trait Env: Default + std::fmt::Debug + Clone + PartialEq {
     fn annotate(_input: &Self::Input, _output: &Self::Output) -> Self::Annotation {
         Default::default()
     }
+    /// The behaviour of the reference implementation exercised by `input`,
+    /// where `output` is what it ran to, which coverage-guided generation
+    /// steers towards more of.
+    fn coverage(_input: &Self::Input, _output: &Self::Output) -> Coverage {
+        Default::default()
+    }
 
     fn run(input: &Self::Input) -> Result<Self::Output>;
     fn validate(
@@ -165,6 +187,15 @@ macro_rules! define_env {
                         // There's nothing we can do here ...
                     }
                     res => {
+                        // Report the smallest input which still fails in the
+                        // same way, rather than the one which was generated
+                        let input = $crate::shrink::minimize(input, 1000, |input| {
+                            <$name as $crate::Env>::run(input)
+                                .and_then(|output| <$name as $crate::Env>::validate(input, &output))
+                                .is_ok_and(|(result, _)| {
+                                    std::mem::discriminant(&result) == std::mem::discriminant(&res)
+                                })
+                        });
                         eprintln!("{}", serde_json::to_string_pretty(&input).unwrap());
                         panic!("validation failed! {res:?}")
                     }
@@ -182,3 +213,19 @@ pub enum ValidationResult {
     Mismatch { reason: String },
     TimeOut,
 }
+
+/// Programs which are not equivalent are reported with the input on which they
+/// differ as the reason.
+impl From<gcl::equivalence::Equivalence> for ValidationResult {
+    fn from(result: gcl::equivalence::Equivalence) -> Self {
+        match result {
+            gcl::equivalence::Equivalence::Equivalent => ValidationResult::Correct,
+            gcl::equivalence::Equivalence::Counterexample(cx) => ValidationResult::Mismatch {
+                reason: cx.to_string(),
+            },
+            gcl::equivalence::Equivalence::Unknown { reason } => {
+                ValidationResult::Unknown { reason }
+            }
+        }
+    }
+}
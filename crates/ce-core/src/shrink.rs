@@ -0,0 +1,162 @@
+mod gcl_shrink;
+
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
+
+use stdx::stringify::Stringify;
+
+/// The counterpart to [`Generate`](crate::Generate), making values smaller,
+/// such that a large generated input on which something fails can be reduced
+/// to one a person can make sense of.
+pub trait Shrink: Sized {
+    /// Values which are smaller than this one, with the most aggressive
+    /// reductions first.
+    fn shrink(&self) -> Vec<Self>;
+}
+
+/// Shrinks `value` for as long as a smaller value still `fails`, trying at most
+/// `limit` candidates, and returns the smallest failing value found.
+pub fn minimize<T: Shrink>(value: T, limit: usize, mut fails: impl FnMut(&T) -> bool) -> T {
+    let mut minimizer = Minimizer::new(value, limit);
+    while let Some(candidate) = minimizer.candidate() {
+        if fails(&candidate) {
+            minimizer.accept(candidate);
+        }
+    }
+    minimizer.into_value()
+}
+
+/// The steps of [`minimize`], for when checking whether a candidate fails
+/// cannot be done in a closure, such as when it is `async`.
+#[derive(Debug)]
+pub struct Minimizer<T> {
+    value: T,
+    candidates: std::vec::IntoIter<T>,
+    tries: usize,
+    limit: usize,
+}
+
+impl<T: Shrink> Minimizer<T> {
+    pub fn new(value: T, limit: usize) -> Self {
+        let candidates = value.shrink().into_iter();
+        Minimizer {
+            value,
+            candidates,
+            tries: 0,
+            limit,
+        }
+    }
+    /// The next candidate to try, or `None` if there are no smaller values or
+    /// `limit` candidates have been tried.
+    pub fn candidate(&mut self) -> Option<T> {
+        if self.tries == self.limit {
+            return None;
+        }
+        let candidate = self.candidates.next()?;
+        self.tries += 1;
+        Some(candidate)
+    }
+    /// Continues shrinking from `candidate`, which still fails.
+    pub fn accept(&mut self, candidate: T) {
+        self.candidates = candidate.shrink().into_iter();
+        self.value = candidate;
+    }
+    /// The number of candidates tried so far.
+    pub fn tries(&self) -> usize {
+        self.tries
+    }
+    /// The smallest failing value found.
+    pub fn into_value(self) -> T {
+        self.value
+    }
+}
+
+/// The lists with a chunk of `items` removed, starting with the first and the
+/// second half and going down to single items.
+pub(crate) fn removals<T: Clone>(items: &[T]) -> Vec<Vec<T>> {
+    let mut result = vec![];
+    let mut size = items.len().div_ceil(2);
+    while size > 0 {
+        for start in (0..items.len()).step_by(size) {
+            let mut rest = items[..start].to_vec();
+            rest.extend_from_slice(&items[(start + size).min(items.len())..]);
+            result.push(rest);
+        }
+        size /= 2;
+    }
+    result
+}
+
+/// The lists with one of `items` shrunk.
+pub(crate) fn shrink_each<T: Shrink + Clone>(items: &[T]) -> Vec<Vec<T>> {
+    items
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, item)| {
+            item.shrink().into_iter().map(move |item| {
+                let mut items = items.to_vec();
+                items[idx] = item;
+                items
+            })
+        })
+        .collect()
+}
+
+impl Shrink for i32 {
+    fn shrink(&self) -> Vec<Self> {
+        let x = *self;
+        let mut result: Vec<i32> = vec![];
+        let candidates = [Some(0), x.checked_neg().filter(|_| x < 0), Some(x / 2)];
+        for c in candidates.into_iter().flatten().chain([x - x.signum()]) {
+            if c != x && !result.contains(&c) {
+                result.push(c);
+            }
+        }
+        result
+    }
+}
+
+impl Shrink for bool {
+    fn shrink(&self) -> Vec<Self> {
+        if *self { vec![false] } else { vec![] }
+    }
+}
+
+impl<T: Shrink> Shrink for Box<T> {
+    fn shrink(&self) -> Vec<Self> {
+        (**self).shrink().into_iter().map(Box::new).collect()
+    }
+}
+
+impl<T: Shrink + Clone> Shrink for Vec<T> {
+    fn shrink(&self) -> Vec<Self> {
+        let mut result = removals(self);
+        result.extend(shrink_each(self));
+        result
+    }
+}
+
+/// The keys are kept, as they are usually names which must be present, and
+/// only the values are shrunk.
+impl<K: Ord + Clone, V: Shrink + Clone> Shrink for BTreeMap<K, V> {
+    fn shrink(&self) -> Vec<Self> {
+        self.iter()
+            .flat_map(|(k, v)| {
+                v.shrink().into_iter().map(move |v| {
+                    let mut map = self.clone();
+                    map.insert(k.clone(), v);
+                    map
+                })
+            })
+            .collect()
+    }
+}
+
+/// Values which do not parse are not shrunk.
+impl<T: Shrink + FromStr + Display + Clone> Shrink for Stringify<T> {
+    fn shrink(&self) -> Vec<Self> {
+        match self.try_parse() {
+            Ok(t) => t.shrink().into_iter().map(Stringify::new).collect(),
+            Err(_) => vec![],
+        }
+    }
+}
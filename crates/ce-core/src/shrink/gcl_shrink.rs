@@ -0,0 +1,295 @@
+use gcl::{
+    ast::{
+        AExpr, AExprKind, BExpr, BExprKind, Command, CommandKind, Commands, Function, Guard,
+        ProcDecl, Target,
+    },
+    interpreter::InterpreterMemory,
+};
+use itertools::chain;
+
+use super::{Shrink, removals, shrink_each};
+
+impl Shrink for Commands {
+    fn shrink(&self) -> Vec<Self> {
+        let cmds = &self.0;
+        let mut result = removals(cmds)
+            .into_iter()
+            .filter(|cmds| !cmds.is_empty())
+            .map(Commands)
+            .collect::<Vec<_>>();
+        if let [cmd] = cmds.as_slice()
+            && cmd.kind != CommandKind::Skip
+        {
+            result.push(Commands(vec![Command::new(CommandKind::Skip, cmd.span)]));
+        }
+        // Replace compound commands by the commands they are made of
+        for (idx, cmd) in cmds.iter().enumerate() {
+            let bodies = match &cmd.kind {
+                CommandKind::If(guards) | CommandKind::Loop(guards) => {
+                    guards.iter().map(|g| g.cmds.clone()).collect()
+                }
+                CommandKind::Par(components) => components.clone(),
+                _ => vec![],
+            };
+            for body in bodies {
+                let mut spliced = cmds[..idx].to_vec();
+                spliced.extend(body.0);
+                spliced.extend_from_slice(&cmds[idx + 1..]);
+                result.push(Commands(spliced));
+            }
+        }
+        result.extend(shrink_each(cmds).into_iter().map(Commands));
+        result
+    }
+}
+
+impl Shrink for Command {
+    fn shrink(&self) -> Vec<Self> {
+        let kinds: Vec<CommandKind> = match &self.kind {
+            CommandKind::Assignment(t, a) => chain!(
+                t.shrink()
+                    .into_iter()
+                    .map(|t| CommandKind::Assignment(t, a.clone())),
+                a.shrink()
+                    .into_iter()
+                    .map(|a| CommandKind::Assignment(t.clone(), a)),
+            )
+            .collect(),
+            CommandKind::BoolAssignment(t, b) => chain!(
+                t.shrink()
+                    .into_iter()
+                    .map(|t| CommandKind::BoolAssignment(t, b.clone())),
+                b.shrink()
+                    .into_iter()
+                    .map(|b| CommandKind::BoolAssignment(t.clone(), b)),
+            )
+            .collect(),
+            CommandKind::ArrayAssignment(arr, elems) => elems
+                .shrink()
+                .into_iter()
+                .map(|elems| CommandKind::ArrayAssignment(arr.clone(), elems))
+                .collect(),
+            CommandKind::Skip => vec![],
+            CommandKind::Abort => vec![CommandKind::Skip],
+            CommandKind::If(guards) => shrink_guards(guards)
+                .into_iter()
+                .map(CommandKind::If)
+                .collect(),
+            CommandKind::Loop(guards) => shrink_guards(guards)
+                .into_iter()
+                .map(CommandKind::Loop)
+                .collect(),
+            CommandKind::Assert(b) => chain!(
+                [CommandKind::Skip],
+                b.shrink().into_iter().map(CommandKind::Assert)
+            )
+            .collect(),
+            CommandKind::Proc(decl) => decl
+                .body
+                .shrink()
+                .into_iter()
+                .map(|body| {
+                    CommandKind::Proc(ProcDecl {
+                        body,
+                        ..decl.clone()
+                    })
+                })
+                .collect(),
+            // The arguments must match the parameters, so they are only shrunk
+            CommandKind::Call(name, args) => shrink_each(args)
+                .into_iter()
+                .map(|args| CommandKind::Call(name.clone(), args))
+                .collect(),
+            CommandKind::Par(components) => chain!(
+                removals(components)
+                    .into_iter()
+                    .filter(|components| !components.is_empty()),
+                shrink_each(components),
+            )
+            .map(CommandKind::Par)
+            .collect(),
+        };
+        kinds
+            .into_iter()
+            .map(|kind| Command::new(kind, self.span))
+            .collect()
+    }
+}
+
+fn shrink_guards(guards: &[Guard]) -> Vec<Vec<Guard>> {
+    chain!(
+        removals(guards)
+            .into_iter()
+            .filter(|guards| !guards.is_empty()),
+        shrink_each(guards),
+    )
+    .collect()
+}
+
+impl Shrink for Guard {
+    fn shrink(&self) -> Vec<Self> {
+        chain!(
+            self.guard.shrink().into_iter().map(|guard| Guard {
+                guard,
+                ..self.clone()
+            }),
+            self.cmds.shrink().into_iter().map(|cmds| Guard {
+                cmds,
+                ..self.clone()
+            }),
+        )
+        .collect()
+    }
+}
+
+impl Shrink for Target<Box<AExpr>> {
+    fn shrink(&self) -> Vec<Self> {
+        match self {
+            Target::Variable(_) => vec![],
+            Target::Array(arr, idx) => idx
+                .shrink()
+                .into_iter()
+                .map(|idx| Target::Array(arr.clone(), idx))
+                .collect(),
+        }
+    }
+}
+
+impl Shrink for AExpr {
+    fn shrink(&self) -> Vec<Self> {
+        let zero = AExpr::number(0);
+        match &self.kind {
+            AExprKind::Number(n) => n.shrink().into_iter().map(AExpr::number).collect(),
+            AExprKind::Reference(t) => {
+                chain!([zero], t.shrink().into_iter().map(AExpr::reference)).collect()
+            }
+            AExprKind::Binary(l, op, r) => chain!(
+                [zero, (**l).clone(), (**r).clone()],
+                l.shrink()
+                    .into_iter()
+                    .map(|l| AExpr::from(AExprKind::Binary(l, *op, r.clone()))),
+                r.shrink()
+                    .into_iter()
+                    .map(|r| AExpr::from(AExprKind::Binary(l.clone(), *op, r))),
+            )
+            .collect(),
+            AExprKind::Minus(x) => chain!(
+                [zero, (**x).clone()],
+                x.shrink()
+                    .into_iter()
+                    .map(|x| AExpr::from(AExprKind::Minus(x)))
+            )
+            .collect(),
+            AExprKind::Function(f) => chain!(
+                [zero],
+                f.args().cloned(),
+                f.shrink().into_iter().map(AExpr::function)
+            )
+            .collect(),
+        }
+    }
+}
+
+/// Only the arguments of functions are shrunk, and not which function it is.
+impl Shrink for Function {
+    fn shrink(&self) -> Vec<Self> {
+        match self {
+            Function::Min(l, r) | Function::Max(l, r) => {
+                let f = match self {
+                    Function::Min(_, _) => Function::Min,
+                    _ => Function::Max,
+                };
+                chain!(
+                    l.shrink().into_iter().map(|l| f(l, r.clone())),
+                    r.shrink().into_iter().map(|r| f(l.clone(), r)),
+                )
+                .collect()
+            }
+            Function::Length(_) => vec![],
+            Function::Count(arr, x) => x
+                .shrink()
+                .into_iter()
+                .map(|x| Function::Count(arr.clone(), x))
+                .collect(),
+        }
+    }
+}
+
+impl Shrink for BExpr {
+    fn shrink(&self) -> Vec<Self> {
+        let constants = [BExpr::bool(true), BExpr::bool(false)];
+        match &self.kind {
+            BExprKind::Bool(b) => b.shrink().into_iter().map(BExpr::bool).collect(),
+            BExprKind::Reference(t) => {
+                chain!(constants, t.shrink().into_iter().map(BExpr::reference)).collect()
+            }
+            BExprKind::Rel(l, op, r) => chain!(
+                constants,
+                l.shrink()
+                    .into_iter()
+                    .map(|l| BExpr::rel(l, *op, r.clone())),
+                r.shrink()
+                    .into_iter()
+                    .map(|r| BExpr::rel(l.clone(), *op, r)),
+            )
+            .collect(),
+            BExprKind::Logic(l, op, r) => chain!(
+                constants,
+                [(**l).clone(), (**r).clone()],
+                l.shrink()
+                    .into_iter()
+                    .map(|l| BExpr::from(BExprKind::Logic(l, *op, r.clone()))),
+                r.shrink()
+                    .into_iter()
+                    .map(|r| BExpr::from(BExprKind::Logic(l.clone(), *op, r))),
+            )
+            .collect(),
+            BExprKind::Not(b) => chain!(
+                constants,
+                [(**b).clone()],
+                b.shrink()
+                    .into_iter()
+                    .map(|x| BExpr::from(BExprKind::Not(x)))
+            )
+            .collect(),
+        }
+    }
+}
+
+/// The values are shrunk, while the variables and arrays are kept, as the
+/// program must find all of the targets it uses in the memory.
+impl Shrink for InterpreterMemory {
+    fn shrink(&self) -> Vec<Self> {
+        chain!(
+            self.variables
+                .shrink()
+                .into_iter()
+                .map(|variables| InterpreterMemory {
+                    variables,
+                    ..self.clone()
+                }),
+            self.arrays
+                .shrink()
+                .into_iter()
+                .map(|arrays| InterpreterMemory {
+                    arrays,
+                    ..self.clone()
+                }),
+            self.bools
+                .shrink()
+                .into_iter()
+                .map(|bools| InterpreterMemory {
+                    bools,
+                    ..self.clone()
+                }),
+            self.bool_arrays
+                .shrink()
+                .into_iter()
+                .map(|bool_arrays| InterpreterMemory {
+                    bool_arrays,
+                    ..self.clone()
+                }),
+        )
+        .collect()
+    }
+}
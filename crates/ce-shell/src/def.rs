@@ -3,7 +3,7 @@ macro_rules! define_shell {
     ($($krate:path[$name:ident, $display:literal]),*$(,)?) => {
         use std::str::FromStr;
 
-        use ce_core::{Env, EnvError, Generate, ValidationResult};
+        use ce_core::{Coverage, Env, EnvError, Generate, Shrink, ValidationResult};
         use itertools::Itertools;
 
         pub mod envs {
@@ -64,6 +64,17 @@ macro_rules! define_shell {
                     }),*
                 }
             }
+            /// Generates an input which covers behaviour not in `seen`, see
+            /// [`ce_core::coverage`].
+            #[tracing::instrument(skip_all, fields(analysis = self.to_string()))]
+            pub fn gen_input_guided(self, seen: &mut Coverage, rng: &mut rand::rngs::SmallRng) -> Input {
+                match self {
+                    $(Analysis::$name => {
+                        let input = ce_core::coverage::gn_guided::<$krate, _>(seen, rng);
+                        Input::new::<$krate>(&input)
+                    }),*
+                }
+            }
             // #[tracing::instrument(skip_all, fields(analysis = self.to_string(), ?src))]
             pub fn input_from_str(self, src: &str) -> Result<Input, $crate::io::Error> {
                 match self {
@@ -132,6 +143,18 @@ macro_rules! define_shell {
                     }),*
                 }
             }
+            /// The behaviour of the reference implementation this input
+            /// covers, which is empty if it is not a valid input.
+            pub fn coverage(&self) -> Coverage {
+                match self.analysis() {
+                    $(Analysis::$name => {
+                        let Ok(input) = self.data::<$krate>() else {
+                            return Default::default();
+                        };
+                        ce_core::coverage::reference_coverage::<$krate>(&input)
+                    }),*
+                }
+            }
             fn validate_output_helper(&self, output: &Output) -> Result<(ValidationResult, Annotation), EnvError> {
                 assert_eq!(self.analysis(), output.analysis());
 
@@ -149,6 +172,21 @@ macro_rules! define_shell {
             }
         }
 
+        /// Inputs which are smaller than this one, or none if it is not a valid
+        /// input for its analysis.
+        impl Shrink for Input {
+            fn shrink(&self) -> Vec<Input> {
+                match self.analysis() {
+                    $(Analysis::$name => {
+                        let Ok(input) = self.data::<$krate>() else {
+                            return vec![];
+                        };
+                        input.shrink().iter().map(Input::new::<$krate>).collect()
+                    }),*
+                }
+            }
+        }
+
         $(
             impl EnvExt for $krate {
                 const ANALYSIS: Analysis = Analysis::$name;
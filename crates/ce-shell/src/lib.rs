@@ -22,6 +22,13 @@ define_shell!(
     ce_riscv::RiscVEnv[RiscV, "RISC-V"],
     ce_security::SecurityEnv[Security, "Security"],
     ce_sign::SignEnv[Sign, "Sign Analysis"],
+    ce_interval::IntervalEnv[Interval, "Interval Analysis"],
+    ce_dataflow::ReachingDefinitionsEnv[ReachingDefinitions, "Reaching Definitions"],
+    ce_dataflow::LiveVariablesEnv[LiveVariables, "Live Variables"],
+    ce_dataflow::AvailableExpressionsEnv[AvailableExpressions, "Available Expressions"],
+    ce_dataflow::VeryBusyExpressionsEnv[VeryBusyExpressions, "Very Busy Expressions"],
+    ce_optimizer::OptimizerEnv[Optimizer, "Optimizer"],
+    ce_reachability::ReachabilityEnv[Reachability, "Reachability"],
 );
 
 impl Analysis {
@@ -32,6 +39,13 @@ impl Analysis {
         };
         self.gen_input(&mut rng)
     }
+    pub fn gen_input_guided_seeded(self, seen: &mut Coverage, seed: Option<u64>) -> Input {
+        let mut rng = match seed {
+            Some(seed) => rand::rngs::SmallRng::seed_from_u64(seed),
+            None => rand::rngs::SmallRng::from_os_rng(),
+        };
+        self.gen_input_guided(seen, &mut rng)
+    }
 }
 
 impl Input {
@@ -7,9 +7,10 @@
 //! and delegates the analysis to the `ce-shell` crate which is the join point
 //! for all analysis.
 
-use std::time::Duration;
+use std::{path::PathBuf, time::Duration};
 
 use clap::{Parser, Subcommand};
+use color_eyre::eyre::{Context, bail};
 
 #[derive(Debug, Parser)]
 #[command(version)]
@@ -30,6 +31,29 @@ enum Cmd {
         analysis: ce_shell::Analysis,
         input: String,
     },
+    /// Format GCL programs
+    Fmt {
+        /// The programs to format. If none are given, a program is read from
+        /// stdin
+        paths: Vec<PathBuf>,
+        /// The number of columns lines are kept within, where possible
+        #[clap(long, default_value = "80")]
+        width: usize,
+        /// The number of spaces to indent with
+        #[clap(long, default_value = "3")]
+        indent: usize,
+        /// Indent with tabs rather than spaces
+        #[clap(long, conflicts_with = "indent")]
+        tabs: bool,
+        /// Overwrite the files with their formatted programs, rather than
+        /// printing them
+        #[clap(long, short)]
+        write: bool,
+        /// Fail if any of the programs are not formatted, rather than printing
+        /// them
+        #[clap(long, conflicts_with = "write")]
+        check: bool,
+    },
 }
 
 fn main() -> color_eyre::Result<()> {
@@ -58,6 +82,65 @@ fn main() -> color_eyre::Result<()> {
             let output = input.reference_output()?;
             println!("{output}");
 
+            Ok(())
+        }
+        Cmd::Fmt {
+            paths,
+            width,
+            indent,
+            tabs,
+            write,
+            check,
+        } => {
+            let options = gcl::fmt::FormatOptions {
+                width: *width,
+                indent: if *tabs {
+                    gcl::fmt::Indent::Tabs
+                } else {
+                    gcl::fmt::Indent::Spaces(*indent)
+                },
+            };
+
+            if paths.is_empty() {
+                let src = std::io::read_to_string(std::io::stdin())?;
+                let formatted =
+                    gcl::fmt::format(&src, &options).wrap_err("failed to parse program")?;
+                if *check {
+                    if formatted != src {
+                        bail!("the program is not formatted");
+                    }
+                } else {
+                    print!("{formatted}");
+                }
+                return Ok(());
+            }
+
+            let mut unformatted = Vec::new();
+            for path in paths {
+                let src = std::fs::read_to_string(path)
+                    .wrap_err_with(|| format!("failed to read {}", path.display()))?;
+                let formatted = gcl::fmt::format(&src, &options)
+                    .wrap_err_with(|| format!("failed to parse {}", path.display()))?;
+                if *check {
+                    if formatted != src {
+                        unformatted.push(path.display().to_string());
+                    }
+                } else if *write {
+                    if formatted != src {
+                        std::fs::write(path, formatted)
+                            .wrap_err_with(|| format!("failed to write {}", path.display()))?;
+                    }
+                } else {
+                    print!("{formatted}");
+                }
+            }
+            if !unformatted.is_empty() {
+                bail!(
+                    "the following programs are not formatted:\n{}",
+                    unformatted.join("\n")
+                );
+            }
+
             Ok(())
         }
     }
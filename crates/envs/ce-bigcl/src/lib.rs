@@ -1,9 +1,11 @@
-use ce_core::{Env, Generate, ValidationResult, define_env, rand};
+use ce_core::{Env, Generate, Shrink, ValidationResult, define_env, rand};
 use gcl::{
-    ast::{AExpr, BExpr, Command, Commands, Guard, LogicOp, Target, Variable},
-    interpreter::InterpreterMemory,
-    pg::Node,
-    semantics::SemanticsContext,
+    ast::{
+        AExpr, AExprKind, BExpr, BExprKind, Command, CommandKind, Commands, Function, Guard,
+        LogicOp, RelOp, Target, Variable,
+    },
+    equivalence::EquivalenceChecker,
+    pg::{Determinism, ProgramGraph},
 };
 use indexmap::IndexSet;
 use serde::{Deserialize, Serialize};
@@ -40,9 +42,10 @@ impl Env for BiGCLEnv {
                 .map_err(ce_core::EnvError::invalid_input_for_program(
                     "failed to parse commands",
                 ))?;
+        cmd.check(None)?;
         let mut fv = Ctx::new(cmd.fv().into_iter().map(|t| t.name().to_string()).collect());
         Ok(Output {
-            binary: Stringify::new(cmd.binify(&mut fv)),
+            binary: Stringify::new(cmd.resolve_types().binify(&mut fv)),
         })
     }
 
@@ -93,6 +96,16 @@ impl Generate for Input {
     }
 }
 
+impl Shrink for Input {
+    fn shrink(&self) -> Vec<Self> {
+        self.commands
+            .shrink()
+            .into_iter()
+            .map(|commands| Input { commands })
+            .collect()
+    }
+}
+
 #[derive(Debug)]
 pub struct Ctx {
     next_id: u32,
@@ -136,6 +149,9 @@ trait Bitify {
 impl Binify for Commands {
     type Output = Commands;
     fn binify(&self, ctx: &mut Ctx) -> Commands {
+        if self.has_procedures() {
+            return self.inline_procedures().binify(ctx);
+        }
         Commands(self.0.iter().flat_map(|c| c.binify(ctx).0).collect())
     }
 }
@@ -146,68 +162,111 @@ impl Binify for Command {
         if self.is_binary() {
             return Commands([self.clone()].to_vec());
         }
-        match self {
-            Command::Assignment(target, x) => {
+        match &self.kind {
+            CommandKind::Assignment(target, x) => {
                 let (target_cmds, target) = target.binify(ctx);
                 let (x_cmds, x) = x.binify(ctx);
+                target_cmds.concat(&x_cmds).extend(Command::new(
+                    CommandKind::Assignment(target.clone(), x),
+                    self.span,
+                ))
+            }
+            CommandKind::BoolAssignment(target, b) => {
+                let (target_cmds, target) = target.binify(ctx);
+                let tmp = ctx.fresh();
+                let g = BExpr::rel(
+                    AExpr::reference(tmp.clone()),
+                    gcl::ast::RelOp::Eq,
+                    AExpr::number(1),
+                );
                 target_cmds
-                    .concat(&x_cmds)
-                    .extend(Command::Assignment(target.clone(), x))
+                    .concat(&b.bitify(ctx, &tmp))
+                    .extend(Command::new(
+                        CommandKind::If(
+                            [
+                                Guard::new(g.clone(), set_bool(target.clone(), true)),
+                                Guard::new(BExpr::not(g), set_bool(target, false)),
+                            ]
+                            .to_vec(),
+                        ),
+                        self.span,
+                    ))
+            }
+            CommandKind::ArrayAssignment(arr, elems) => {
+                let (cmds, elems): (Vec<_>, Vec<_>) = elems.iter().map(|a| a.binify(ctx)).unzip();
+                Commands(cmds.into_iter().flat_map(|c| c.0).collect()).extend(Command::new(
+                    CommandKind::ArrayAssignment(arr.clone(), elems),
+                    self.span,
+                ))
+            }
+            CommandKind::Skip | CommandKind::Abort => Commands([self.clone()].to_vec()),
+            CommandKind::Assert(b) => {
+                let tmp = ctx.fresh();
+                b.bitify(ctx, &tmp).extend(Command::new(
+                    CommandKind::Assert(BExpr::rel(
+                        AExpr::reference(tmp.clone()),
+                        gcl::ast::RelOp::Eq,
+                        AExpr::number(1),
+                    )),
+                    self.span,
+                ))
             }
-            Command::Skip => Commands([Command::Skip].to_vec()),
-            Command::If(guards) => guards.iter().rfold(
+            CommandKind::If(guards) => guards.iter().rfold(
                 Commands(
                     // NOTE: if all branches fail, we divide by zero to indicate
                     // stuck. Perhaps for the futrure we want to have a "false
                     // -> skip" branch
-                    [Command::Assignment(
+                    [Command::from(CommandKind::Assignment(
                         Target::Variable(Variable("stuck_".to_string())),
-                        AExpr::Binary(
-                            Box::new(AExpr::Number(1)),
-                            gcl::ast::AOp::Divide,
-                            Box::new(AExpr::Number(0)),
-                        ),
-                    )]
+                        AExpr::binary(AExpr::number(1), gcl::ast::AOp::Divide, AExpr::number(0)),
+                    ))]
                     .to_vec(),
                 ),
-                |else_, Guard(b, c)| {
+                |else_,
+                 Guard {
+                     guard: b, cmds: c, ..
+                 }| {
                     if b.is_binary() {
-                        return Command::If(
+                        return Command::from(CommandKind::If(
                             [
-                                Guard(b.clone(), c.binify(ctx)),
-                                Guard(BExpr::Not(Box::new(b.clone())), else_),
+                                Guard::new(b.clone(), c.binify(ctx)),
+                                Guard::new(BExpr::not(b.clone()), else_),
                             ]
                             .to_vec(),
-                        )
+                        ))
                         .binify(ctx);
                     }
                     let tmp = ctx.fresh();
                     let pre = b.bitify(ctx, &tmp);
-                    let g = BExpr::Rel(
-                        AExpr::Reference(tmp.clone()),
+                    let g = BExpr::rel(
+                        AExpr::reference(tmp.clone()),
                         gcl::ast::RelOp::Eq,
-                        AExpr::Number(1),
+                        AExpr::number(1),
                     );
-                    pre.extend(Command::If(
+                    pre.extend(CommandKind::If(
                         [
-                            Guard(g.clone(), c.binify(ctx)),
-                            Guard(BExpr::Not(Box::new(g)), else_),
+                            Guard::new(g.clone(), c.binify(ctx)),
+                            Guard::new(BExpr::not(g), else_),
                         ]
                         .to_vec(),
                     ))
                 },
             ),
-            Command::Loop(guards) => match guards.as_slice() {
-                [] => Commands([Command::Loop([].to_vec())].to_vec()),
-                [Guard(b, c)] => {
+            CommandKind::Loop(guards) => match guards.as_slice() {
+                [] => Commands([self.clone()].to_vec()),
+                [
+                    Guard {
+                        guard: b, cmds: c, ..
+                    },
+                ] => {
                     let tmp = ctx.fresh();
                     let cmds = b.bitify(ctx, &tmp);
-                    cmds.clone().extend(Command::Loop(
-                        [Guard(
-                            BExpr::Rel(
-                                AExpr::Reference(tmp.clone()),
+                    cmds.clone().extend(CommandKind::Loop(
+                        [Guard::new(
+                            BExpr::rel(
+                                AExpr::reference(tmp.clone()),
                                 gcl::ast::RelOp::Eq,
-                                AExpr::Number(1),
+                                AExpr::number(1),
                             ),
                             c.binify(ctx).concat(&cmds),
                         )]
@@ -215,17 +274,27 @@ impl Binify for Command {
                     ))
                 }
                 _ => Commands(
-                    [Command::Loop(
-                        [Guard(
+                    [Command::from(CommandKind::Loop(
+                        [Guard::new(
                             any(guards),
-                            Commands([Command::If(guards.clone())].to_vec()),
+                            Commands([Command::from(CommandKind::If(guards.clone()))].to_vec()),
                         )]
                         .to_vec(),
-                    )]
+                    ))]
                     .to_vec(),
                 )
                 .binify(ctx),
             },
+            CommandKind::Par(components) => Commands(
+                [Command::new(
+                    CommandKind::Par(components.iter().map(|c| c.binify(ctx)).collect()),
+                    self.span,
+                )]
+                .to_vec(),
+            ),
+            CommandKind::Proc(_) | CommandKind::Call(_, _) => {
+                unreachable!("procedures are inlined before binification")
+            }
         }
     }
 }
@@ -233,61 +302,98 @@ impl Binify for Command {
 fn any(guards: &[Guard]) -> BExpr {
     guards
         .iter()
-        .map(|Guard(b, _c)| b.clone())
+        .map(|g| g.guard.clone())
         .reduce(|a, b| BExpr::logic(a, LogicOp::Or, b))
-        .unwrap_or(BExpr::Bool(false))
+        .unwrap_or(BExpr::bool(false))
 }
 
 fn set_n(target: Target<Box<AExpr>>, n: i32) -> Commands {
-    Commands([Command::Assignment(target, AExpr::Number(n))].to_vec())
+    Commands(
+        [Command::from(CommandKind::Assignment(
+            target,
+            AExpr::number(n),
+        ))]
+        .to_vec(),
+    )
+}
+
+fn set_to(target: Target<Box<AExpr>>, a: AExpr) -> Commands {
+    Commands([Command::from(CommandKind::Assignment(target, a))].to_vec())
+}
+
+fn set_bool(target: Target<Box<AExpr>>, b: bool) -> Commands {
+    Commands(
+        [Command::from(CommandKind::BoolAssignment(
+            target,
+            BExpr::bool(b),
+        ))]
+        .to_vec(),
+    )
 }
 
 impl Bitify for BExpr {
     type Output = Commands;
 
     fn bitify(&self, ctx: &mut Ctx, target: &Target<Box<AExpr>>) -> Self::Output {
-        match self {
-            BExpr::Bool(true) => set_n(target.clone(), 1),
-            BExpr::Bool(false) => set_n(target.clone(), 0),
-            BExpr::Rel(l, op, r) => {
+        match &self.kind {
+            BExprKind::Bool(true) => set_n(target.clone(), 1),
+            BExprKind::Bool(false) => set_n(target.clone(), 0),
+            BExprKind::Reference(t) => {
+                let (cmds, t) = t.binify(ctx);
+                let b = BExpr::reference(t);
+                cmds.extend(CommandKind::If(
+                    [
+                        Guard::new(b.clone(), set_n(target.clone(), 1)),
+                        Guard::new(BExpr::not(b), set_n(target.clone(), 0)),
+                    ]
+                    .to_vec(),
+                ))
+            }
+            BExprKind::Rel(l, op, r) => {
                 let (l_cmds, l) = l.binify(ctx);
                 let (r_cmds, r) = r.binify(ctx);
                 let cmds = l_cmds.concat(&r_cmds);
-                let t = BExpr::Rel(l, *op, r);
-                let f = BExpr::Not(Box::new(t.clone()));
-                cmds.extend(Command::If(
+                let t = BExpr::rel(l, *op, r);
+                let f = BExpr::not(t.clone());
+                cmds.extend(CommandKind::If(
                     [
-                        Guard(t, set_n(target.clone(), 1)),
-                        Guard(f, set_n(target.clone(), 0)),
+                        Guard::new(t, set_n(target.clone(), 1)),
+                        Guard::new(f, set_n(target.clone(), 0)),
                     ]
                     .to_vec(),
                 ))
             }
-            BExpr::Logic(l, op, r) => {
+            BExprKind::Logic(l, op, r) => {
                 match op {
                     LogicOp::And => Commands(
-                        [Command::If(
+                        [Command::from(CommandKind::If(
                             [
-                                Guard(
+                                Guard::new(
                                     l.as_ref().clone(),
                                     Commands(
-                                        [Command::If(
+                                        [Command::from(CommandKind::If(
                                             [
-                                                Guard(r.as_ref().clone(), set_n(target.clone(), 1)),
-                                                Guard(
-                                                    BExpr::Not(r.clone()),
+                                                Guard::new(
+                                                    r.as_ref().clone(),
+                                                    set_n(target.clone(), 1),
+                                                ),
+                                                Guard::new(
+                                                    BExpr::not(r.as_ref().clone()),
                                                     set_n(target.clone(), 0),
                                                 ),
                                             ]
                                             .to_vec(),
-                                        )]
+                                        ))]
                                         .to_vec(),
                                     ),
                                 ),
-                                Guard(BExpr::Not(l.clone()), set_n(target.clone(), 0)),
+                                Guard::new(
+                                    BExpr::not(l.as_ref().clone()),
+                                    set_n(target.clone(), 0),
+                                ),
                             ]
                             .to_vec(),
-                        )]
+                        ))]
                         .to_vec(),
                     )
                     .binify(ctx),
@@ -296,38 +402,41 @@ impl Bitify for BExpr {
                         let r_tmp = ctx.fresh();
                         let l_cmds = l.bitify(ctx, &l_tmp);
                         let r_cmds = r.bitify(ctx, &r_tmp);
-                        l_cmds.concat(&r_cmds).extend(Command::Assignment(
+                        l_cmds.concat(&r_cmds).extend(CommandKind::Assignment(
                             target.clone(),
-                            AExpr::Binary(
-                                Box::new(AExpr::Reference(l_tmp)),
+                            AExpr::binary(
+                                AExpr::reference(l_tmp),
                                 gcl::ast::AOp::Times,
-                                Box::new(AExpr::Reference(r_tmp)),
+                                AExpr::reference(r_tmp),
                             ),
                         ))
                     }
                     LogicOp::Or => Commands(
-                        [Command::If(
+                        [Command::from(CommandKind::If(
                             [
-                                Guard(l.as_ref().clone(), set_n(target.clone(), 1)),
-                                Guard(
-                                    BExpr::Not(l.clone()),
+                                Guard::new(l.as_ref().clone(), set_n(target.clone(), 1)),
+                                Guard::new(
+                                    BExpr::not(l.as_ref().clone()),
                                     Commands(
-                                        [Command::If(
+                                        [Command::from(CommandKind::If(
                                             [
-                                                Guard(r.as_ref().clone(), set_n(target.clone(), 1)),
-                                                Guard(
-                                                    BExpr::Not(r.clone()),
+                                                Guard::new(
+                                                    r.as_ref().clone(),
+                                                    set_n(target.clone(), 1),
+                                                ),
+                                                Guard::new(
+                                                    BExpr::not(r.as_ref().clone()),
                                                     set_n(target.clone(), 0),
                                                 ),
                                             ]
                                             .to_vec(),
-                                        )]
+                                        ))]
                                         .to_vec(),
                                     ),
                                 ),
                             ]
                             .to_vec(),
-                        )]
+                        ))]
                         .to_vec(),
                     )
                     .binify(ctx),
@@ -339,43 +448,43 @@ impl Bitify for BExpr {
                         l_cmds
                             .concat(&r_cmds)
                             // t := l + r
-                            .extend(Command::Assignment(
+                            .extend(CommandKind::Assignment(
                                 target.clone(),
-                                AExpr::Binary(
-                                    Box::new(AExpr::Reference(l_tmp)),
+                                AExpr::binary(
+                                    AExpr::reference(l_tmp),
                                     gcl::ast::AOp::Plus,
-                                    Box::new(AExpr::Reference(r_tmp)),
+                                    AExpr::reference(r_tmp),
                                 ),
                             ))
                             // t := t + 1
-                            .extend(Command::Assignment(
+                            .extend(CommandKind::Assignment(
                                 target.clone(),
-                                AExpr::Binary(
-                                    Box::new(AExpr::Reference(target.clone())),
+                                AExpr::binary(
+                                    AExpr::reference(target.clone()),
                                     gcl::ast::AOp::Plus,
-                                    Box::new(AExpr::Number(1)),
+                                    AExpr::number(1),
                                 ),
                             ))
                             // t := t / 2
-                            .extend(Command::Assignment(
+                            .extend(CommandKind::Assignment(
                                 target.clone(),
-                                AExpr::Binary(
-                                    Box::new(AExpr::Reference(target.clone())),
+                                AExpr::binary(
+                                    AExpr::reference(target.clone()),
                                     gcl::ast::AOp::Divide,
-                                    Box::new(AExpr::Number(2)),
+                                    AExpr::number(2),
                                 ),
                             ))
                     }
                 }
             }
-            BExpr::Not(b) => {
+            BExprKind::Not(b) => {
                 let cmds = b.bitify(ctx, target);
-                cmds.extend(Command::Assignment(
+                cmds.extend(CommandKind::Assignment(
                     target.clone(),
-                    AExpr::Binary(
-                        Box::new(AExpr::Number(1)),
+                    AExpr::binary(
+                        AExpr::number(1),
                         gcl::ast::AOp::Minus,
-                        Box::new(AExpr::Reference(target.clone())),
+                        AExpr::reference(target.clone()),
                     ),
                 ))
             }
@@ -399,122 +508,88 @@ impl Binify for AExpr {
     type Output = (Commands, AExpr);
 
     fn binify(&self, ctx: &mut Ctx) -> Self::Output {
-        match self {
-            AExpr::Number(n) => (Commands([].to_vec()), AExpr::Number(*n)),
-            AExpr::Reference(target) => {
+        match &self.kind {
+            AExprKind::Number(n) => (Commands([].to_vec()), AExpr::number(*n)),
+            AExprKind::Reference(target) => {
                 let (cmds, target) = target.binify(ctx);
-                (cmds, AExpr::Reference(target.clone()))
+                (cmds, AExpr::reference(target.clone()))
             }
-            AExpr::Binary(l, op, r) => {
+            AExprKind::Binary(l, op, r) => {
                 let (l_cmds, l) = l.binify(ctx);
                 let (r_cmds, r) = r.binify(ctx);
                 let mut cmds = l_cmds.concat(&r_cmds);
                 let fresh = ctx.fresh();
-                let cmd = Command::Assignment(
+                let cmd = Command::from(CommandKind::Assignment(
                     fresh.clone(),
-                    AExpr::Binary(Box::new(l), *op, Box::new(r)),
-                );
+                    AExpr::binary(l, *op, r),
+                ));
                 cmds.0.push(cmd);
-                (cmds, AExpr::Reference(fresh))
+                (cmds, AExpr::reference(fresh))
             }
-            AExpr::Minus(x) => {
+            AExprKind::Minus(x) => {
                 let (mut cmds, x) = x.binify(ctx);
                 let fresh = ctx.fresh();
-                let cmd = Command::Assignment(fresh.clone(), AExpr::Minus(Box::new(x)));
+                let cmd = Command::from(CommandKind::Assignment(fresh.clone(), AExpr::minus(x)));
                 cmds.0.push(cmd);
-                (cmds, AExpr::Reference(fresh))
+                (cmds, AExpr::reference(fresh))
             }
-        }
-    }
-}
-
-fn check_programs_for_semantic_equivalence(p1: &Commands, p2: &Commands) -> ValidationResult {
-    let pg1 = gcl::pg::ProgramGraph::new(gcl::pg::Determinism::Deterministic, p1);
-    let pg2 = gcl::pg::ProgramGraph::new(gcl::pg::Determinism::Deterministic, p2);
-
-    let n_samples = 10;
-    let n_steps = 1000;
-
-    let mut rng = <rand::rngs::SmallRng as rand::SeedableRng>::seed_from_u64(0xCEC34);
-
-    for _ in 0..n_samples {
-        let assignment = generate_input_assignment(p2, &mut rng);
-
-        let mut node1 = Node::Start;
-        let mut mem1 = assignment.clone();
-        let mut node2 = Node::Start;
-        let mut mem2 = assignment.clone();
-        let mut term1 = false;
-        let mut term2 = false;
-
-        for _ in 0..n_steps {
-            if !term1 && let Some(next) = node1.next(&pg1, &mem1) {
-                node1 = next.0;
-                mem1 = next.1;
-            } else {
-                term1 = true;
-            }
-            if !term2 && let Some(next) = node2.next(&pg2, &mem2) {
-                node2 = next.0;
-                mem2 = next.1;
-            } else {
-                term2 = true;
-            }
-        }
-
-        match (term1, term2) {
-            (true, true) => {
-                if mem1.agrees_on(&p1.fv(), &mem2) {
-                    // NOTE: nothing more to do!
-                } else {
-                    return ValidationResult::Mismatch {
-                        reason: format!(
-                            "final memories differ:
-                        {:?}
-                        {:?}",
-                            mem1, mem2
-                        ),
-                    };
-                }
-            }
-            (true, false) => {
-                return ValidationResult::Unknown {
-                    reason: "output program did not terminate".to_string(),
-                };
-            }
-            (false, true) => {
-                return ValidationResult::Unknown {
-                    reason: "input program did not terminate".to_string(),
+            AExprKind::Function(f @ (Function::Min(l, r) | Function::Max(l, r))) => {
+                let (l_cmds, l) = l.binify(ctx);
+                let (r_cmds, r) = r.binify(ctx);
+                let fresh = ctx.fresh();
+                // min(l, r) is l if l <= r, and r otherwise
+                let op = match f {
+                    Function::Min(_, _) => RelOp::Le,
+                    _ => RelOp::Ge,
                 };
+                let g = BExpr::rel(l.clone(), op, r.clone());
+                let cmds = l_cmds.concat(&r_cmds).extend(CommandKind::If(
+                    [
+                        Guard::new(g.clone(), set_to(fresh.clone(), l)),
+                        Guard::new(BExpr::not(g), set_to(fresh.clone(), r)),
+                    ]
+                    .to_vec(),
+                ));
+                (cmds, AExpr::reference(fresh))
             }
-            (false, false) => {
-                return ValidationResult::Unknown {
-                    reason: "input and output program did not terminate".to_string(),
-                };
+            AExprKind::Function(f) => {
+                let mut cmds = Commands([].to_vec());
+                let f = f.map(|a| {
+                    let (a_cmds, a) = a.binify(ctx);
+                    cmds.0.extend(a_cmds.0);
+                    a
+                });
+                let fresh = ctx.fresh();
+                cmds.0.push(Command::from(CommandKind::Assignment(
+                    fresh.clone(),
+                    AExpr::function(f),
+                )));
+                (cmds, AExpr::reference(fresh))
             }
         }
     }
+}
+
+/// Checks that the deterministic program graphs of `p1` and `p2` end in
+/// memories which agree on the variables of `p1`.
+pub fn check_programs_for_semantic_equivalence(p1: &Commands, p2: &Commands) -> ValidationResult {
+    let pg1 = ProgramGraph::new(Determinism::Deterministic, p1);
+    let pg2 = ProgramGraph::new(Determinism::Deterministic, p2);
 
-    ValidationResult::Correct
+    EquivalenceChecker::default()
+        .check(&pg1, &pg2, &p1.fv())
+        .into()
 }
 
-fn generate_input_assignment(
-    commands: &gcl::ast::Commands,
-    mut rng: &mut impl rand::Rng,
-) -> InterpreterMemory {
-    let initial_memory = gcl::memory::Memory::from_targets_with(
-        commands.fv(),
-        &mut rng,
-        |rng, _| rng.random_range(-10..=10),
-        |rng, _| {
-            let len = rng.random_range(5..=10);
-            (0..len).map(|_| rng.random_range(-10..=10)).collect()
-        },
-    );
-    InterpreterMemory {
-        variables: initial_memory.variables,
-        arrays: initial_memory.arrays,
-    }
+/// Checks that `pg1` and `pg2` end in memories which agree on the variables
+/// of `pg1`.
+pub fn check_program_graphs_for_semantic_equivalence(
+    pg1: &ProgramGraph,
+    pg2: &ProgramGraph,
+) -> ValidationResult {
+    EquivalenceChecker::default()
+        .check(pg1, pg2, &pg1.fv())
+        .into()
 }
 
 impl IsBinary for Commands {
@@ -533,11 +608,33 @@ impl IsBinary for Target<Box<AExpr>> {
 }
 impl IsBinary for Command {
     fn is_binary(&self) -> bool {
-        match self {
-            Command::Assignment(t, a) => t.is_binary() && a.is_binary(),
-            Command::Skip => true,
-            Command::If(guards) => {
-                if let [Guard(a, a_body), Guard(BExpr::Not(b), b_body)] = guards.as_slice()
+        match &self.kind {
+            CommandKind::Assignment(t, a) => t.is_binary() && a.is_binary(),
+            CommandKind::BoolAssignment(t, b) => {
+                t.is_binary()
+                    && matches!(&b.kind, BExprKind::Bool(_) | BExprKind::Reference(_))
+                    && b.is_binary()
+            }
+            CommandKind::ArrayAssignment(_, elems) => elems.iter().all(|a| a.is_atomic()),
+            CommandKind::Skip | CommandKind::Abort => true,
+            CommandKind::Assert(b) => b.is_binary(),
+            CommandKind::If(guards) => {
+                if let [
+                    Guard {
+                        guard: a,
+                        cmds: a_body,
+                        ..
+                    },
+                    Guard {
+                        guard:
+                            BExpr {
+                                kind: BExprKind::Not(b),
+                                ..
+                            },
+                        cmds: b_body,
+                        ..
+                    },
+                ] = guards.as_slice()
                     && a == &**b
                     && a.is_binary()
                 {
@@ -546,8 +643,14 @@ impl IsBinary for Command {
                     false
                 }
             }
-            Command::Loop(guards) => {
-                if let [Guard(a, a_body)] = guards.as_slice()
+            CommandKind::Loop(guards) => {
+                if let [
+                    Guard {
+                        guard: a,
+                        cmds: a_body,
+                        ..
+                    },
+                ] = guards.as_slice()
                     && a.is_binary()
                 {
                     a_body.is_binary()
@@ -555,36 +658,43 @@ impl IsBinary for Command {
                     false
                 }
             }
+            CommandKind::Par(components) => components.iter().all(|c| c.is_binary()),
+            CommandKind::Proc(_) | CommandKind::Call(_, _) => false,
         }
     }
 }
 
 impl IsBinary for AExpr {
     fn is_binary(&self) -> bool {
-        match self {
-            AExpr::Number(_) | AExpr::Reference(_) => true,
-            AExpr::Binary(l, _, r) => l.is_atomic() && r.is_atomic(),
-            AExpr::Minus(x) => x.is_atomic(),
+        match &self.kind {
+            AExprKind::Number(_) | AExprKind::Reference(_) => true,
+            AExprKind::Binary(l, _, r) => l.is_atomic() && r.is_atomic(),
+            AExprKind::Minus(x) => x.is_atomic(),
+            // min and max are turned into conditionals, so only functions of
+            // arrays are left
+            AExprKind::Function(Function::Min(_, _) | Function::Max(_, _)) => false,
+            AExprKind::Function(f) => f.args().all(|a| a.is_atomic()),
         }
     }
 }
 
 impl IsAtomic for AExpr {
     fn is_atomic(&self) -> bool {
-        match self {
-            AExpr::Number(_) | AExpr::Reference(_) => true,
-            AExpr::Binary(_, _, _) | AExpr::Minus(_) => false,
+        match &self.kind {
+            AExprKind::Number(_) | AExprKind::Reference(_) => true,
+            AExprKind::Binary(_, _, _) | AExprKind::Minus(_) | AExprKind::Function(_) => false,
         }
     }
 }
 
 impl IsBinary for BExpr {
     fn is_binary(&self) -> bool {
-        match self {
-            BExpr::Bool(_) => true,
-            BExpr::Rel(l, _, r) => l.is_atomic() && r.is_atomic(),
-            BExpr::Logic(_, _, _) => false,
-            BExpr::Not(_) => false,
+        match &self.kind {
+            BExprKind::Bool(_) => true,
+            BExprKind::Reference(t) => t.is_binary(),
+            BExprKind::Rel(l, _, r) => l.is_atomic() && r.is_atomic(),
+            BExprKind::Logic(_, _, _) => false,
+            BExprKind::Not(_) => false,
         }
     }
 }
@@ -595,14 +705,14 @@ mod tests {
 
     #[test]
     fn test_1() {
-        let res = BExpr::Logic(
-            Box::new(BExpr::Bool(true)),
+        let res = BExpr::logic(
+            BExpr::bool(true),
             LogicOp::Or,
-            Box::new(BExpr::Not(Box::new(BExpr::Rel(
-                AExpr::Number(1),
+            BExpr::not(BExpr::rel(
+                AExpr::number(1),
                 gcl::ast::RelOp::Eq,
-                AExpr::Number(2),
-            )))),
+                AExpr::number(2),
+            )),
         )
         .bitify(
             &mut Ctx::new(Default::default()),
@@ -647,4 +757,38 @@ mod tests {
 fi"#
         );
     }
+
+    #[test]
+    fn inequivalent_programs_are_mismatched() {
+        let p1: Commands = "if x > 3 -> y := x [] x <= 3 -> y := 0 fi".parse().unwrap();
+        let p2: Commands = "if x > 3 -> y := x + 1 [] x <= 3 -> y := 0 fi"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            check_programs_for_semantic_equivalence(&p1, &p1),
+            ValidationResult::Correct
+        );
+        assert!(matches!(
+            check_programs_for_semantic_equivalence(&p1, &p2),
+            ValidationResult::Mismatch { .. }
+        ));
+    }
+
+    #[test]
+    fn min_and_max_become_conditionals() {
+        let cmds: Commands = "y := max(x, min(z, 3)) + 1".parse().unwrap();
+        let mut ctx = Ctx::new(
+            cmds.fv()
+                .into_iter()
+                .map(|t| t.name().to_string())
+                .collect(),
+        );
+        let binary = cmds.binify(&mut ctx);
+        assert!(binary.is_binary(), "{binary}");
+        assert!(!binary.to_string().contains("max"), "{binary}");
+        assert_eq!(
+            check_programs_for_semantic_equivalence(&cmds, &binary),
+            ValidationResult::Correct
+        );
+    }
 }
@@ -1,5 +1,5 @@
-use ce_core::{Env, Generate, ValidationResult, define_env, gn::GclGenContext, rand};
-use gcl::ast::AExpr;
+use ce_core::{Env, Generate, Shrink, ValidationResult, define_env, gn::GclGenContext, rand};
+use gcl::{ast::AExpr, parse::ParseError, semantics::EmptySemanticsContext};
 use serde::{Deserialize, Serialize};
 use stdx::stringify::Stringify;
 
@@ -28,14 +28,24 @@ impl Env for CalcEnv {
     type Annotation = ();
 
     fn run(input: &Self::Input) -> ce_core::Result<Self::Output> {
-        let expr =
-            input
-                .expression
-                .try_parse()
-                .map_err(ce_core::EnvError::invalid_input_for_program(
+        let expr = match input.expression.try_parse() {
+            Ok(expr) => expr,
+            // Literals are parsed as program integers, so those too large for
+            // one are reported as failing calculations
+            Err(err @ ParseError::IntegerTooLarge { .. }) => {
+                return Ok(Output {
+                    result: String::new(),
+                    error: err.to_string(),
+                });
+            }
+            Err(err) => {
+                return Err(ce_core::EnvError::invalid_input_for_program(
                     "failed to parse expression",
-                ))?;
-        let (result, error) = match expr.semantics(&gcl::semantics::EmptySemanticsContext) {
+                )(err));
+            }
+        };
+        // Calculations are not bound by the integer size of the programs
+        let (result, error) = match expr.unbounded_semantics(&EmptySemanticsContext) {
             Ok(result) => (result.to_string(), String::new()),
             Err(err) => {
                 let error = format!("{err}");
@@ -94,10 +104,7 @@ impl Generate for Input {
 
         let mut expr = gen_expr();
         for _ in 0..10 {
-            if expr
-                .semantics(&gcl::semantics::EmptySemanticsContext)
-                .is_ok()
-            {
+            if expr.unbounded_semantics(&EmptySemanticsContext).is_ok() {
                 break;
             }
             expr = gen_expr();
@@ -108,3 +115,13 @@ impl Generate for Input {
         }
     }
 }
+
+impl Shrink for Input {
+    fn shrink(&self) -> Vec<Self> {
+        self.expression
+            .shrink()
+            .into_iter()
+            .map(|expression| Input { expression })
+            .collect()
+    }
+}
@@ -1,14 +1,14 @@
 mod dot;
+mod matching;
 
-use std::collections::{BTreeMap, BTreeSet};
-
-use ce_core::{Env, Generate, ValidationResult, define_env};
+use ce_core::{Env, Generate, Shrink, ValidationResult, define_env};
 use gcl::{
     ast::Commands,
     interpreter::InterpreterMemory,
     pg::{Determinism, ProgramGraph},
 };
 use itertools::Itertools;
+use petgraph::graph::{EdgeIndex, NodeIndex};
 use rand::{Rng, seq::IndexedRandom};
 use serde::{Deserialize, Serialize};
 use stdx::stringify::Stringify;
@@ -28,6 +28,26 @@ pub struct Output {
     pub dot: String,
 }
 
+/// The edges of the output which could not be matched to the expected graph,
+/// and the edges of the expected graph missing from the output.
+#[derive(tapi::Tapi, Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[tapi(path = "Compiler")]
+pub struct Annotation {
+    /// The output graph with its unmatched edges highlighted and its missing
+    /// edges added as dashed edges
+    pub dot: String,
+    pub unmatched: Vec<AnnotatedEdge>,
+    pub missing: Vec<AnnotatedEdge>,
+}
+
+#[derive(tapi::Tapi, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[tapi(path = "Compiler")]
+pub struct AnnotatedEdge {
+    pub from: String,
+    pub action: String,
+    pub to: String,
+}
+
 impl Env for CompilerEnv {
     type Input = Input;
 
@@ -35,24 +55,25 @@ impl Env for CompilerEnv {
 
     type Meta = ();
 
-    type Annotation = ();
+    type Annotation = Annotation;
 
     fn run(input: &Self::Input) -> ce_core::Result<Self::Output> {
-        let dot =
-            ProgramGraph::new(
-                input.determinism,
-                &input.commands.try_parse().map_err(
-                    ce_core::EnvError::invalid_input_for_program("failed to parse commands"),
-                )?,
-            )
-            .dot();
+        let commands =
+            input
+                .commands
+                .try_parse()
+                .map_err(ce_core::EnvError::invalid_input_for_program(
+                    "failed to parse commands",
+                ))?;
+        commands.check(None)?;
+        let dot = ProgramGraph::new(input.determinism, &commands).dot();
         Ok(Output { dot })
     }
 
     fn validate(
         input: &Self::Input,
         output: &Self::Output,
-    ) -> ce_core::Result<(ValidationResult, ())> {
+    ) -> ce_core::Result<(ValidationResult, Annotation)> {
         let commands =
             input
                 .commands
@@ -77,6 +98,7 @@ impl Env for CompilerEnv {
                 InterpreterMemory {
                     variables: initial_memory.variables,
                     arrays: initial_memory.arrays,
+                    ..Default::default()
                 }
             })
             .collect_vec();
@@ -88,22 +110,88 @@ impl Env for CompilerEnv {
                     ValidationResult::Mismatch {
                         reason: format!("failed to parse dot: {err}"),
                     },
-                    (),
+                    Annotation::default(),
                 ));
             }
         };
         let o_g = dot::dot_to_petgraph(&o_dot).expect("we always produce valid dot");
 
-        if action_bag(&o_g, &sample_mems) != action_bag(&t_g, &sample_mems) {
-            Ok((
-                ValidationResult::Mismatch {
-                    reason: "the graphs have different structure".to_string(),
-                },
-                (),
-            ))
+        let matching = matching::match_graphs(&o_g, &t_g, |a| fingerprint(a, &sample_mems));
+        let annotation = annotate(&o_g, &t_g, &matching, &output.dot);
+
+        let result = if let Some(e) = annotation.unmatched.first() {
+            ValidationResult::Mismatch {
+                reason: format!(
+                    "the edge from {} to {} labelled `{}` does not match any edge of the expected graph",
+                    e.from, e.to, e.action
+                ),
+            }
+        } else if let Some(e) = annotation.missing.first() {
+            ValidationResult::Mismatch {
+                reason: format!(
+                    "the graph is missing an edge from {} to {} labelled `{}`",
+                    e.from, e.to, e.action
+                ),
+            }
         } else {
-            Ok((ValidationResult::Correct, ()))
+            ValidationResult::Correct
+        };
+        Ok((result, annotation))
+    }
+}
+
+/// Describes the unmatched and missing edges of `matching`, naming nodes of
+/// the expected graph by the nodes of the output they were matched to.
+fn annotate(
+    o_g: &dot::ParsedGraph,
+    t_g: &dot::ParsedGraph,
+    matching: &matching::Matching,
+    t_dot: &str,
+) -> Annotation {
+    let t_name = |n: NodeIndex| t_g.graph[n].clone();
+    let o_name = |n: NodeIndex| match matching.nodes.get(&n) {
+        Some(m) => t_name(*m),
+        None => o_g.graph[n].clone(),
+    };
+    let edge = |g: &dot::ParsedGraph, e: EdgeIndex, name: &dyn Fn(NodeIndex) -> String| {
+        let (from, to) = g.graph.edge_endpoints(e).unwrap();
+        AnnotatedEdge {
+            from: name(from),
+            action: g.graph[e].to_string(),
+            to: name(to),
         }
+    };
+    let unmatched = matching
+        .unmatched
+        .iter()
+        .map(|e| edge(t_g, *e, &t_name))
+        .collect_vec();
+    let missing = matching
+        .missing
+        .iter()
+        .map(|e| edge(o_g, *e, &o_name))
+        .collect_vec();
+
+    // The edges of the parsed graph are in the order of the edges of the dot
+    // graph, so the unmatched edges can be found by their index
+    let mut graph = gcl::pg::dot::DotGraph::parse(t_dot).expect("the output was already parsed");
+    for e in &matching.unmatched {
+        let attributes = &mut graph.edges[e.index()].attributes;
+        attributes.insert("color".to_string(), "red".to_string());
+        attributes.insert("fontcolor".to_string(), "red".to_string());
+    }
+    for e in &missing {
+        let attributes = graph.edge(e.from.clone(), e.to.clone());
+        attributes.insert("label".to_string(), e.action.clone());
+        attributes.insert("color".to_string(), "orange".to_string());
+        attributes.insert("fontcolor".to_string(), "orange".to_string());
+        attributes.insert("style".to_string(), "dashed".to_string());
+    }
+
+    Annotation {
+        dot: graph.to_string(),
+        unmatched,
+        missing,
     }
 }
 
@@ -122,23 +210,17 @@ impl Generate for Input {
     }
 }
 
-fn action_bag(
-    g: &dot::ParsedGraph,
-    mems: &[InterpreterMemory],
-) -> BTreeMap<[BTreeSet<Fingerprint>; 2], usize> {
-    let mut counts = BTreeMap::new();
-
-    for i in g.graph.node_indices() {
-        let id = [petgraph::Incoming, petgraph::Outgoing].map(|dir| {
-            g.graph
-                .edges_directed(i, dir)
-                .map(|e| fingerprint(e.weight(), mems))
-                .collect()
-        });
-        *counts.entry(id).or_insert(0) += 1;
+impl Shrink for Input {
+    fn shrink(&self) -> Vec<Self> {
+        self.commands
+            .shrink()
+            .into_iter()
+            .map(|commands| Input {
+                commands,
+                ..self.clone()
+            })
+            .collect()
     }
-
-    counts
 }
 
 type Fingerprint = (ActionKind, Vec<Option<InterpreterMemory>>);
@@ -152,7 +234,12 @@ fn fingerprint(a: &gcl::pg::Action, mems: &[InterpreterMemory]) -> Fingerprint {
 impl From<&'_ gcl::pg::Action> for ActionKind {
     fn from(action: &'_ gcl::pg::Action) -> Self {
         match action {
-            gcl::pg::Action::Assignment(t, _) => ActionKind::Assignment(t.clone().map_idx(|_| ())),
+            gcl::pg::Action::Assignment(t, _) | gcl::pg::Action::BoolAssignment(t, _) => {
+                ActionKind::Assignment(t.clone().map_idx(|_| ()))
+            }
+            gcl::pg::Action::ArrayAssignment(arr, _) => {
+                ActionKind::Assignment(gcl::ast::Target::Array(arr.clone(), ()))
+            }
             gcl::pg::Action::Skip => ActionKind::Skip,
             gcl::pg::Action::Condition(_) => ActionKind::Condition,
         }
@@ -165,3 +252,103 @@ enum ActionKind {
     Skip,
     Condition,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input() -> Input {
+        Input {
+            commands: Stringify::new(
+                "x := 1 ; if x > 0 -> y := 2 [] x <= 0 -> z := 3 fi"
+                    .parse()
+                    .unwrap(),
+            ),
+            determinism: Determinism::NonDeterministic,
+        }
+    }
+
+    #[test]
+    fn dot_from_other_tools_is_accepted() {
+        let dot = r#"
+            /* written by hand */
+            strict digraph "program graph" {
+                rankdir = LR;
+                node [shape = circle]
+                edge [fontname = "Courier"]
+                start [label = "q▷"]
+                start -> a [label = "x := 1"];
+                subgraph cluster_0 {
+                    a -> b [label = "x > 0", color = red]
+                    b -> "end" [label = "y" + " := 2"]
+                }
+                a:e -> c:w // the other branch
+                    [label = "x <= 0"];
+                c -> "end" [label = "z := 3"]
+            }
+        "#;
+        let output = Output {
+            dot: dot.to_string(),
+        };
+        assert_eq!(
+            CompilerEnv::validate(&input(), &output).unwrap().0,
+            ValidationResult::Correct
+        );
+    }
+
+    #[test]
+    fn mismatches_name_the_unmatched_edge() {
+        let dot = r#"
+            digraph {
+                qStart -> q1 [label = "x := 1"]
+                q1 -> q2 [label = "x > 0"]
+                q2 -> qFinal [label = "y := 2"]
+                q1 -> q3 [label = "x <= 0"]
+                q3 -> qFinal [label = "z := 4"]
+            }
+        "#;
+        let output = Output {
+            dot: dot.to_string(),
+        };
+        let (result, annotation) = CompilerEnv::validate(&input(), &output).unwrap();
+        let ValidationResult::Mismatch { reason } = result else {
+            panic!("expected a mismatch, got {result:?}")
+        };
+        assert!(
+            reason.contains("q3 to qFinal labelled `z := 4`"),
+            "{reason}"
+        );
+        assert_eq!(annotation.unmatched.len(), 1);
+        assert_eq!(annotation.missing.len(), 1);
+        assert_eq!(annotation.missing[0].action, "z := 3");
+        assert_eq!(annotation.missing[0].from, "q3");
+    }
+
+    #[test]
+    fn renamed_nodes_are_matched() {
+        let commands = input().commands.try_parse().unwrap();
+        let pg = ProgramGraph::new(Determinism::NonDeterministic, &commands);
+        let dot = pg.dot().replace("q1", "a").replace("q2", "b");
+        let output = Output { dot };
+        assert_eq!(
+            CompilerEnv::validate(&input(), &output).unwrap().0,
+            ValidationResult::Correct
+        );
+    }
+
+    #[test]
+    fn program_graphs_roundtrip() {
+        let commands = input().commands.try_parse().unwrap();
+        let pg = ProgramGraph::new(Determinism::Deterministic, &commands);
+
+        let from_dot = ProgramGraph::from_dot(&pg.dot()).unwrap();
+        assert_eq!(from_dot.edges(), pg.edges());
+
+        let from_graphml = ProgramGraph::from_graphml(&pg.graphml()).unwrap();
+        assert_eq!(from_graphml.edges(), pg.edges());
+
+        let json = serde_json::to_string(&pg).unwrap();
+        let from_json: ProgramGraph = serde_json::from_str(&json).unwrap();
+        assert_eq!(from_json.edges(), pg.edges());
+    }
+}
@@ -0,0 +1,324 @@
+use std::collections::BTreeMap;
+
+use petgraph::{
+    graph::{EdgeIndex, NodeIndex},
+    visit::EdgeRef,
+};
+
+use crate::dot::ParsedGraph;
+
+/// The number of nodes tried before the search gives up, and reports the
+/// largest partial matching found.
+const BUDGET: usize = 100_000;
+
+/// A matching of the nodes of the reference graph to those of the output,
+/// which is an isomorphism if every edge is matched by one with the same
+/// label.
+#[derive(Debug)]
+pub struct Matching {
+    pub nodes: BTreeMap<NodeIndex, NodeIndex>,
+    /// The edges of the reference graph without a matching edge in the output
+    pub missing: Vec<EdgeIndex>,
+    /// The edges of the output without a matching edge in the reference graph
+    pub unmatched: Vec<EdgeIndex>,
+}
+
+struct Labelled<'a> {
+    graph: &'a ParsedGraph,
+    labels: Vec<usize>,
+    /// The sorted labels of the incoming and outgoing edges of each node
+    signatures: Vec<[Vec<usize>; 2]>,
+}
+
+impl<'a> Labelled<'a> {
+    fn new(graph: &'a ParsedGraph, label: &mut impl FnMut(&gcl::pg::Action) -> usize) -> Self {
+        let g = &graph.graph;
+        let labels = g.edge_indices().map(|e| label(&g[e])).collect();
+        let mut labelled = Labelled {
+            graph,
+            labels,
+            signatures: vec![],
+        };
+        labelled.signatures = g
+            .node_indices()
+            .map(|n| {
+                [petgraph::Incoming, petgraph::Outgoing].map(|dir| {
+                    let mut labels = g
+                        .edges_directed(n, dir)
+                        .map(|e| labelled.labels[e.id().index()])
+                        .collect::<Vec<_>>();
+                    labels.sort();
+                    labels
+                })
+            })
+            .collect();
+        labelled
+    }
+
+    /// The sorted labels of the edges from `a` to `b`.
+    fn between(&self, a: NodeIndex, b: NodeIndex) -> Vec<usize> {
+        let mut labels = self
+            .graph
+            .graph
+            .edges_connecting(a, b)
+            .map(|e| self.labels[e.id().index()])
+            .collect::<Vec<_>>();
+        labels.sort();
+        labels
+    }
+
+    /// The start, final and error nodes, which must be matched to each other.
+    fn special(&self, n: NodeIndex) -> Option<gcl::pg::Node> {
+        self.graph
+            .names
+            .get(&n)
+            .copied()
+            .filter(|n| !matches!(n, gcl::pg::Node::Node(_)))
+    }
+
+    fn find(&self, special: gcl::pg::Node) -> Option<NodeIndex> {
+        self.graph
+            .graph
+            .node_indices()
+            .find(|n| self.special(*n) == Some(special))
+    }
+}
+
+struct Search<'a> {
+    reference: Labelled<'a>,
+    output: Labelled<'a>,
+    order: Vec<NodeIndex>,
+    mapping: Vec<Option<NodeIndex>>,
+    used: Vec<bool>,
+    best: Vec<Option<NodeIndex>>,
+    best_depth: usize,
+    steps: usize,
+}
+
+impl Search<'_> {
+    /// The unused nodes of the output which `r` may be matched to, given the
+    /// start, final and error nodes.
+    fn anchored(&self, r: NodeIndex) -> impl Iterator<Item = NodeIndex> + '_ {
+        let anchor = self.reference.special(r).and_then(|s| self.output.find(s));
+        self.output
+            .graph
+            .graph
+            .node_indices()
+            .filter(|o| !self.used[o.index()])
+            .filter(move |o| anchor.is_none_or(|a| a == *o))
+            .filter(move |o| {
+                self.output
+                    .special(*o)
+                    .is_none_or(|s| self.reference.find(s).is_none_or(|n| n == r))
+            })
+    }
+
+    fn candidates(&self, r: NodeIndex) -> Vec<NodeIndex> {
+        self.anchored(r)
+            .filter(|o| self.reference.signatures[r.index()] == self.output.signatures[o.index()])
+            .collect()
+    }
+
+    /// The number of edges between `r` and the nodes matched so far, which
+    /// are matched by edges between `o` and the nodes they are matched to.
+    fn overlap(&self, r: NodeIndex, o: NodeIndex) -> usize {
+        let common = |mut a: Vec<usize>, b: Vec<usize>| {
+            b.into_iter()
+                .filter(|l| match a.iter().position(|m| m == l) {
+                    Some(i) => {
+                        a.swap_remove(i);
+                        true
+                    }
+                    None => false,
+                })
+                .count()
+        };
+        let g = &self.reference.graph.graph;
+        g.neighbors_undirected(r)
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .map(|r2| {
+                let o2 = if r2 == r {
+                    o
+                } else {
+                    match self.mapping[r2.index()] {
+                        Some(o2) => o2,
+                        None => return 0,
+                    }
+                };
+                let back = if r2 == r {
+                    0
+                } else {
+                    common(self.reference.between(r2, r), self.output.between(o2, o))
+                };
+                common(self.reference.between(r, r2), self.output.between(o, o2)) + back
+            })
+            .sum()
+    }
+
+    /// Extends the best matching found by the search, by greedily matching
+    /// the remaining nodes to the nodes sharing the most edges with them.
+    fn extend(&mut self) {
+        self.mapping = self.best.clone();
+        self.used.fill(false);
+        for o in self.mapping.iter().flatten() {
+            self.used[o.index()] = true;
+        }
+        for i in 0..self.order.len() {
+            let r = self.order[i];
+            if self.mapping[r.index()].is_some() {
+                continue;
+            }
+            let best = self
+                .anchored(r)
+                .map(|o| (self.overlap(r, o), o))
+                .filter(|(overlap, _)| *overlap > 0)
+                .max_by_key(|(overlap, o)| (*overlap, std::cmp::Reverse(*o)));
+            if let Some((_, o)) = best {
+                self.mapping[r.index()] = Some(o);
+                self.used[o.index()] = true;
+            }
+        }
+        self.best = self.mapping.clone();
+    }
+
+    /// Whether the edges between `r` and the nodes matched so far are matched
+    /// by those between `o` and the nodes they are matched to.
+    fn consistent(&self, r: NodeIndex, o: NodeIndex) -> bool {
+        let g = &self.reference.graph.graph;
+        g.neighbors_undirected(r).all(|r2| {
+            let o2 = if r2 == r {
+                o
+            } else {
+                match self.mapping[r2.index()] {
+                    Some(o2) => o2,
+                    None => return true,
+                }
+            };
+            self.reference.between(r, r2) == self.output.between(o, o2)
+                && self.reference.between(r2, r) == self.output.between(o2, o)
+        })
+    }
+
+    fn search(&mut self, depth: usize) -> bool {
+        if depth > self.best_depth {
+            self.best_depth = depth;
+            self.best = self.mapping.clone();
+        }
+        if depth == self.order.len() {
+            return true;
+        }
+        if self.steps >= BUDGET {
+            return false;
+        }
+        self.steps += 1;
+
+        let r = self.order[depth];
+        for o in self.candidates(r) {
+            if !self.consistent(r, o) {
+                continue;
+            }
+            self.mapping[r.index()] = Some(o);
+            self.used[o.index()] = true;
+            if self.search(depth + 1) {
+                return true;
+            }
+            self.mapping[r.index()] = None;
+            self.used[o.index()] = false;
+        }
+        false
+    }
+}
+
+/// Matches the nodes of `reference` to those of `output`, where edges match if
+/// they have the same label.
+pub fn match_graphs<L: Ord>(
+    reference: &ParsedGraph,
+    output: &ParsedGraph,
+    label: impl Fn(&gcl::pg::Action) -> L,
+) -> Matching {
+    // Labels are numbered, such that they are cheap to compare
+    let mut numbers = BTreeMap::new();
+    let mut number = |a: &gcl::pg::Action| {
+        let next = numbers.len();
+        *numbers.entry(label(a)).or_insert(next)
+    };
+    let reference = Labelled::new(reference, &mut number);
+    let output = Labelled::new(output, &mut number);
+
+    // The nodes are matched in breadth-first order from the start node, such
+    // that each node is connected to those matched before it
+    let g = &reference.graph.graph;
+    let mut order = vec![];
+    let mut seen = vec![false; g.node_count()];
+    let roots = reference
+        .find(gcl::pg::Node::Start)
+        .into_iter()
+        .chain(g.node_indices());
+    for root in roots {
+        if seen[root.index()] {
+            continue;
+        }
+        seen[root.index()] = true;
+        let mut queue = std::collections::VecDeque::from([root]);
+        while let Some(n) = queue.pop_front() {
+            order.push(n);
+            for m in g.neighbors_undirected(n) {
+                if !seen[m.index()] {
+                    seen[m.index()] = true;
+                    queue.push_back(m);
+                }
+            }
+        }
+    }
+
+    let mut search = Search {
+        mapping: vec![None; g.node_count()],
+        used: vec![false; output.graph.graph.node_count()],
+        best: vec![None; g.node_count()],
+        best_depth: 0,
+        steps: 0,
+        reference,
+        output,
+        order,
+    };
+    if !search.search(0) {
+        search.extend();
+    }
+
+    let nodes: BTreeMap<NodeIndex, NodeIndex> = search
+        .best
+        .iter()
+        .enumerate()
+        .filter_map(|(r, o)| Some((NodeIndex::new(r), (*o)?)))
+        .collect();
+
+    // Edges are matched in the order of the reference graph to the first
+    // unmatched edge of the output between the matched nodes
+    let og = &search.output.graph.graph;
+    let mut matched = vec![false; og.edge_count()];
+    let mut missing = vec![];
+    for e in g.edge_references() {
+        let found = nodes
+            .get(&e.source())
+            .zip(nodes.get(&e.target()))
+            .and_then(|(a, b)| {
+                og.edges_connecting(*a, *b).find(|o| {
+                    !matched[o.id().index()]
+                        && search.output.labels[o.id().index()]
+                            == search.reference.labels[e.id().index()]
+                })
+            });
+        match found {
+            Some(o) => matched[o.id().index()] = true,
+            None => missing.push(e.id()),
+        }
+    }
+    let unmatched = og.edge_indices().filter(|e| !matched[e.index()]).collect();
+
+    Matching {
+        nodes,
+        missing,
+        unmatched,
+    }
+}
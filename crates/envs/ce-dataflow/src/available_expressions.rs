@@ -0,0 +1,202 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use ce_core::{Env, define_env};
+use gcl::{
+    ast::{AExpr, AExprKind, BExpr, BExprKind, Target},
+    pg::{
+        Action, Edge, Node, ProgramGraph,
+        analysis::{Direction, FiFo, MonotoneFramework, Must, mono_analysis},
+    },
+};
+use indexmap::{IndexMap, IndexSet};
+use serde::{Deserialize, Serialize};
+
+use crate::{Input, assigned, validate_nodes};
+
+define_env!(AvailableExpressionsEnv);
+
+#[derive(tapi::Tapi, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[tapi(path = "AvailableExpressions")]
+pub struct Output {
+    pub initial_node: String,
+    pub final_node: String,
+    pub nodes: IndexMap<String, BTreeSet<String>>,
+    pub dot: String,
+}
+
+/// The non-trivial arithmetic expressions evaluated somewhere, written as they
+/// are printed, along with the targets they depend on.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Expressions(BTreeMap<String, IndexSet<Target>>);
+
+impl Expressions {
+    pub(crate) fn of_pg(pg: &ProgramGraph) -> Expressions {
+        let mut exprs = Expressions::default();
+        for e in pg.edges() {
+            exprs.action(e.action());
+        }
+        exprs
+    }
+    pub(crate) fn of_action(action: &Action) -> Expressions {
+        let mut exprs = Expressions::default();
+        exprs.action(action);
+        exprs
+    }
+    pub(crate) fn all(&self) -> BTreeSet<String> {
+        self.0.keys().cloned().collect()
+    }
+    /// Whether the value of the expression may change when `t` is assigned.
+    pub(crate) fn depends_on(&self, expr: &str, t: &Target) -> bool {
+        self.0.get(expr).is_some_and(|fv| fv.contains(t))
+    }
+
+    fn action(&mut self, action: &Action) {
+        match action {
+            Action::Assignment(t, a) => {
+                self.target(t);
+                self.aexpr(a);
+            }
+            Action::BoolAssignment(t, b) => {
+                self.target(t);
+                self.bexpr(b);
+            }
+            Action::ArrayAssignment(_, elems) => {
+                for a in elems {
+                    self.aexpr(a);
+                }
+            }
+            Action::Condition(b) => self.bexpr(b),
+            Action::Skip => {}
+        }
+    }
+    fn target(&mut self, t: &Target<Box<AExpr>>) {
+        if let Target::Array(_, idx) = t {
+            self.aexpr(idx);
+        }
+    }
+    fn aexpr(&mut self, a: &AExpr) {
+        match &a.kind {
+            AExprKind::Number(_) => {}
+            AExprKind::Reference(t) => self.target(t),
+            AExprKind::Binary(l, _, r) => {
+                self.0.insert(a.to_string(), a.fv());
+                self.aexpr(l);
+                self.aexpr(r);
+            }
+            AExprKind::Minus(x) => {
+                // Negative literals are as trivial as any other number
+                if !matches!(x.kind, AExprKind::Number(_)) {
+                    self.0.insert(a.to_string(), a.fv());
+                }
+                self.aexpr(x);
+            }
+            AExprKind::Function(f) => {
+                self.0.insert(a.to_string(), a.fv());
+                for x in f.args() {
+                    self.aexpr(x);
+                }
+            }
+        }
+    }
+    fn bexpr(&mut self, b: &BExpr) {
+        match &b.kind {
+            BExprKind::Bool(_) => {}
+            BExprKind::Reference(t) => self.target(t),
+            BExprKind::Rel(l, _, r) => {
+                self.aexpr(l);
+                self.aexpr(r);
+            }
+            BExprKind::Logic(l, _, r) => {
+                self.bexpr(l);
+                self.bexpr(r);
+            }
+            BExprKind::Not(x) => self.bexpr(x),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AvailableExpressions {
+    expressions: Expressions,
+}
+
+impl MonotoneFramework for AvailableExpressions {
+    type Domain = Must<String>;
+
+    fn semantic(&self, _pg: &ProgramGraph, e: &Edge, prev: &Self::Domain) -> Self::Domain {
+        let mut next = prev.facts(|| self.expressions.all());
+        next.extend(Expressions::of_action(e.action()).all());
+        // Expressions evaluated by an assignment are no longer available if
+        // the assignment changes their value
+        if let Some(t) = assigned(e.action()) {
+            next.retain(|expr| !self.expressions.depends_on(expr, &t));
+        }
+        Must(Some(next))
+    }
+
+    fn direction() -> Direction {
+        Direction::Forward
+    }
+
+    fn initial(&self, _pg: &ProgramGraph) -> Self::Domain {
+        Must(Some(BTreeSet::new()))
+    }
+}
+
+impl Env for AvailableExpressionsEnv {
+    type Input = Input;
+
+    type Output = Output;
+
+    type Meta = ();
+
+    type Annotation = ();
+
+    fn meta(_input: &Self::Input) -> Self::Meta {}
+
+    fn run(input: &Self::Input) -> ce_core::Result<Self::Output> {
+        let pg = input.program_graph()?;
+        let expressions = Expressions::of_pg(&pg);
+        let nodes = mono_analysis::<_, FiFo>(
+            AvailableExpressions {
+                expressions: expressions.clone(),
+            },
+            &pg,
+        )
+        .facts
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.facts(|| expressions.all())))
+        .collect();
+        Ok(Output {
+            initial_node: Node::Start.to_string(),
+            final_node: Node::End.to_string(),
+            nodes,
+            dot: pg.dot(),
+        })
+    }
+
+    fn validate(
+        input: &Self::Input,
+        output: &Self::Output,
+    ) -> ce_core::Result<(ce_core::ValidationResult, ())> {
+        let reference = Self::run(input)?;
+        Ok((validate_nodes(&reference.nodes, &output.nodes), ()))
+    }
+}
+
+#[test]
+fn expressions_must_be_available_on_every_path() {
+    let input = Input {
+        commands: stdx::stringify::Stringify::new(
+            "if x > 0 -> y := x * 2 ; z := x + 1 [] x <= 0 -> y := x * 2 ; z := 0 fi"
+                .parse()
+                .unwrap(),
+        ),
+        determinism: gcl::pg::Determinism::Deterministic,
+    };
+    let output = AvailableExpressionsEnv::run(&input).unwrap();
+    assert_eq!(
+        output.nodes[&output.final_node],
+        BTreeSet::from(["(x * 2)".to_string()])
+    );
+}
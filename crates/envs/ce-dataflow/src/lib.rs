@@ -0,0 +1,114 @@
+//! Environments for the classic bit-vector analyses, which are all instances
+//! of the monotone framework in [`gcl::pg::analysis`]. Each of them finds a
+//! set of facts at every node of the program graph, and answers are checked
+//! against the least fixed point of the analysis.
+
+mod available_expressions;
+mod live_variables;
+mod reaching_definitions;
+mod very_busy_expressions;
+
+use std::collections::BTreeSet;
+
+use ce_core::{
+    Generate, Shrink, ValidationResult,
+    rand::{self, seq::IndexedRandom},
+};
+use gcl::{
+    ast::{Commands, Target},
+    pg::{Action, Determinism, ProgramGraph},
+};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use stdx::stringify::Stringify;
+
+pub use available_expressions::AvailableExpressionsEnv;
+pub use live_variables::LiveVariablesEnv;
+pub use reaching_definitions::{Definition, ReachingDefinitionsEnv};
+pub use very_busy_expressions::VeryBusyExpressionsEnv;
+
+#[derive(tapi::Tapi, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[tapi(path = "Dataflow")]
+pub struct Input {
+    pub commands: Stringify<Commands>,
+    pub determinism: Determinism,
+}
+
+impl Input {
+    fn program_graph(&self) -> ce_core::Result<ProgramGraph> {
+        let commands =
+            self.commands
+                .try_parse()
+                .map_err(ce_core::EnvError::invalid_input_for_program(
+                    "failed to parse commands",
+                ))?;
+        commands.check(None)?;
+        Ok(ProgramGraph::new(self.determinism, &commands))
+    }
+}
+
+/// The variable or array assigned by the action, if any.
+fn assigned(action: &Action) -> Option<Target> {
+    match action {
+        Action::Assignment(t, _) | Action::BoolAssignment(t, _) => Some(match t {
+            Target::Variable(x) => Target::Variable(x.clone()),
+            Target::Array(a, _) => Target::Array(a.clone(), ()),
+        }),
+        Action::ArrayAssignment(a, _) => Some(Target::Array(a.clone(), ())),
+        Action::Skip | Action::Condition(_) => None,
+    }
+}
+
+/// Compares the facts found at every node with those of the reference.
+fn validate_nodes<T: Ord + std::fmt::Display>(
+    reference: &IndexMap<String, BTreeSet<T>>,
+    output: &IndexMap<String, BTreeSet<T>>,
+) -> ValidationResult {
+    for (n, r) in reference {
+        let Some(o) = output.get(n) else {
+            return ValidationResult::Mismatch {
+                reason: format!("No facts were given for {n}"),
+            };
+        };
+        if let Some(missing) = r.difference(o).next() {
+            return ValidationResult::Mismatch {
+                reason: format!("{missing} is missing at {n}"),
+            };
+        }
+        if let Some(extra) = o.difference(r).next() {
+            return ValidationResult::Mismatch {
+                reason: format!("{extra} does not hold at {n}"),
+            };
+        }
+    }
+    ValidationResult::Correct
+}
+
+impl Generate for Input {
+    type Context = ();
+
+    fn gn<R: rand::Rng>(_cx: &mut Self::Context, rng: &mut R) -> Self {
+        let commands = Commands::gn(&mut Default::default(), rng);
+        let determinism = *[Determinism::Deterministic, Determinism::NonDeterministic]
+            .choose(rng)
+            .unwrap();
+
+        Input {
+            commands: Stringify::new(commands),
+            determinism,
+        }
+    }
+}
+
+impl Shrink for Input {
+    fn shrink(&self) -> Vec<Self> {
+        self.commands
+            .shrink()
+            .into_iter()
+            .map(|commands| Input {
+                commands,
+                ..self.clone()
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::BTreeSet;
+
+use ce_core::{Env, define_env};
+use gcl::{
+    ast::Target,
+    pg::{
+        Action, Edge, Node, ProgramGraph,
+        analysis::{Direction, FiFo, MonotoneFramework, mono_analysis},
+    },
+};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{Input, validate_nodes};
+
+define_env!(LiveVariablesEnv);
+
+#[derive(tapi::Tapi, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[tapi(path = "LiveVariables")]
+pub struct Output {
+    pub initial_node: String,
+    pub final_node: String,
+    pub nodes: IndexMap<String, BTreeSet<String>>,
+    pub dot: String,
+}
+
+#[derive(Debug, Clone)]
+struct LiveVariables;
+
+impl MonotoneFramework for LiveVariables {
+    type Domain = BTreeSet<String>;
+
+    fn semantic(&self, _pg: &ProgramGraph, e: &Edge, prev: &Self::Domain) -> Self::Domain {
+        let mut next = prev.clone();
+        let used = match e.action() {
+            Action::Assignment(t, a) => {
+                let mut used = a.fv();
+                match t {
+                    Target::Variable(x) => {
+                        next.remove(&x.to_string());
+                    }
+                    // Assigning to an element does not make the rest of the
+                    // array dead, and only the index is used
+                    Target::Array(_, idx) => used.extend(idx.fv()),
+                }
+                used
+            }
+            Action::BoolAssignment(t, b) => {
+                let mut used = b.fv();
+                match t {
+                    Target::Variable(x) => {
+                        next.remove(&x.to_string());
+                    }
+                    Target::Array(_, idx) => used.extend(idx.fv()),
+                }
+                used
+            }
+            // Assigning the whole array does make it dead
+            Action::ArrayAssignment(arr, elems) => {
+                next.remove(&arr.to_string());
+                elems.iter().flat_map(|a| a.fv()).collect()
+            }
+            Action::Condition(b) => b.fv(),
+            Action::Skip => Default::default(),
+        };
+        next.extend(used.iter().map(|t| t.to_string()));
+        next
+    }
+
+    fn direction() -> Direction {
+        Direction::Backward
+    }
+
+    fn initial(&self, _pg: &ProgramGraph) -> Self::Domain {
+        BTreeSet::new()
+    }
+}
+
+impl Env for LiveVariablesEnv {
+    type Input = Input;
+
+    type Output = Output;
+
+    type Meta = ();
+
+    type Annotation = ();
+
+    fn meta(_input: &Self::Input) -> Self::Meta {}
+
+    fn run(input: &Self::Input) -> ce_core::Result<Self::Output> {
+        let pg = input.program_graph()?;
+        let nodes = mono_analysis::<_, FiFo>(LiveVariables, &pg)
+            .facts
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+        Ok(Output {
+            initial_node: Node::Start.to_string(),
+            final_node: Node::End.to_string(),
+            nodes,
+            dot: pg.dot(),
+        })
+    }
+
+    fn validate(
+        input: &Self::Input,
+        output: &Self::Output,
+    ) -> ce_core::Result<(ce_core::ValidationResult, ())> {
+        let reference = Self::run(input)?;
+        Ok((validate_nodes(&reference.nodes, &output.nodes), ()))
+    }
+}
+
+#[test]
+fn variables_are_dead_after_reassignment_and_live_across_loops() {
+    let input = Input {
+        commands: stdx::stringify::Stringify::new(
+            "y := x ; x := 1 ; do x < 10 -> x := x + n od ; z := x"
+                .parse()
+                .unwrap(),
+        ),
+        determinism: gcl::pg::Determinism::Deterministic,
+    };
+    let output = LiveVariablesEnv::run(&input).unwrap();
+    let live = |node: &str| {
+        output.nodes[node]
+            .iter()
+            .map(|v| v.as_str())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(live(&output.initial_node), ["n", "x"]);
+    // `x` is reassigned before it is used again
+    assert_eq!(live("q1"), ["n"]);
+    // Both are used in the loop, while only `x` is used after it
+    assert_eq!(live("q2"), ["n", "x"]);
+    assert_eq!(live("q3"), ["x"]);
+    assert_eq!(live(&output.final_node), Vec::<&str>::new());
+}
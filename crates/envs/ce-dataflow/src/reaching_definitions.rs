@@ -0,0 +1,138 @@
+use std::collections::BTreeSet;
+
+use ce_core::{Env, define_env};
+use gcl::pg::{
+    Edge, Node, ProgramGraph,
+    analysis::{Direction, FiFo, MonotoneFramework, mono_analysis},
+};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{Input, assigned, validate_nodes};
+
+define_env!(ReachingDefinitionsEnv);
+
+/// An assignment to `target` on the edge from `from` to `to`. The initial
+/// value of a target is written as a definition without a source, `(x, ?, q▷)`.
+#[derive(
+    tapi::Tapi, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[tapi(path = "ReachingDefinitions")]
+pub struct Definition {
+    pub target: String,
+    pub from: Option<String>,
+    pub to: String,
+}
+
+impl std::fmt::Display for Definition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let from = self.from.as_deref().unwrap_or("?");
+        write!(f, "({}, {from}, {})", self.target, self.to)
+    }
+}
+
+#[derive(tapi::Tapi, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[tapi(path = "ReachingDefinitions")]
+pub struct Output {
+    pub initial_node: String,
+    pub final_node: String,
+    pub nodes: IndexMap<String, BTreeSet<Definition>>,
+    pub dot: String,
+}
+
+#[derive(Debug, Clone)]
+struct ReachingDefinitions;
+
+impl MonotoneFramework for ReachingDefinitions {
+    type Domain = BTreeSet<Definition>;
+
+    fn semantic(&self, _pg: &ProgramGraph, e: &Edge, prev: &Self::Domain) -> Self::Domain {
+        let Some(t) = assigned(e.action()) else {
+            return prev.clone();
+        };
+        let target = t.to_string();
+        let mut next = prev.clone();
+        // Assigning to an array element leaves the other elements untouched,
+        // so only assignments to variables kill earlier definitions
+        if matches!(t, gcl::ast::Target::Variable(_)) {
+            next.retain(|d| d.target != target);
+        }
+        next.insert(Definition {
+            target,
+            from: Some(e.from().to_string()),
+            to: e.to().to_string(),
+        });
+        next
+    }
+
+    fn direction() -> Direction {
+        Direction::Forward
+    }
+
+    fn initial(&self, pg: &ProgramGraph) -> Self::Domain {
+        pg.fv()
+            .into_iter()
+            .map(|t| Definition {
+                target: t.to_string(),
+                from: None,
+                to: Node::Start.to_string(),
+            })
+            .collect()
+    }
+}
+
+impl Env for ReachingDefinitionsEnv {
+    type Input = Input;
+
+    type Output = Output;
+
+    type Meta = ();
+
+    type Annotation = ();
+
+    fn meta(_input: &Self::Input) -> Self::Meta {}
+
+    fn run(input: &Self::Input) -> ce_core::Result<Self::Output> {
+        let pg = input.program_graph()?;
+        let nodes = mono_analysis::<_, FiFo>(ReachingDefinitions, &pg)
+            .facts
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+        Ok(Output {
+            initial_node: Node::Start.to_string(),
+            final_node: Node::End.to_string(),
+            nodes,
+            dot: pg.dot(),
+        })
+    }
+
+    fn validate(
+        input: &Self::Input,
+        output: &Self::Output,
+    ) -> ce_core::Result<(ce_core::ValidationResult, ())> {
+        let reference = Self::run(input)?;
+        Ok((validate_nodes(&reference.nodes, &output.nodes), ()))
+    }
+}
+
+#[test]
+fn assignments_kill_earlier_definitions() {
+    let input = Input {
+        commands: stdx::stringify::Stringify::new(
+            "x := 1 ; do x < 10 -> x := x + 1 ; A[x] := x od"
+                .parse()
+                .unwrap(),
+        ),
+        determinism: gcl::pg::Determinism::Deterministic,
+    };
+    let output = ReachingDefinitionsEnv::run(&input).unwrap();
+    let end = output.nodes[&output.final_node]
+        .iter()
+        .map(|d| d.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        end,
+        ["(A, ?, q▷)", "(A, q3, q1)", "(x, q2, q3)", "(x, q▷, q1)"]
+    );
+}
@@ -0,0 +1,108 @@
+use std::collections::BTreeSet;
+
+use ce_core::{Env, define_env};
+use gcl::pg::{
+    Edge, Node, ProgramGraph,
+    analysis::{Direction, FiFo, MonotoneFramework, Must, mono_analysis},
+};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{Input, assigned, available_expressions::Expressions, validate_nodes};
+
+define_env!(VeryBusyExpressionsEnv);
+
+#[derive(tapi::Tapi, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[tapi(path = "VeryBusyExpressions")]
+pub struct Output {
+    pub initial_node: String,
+    pub final_node: String,
+    pub nodes: IndexMap<String, BTreeSet<String>>,
+    pub dot: String,
+}
+
+#[derive(Debug, Clone)]
+struct VeryBusyExpressions {
+    expressions: Expressions,
+}
+
+impl MonotoneFramework for VeryBusyExpressions {
+    type Domain = Must<String>;
+
+    fn semantic(&self, _pg: &ProgramGraph, e: &Edge, prev: &Self::Domain) -> Self::Domain {
+        let mut next = prev.facts(|| self.expressions.all());
+        if let Some(t) = assigned(e.action()) {
+            next.retain(|expr| !self.expressions.depends_on(expr, &t));
+        }
+        // The expressions of the action are evaluated before the assignment
+        // takes place, so they are busy regardless of what it assigns
+        next.extend(Expressions::of_action(e.action()).all());
+        Must(Some(next))
+    }
+
+    fn direction() -> Direction {
+        Direction::Backward
+    }
+
+    fn initial(&self, _pg: &ProgramGraph) -> Self::Domain {
+        Must(Some(BTreeSet::new()))
+    }
+}
+
+impl Env for VeryBusyExpressionsEnv {
+    type Input = Input;
+
+    type Output = Output;
+
+    type Meta = ();
+
+    type Annotation = ();
+
+    fn meta(_input: &Self::Input) -> Self::Meta {}
+
+    fn run(input: &Self::Input) -> ce_core::Result<Self::Output> {
+        let pg = input.program_graph()?;
+        let expressions = Expressions::of_pg(&pg);
+        let nodes = mono_analysis::<_, FiFo>(
+            VeryBusyExpressions {
+                expressions: expressions.clone(),
+            },
+            &pg,
+        )
+        .facts
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.facts(|| expressions.all())))
+        .collect();
+        Ok(Output {
+            initial_node: Node::Start.to_string(),
+            final_node: Node::End.to_string(),
+            nodes,
+            dot: pg.dot(),
+        })
+    }
+
+    fn validate(
+        input: &Self::Input,
+        output: &Self::Output,
+    ) -> ce_core::Result<(ce_core::ValidationResult, ())> {
+        let reference = Self::run(input)?;
+        Ok((validate_nodes(&reference.nodes, &output.nodes), ()))
+    }
+}
+
+#[test]
+fn expressions_must_be_used_on_every_path() {
+    let input = Input {
+        commands: stdx::stringify::Stringify::new(
+            "if a > b -> x := b - a ; y := a - b [] a <= b -> y := b - a ; a := 0 ; x := a - b fi"
+                .parse()
+                .unwrap(),
+        ),
+        determinism: gcl::pg::Determinism::Deterministic,
+    };
+    let output = VeryBusyExpressionsEnv::run(&input).unwrap();
+    assert_eq!(
+        output.nodes[&output.initial_node],
+        BTreeSet::from(["(b - a)".to_string()])
+    );
+}
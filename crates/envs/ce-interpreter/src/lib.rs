@@ -4,14 +4,17 @@ mod tests;
 use std::collections::BTreeSet;
 
 use ce_core::{
-    Env, Generate, ValidationResult, define_env,
+    Coverage, Env, Generate, Shrink, ValidationResult, define_env,
+    gn::GclGenContext,
     rand::{self, seq::IndexedRandom},
 };
 use gcl::{
-    ast::{Commands, Int, TargetDef},
-    interpreter::{Execution, InterpreterMemory, Step, TerminationState},
-    pg::{Determinism, Node},
+    ast::{Commands, Int, Target, TargetDef},
+    interpreter::{Execution, FailedAssertion, InterpreterMemory, Step, TerminationState},
+    pg::{Action, Determinism, Node},
+    reachability::StateSpace,
 };
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use stdx::stringify::Stringify;
 
@@ -34,6 +37,8 @@ pub struct Output {
     pub dot: String,
     pub trace: Vec<Step>,
     pub termination: TerminationState,
+    #[serde(default)]
+    pub assertion: Option<FailedAssertion>,
 }
 
 impl Env for InterpreterEnv {
@@ -47,20 +52,22 @@ impl Env for InterpreterEnv {
 
     fn meta(input: &Self::Input) -> Self::Meta {
         if let Ok(commands) = input.commands.try_parse() {
-            commands.fv().into_iter().map(|t| t.def()).collect()
+            commands.target_defs()
         } else {
             Default::default()
         }
     }
 
     fn run(input: &Self::Input) -> ce_core::Result<Self::Output> {
-        let pg =
-            gcl::pg::ProgramGraph::new(
-                input.determinism,
-                &input.commands.try_parse().map_err(
-                    ce_core::EnvError::invalid_input_for_program("failed to parse commands"),
-                )?,
-            );
+        let commands =
+            input
+                .commands
+                .try_parse()
+                .map_err(ce_core::EnvError::invalid_input_for_program(
+                    "failed to parse commands",
+                ))?;
+        commands.check(Some(&input.assignment.target_defs()))?;
+        let pg = gcl::pg::ProgramGraph::new(input.determinism, &commands);
 
         let mut exe = Execution::new(input.assignment.clone());
 
@@ -81,8 +88,18 @@ impl Env for InterpreterEnv {
             initial_node: Node::Start.to_string(),
             final_node: Node::End.to_string(),
             dot: pg.dot(),
-            trace: exe.trace().iter().map(|(s, _)| s.clone()).collect(),
+            // The parameters of procedures are not part of the memory of the
+            // program
+            trace: exe
+                .trace()
+                .iter()
+                .map(|(s, _)| Step {
+                    memory: s.memory.without_locals(),
+                    ..s.clone()
+                })
+                .collect(),
             termination: exe.state(&pg),
+            assertion: exe.failed_assertion(&pg, &input.commands.to_string()),
         })
     }
 
@@ -108,31 +125,59 @@ impl Env for InterpreterEnv {
                     ce_core::EnvError::invalid_input_for_program("failed to parse commands"),
                 )?,
             );
-        let mut possible_executions = vec![Execution::new(input.assignment.clone())];
+        // The configurations the executions matching the trace so far can be
+        // in, where executions reaching the same configuration are only
+        // followed once
+        let mut space = StateSpace::new(&pg, input.assignment.clone());
+        let mut possible = BTreeSet::from([StateSpace::INITIAL]);
 
-        for step in &output.trace {
-            possible_executions = possible_executions
-                .iter()
-                .flat_map(|exe| exe.nexts(&pg))
-                .filter(|exe| exe.current_mem() == &step.memory)
-                .collect();
+        for (idx, step) in output.trace.iter().enumerate() {
+            let mut next = BTreeSet::new();
+            for id in &possible {
+                let tos = space.successors(*id).iter().map(|t| t.to).collect_vec();
+                next.extend(tos.into_iter().filter(|to| {
+                    space.configuration(*to).memory.without_locals() == step.memory.without_locals()
+                }));
+            }
 
-            if possible_executions.is_empty() {
-                return Ok((
-                    ValidationResult::Mismatch {
-                        reason: "No possible execution found".to_string(),
-                    },
-                    (),
-                ));
+            if next.is_empty() {
+                let src = input.commands.to_string();
+                let locations = possible
+                    .iter()
+                    .flat_map(|id| pg.outgoing(space.configuration(*id).node))
+                    .filter_map(|edge| pg.span(edge))
+                    .map(|span| {
+                        let (line, col) = span.line_col(&src);
+                        format!("{line}:{col}")
+                    })
+                    .unique()
+                    .join(", ");
+                let reason = if locations.is_empty() {
+                    format!("No possible execution found for step {}", idx + 1)
+                } else {
+                    format!(
+                        "No possible execution found for step {}. None of the actions at {locations} produce the given memory",
+                        idx + 1
+                    )
+                };
+                return Ok((ValidationResult::Mismatch { reason }, ()));
             }
+
+            possible = next;
         }
 
-        if output.termination == TerminationState::Running && !possible_executions.is_empty() {
+        let at = |node: Node| {
+            possible
+                .iter()
+                .any(|id| space.configuration(*id).node == node)
+        };
+
+        if output.termination == TerminationState::Running && !possible.is_empty() {
             return Ok((ValidationResult::Correct, ()));
         }
 
         if output.termination == TerminationState::Terminated {
-            if possible_executions.iter().any(|s| s.is_finished()) {
+            if at(Node::End) {
                 return Ok((ValidationResult::Correct, ()));
             }
             return Ok((
@@ -143,6 +188,18 @@ impl Env for InterpreterEnv {
             ));
         }
 
+        if output.termination == TerminationState::AssertionFailed {
+            if at(Node::Error) {
+                return Ok((ValidationResult::Correct, ()));
+            }
+            return Ok((
+                ValidationResult::Mismatch {
+                    reason: "No execution failed an assertion".to_string(),
+                },
+                (),
+            ));
+        }
+
         if output.trace.len() < input.trace_length as usize
             || output.termination == TerminationState::Stuck
         {
@@ -155,7 +212,7 @@ impl Env for InterpreterEnv {
                 ));
             }
 
-            if !possible_executions.iter().any(|exe| exe.is_stuck(&pg)) {
+            if !possible.iter().any(|id| space.successors(*id).is_empty()) {
                 return Ok((
                     ValidationResult::Mismatch {
                         reason: "No stuck execution found".to_string(),
@@ -171,25 +228,62 @@ impl Env for InterpreterEnv {
 
         Ok((ValidationResult::Correct, ()))
     }
+
+    /// The kinds of actions taken and how the execution ended, along with the
+    /// share of the edges of the program graph taken, in quarters.
+    fn coverage(input: &Self::Input, output: &Self::Output) -> Coverage {
+        let mut coverage: Coverage = output
+            .trace
+            .iter()
+            .filter_map(|step| step.action.try_parse().ok())
+            .map(|action| match action {
+                Action::Assignment(Target::Variable(_), _) => "assignment",
+                Action::Assignment(Target::Array(_, _), _) => "array element assignment",
+                Action::BoolAssignment(_, _) => "bool assignment",
+                Action::ArrayAssignment(_, _) => "array assignment",
+                Action::Skip => "skip",
+                Action::Condition(_) => "condition",
+            })
+            .collect();
+        coverage.insert(format!("{:?}", output.termination));
+
+        if let Ok(commands) = input.commands.try_parse() {
+            let pg = gcl::pg::ProgramGraph::new(input.determinism, &commands);
+            let taken = std::iter::once(output.initial_node.as_str())
+                .chain(output.trace.iter().map(|step| step.node.as_str()))
+                .tuple_windows::<(_, _)>()
+                .unique()
+                .count();
+            let quarters = (4 * taken).div_ceil(pg.edges().len().max(1)).min(4);
+            coverage.insert(format!("{quarters}/4 of edges taken"));
+        }
+
+        coverage
+    }
 }
 
 impl Generate for Input {
     type Context = ();
 
     fn gn<R: rand::Rng>(_cx: &mut Self::Context, mut rng: &mut R) -> Self {
-        let commands = gcl::ast::Commands::gn(&mut Default::default(), rng);
+        let mut cx = GclGenContext {
+            arrays: true,
+            ..Default::default()
+        };
+        let commands = gcl::ast::Commands::gn(&mut cx, rng);
         let initial_memory = gcl::memory::Memory::from_targets_with(
             commands.fv(),
             &mut rng,
             |rng, _| rng.random_range(-10..=10),
             |rng, _| {
-                let len = rng.random_range(5..=10);
+                let len = cx.array_length(rng);
                 (0..len).map(|_| rng.random_range(-10..=10)).collect()
             },
         );
         let assignment = InterpreterMemory {
             variables: initial_memory.variables,
             arrays: initial_memory.arrays,
+            ..Default::default()
         };
 
         let determinism = *[Determinism::Deterministic, Determinism::NonDeterministic]
@@ -204,3 +298,29 @@ impl Generate for Input {
         }
     }
 }
+
+impl Shrink for Input {
+    fn shrink(&self) -> Vec<Self> {
+        itertools::chain!(
+            self.commands.shrink().into_iter().map(|commands| Input {
+                commands,
+                ..self.clone()
+            }),
+            self.assignment
+                .shrink()
+                .into_iter()
+                .map(|assignment| Input {
+                    assignment,
+                    ..self.clone()
+                }),
+            self.trace_length
+                .shrink()
+                .into_iter()
+                .map(|trace_length| Input {
+                    trace_length,
+                    ..self.clone()
+                }),
+        )
+        .collect()
+    }
+}
@@ -1,9 +1,13 @@
-use ce_core::{Env, ValidationResult};
+use std::collections::BTreeSet;
+
+use ce_core::{Env, EnvError, ValidationResult};
 use gcl::{
-    ast::Variable,
-    interpreter::TerminationState,
-    pg::{Determinism, Node},
+    ast::{Array, Commands, Target, Variable},
+    interpreter::{Execution, Failure, TerminationState},
+    pg::{Determinism, Node, ProgramGraph},
+    procedures::MAX_CALL_DEPTH,
 };
+use itertools::Itertools;
 use stdx::stringify::Stringify;
 
 use crate::{Input, InterpreterEnv, InterpreterMemory, Output};
@@ -46,7 +50,7 @@ fn test_true_skip() {
             ]
             .into_iter()
             .collect(),
-            arrays: Default::default(),
+            ..Default::default()
         },
         trace_length: 11,
     };
@@ -83,7 +87,7 @@ fn test_thingy() {
             ]
             .into_iter()
             .collect(),
-            arrays: Default::default(),
+            ..Default::default()
         },
         trace_length: 11,
     };
@@ -110,7 +114,7 @@ fn empty_trace_running() {
         determinism: Determinism::Deterministic,
         assignment: InterpreterMemory {
             variables: [(Variable("x".to_string()), 0)].into_iter().collect(),
-            arrays: Default::default(),
+            ..Default::default()
         },
         trace_length: 1,
     };
@@ -120,6 +124,7 @@ fn empty_trace_running() {
         dot: "".to_string(),
         trace: Vec::new(),
         termination: TerminationState::Running,
+        assertion: None,
     };
 
     assert_eq!(
@@ -144,7 +149,7 @@ fn empty_trace_terminated() {
         determinism: Determinism::Deterministic,
         assignment: InterpreterMemory {
             variables: [(Variable("x".to_string()), 0)].into_iter().collect(),
-            arrays: Default::default(),
+            ..Default::default()
         },
         trace_length: 1,
     };
@@ -154,6 +159,7 @@ fn empty_trace_terminated() {
         dot: "".to_string(),
         trace: Vec::new(),
         termination: TerminationState::Terminated,
+        assertion: None,
     };
 
     assert_eq!(
@@ -166,3 +172,408 @@ fn empty_trace_terminated() {
 
 #[test]
 fn mutation_of_valid_trace() {}
+
+#[test]
+fn mismatch_points_at_source() {
+    let input = Input {
+        commands: Stringify::Unparsed("x := 1;\ny := 2".to_string()),
+        determinism: Determinism::Deterministic,
+        assignment: InterpreterMemory {
+            variables: [
+                (Variable("x".to_string()), 0),
+                (Variable("y".to_string()), 0),
+            ]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        },
+        trace_length: 10,
+    };
+    let mut output = InterpreterEnv::run(&input).unwrap();
+    output.trace[1]
+        .memory
+        .variables
+        .insert(Variable("y".to_string()), 3);
+    match InterpreterEnv::validate(&input, &output).unwrap().0 {
+        ValidationResult::Mismatch { reason } => assert!(reason.contains("2:1"), "{reason}"),
+        ValidationResult::Correct
+        | ValidationResult::Unknown { .. }
+        | ValidationResult::TimeOut => {
+            panic!()
+        }
+    }
+}
+
+#[test]
+fn recursive_procedure() {
+    let src = r#"
+        proc fact(in n, out r) {
+            if n <= 1 -> r := 1
+            [] n > 1 -> fact(n - 1, r); r := r * n
+            fi
+        };
+        n := 0;
+        fact(x, y)
+    "#;
+    let commands: Commands = src.parse().unwrap();
+    // The parameters of `fact` are local, and do not clash with `n`
+    assert_eq!(
+        commands.fv().into_iter().sorted().collect_vec(),
+        ["n", "x", "y"].map(|v| Target::Variable(Variable(v.to_string())))
+    );
+    let run = |x: i32| {
+        let input = Input {
+            commands: Stringify::Unparsed(src.to_string()),
+            determinism: Determinism::Deterministic,
+            assignment: InterpreterMemory {
+                variables: [("n", 7), ("x", x), ("y", 0)]
+                    .into_iter()
+                    .map(|(v, n)| (Variable(v.to_string()), n))
+                    .collect(),
+                ..Default::default()
+            },
+            trace_length: 1000,
+        };
+        let output = InterpreterEnv::run(&input).unwrap();
+        match InterpreterEnv::validate(&input, &output).unwrap().0 {
+            ValidationResult::Correct => (),
+            ValidationResult::Mismatch { reason } => panic!("reason: {reason:?}"),
+            ValidationResult::Unknown { .. } | ValidationResult::TimeOut => panic!(),
+        }
+        output
+    };
+
+    let output = run(5);
+    assert_eq!(output.termination, TerminationState::Terminated);
+    let last = output.trace.last().unwrap();
+    let expected = [("n", 0), ("x", 5), ("y", 120)]
+        .into_iter()
+        .map(|(v, n)| (Variable(v.to_string()), n))
+        .collect();
+    assert_eq!(last.memory.variables, expected);
+
+    // Recursing deeper than the calls are inlined fails at the call
+    let output = run(MAX_CALL_DEPTH as i32 + 1);
+    assert_eq!(output.termination, TerminationState::AssertionFailed);
+    let assertion = output.assertion.unwrap();
+    assert_eq!(assertion.failure, Failure::CallDepthExceeded);
+    assert!(assertion.message.contains("interpreter's limit"));
+    assert_eq!(assertion.command, "fact(n - 1, r)");
+    assert_eq!(assertion.location, Some((4, 25)));
+}
+
+#[test]
+fn boolean_variables() {
+    let input = Input {
+        commands: Stringify::Unparsed(
+            r#"
+            found := false;
+            i := 0;
+            do !found && i < 3 ->
+                found := A[i] = x;
+                seen[i] := found;
+                i := i + 1
+            od;
+            done := found
+            "#
+            .to_string(),
+        ),
+        determinism: Determinism::Deterministic,
+        assignment: InterpreterMemory {
+            variables: [("i", 0), ("x", 7)]
+                .into_iter()
+                .map(|(v, n)| (Variable(v.to_string()), n))
+                .collect(),
+            arrays: [(Array("A".to_string()), vec![3, 7, 9])]
+                .into_iter()
+                .collect(),
+            bools: [("found", true), ("done", false)]
+                .into_iter()
+                .map(|(v, b)| (Variable(v.to_string()), b))
+                .collect(),
+            bool_arrays: [(Array("seen".to_string()), vec![false; 3])]
+                .into_iter()
+                .collect(),
+        },
+        trace_length: 100,
+    };
+    let output = InterpreterEnv::run(&input).unwrap();
+    assert_eq!(output.termination, TerminationState::Terminated);
+    let last = &output.trace.last().unwrap().memory;
+    assert!(last.bools[&Variable("done".to_string())]);
+    assert_eq!(
+        last.bool_arrays[&Array("seen".to_string())],
+        vec![false, true, false]
+    );
+    match InterpreterEnv::validate(&input, &output).unwrap().0 {
+        ValidationResult::Correct => (),
+        ValidationResult::Mismatch { reason } => panic!("reason: {reason:?}"),
+        ValidationResult::Unknown { .. } | ValidationResult::TimeOut => panic!(),
+    }
+}
+
+#[test]
+fn array_functions_and_literals() {
+    let input = Input {
+        commands: Stringify::Unparsed(
+            r#"
+            A := [4, -2, 4, x];
+            n := len(A);
+            c := count(A, 4);
+            lo := min(A[1], x);
+            hi := max(A[0], x)
+            "#
+            .to_string(),
+        ),
+        determinism: Determinism::Deterministic,
+        assignment: InterpreterMemory {
+            variables: [("x", 4), ("n", 0), ("c", 0), ("lo", 0), ("hi", 0)]
+                .into_iter()
+                .map(|(v, n)| (Variable(v.to_string()), n))
+                .collect(),
+            arrays: [(Array("A".to_string()), vec![0])].into_iter().collect(),
+            ..Default::default()
+        },
+        trace_length: 100,
+    };
+    let output = InterpreterEnv::run(&input).unwrap();
+    assert_eq!(output.termination, TerminationState::Terminated);
+    let last = &output.trace.last().unwrap().memory;
+    assert_eq!(last.arrays[&Array("A".to_string())], vec![4, -2, 4, 4]);
+    let value = |v: &str| last.variables[&Variable(v.to_string())];
+    assert_eq!(
+        [value("n"), value("c"), value("lo"), value("hi")],
+        [4, 3, -2, 4]
+    );
+    match InterpreterEnv::validate(&input, &output).unwrap().0 {
+        ValidationResult::Correct => (),
+        ValidationResult::Mismatch { reason } => panic!("reason: {reason:?}"),
+        ValidationResult::Unknown { .. } | ValidationResult::TimeOut => panic!(),
+    }
+}
+
+#[test]
+fn failing_inputs_are_minimized() {
+    let input = Input {
+        commands: Stringify::Unparsed(
+            "y := 2; x := y * 3; if x > 4 -> z := x [] x <= 4 -> z := 0 fi; y := 5".to_string(),
+        ),
+        determinism: Determinism::Deterministic,
+        assignment: InterpreterMemory {
+            variables: [("x", 7), ("y", -3), ("z", 0)]
+                .into_iter()
+                .map(|(v, n)| (Variable(v.to_string()), n))
+                .collect(),
+            ..Default::default()
+        },
+        trace_length: 10,
+    };
+    // Stands in for an implementation which gets `z` wrong whenever it ends
+    // up positive
+    let fails = |input: &Input| {
+        InterpreterEnv::run(input).is_ok_and(|output| {
+            output.trace.last().is_some_and(|step| {
+                step.memory
+                    .variables
+                    .get(&Variable("z".to_string()))
+                    .is_some_and(|z| *z > 0)
+            })
+        })
+    };
+    assert!(fails(&input));
+    let minimized = ce_core::shrink::minimize(input, 1000, fails);
+    assert_eq!(minimized.commands.to_string(), "z := x");
+    assert_eq!(minimized.trace_length, 1);
+    let value = |v: &str| minimized.assignment.variables[&Variable(v.to_string())];
+    assert_eq!([value("x"), value("y"), value("z")], [1, 0, 0]);
+}
+
+#[test]
+fn coverage_of_execution() {
+    let input = Input {
+        commands: Stringify::Unparsed("x := 1; if x > 0 -> skip fi".to_string()),
+        determinism: Determinism::Deterministic,
+        assignment: InterpreterMemory {
+            variables: [(Variable("x".to_string()), 0)].into_iter().collect(),
+            ..Default::default()
+        },
+        trace_length: 10,
+    };
+    let coverage = ce_core::coverage::reference_coverage::<InterpreterEnv>(&input);
+    assert_eq!(
+        coverage.iter().collect::<BTreeSet<_>>(),
+        BTreeSet::from([
+            "4/4 of edges taken",
+            "Terminated",
+            "assignment",
+            "condition",
+            "skip"
+        ])
+    );
+}
+
+#[test]
+fn guided_generation_accumulates_coverage() {
+    use ce_core::rand::{SeedableRng, rngs::SmallRng};
+
+    let mut rng = SmallRng::seed_from_u64(25);
+    let mut seen = ce_core::Coverage::default();
+    let mut covered = ce_core::Coverage::default();
+    for _ in 0..10 {
+        let input = ce_core::coverage::gn_guided::<InterpreterEnv, _>(&mut seen, &mut rng);
+        covered.extend(ce_core::coverage::reference_coverage::<InterpreterEnv>(
+            &input,
+        ));
+    }
+    assert!(!seen.is_empty());
+    assert_eq!(seen, covered);
+}
+
+#[test]
+fn ill_typed_programs_are_rejected() {
+    for src in [
+        "x := true + 1",
+        "b := true; x := b + 1",
+        "if x -> x := 1 fi",
+    ] {
+        let input = Input {
+            commands: Stringify::Unparsed(src.to_string()),
+            determinism: Determinism::Deterministic,
+            assignment: Default::default(),
+            trace_length: 1,
+        };
+        assert!(InterpreterEnv::run(&input).is_err(), "accepted `{src}`");
+    }
+}
+
+#[test]
+fn inputs_are_checked_against_program() {
+    let run = |src: &str, assignment: InterpreterMemory| {
+        let input = Input {
+            commands: Stringify::Unparsed(src.to_string()),
+            determinism: Determinism::Deterministic,
+            assignment,
+            trace_length: 10,
+        };
+        match InterpreterEnv::run(&input) {
+            Err(EnvError::InvalidInputForProgram { message, .. }) => message,
+            res => panic!("expected invalid input for `{src}`, got {res:?}"),
+        }
+    };
+    let x = |value| InterpreterMemory {
+        variables: [(Variable("x".to_string()), value)].into_iter().collect(),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        run("x := y + 1", x(0)),
+        "'y' is used as an integer variable, but is not in the given input"
+    );
+    assert_eq!(
+        run("x := A[0]", x(0)),
+        "'A' is used as an integer array, but is not in the given input"
+    );
+    assert_eq!(
+        run("x[0] := 1", x(0)),
+        "'x' is used as an integer array, but is given as an integer variable in the input"
+    );
+
+    // Programs which are ill-formed regardless of the input fail the check
+    let commands: Commands = "x := 1; x[0] := 2".parse().unwrap();
+    let err = commands.check(None).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("'x' is used both as a variable and as an array"),
+        "{err}"
+    );
+}
+
+#[test]
+fn par_explores_all_interleavings() {
+    let commands: Commands = "par x := x + 1 ; y := x [] x := x * 2 rap".parse().unwrap();
+    let pg = ProgramGraph::new(Determinism::Deterministic, &commands);
+    let memory = InterpreterMemory {
+        variables: [
+            (Variable("x".to_string()), 1),
+            (Variable("y".to_string()), 0),
+        ]
+        .into_iter()
+        .collect(),
+        ..Default::default()
+    };
+
+    let outcomes: BTreeSet<_> = Execution::new(memory.clone())
+        .explore(&pg, 10)
+        .iter()
+        .map(|exe| {
+            assert_eq!(exe.state(&pg), TerminationState::Terminated);
+            let vars = &exe.current_mem().variables;
+            (
+                vars[&Variable("x".to_string())],
+                vars[&Variable("y".to_string())],
+            )
+        })
+        .collect();
+    assert_eq!(outcomes, [(3, 3), (4, 2), (4, 4)].into_iter().collect());
+
+    // Every interleaving is accepted as a valid trace
+    for choice in 0..3 {
+        let exe = Execution::new(memory.clone()).run(&pg, 10, |_| choice);
+        let input = Input {
+            commands: Stringify::Parsed(commands.clone()),
+            determinism: Determinism::Deterministic,
+            assignment: memory.clone(),
+            trace_length: 10,
+        };
+        let output = Output {
+            initial_node: Node::Start.to_string(),
+            final_node: Node::End.to_string(),
+            dot: pg.dot(),
+            trace: exe.trace().iter().map(|(step, _)| step.clone()).collect(),
+            termination: exe.state(&pg),
+            assertion: None,
+        };
+        match InterpreterEnv::validate(&input, &output).unwrap().0 {
+            ValidationResult::Correct => (),
+            ValidationResult::Mismatch { reason } => panic!("reason: {reason:?}"),
+            ValidationResult::Unknown { .. } | ValidationResult::TimeOut => panic!(),
+        }
+    }
+}
+
+#[test]
+fn failed_assertion_is_reported() {
+    let input = Input {
+        commands: Stringify::Unparsed(
+            "x := 1 ;\ndo x < 4 -> x := x + 1 ;\n   assert x != 3\nod".to_string(),
+        ),
+        determinism: Determinism::Deterministic,
+        assignment: InterpreterMemory {
+            variables: [(Variable("x".to_string()), 0)].into_iter().collect(),
+            ..Default::default()
+        },
+        trace_length: 20,
+    };
+    let output = InterpreterEnv::run(&input).unwrap();
+    assert_eq!(output.termination, TerminationState::AssertionFailed);
+    let assertion = output.assertion.clone().unwrap();
+    assert_eq!(assertion.step, 7);
+    assert_eq!(assertion.command, "assert x != 3");
+    assert_eq!(assertion.location, Some((3, 4)));
+    match InterpreterEnv::validate(&input, &output).unwrap().0 {
+        ValidationResult::Correct => (),
+        ValidationResult::Mismatch { reason } => panic!("reason: {reason:?}"),
+        ValidationResult::Unknown { .. } | ValidationResult::TimeOut => panic!(),
+    }
+
+    // Claiming that the program terminated is rejected
+    let output = Output {
+        termination: TerminationState::Terminated,
+        assertion: None,
+        ..output
+    };
+    match InterpreterEnv::validate(&input, &output).unwrap().0 {
+        ValidationResult::Mismatch { .. } => (),
+        res => panic!("expected a mismatch, got {res:?}"),
+    }
+}
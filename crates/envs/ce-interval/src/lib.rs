@@ -0,0 +1,205 @@
+#![allow(non_snake_case)]
+
+mod semantics;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use ce_core::{
+    Env, Generate, Shrink, ValidationResult, define_env,
+    rand::{self, seq::IndexedRandom},
+};
+use gcl::{
+    ast::{Commands, TargetDef},
+    memory::Memory,
+    pg::{
+        Determinism, Node, ProgramGraph,
+        analysis::{
+            FiFo, Lattice, MonotoneFramework, WorklistKind, mono_analysis, mono_analysis_with,
+            semantic_calls,
+        },
+    },
+};
+use indexmap::IndexMap;
+pub use semantics::{Interval, IntervalAnalysis, IntervalFacts, IntervalMemory};
+use serde::{Deserialize, Serialize};
+use stdx::stringify::Stringify;
+
+define_env!(IntervalEnv);
+
+#[derive(tapi::Tapi, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[tapi(path = "IntervalAnalysis")]
+pub struct Input {
+    pub commands: Stringify<Commands>,
+    pub determinism: Determinism,
+    pub assignment: IntervalMemory,
+}
+
+#[derive(tapi::Tapi, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[tapi(path = "IntervalAnalysis")]
+pub struct Output {
+    pub initial_node: String,
+    pub final_node: String,
+    /// The possible values at each node, or `None` if it is unreachable
+    pub nodes: IndexMap<String, Option<IntervalMemory>>,
+    pub dot: String,
+    /// The work needed to reach the solution with each worklist strategy
+    #[serde(default)]
+    pub semantic_calls: BTreeMap<WorklistKind, usize>,
+}
+
+fn nodes(facts: IndexMap<Node, IntervalFacts>) -> IndexMap<String, Option<IntervalMemory>> {
+    facts
+        .into_iter()
+        .map(|(k, v)| (format!("{k}"), v.0))
+        .collect()
+}
+
+impl Env for IntervalEnv {
+    type Input = Input;
+
+    type Output = Output;
+
+    type Meta = BTreeSet<TargetDef>;
+
+    type Annotation = ();
+
+    fn meta(input: &Self::Input) -> Self::Meta {
+        if let Ok(commands) = input.commands.try_parse() {
+            commands.target_defs()
+        } else {
+            Default::default()
+        }
+    }
+
+    fn run(input: &Self::Input) -> ce_core::Result<Self::Output> {
+        let commands =
+            input
+                .commands
+                .try_parse()
+                .map_err(ce_core::EnvError::invalid_input_for_program(
+                    "failed to parse commands",
+                ))?;
+        commands.check(Some(&input.assignment.target_defs()))?;
+        let pg = ProgramGraph::new(input.determinism, &commands);
+
+        let analysis = IntervalAnalysis {
+            assignment: input.assignment.clone(),
+        };
+        let semantic_calls = semantic_calls(&analysis, &pg);
+        let nodes = nodes(mono_analysis::<_, FiFo>(analysis, &pg).facts);
+        Ok(Output {
+            initial_node: Node::Start.to_string(),
+            final_node: Node::End.to_string(),
+            nodes,
+            dot: pg.dot(),
+            semantic_calls,
+        })
+    }
+
+    fn validate(
+        input: &Self::Input,
+        output: &Self::Output,
+    ) -> ce_core::Result<(ce_core::ValidationResult, ())> {
+        let reference = Self::run(input)?;
+
+        // Widening makes the result depend on the order in which nodes are
+        // visited, so the result of any of the worklists is accepted
+        let commands = input.commands.try_parse().expect("parsed in run");
+        let pg = ProgramGraph::new(input.determinism, &commands);
+        let analysis = IntervalAnalysis {
+            assignment: input.assignment.clone(),
+        };
+        if WorklistKind::ALL.into_iter().any(|kind| {
+            nodes(mono_analysis_with(kind, analysis.clone(), &pg).facts) == output.nodes
+        }) {
+            return Ok((ValidationResult::Correct, ()));
+        }
+
+        let mismatch = |reason: String| Ok((ValidationResult::Mismatch { reason }, ()));
+        let mut facts = IndexMap::new();
+        for (n, r) in &reference.nodes {
+            let Some(o) = output.nodes.get(n) else {
+                return mismatch(format!("No intervals were given for {n}"));
+            };
+            let (o, r) = (IntervalFacts(o.clone()), IntervalFacts(r.clone()));
+            if !r.contains(&o) {
+                tracing::error!(node = n, reference = ?r, output = ?o, "less precise");
+                return mismatch(format!(
+                    "The intervals at {n} are less precise than the reference: {o:?} is not contained in {r:?}"
+                ));
+            }
+            facts.insert(n.clone(), o);
+        }
+
+        // The output is at least as precise as the reference, so it is only
+        // correct if it is still a solution to the analysis
+        let at = |n: Node| &facts[&n.to_string()];
+        if !at(Node::Start).contains(&analysis.initial(&pg)) {
+            return mismatch(format!(
+                "The intervals at {} do not contain the initial assignment",
+                Node::Start
+            ));
+        }
+        for e in pg.edges() {
+            if !at(e.to()).contains(&analysis.semantic(&pg, e, at(e.from()))) {
+                return mismatch(format!(
+                    "The intervals at {} do not contain the effect of the edge {} -> {}",
+                    e.to(),
+                    e.from(),
+                    e.to(),
+                ));
+            }
+        }
+
+        Ok((ValidationResult::Correct, ()))
+    }
+}
+
+impl Generate for Input {
+    type Context = ();
+
+    fn gn<R: rand::Rng>(_cx: &mut Self::Context, mut rng: &mut R) -> Self {
+        let commands = Commands::gn(&mut Default::default(), rng);
+        let assignment: IntervalMemory = Memory::from_targets_with(
+            commands.fv(),
+            &mut rng,
+            |rng, _| Generate::gn(&mut (), rng),
+            |rng, _| Generate::gn(&mut (), rng),
+        )
+        .into();
+
+        let determinism = *[Determinism::Deterministic, Determinism::NonDeterministic]
+            .choose(rng)
+            .unwrap();
+
+        Input {
+            commands: Stringify::new(commands),
+            assignment,
+            determinism,
+        }
+    }
+}
+
+/// Only the commands are shrunk, as the initial abstract memory is already
+/// made of the few values of the domain.
+impl Shrink for Input {
+    fn shrink(&self) -> Vec<Self> {
+        self.commands
+            .shrink()
+            .into_iter()
+            .map(|commands| Input {
+                commands,
+                ..self.clone()
+            })
+            .collect()
+    }
+}
+
+impl Generate for Interval {
+    type Context = ();
+
+    fn gn<R: rand::Rng>(_cx: &mut Self::Context, rng: &mut R) -> Self {
+        let lower = rng.random_range(-10..=10);
+        Interval::new(Some(lower), Some(lower + rng.random_range(0..=10)))
+    }
+}
@@ -0,0 +1,652 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use gcl::{
+    ast::{
+        AExpr, AExprKind, AOp, Array, BExpr, BExprKind, Function, Int, LogicOp, RelOp, Target,
+        TargetDef, Type, Variable,
+    },
+    pg::{
+        Action, Edge, ProgramGraph,
+        analysis::{Direction, Lattice, MonotoneFramework},
+    },
+};
+use itertools::{Itertools, chain};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct IntervalAnalysis {
+    pub assignment: IntervalMemory,
+}
+
+/// A non-empty range of integers, where a missing bound is infinite.
+#[derive(tapi::Tapi, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[tapi(path = "IntervalAnalysis")]
+pub struct Interval {
+    pub lower: Option<Int>,
+    pub upper: Option<Int>,
+}
+
+impl std::fmt::Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.lower {
+            Some(l) => write!(f, "[{l}, ")?,
+            None => write!(f, "[-∞, ")?,
+        }
+        match self.upper {
+            Some(u) => write!(f, "{u}]"),
+            None => write!(f, "∞]"),
+        }
+    }
+}
+
+/// A bound of an interval. Bounds are computed without overflow, and are only
+/// brought back into the range of [`Int`] when forming intervals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Bound {
+    NegInf,
+    Fin(i128),
+    PosInf,
+}
+
+impl Bound {
+    fn inf(negative: bool) -> Bound {
+        if negative {
+            Bound::NegInf
+        } else {
+            Bound::PosInf
+        }
+    }
+    fn is_negative(self) -> bool {
+        self < Bound::Fin(0)
+    }
+    fn add(self, other: Bound) -> Bound {
+        match (self, other) {
+            (Bound::Fin(a), Bound::Fin(b)) => Bound::Fin(a + b),
+            (Bound::Fin(_), inf) | (inf, _) => inf,
+        }
+    }
+    fn neg(self) -> Bound {
+        match self {
+            Bound::NegInf => Bound::PosInf,
+            Bound::Fin(a) => Bound::Fin(-a),
+            Bound::PosInf => Bound::NegInf,
+        }
+    }
+    fn mul(self, other: Bound) -> Bound {
+        match (self, other) {
+            (Bound::Fin(0), _) | (_, Bound::Fin(0)) => Bound::Fin(0),
+            (Bound::Fin(a), Bound::Fin(b)) => a
+                .checked_mul(b)
+                .map_or(Bound::inf((a < 0) != (b < 0)), Bound::Fin),
+            (a, b) => Bound::inf(a.is_negative() != b.is_negative()),
+        }
+    }
+    /// Division rounding towards zero, where `other` is not zero. Returns
+    /// `None` if both bounds are infinite.
+    fn div(self, other: Bound) -> Option<Bound> {
+        match (self, other) {
+            (Bound::Fin(a), Bound::Fin(b)) => Some(Bound::Fin(a / b)),
+            (Bound::Fin(_), _) => Some(Bound::Fin(0)),
+            (a, Bound::Fin(_)) => Some(Bound::inf(a.is_negative() != other.is_negative())),
+            _ => None,
+        }
+    }
+    fn pow(self, exp: u32) -> Bound {
+        let negative = self.is_negative() && exp % 2 == 1;
+        match self {
+            _ if exp == 0 => Bound::Fin(1),
+            Bound::Fin(a) => a.checked_pow(exp).map_or(Bound::inf(negative), Bound::Fin),
+            _ => Bound::inf(negative),
+        }
+    }
+    fn abs(self) -> Bound {
+        if self.is_negative() { self.neg() } else { self }
+    }
+}
+
+impl Interval {
+    pub const TOP: Interval = Interval {
+        lower: None,
+        upper: None,
+    };
+
+    pub fn new(lower: Option<Int>, upper: Option<Int>) -> Interval {
+        Interval { lower, upper }
+    }
+    pub fn constant(n: Int) -> Interval {
+        Interval::new(Some(n), Some(n))
+    }
+
+    fn lo(self) -> Bound {
+        self.lower.map_or(Bound::NegInf, |l| Bound::Fin(l as _))
+    }
+    fn hi(self) -> Bound {
+        self.upper.map_or(Bound::PosInf, |u| Bound::Fin(u as _))
+    }
+    /// The interval between the given bounds, excluding values which do not
+    /// fit in an [`Int`]. Such values can never be the result of a successful
+    /// computation, as it would have overflowed.
+    fn from_bounds(lo: Bound, hi: Bound) -> Option<Interval> {
+        let (min, max) = (Bound::Fin(Int::MIN as _), Bound::Fin(Int::MAX as _));
+        if lo > hi || lo > max || hi < min {
+            return None;
+        }
+        let clamp = |b: Bound| match b {
+            Bound::Fin(n) => Some(n.clamp(Int::MIN as _, Int::MAX as _) as Int),
+            Bound::NegInf | Bound::PosInf => None,
+        };
+        Some(Interval::new(clamp(lo), clamp(hi)))
+    }
+    /// The smallest interval containing all of the given bounds.
+    fn spanning(bounds: impl IntoIterator<Item = Bound>) -> Option<Interval> {
+        let (lo, hi) = bounds.into_iter().minmax().into_option()?;
+        Interval::from_bounds(lo, hi)
+    }
+
+    pub fn contains(self, other: Interval) -> bool {
+        self.lo() <= other.lo() && other.hi() <= self.hi()
+    }
+    pub fn hull(self, other: Interval) -> Interval {
+        Interval::from_bounds(self.lo().min(other.lo()), self.hi().max(other.hi()))
+            .expect("the hull of two intervals is non-empty")
+    }
+    pub fn meet(self, other: Interval) -> Option<Interval> {
+        Interval::from_bounds(self.lo().max(other.lo()), self.hi().min(other.hi()))
+    }
+    /// Bounds which grow are made infinite.
+    pub fn widen(self, next: Interval) -> Interval {
+        Interval {
+            lower: if next.lo() < self.lo() {
+                None
+            } else {
+                self.lower
+            },
+            upper: if next.hi() > self.hi() {
+                None
+            } else {
+                self.upper
+            },
+        }
+    }
+    /// Infinite bounds are replaced by those of `next`.
+    pub fn narrow(self, next: Interval) -> Interval {
+        Interval {
+            lower: self.lower.or(next.lower),
+            upper: self.upper.or(next.upper),
+        }
+    }
+    fn is_constant(self) -> Option<Int> {
+        self.lower.filter(|_| self.lower == self.upper)
+    }
+    fn may_be_non_negative(self) -> bool {
+        self.hi() >= Bound::Fin(0)
+    }
+
+    fn neg(self) -> Option<Interval> {
+        Interval::from_bounds(self.hi().neg(), self.lo().neg())
+    }
+    fn binary(self, op: AOp, other: Interval) -> Option<Interval> {
+        let (a, b, c, d) = (self.lo(), self.hi(), other.lo(), other.hi());
+        match op {
+            AOp::Plus => Interval::from_bounds(a.add(c), b.add(d)),
+            AOp::Minus => Interval::from_bounds(a.add(d.neg()), b.add(c.neg())),
+            AOp::Times => Interval::spanning([a.mul(c), a.mul(d), b.mul(c), b.mul(d)]),
+            AOp::Divide => {
+                // Division by zero is an error, so the divisor is split into
+                // its negative and its positive part
+                let parts = [
+                    Interval::from_bounds(c, d.min(Bound::Fin(-1))),
+                    Interval::from_bounds(c.max(Bound::Fin(1)), d),
+                ];
+                let mut bounds = vec![];
+                for part in parts.into_iter().flatten() {
+                    for (n, m) in [a, b].into_iter().cartesian_product([part.lo(), part.hi()]) {
+                        match n.div(m) {
+                            Some(q) => bounds.push(q),
+                            None => return Some(Interval::TOP),
+                        }
+                    }
+                }
+                Interval::spanning(bounds)
+            }
+            AOp::Pow => {
+                // Negative exponents are an error
+                let exp = other.meet(Interval::new(Some(0), None))?;
+                match exp.is_constant() {
+                    Some(k) => {
+                        let k = k as u32;
+                        if k % 2 == 1 {
+                            Interval::from_bounds(a.pow(k), b.pow(k))
+                        } else {
+                            let lo = if a <= Bound::Fin(0) && Bound::Fin(0) <= b {
+                                Bound::Fin(0)
+                            } else {
+                                a.abs().min(b.abs())
+                            };
+                            Interval::from_bounds(lo.pow(k), a.abs().max(b.abs()).pow(k))
+                        }
+                    }
+                    None if a >= Bound::Fin(0) => {
+                        let base = b.max(Bound::Fin(1));
+                        let hi = match exp.hi() {
+                            Bound::Fin(k) => base.pow(k as u32),
+                            _ if base == Bound::Fin(1) => base,
+                            _ => Bound::PosInf,
+                        };
+                        Interval::from_bounds(Bound::Fin(0), hi)
+                    }
+                    None => Some(Interval::TOP),
+                }
+            }
+        }
+    }
+
+    /// The values `x` for which `x op y` holds for some `y` in the interval.
+    fn satisfying(self, op: RelOp) -> Interval {
+        let (lo, hi) = match op {
+            RelOp::Eq => (self.lo(), self.hi()),
+            RelOp::Ne => (Bound::NegInf, Bound::PosInf),
+            RelOp::Lt => (Bound::NegInf, self.hi().add(Bound::Fin(-1))),
+            RelOp::Le => (Bound::NegInf, self.hi()),
+            RelOp::Gt => (self.lo().add(Bound::Fin(1)), Bound::PosInf),
+            RelOp::Ge => (self.lo(), Bound::PosInf),
+        };
+        Interval::from_bounds(lo, hi).unwrap_or(Interval::TOP)
+    }
+    /// Restricts the interval to the values `x` for which `x op y` holds for
+    /// some `y` in `other`.
+    fn restrict(self, op: RelOp, other: Interval) -> Option<Interval> {
+        if op == RelOp::Ne
+            && let Some(c) = other.is_constant()
+        {
+            let c = Bound::Fin(c as _);
+            let lo = if self.lo() == c {
+                c.add(Bound::Fin(1))
+            } else {
+                self.lo()
+            };
+            let hi = if self.hi() == c {
+                c.add(Bound::Fin(-1))
+            } else {
+                self.hi()
+            };
+            return Interval::from_bounds(lo, hi);
+        }
+        self.meet(other.satisfying(op))
+    }
+}
+
+fn negate(op: RelOp) -> RelOp {
+    match op {
+        RelOp::Eq => RelOp::Ne,
+        RelOp::Ne => RelOp::Eq,
+        RelOp::Gt => RelOp::Le,
+        RelOp::Ge => RelOp::Lt,
+        RelOp::Lt => RelOp::Ge,
+        RelOp::Le => RelOp::Gt,
+    }
+}
+
+/// The relation with the sides swapped, such that `x op y` iff `y flip(op) x`.
+fn flip(op: RelOp) -> RelOp {
+    match op {
+        RelOp::Eq => RelOp::Eq,
+        RelOp::Ne => RelOp::Ne,
+        RelOp::Gt => RelOp::Lt,
+        RelOp::Ge => RelOp::Le,
+        RelOp::Lt => RelOp::Gt,
+        RelOp::Le => RelOp::Ge,
+    }
+}
+
+/// The possible values of the targets of a program. The elements of an array
+/// are summarized by a single interval.
+#[derive(tapi::Tapi, Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[tapi(path = "IntervalAnalysis")]
+pub struct IntervalMemory {
+    pub variables: BTreeMap<Variable, Interval>,
+    pub arrays: BTreeMap<Array, Interval>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub bools: BTreeMap<Variable, BTreeSet<bool>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub bool_arrays: BTreeMap<Array, BTreeSet<bool>>,
+}
+
+impl IntervalMemory {
+    /// The variables and arrays in memory along with their types.
+    pub fn target_defs(&self) -> BTreeSet<TargetDef> {
+        chain!(
+            self.variables
+                .keys()
+                .map(|v| Target::<()>::Variable(v.clone()).typed_def(Type::Int)),
+            self.arrays
+                .keys()
+                .map(|a| Target::Array(a.clone(), ()).typed_def(Type::Int)),
+            self.bools
+                .keys()
+                .map(|v| Target::<()>::Variable(v.clone()).typed_def(Type::Bool)),
+            self.bool_arrays
+                .keys()
+                .map(|a| Target::Array(a.clone(), ()).typed_def(Type::Bool)),
+        )
+        .collect()
+    }
+
+    /// Whether every value possible in `other` is possible in `self`.
+    pub fn contains(&self, other: &IntervalMemory) -> bool {
+        fn all<K: Ord, V>(
+            a: &BTreeMap<K, V>,
+            b: &BTreeMap<K, V>,
+            f: impl Fn(&V, &V) -> bool,
+        ) -> bool {
+            b.iter().all(|(k, y)| a.get(k).is_some_and(|x| f(x, y)))
+        }
+        all(&self.variables, &other.variables, |a, b| a.contains(*b))
+            && all(&self.arrays, &other.arrays, |a, b| a.contains(*b))
+            && all(&self.bools, &other.bools, |a, b| a.is_superset(b))
+            && all(&self.bool_arrays, &other.bool_arrays, |a, b| {
+                a.is_superset(b)
+            })
+    }
+
+    /// Combines the memories target by target.
+    fn zip_with(
+        &self,
+        other: &IntervalMemory,
+        f: impl Fn(Interval, Interval) -> Interval,
+    ) -> IntervalMemory {
+        fn zip<K: Ord + Clone, V: Clone>(
+            a: &BTreeMap<K, V>,
+            b: &BTreeMap<K, V>,
+            f: impl Fn(&V, &V) -> V,
+        ) -> BTreeMap<K, V> {
+            let mut result = a.clone();
+            for (k, y) in b {
+                let v = match a.get(k) {
+                    Some(x) => f(x, y),
+                    None => y.clone(),
+                };
+                result.insert(k.clone(), v);
+            }
+            result
+        }
+        IntervalMemory {
+            variables: zip(&self.variables, &other.variables, |a, b| f(*a, *b)),
+            arrays: zip(&self.arrays, &other.arrays, |a, b| f(*a, *b)),
+            bools: zip(&self.bools, &other.bools, |a, b| a | b),
+            bool_arrays: zip(&self.bool_arrays, &other.bool_arrays, |a, b| a | b),
+        }
+    }
+
+    pub fn hull(&self, other: &IntervalMemory) -> IntervalMemory {
+        self.zip_with(other, Interval::hull)
+    }
+
+    fn eval(&self, a: &AExpr) -> Option<Interval> {
+        match &a.kind {
+            AExprKind::Number(n) => Some(Interval::constant(*n)),
+            AExprKind::Reference(Target::Variable(x)) => {
+                Some(self.variables.get(x).copied().unwrap_or(Interval::TOP))
+            }
+            AExprKind::Reference(Target::Array(arr, idx)) => {
+                if !self.eval(idx)?.may_be_non_negative() {
+                    return None;
+                }
+                Some(self.arrays.get(arr).copied().unwrap_or(Interval::TOP))
+            }
+            AExprKind::Binary(l, op, r) => self.eval(l)?.binary(*op, self.eval(r)?),
+            AExprKind::Minus(x) => self.eval(x)?.neg(),
+            AExprKind::Function(Function::Min(l, r)) => {
+                let (l, r) = (self.eval(l)?, self.eval(r)?);
+                Interval::from_bounds(l.lo().min(r.lo()), l.hi().min(r.hi()))
+            }
+            AExprKind::Function(Function::Max(l, r)) => {
+                let (l, r) = (self.eval(l)?, self.eval(r)?);
+                Interval::from_bounds(l.lo().max(r.lo()), l.hi().max(r.hi()))
+            }
+            AExprKind::Function(Function::Length(_)) => Some(Interval::new(Some(0), None)),
+            // No element can be equal to a value outside of the interval of
+            // the elements
+            AExprKind::Function(Function::Count(arr, x)) => {
+                let x = self.eval(x)?;
+                let elements = self.arrays.get(arr).copied().unwrap_or(Interval::TOP);
+                match x.meet(elements) {
+                    Some(_) => Some(Interval::new(Some(0), None)),
+                    None => Some(Interval::constant(0)),
+                }
+            }
+        }
+    }
+
+    /// The possible values of `b`.
+    fn eval_bool(&self, b: &BExpr) -> BTreeSet<bool> {
+        [false, true]
+            .into_iter()
+            .filter(|&v| self.refine(b, v).is_some())
+            .collect()
+    }
+
+    /// Restricts the memory to the values for which `b` evaluates to
+    /// `expected`, or `None` if there are none.
+    fn refine(&self, b: &BExpr, expected: bool) -> Option<IntervalMemory> {
+        match &b.kind {
+            BExprKind::Bool(v) => (*v == expected).then(|| self.clone()),
+            BExprKind::Reference(Target::Variable(x)) => {
+                let mut mem = self.clone();
+                let values = mem
+                    .bools
+                    .entry(x.clone())
+                    .or_insert_with(|| [false, true].into());
+                values.contains(&expected).then_some(())?;
+                *values = [expected].into();
+                Some(mem)
+            }
+            BExprKind::Reference(Target::Array(arr, idx)) => {
+                let possible = self.eval(idx)?.may_be_non_negative()
+                    && self
+                        .bool_arrays
+                        .get(arr)
+                        .is_none_or(|values| values.contains(&expected));
+                possible.then(|| self.clone())
+            }
+            BExprKind::Rel(l, op, r) => {
+                let op = if expected { *op } else { negate(*op) };
+                let (lv, rv) = (self.eval(l)?, self.eval(r)?);
+                lv.restrict(op, rv)?;
+                let mut mem = self.clone();
+                if let AExprKind::Reference(Target::Variable(x)) = &l.kind {
+                    mem.variables.insert(x.clone(), lv.restrict(op, rv)?);
+                }
+                if let AExprKind::Reference(Target::Variable(y)) = &r.kind {
+                    let y_value = mem.variables.get(y).copied().unwrap_or(rv);
+                    mem.variables
+                        .insert(y.clone(), y_value.restrict(flip(op), lv)?);
+                }
+                Some(mem)
+            }
+            BExprKind::Logic(l, op, r) => {
+                let all = match op {
+                    LogicOp::And | LogicOp::Land => expected,
+                    LogicOp::Or | LogicOp::Lor => !expected,
+                };
+                if all {
+                    self.refine(l, expected)?.refine(r, expected)
+                } else {
+                    // Either the left side decides the result, or the right
+                    // side does
+                    let left = self.refine(l, expected);
+                    let right = self
+                        .refine(l, !expected)
+                        .and_then(|mem| mem.refine(r, expected));
+                    match (left, right) {
+                        (Some(a), Some(b)) => Some(a.hull(&b)),
+                        (a, b) => a.or(b),
+                    }
+                }
+            }
+            BExprKind::Not(b) => self.refine(b, !expected),
+        }
+    }
+
+    fn semantic(&self, action: &Action) -> Option<IntervalMemory> {
+        let mut mem = self.clone();
+        match action {
+            Action::Assignment(Target::Variable(x), a) => {
+                mem.variables.insert(x.clone(), self.eval(a)?);
+            }
+            Action::Assignment(Target::Array(arr, idx), a) => {
+                if !self.eval(idx)?.may_be_non_negative() {
+                    return None;
+                }
+                let value = self.eval(a)?;
+                let elements = mem.arrays.entry(arr.clone()).or_insert(value);
+                *elements = elements.hull(value);
+            }
+            Action::BoolAssignment(Target::Variable(x), b) => {
+                let values = self.eval_bool(b);
+                if values.is_empty() {
+                    return None;
+                }
+                mem.bools.insert(x.clone(), values);
+            }
+            Action::BoolAssignment(Target::Array(arr, idx), b) => {
+                let values = self.eval_bool(b);
+                if values.is_empty() || !self.eval(idx)?.may_be_non_negative() {
+                    return None;
+                }
+                mem.bool_arrays
+                    .entry(arr.clone())
+                    .or_default()
+                    .extend(values);
+            }
+            Action::ArrayAssignment(arr, elems) => {
+                let values = elems
+                    .iter()
+                    .map(|a| self.eval(a))
+                    .collect::<Option<Vec<_>>>()?;
+                // An empty array has no elements to describe
+                if let Some(value) = values.into_iter().reduce(Interval::hull) {
+                    mem.arrays.insert(arr.clone(), value);
+                }
+            }
+            Action::Skip => {}
+            Action::Condition(b) => return self.refine(b, true),
+        }
+        Some(mem)
+    }
+}
+
+impl From<gcl::memory::Memory<Interval, Interval>> for IntervalMemory {
+    fn from(mem: gcl::memory::Memory<Interval, Interval>) -> Self {
+        Self {
+            variables: mem.variables,
+            arrays: mem.arrays,
+            ..Default::default()
+        }
+    }
+}
+
+/// The facts at a node, where `None` marks the node as unreachable.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct IntervalFacts(pub Option<IntervalMemory>);
+
+impl Lattice for IntervalFacts {
+    fn bottom() -> Self {
+        IntervalFacts(None)
+    }
+
+    fn lub(&self, other: &Self) -> Self {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => IntervalFacts(Some(a.hull(b))),
+            (a, b) => IntervalFacts(a.clone().or_else(|| b.clone())),
+        }
+    }
+
+    fn contains(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(a), Some(b)) => a.contains(b),
+        }
+    }
+}
+
+impl MonotoneFramework for IntervalAnalysis {
+    type Domain = IntervalFacts;
+
+    fn semantic(&self, _pg: &ProgramGraph, e: &Edge, prev: &Self::Domain) -> Self::Domain {
+        IntervalFacts(prev.0.as_ref().and_then(|mem| mem.semantic(e.action())))
+    }
+
+    fn direction() -> Direction {
+        Direction::Forward
+    }
+
+    fn initial(&self, _pg: &ProgramGraph) -> Self::Domain {
+        IntervalFacts(Some(self.assignment.clone()))
+    }
+
+    fn widen(&self, prev: &Self::Domain, next: &Self::Domain) -> Option<Self::Domain> {
+        Some(match (&prev.0, &next.0) {
+            (Some(a), Some(b)) => IntervalFacts(Some(a.zip_with(b, Interval::widen))),
+            _ => prev.lub(next),
+        })
+    }
+
+    fn narrow(&self, prev: &Self::Domain, next: &Self::Domain) -> Option<Self::Domain> {
+        Some(match (&prev.0, &next.0) {
+            (Some(a), Some(b)) => IntervalFacts(Some(a.zip_with(b, Interval::narrow))),
+            _ => next.clone(),
+        })
+    }
+}
+
+#[test]
+fn loops_are_widened_and_narrowed() {
+    use gcl::pg::{
+        Determinism, Node,
+        analysis::{FiFo, mono_analysis},
+    };
+
+    let commands = "i := 0 ; do i < 10 -> i := i + 1 od".parse().unwrap();
+    let pg = ProgramGraph::new(Determinism::Deterministic, &commands);
+    let i = Variable("i".to_string());
+    let analysis = IntervalAnalysis {
+        assignment: IntervalMemory {
+            variables: [(i.clone(), Interval::TOP)].into_iter().collect(),
+            ..Default::default()
+        },
+    };
+
+    let results = mono_analysis::<_, FiFo>(analysis, &pg);
+    let at = |n: Node| results.facts[&n].0.as_ref().unwrap().variables[&i];
+    assert_eq!(at(Node::End), Interval::constant(10));
+    let head = pg.outgoing(Node::Start)[0].to();
+    assert_eq!(at(head), Interval::new(Some(0), Some(10)));
+}
+
+#[test]
+fn arithmetic_is_sound() {
+    let values = [-7, -2, -1, 0, 1, 3, 8];
+    let intervals = values
+        .iter()
+        .tuple_combinations()
+        .map(|(&a, &b)| Interval::new(Some(a), Some(b)))
+        .collect_vec();
+    for op in [AOp::Plus, AOp::Minus, AOp::Times, AOp::Divide, AOp::Pow] {
+        for (&x, &y) in intervals.iter().cartesian_product(&intervals) {
+            let result = x.binary(op, y);
+            for (a, b) in (x.lower.unwrap()..=x.upper.unwrap())
+                .cartesian_product(y.lower.unwrap()..=y.upper.unwrap())
+            {
+                if let Ok(v) = op.semantic(a, b) {
+                    assert!(
+                        result.is_some_and(|r| r.contains(Interval::constant(v))),
+                        "{a} {op} {b} = {v} is not in {x} {op} {y} = {result:?}"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use ce_core::{Env, Generate, Shrink, ValidationResult, define_env, rand};
+use gcl::{
+    ast::Commands,
+    pg::{Action, Determinism, Node, ProgramGraph, optimize::Pass},
+};
+use serde::{Deserialize, Serialize};
+use stdx::stringify::Stringify;
+
+define_env!(OptimizerEnv);
+
+#[derive(tapi::Tapi, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[tapi(path = "Optimizer")]
+pub struct Input {
+    pub commands: Stringify<Commands>,
+}
+
+/// An edge of the optimized program graph, with the nodes written as `q▷`,
+/// `q◀`, `q⊥` or `qN`, and the action as in the program graph.
+#[derive(tapi::Tapi, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[tapi(path = "Optimizer")]
+pub struct Edge {
+    pub from: String,
+    pub action: String,
+    pub to: String,
+}
+
+#[derive(tapi::Tapi, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[tapi(path = "Optimizer")]
+pub struct Output {
+    pub edges: Vec<Edge>,
+    pub dot: String,
+}
+
+impl Input {
+    fn program_graph(&self) -> ce_core::Result<ProgramGraph> {
+        let commands =
+            self.commands
+                .try_parse()
+                .map_err(ce_core::EnvError::invalid_input_for_program(
+                    "failed to parse commands",
+                ))?;
+        commands.check(None)?;
+        Ok(ProgramGraph::new(Determinism::Deterministic, &commands))
+    }
+}
+
+impl Output {
+    fn program_graph(&self) -> Result<ProgramGraph, String> {
+        let node = |n: &str| {
+            n.parse::<Node>()
+                .map_err(|()| format!("'{n}' is not the name of a node"))
+        };
+        self.edges
+            .iter()
+            .map(|e| {
+                let action = e
+                    .action
+                    .parse::<Action>()
+                    .map_err(|err| format!("failed to parse action '{}': {err}", e.action))?;
+                Ok(gcl::pg::Edge(node(&e.from)?, action, node(&e.to)?))
+            })
+            .collect::<Result<Vec<_>, String>>()
+            .map(ProgramGraph::from_edges)
+    }
+}
+
+impl Env for OptimizerEnv {
+    type Input = Input;
+
+    type Output = Output;
+
+    type Meta = ();
+
+    type Annotation = ();
+
+    fn meta(_input: &Self::Input) -> Self::Meta {}
+
+    fn run(input: &Self::Input) -> ce_core::Result<Self::Output> {
+        let pg = input.program_graph()?.optimize(&Pass::ALL);
+        Ok(Output {
+            edges: pg
+                .edges()
+                .iter()
+                .map(|e| Edge {
+                    from: e.from().to_string(),
+                    action: e.action().to_string(),
+                    to: e.to().to_string(),
+                })
+                .collect(),
+            dot: pg.dot(),
+        })
+    }
+
+    fn validate(
+        input: &Self::Input,
+        output: &Self::Output,
+    ) -> ce_core::Result<(ValidationResult, ())> {
+        let optimized = match output.program_graph() {
+            Ok(pg) => pg,
+            Err(reason) => return Ok((ValidationResult::Mismatch { reason }, ())),
+        };
+
+        let original = input.program_graph()?;
+        match ce_bigcl::check_program_graphs_for_semantic_equivalence(&original, &optimized) {
+            ValidationResult::Correct => {}
+            result => return Ok((result, ())),
+        }
+
+        let reference = Self::run(input)?;
+        if output.edges.len() > reference.edges.len() {
+            return Ok((
+                ValidationResult::Mismatch {
+                    reason: format!(
+                        "the optimized graph has {} edges, but it can be optimized to {}",
+                        output.edges.len(),
+                        reference.edges.len()
+                    ),
+                },
+                (),
+            ));
+        }
+
+        Ok((ValidationResult::Correct, ()))
+    }
+}
+
+impl Generate for Input {
+    type Context = ();
+
+    fn gn<R: rand::Rng>(_cx: &mut Self::Context, rng: &mut R) -> Self {
+        Input {
+            commands: Stringify::new(Commands::gn(&mut Default::default(), rng)),
+        }
+    }
+}
+
+impl Shrink for Input {
+    fn shrink(&self) -> Vec<Self> {
+        self.commands
+            .shrink()
+            .into_iter()
+            .map(|commands| Input { commands })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn optimize(src: &str) -> Vec<String> {
+        let input = Input {
+            commands: Stringify::new(src.parse().unwrap()),
+        };
+        OptimizerEnv::run(&input)
+            .unwrap()
+            .edges
+            .into_iter()
+            .map(|e| format!("{} -> {} [{}]", e.from, e.to, e.action))
+            .collect()
+    }
+
+    #[test]
+    fn constants_are_propagated() {
+        assert_eq!(
+            optimize("x := 2 ; y := x * 3 ; if y > 5 -> z := y [] y <= 5 -> z := 0 fi"),
+            [
+                "q▷ -> q1 [x := 2]",
+                "q1 -> q2 [y := 6]",
+                "q2 -> q◀ [z := 6]"
+            ]
+        );
+    }
+
+    #[test]
+    fn conditions_are_contracted() {
+        assert_eq!(
+            optimize("if x > 0 -> if y > 0 -> z := 1 fi fi ; skip"),
+            ["q▷ -> q1 [((x > 0) && (y > 0))]", "q1 -> q◀ [z := 1]"]
+        );
+    }
+
+    #[test]
+    fn unoptimized_graphs_are_rejected() {
+        let input = Input {
+            commands: Stringify::new("skip ; x := 1 + 2".parse().unwrap()),
+        };
+        let edge = |from: &str, action: &str, to: &str| Edge {
+            from: from.to_string(),
+            action: action.to_string(),
+            to: to.to_string(),
+        };
+        let unoptimized = Output {
+            edges: vec![edge("q▷", "skip", "q1"), edge("q1", "x := 1 + 2", "q◀")],
+            dot: String::new(),
+        };
+        let wrong = Output {
+            edges: vec![edge("q▷", "x := 4", "q◀")],
+            dot: String::new(),
+        };
+        let optimized = Output {
+            edges: vec![edge("q▷", "x := 3", "q◀")],
+            dot: String::new(),
+        };
+        let result = |output| OptimizerEnv::validate(&input, output).unwrap().0;
+        assert!(matches!(
+            result(&unoptimized),
+            ValidationResult::Mismatch { .. }
+        ));
+        assert!(matches!(result(&wrong), ValidationResult::Mismatch { .. }));
+        assert_eq!(result(&optimized), ValidationResult::Correct);
+    }
+}
@@ -1,5 +1,9 @@
-use ce_core::{Env, Generate, ValidationResult, define_env, rand};
-use gcl::{ast::Commands, interpreter::InterpreterMemory};
+use ce_core::{Env, Generate, Shrink, ValidationResult, define_env, rand};
+use gcl::{
+    ast::Commands,
+    equivalence::{Equivalence, EquivalenceChecker},
+    pg::{Determinism, ProgramGraph},
+};
 use serde::{Deserialize, Serialize};
 use stdx::stringify::Stringify;
 
@@ -27,10 +31,19 @@ impl Env for ParserEnv {
     type Annotation = ();
 
     fn run(input: &Self::Input) -> ce_core::Result<Self::Output> {
+        let commands =
+            input
+                .commands
+                .try_parse()
+                .map_err(ce_core::EnvError::invalid_input_for_program(
+                    "failed to parse commands",
+                ))?;
+        commands.check(None)?;
+        let pretty = gcl::fmt::format(&input.commands.to_string(), &Default::default()).map_err(
+            ce_core::EnvError::invalid_input_for_program("failed to parse commands"),
+        )?;
         Ok(Output {
-            pretty: Stringify::new(input.commands.try_parse().map_err(
-                ce_core::EnvError::invalid_input_for_program("failed to parse commands"),
-            )?),
+            pretty: Stringify::Unparsed(pretty),
         })
     }
 
@@ -53,20 +66,24 @@ impl Env for ParserEnv {
             }
         };
 
-        if !check_programs_for_semantic_equivalence(&o_cmds, &t_cmds) {
-            return Ok((
-                ValidationResult::Mismatch {
-                    reason: concat!(
-                        "the pretty printed program is not semantically equivalent ",
-                        "to the original program"
-                    )
-                    .to_string(),
-                },
-                (),
-            ));
-        }
+        let pg1 = ProgramGraph::new(Determinism::Deterministic, &o_cmds);
+        let pg2 = ProgramGraph::new(Determinism::Deterministic, &t_cmds);
+        let observed = o_cmds.fv().union(&t_cmds.fv()).cloned().collect();
+
+        let result = match EquivalenceChecker::default().check(&pg1, &pg2, &observed) {
+            Equivalence::Counterexample(cx) => ValidationResult::Mismatch {
+                reason: format!(
+                    "the pretty printed program is not semantically equivalent to the original \
+                     program: {cx}"
+                ),
+            },
+            // Programs which never terminate can not be compared by running
+            // them, but a correct pretty printer gives back the same program
+            Equivalence::Unknown { .. } if o_cmds == t_cmds => ValidationResult::Correct,
+            result => result.into(),
+        };
 
-        Ok((ValidationResult::Correct, ()))
+        Ok((result, ()))
     }
 }
 
@@ -80,56 +97,37 @@ impl Generate for Input {
     }
 }
 
-fn check_programs_for_semantic_equivalence(p1: &Commands, p2: &Commands) -> bool {
-    let pg1 = gcl::pg::ProgramGraph::new(gcl::pg::Determinism::Deterministic, p1);
-    let pg2 = gcl::pg::ProgramGraph::new(gcl::pg::Determinism::Deterministic, p2);
-
-    let n_samples = 10;
-    let n_steps = 10;
-
-    let mut rng = <rand::rngs::SmallRng as rand::SeedableRng>::seed_from_u64(0xCEC34);
-
-    for _ in 0..n_samples {
-        let assignment = generate_input_assignment(p1, &mut rng);
-
-        let mut exe1 = gcl::interpreter::Execution::new(assignment.clone());
-        let mut exe2 = gcl::interpreter::Execution::new(assignment.clone());
-
-        for _ in 0..n_steps {
-            match (exe1.nexts(&pg1).first(), exe2.nexts(&pg2).first()) {
-                (Some(next1), Some(next2)) => {
-                    exe1 = next1.clone();
-                    exe2 = next2.clone();
-                }
-                (None, None) => break,
-                // NOTE: one of the executions is stuck while the other is not
-                _ => return false,
-            }
-        }
-
-        if exe1.current_mem() != exe2.current_mem() {
-            return false;
-        }
+impl Shrink for Input {
+    fn shrink(&self) -> Vec<Self> {
+        self.commands
+            .shrink()
+            .into_iter()
+            .map(|commands| Input { commands })
+            .collect()
     }
-
-    true
 }
 
-fn generate_input_assignment(
-    commands: &gcl::ast::Commands,
-    mut rng: &mut impl rand::Rng,
-) -> InterpreterMemory {
-    let initial_memory = gcl::memory::Memory::from_targets_with(
-        commands.fv(),
-        &mut rng,
-        |rng, _| rng.random_range(-10..=10),
-        |rng, _| {
-            let len = rng.random_range(5..=10);
-            (0..len).map(|_| rng.random_range(-10..=10)).collect()
-        },
-    );
-    InterpreterMemory {
-        variables: initial_memory.variables,
-        arrays: initial_memory.arrays,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_terminating_programs_are_compared_structurally() {
+        let input = Input {
+            commands: Stringify::Unparsed("do true -> x := x + 1 od".to_string()),
+        };
+        let output = ParserEnv::run(&input).unwrap();
+        assert_eq!(
+            ParserEnv::validate(&input, &output).unwrap().0,
+            ValidationResult::Correct
+        );
+
+        let wrong = Output {
+            pretty: Stringify::Unparsed("do true -> x := x + 2 od".to_string()),
+        };
+        assert!(matches!(
+            ParserEnv::validate(&input, &wrong).unwrap().0,
+            ValidationResult::Unknown { .. }
+        ));
     }
 }
@@ -0,0 +1,371 @@
+use std::collections::BTreeSet;
+
+use ce_core::{
+    Coverage, Env, Generate, Shrink, ValidationResult, define_env,
+    rand::{self, seq::IndexedRandom},
+};
+use gcl::{
+    ast::{Commands, TargetDef},
+    interpreter::InterpreterMemory,
+    pg::{Determinism, Node, ProgramGraph},
+    reachability::StateSpace,
+};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use stdx::stringify::Stringify;
+
+define_env!(ReachabilityEnv);
+
+/// The number of transitions explored before giving up on the state space
+pub const FUEL: usize = 10_000;
+
+#[derive(tapi::Tapi, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[tapi(path = "Reachability")]
+pub struct Input {
+    pub commands: Stringify<Commands>,
+    pub determinism: Determinism,
+    pub assignment: InterpreterMemory,
+}
+
+/// A node along with the memory at it, with the node written as `q▷`, `q◀`,
+/// `q⊥` or `qN`.
+#[derive(tapi::Tapi, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[tapi(path = "Reachability")]
+pub struct Configuration {
+    pub node: String,
+    pub memory: InterpreterMemory,
+}
+
+#[derive(tapi::Tapi, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[tapi(path = "Reachability")]
+pub struct Output {
+    /// Whether every reachable configuration was explored within the
+    /// [`FUEL`] limit
+    pub complete: bool,
+    /// The number of reachable configurations
+    pub configurations: usize,
+    pub terminated: Vec<Configuration>,
+    pub stuck: Vec<Configuration>,
+    pub failed: Vec<Configuration>,
+    /// Whether an execution can run forever, as a configuration can be
+    /// reached from itself
+    pub may_not_terminate: bool,
+    pub dot: String,
+}
+
+impl Input {
+    fn program_graph(&self) -> ce_core::Result<ProgramGraph> {
+        let commands =
+            self.commands
+                .try_parse()
+                .map_err(ce_core::EnvError::invalid_input_for_program(
+                    "failed to parse commands",
+                ))?;
+        commands.check(Some(&self.assignment.target_defs()))?;
+        Ok(ProgramGraph::new(self.determinism, &commands))
+    }
+}
+
+impl Env for ReachabilityEnv {
+    type Input = Input;
+
+    type Output = Output;
+
+    type Meta = BTreeSet<TargetDef>;
+
+    type Annotation = ();
+
+    fn meta(input: &Self::Input) -> Self::Meta {
+        if let Ok(commands) = input.commands.try_parse() {
+            commands.target_defs()
+        } else {
+            Default::default()
+        }
+    }
+
+    fn run(input: &Self::Input) -> ce_core::Result<Self::Output> {
+        let pg = input.program_graph()?;
+        let mut space = StateSpace::new(&pg, input.assignment.clone());
+        let complete = space.explore(FUEL).is_ok();
+
+        let configurations = |nodes: &[Node]| {
+            space
+                .terminal()
+                .map(|id| space.configuration(id))
+                .filter(|c| nodes.contains(&c.node))
+                .map(|c| Configuration {
+                    node: c.node.to_string(),
+                    memory: c.memory.without_locals(),
+                })
+                .sorted()
+                .dedup()
+                .collect()
+        };
+        let terminated = configurations(&[Node::End]);
+        let failed = configurations(&[Node::Error]);
+        let stuck = space
+            .stuck()
+            .map(|id| {
+                let c = space.configuration(id);
+                Configuration {
+                    node: c.node.to_string(),
+                    memory: c.memory.without_locals(),
+                }
+            })
+            .sorted()
+            .dedup()
+            .collect();
+
+        Ok(Output {
+            complete,
+            configurations: space.configurations().len(),
+            terminated,
+            stuck,
+            failed,
+            may_not_terminate: space.cycle().is_some(),
+            dot: space.dot(),
+        })
+    }
+
+    fn validate(
+        input: &Self::Input,
+        output: &Self::Output,
+    ) -> ce_core::Result<(ValidationResult, ())> {
+        let reference = Self::run(input)?;
+        if !reference.complete {
+            return Ok((
+                ValidationResult::Unknown {
+                    reason: format!("the state space has more than {FUEL} transitions"),
+                },
+                (),
+            ));
+        }
+
+        let categories = [
+            ("terminated", &reference.terminated, &output.terminated),
+            ("stuck", &reference.stuck, &output.stuck),
+            ("failed", &reference.failed, &output.failed),
+        ];
+        for (name, expected, actual) in categories {
+            let expected = match normalize(expected) {
+                Ok(expected) => expected,
+                Err(reason) => panic!("we always produce valid configurations: {reason}"),
+            };
+            let actual = match normalize(actual) {
+                Ok(actual) => actual,
+                Err(reason) => return Ok((ValidationResult::Mismatch { reason }, ())),
+            };
+            if let Some((node, memory)) = expected.difference(&actual).next() {
+                return Ok((
+                    ValidationResult::Mismatch {
+                        reason: format!(
+                            "the {name} configuration at {node} with {memory:?} is missing"
+                        ),
+                    },
+                    (),
+                ));
+            }
+            if let Some((node, memory)) = actual.difference(&expected).next() {
+                return Ok((
+                    ValidationResult::Mismatch {
+                        reason: format!(
+                            "the configuration at {node} with {memory:?} is not a reachable {name} configuration"
+                        ),
+                    },
+                    (),
+                ));
+            }
+        }
+
+        if output.may_not_terminate != reference.may_not_terminate {
+            let reason = if reference.may_not_terminate {
+                "an execution can run forever, but the output says otherwise"
+            } else {
+                "every execution terminates, but the output says otherwise"
+            };
+            return Ok((
+                ValidationResult::Mismatch {
+                    reason: reason.to_string(),
+                },
+                (),
+            ));
+        }
+
+        if output.configurations != reference.configurations {
+            return Ok((
+                ValidationResult::Mismatch {
+                    reason: format!(
+                        "expected {} reachable configurations, but the output has {}",
+                        reference.configurations, output.configurations
+                    ),
+                },
+                (),
+            ));
+        }
+
+        Ok((ValidationResult::Correct, ()))
+    }
+
+    /// The ways executions end, whether the state space was explored fully,
+    /// and its size as a power of two.
+    fn coverage(_input: &Self::Input, output: &Self::Output) -> Coverage {
+        let mut coverage = Coverage::default();
+        for (covered, feature) in [
+            (!output.terminated.is_empty(), "terminates"),
+            (!output.stuck.is_empty(), "gets stuck"),
+            (!output.failed.is_empty(), "fails"),
+            (output.may_not_terminate, "may not terminate"),
+            (!output.complete, "incomplete"),
+        ] {
+            if covered {
+                coverage.insert(feature);
+            }
+        }
+        let size = output.configurations.next_power_of_two().ilog2();
+        coverage.insert(format!("2^{size} configurations"));
+        coverage
+    }
+}
+
+/// The configurations with their nodes parsed, such that either name of a
+/// node is accepted, and without the parameters of procedures.
+fn normalize(
+    configurations: &[Configuration],
+) -> Result<BTreeSet<(Node, InterpreterMemory)>, String> {
+    configurations
+        .iter()
+        .map(|c| {
+            let node = c
+                .node
+                .parse::<Node>()
+                .map_err(|()| format!("'{}' is not the name of a node", c.node))?;
+            Ok((node, c.memory.without_locals()))
+        })
+        .collect()
+}
+
+impl Generate for Input {
+    type Context = ();
+
+    fn gn<R: rand::Rng>(_cx: &mut Self::Context, mut rng: &mut R) -> Self {
+        let commands = Commands::gn(&mut Default::default(), rng);
+        let initial_memory = gcl::memory::Memory::from_targets_with(
+            commands.fv(),
+            &mut rng,
+            |rng, _| rng.random_range(-10..=10),
+            |rng, _| {
+                let len = rng.random_range(5..=10);
+                (0..len).map(|_| rng.random_range(-10..=10)).collect()
+            },
+        );
+        let assignment = InterpreterMemory {
+            variables: initial_memory.variables,
+            arrays: initial_memory.arrays,
+            ..Default::default()
+        };
+
+        let determinism = *[Determinism::Deterministic, Determinism::NonDeterministic]
+            .choose(rng)
+            .unwrap();
+
+        Input {
+            commands: Stringify::new(commands),
+            determinism,
+            assignment,
+        }
+    }
+}
+
+impl Shrink for Input {
+    fn shrink(&self) -> Vec<Self> {
+        itertools::chain!(
+            self.commands.shrink().into_iter().map(|commands| Input {
+                commands,
+                ..self.clone()
+            }),
+            self.assignment
+                .shrink()
+                .into_iter()
+                .map(|assignment| Input {
+                    assignment,
+                    ..self.clone()
+                }),
+        )
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(src: &str, determinism: Determinism) -> Input {
+        let commands: Commands = src.parse().unwrap();
+        let assignment = InterpreterMemory {
+            variables: commands
+                .fv()
+                .into_iter()
+                .filter_map(|t| match t {
+                    gcl::ast::Target::Variable(x) => Some((x, 0)),
+                    gcl::ast::Target::Array(..) => None,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        Input {
+            commands: Stringify::new(commands),
+            determinism,
+            assignment,
+        }
+    }
+
+    #[test]
+    fn nondeterministic_choices_are_all_explored() {
+        let input = input(
+            "if true -> x := 1 [] true -> x := 2 [] true -> x := 3 fi",
+            Determinism::NonDeterministic,
+        );
+        let output = ReachabilityEnv::run(&input).unwrap();
+        assert!(output.complete);
+        assert!(!output.may_not_terminate);
+        let values = output
+            .terminated
+            .iter()
+            .map(|c| c.memory.variables.values().copied().collect_vec())
+            .collect_vec();
+        assert_eq!(values, [[1], [2], [3]]);
+    }
+
+    #[test]
+    fn loops_are_detected() {
+        let input = input(
+            "do x < 2 -> x := x + 1 [] x >= 2 -> x := 0 od",
+            Determinism::Deterministic,
+        );
+        let output = ReachabilityEnv::run(&input).unwrap();
+        assert!(output.complete);
+        assert!(output.may_not_terminate);
+        assert!(output.terminated.is_empty());
+
+        let wrong = Output {
+            may_not_terminate: false,
+            ..output.clone()
+        };
+        assert!(matches!(
+            ReachabilityEnv::validate(&input, &wrong).unwrap().0,
+            ValidationResult::Mismatch { .. }
+        ));
+        assert_eq!(
+            ReachabilityEnv::validate(&input, &output).unwrap().0,
+            ValidationResult::Correct
+        );
+    }
+
+    #[test]
+    fn stuck_configurations_are_reported() {
+        let input = input("if x > 0 -> skip fi", Determinism::Deterministic);
+        let output = ReachabilityEnv::run(&input).unwrap();
+        assert_eq!(output.stuck.len(), 1);
+        assert_eq!(output.stuck[0].node, Node::Start.to_string());
+    }
+}
@@ -1,16 +1,25 @@
 use ce_bigcl::Binify;
-use ce_core::{Env, Generate, ValidationResult, define_env, rand};
+use ce_core::{Env, Generate, Shrink, ValidationResult, define_env, rand};
 use gcl::{
-    ast::{AExpr, AOp, Array, BExpr, Commands, RelOp, Target, Variable},
+    ast::{
+        AExpr, AExprKind, AOp, Array, BExpr, BExprKind, Commands, Function, RelOp, Target,
+        TargetKind, Variable,
+    },
+    equivalence::{Equivalence, Run},
+    interpreter::{InterpreterMemory, TerminationState},
     pg::{Action, Edge, Node, ProgramGraph},
+    semantics::Arithmetic,
 };
 use indexmap::IndexMap;
-use riscvy::{Instruction, Label, Reg, RiscVFile, Word};
+use riscvy::{Instruction, Label, Reg, RiscVFile, RiscVVMDisplay, StepResult, Word};
 use serde::{Deserialize, Serialize};
 use stdx::stringify::Stringify;
 
 define_env!(RiscVEnv);
 
+/// The number of steps after which a program is considered not to terminate.
+const STEPS: usize = 10_000;
+
 #[derive(tapi::Tapi, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[tapi(path = "RiscV")]
 pub struct Input {
@@ -51,7 +60,8 @@ impl Env for RiscVEnv {
                 .map_err(ce_core::EnvError::invalid_input_for_program(
                     "failed to parse commands",
                 ))?;
-        let file = compile(input, &cmd);
+        cmd.check(None)?;
+        let file = compile(input, &cmd)?;
 
         Ok(Output {
             assembly: file.to_string(),
@@ -81,18 +91,18 @@ impl Env for RiscVEnv {
                 .map_err(ce_core::EnvError::invalid_input_for_program(
                     "failed to parse commands",
                 ))?;
-        let ref_file = compile(input, &cmd);
+        let ref_file = compile(input, &cmd)?;
 
-        let (their_res, their_display) = their_file.run(10_000);
-        let (ref_res, ref_display) = ref_file.run(10_000);
+        let (their_res, their_display) = their_file.run(STEPS);
+        let (ref_res, ref_display) = ref_file.run(STEPS);
 
         let ann = Annotation {
             pc: their_display.pc,
-            memory: their_display.memory.into_iter().map(|w| w.0).collect(),
+            memory: their_display.memory.iter().map(|w| w.0).collect(),
             regs: their_display
                 .regs
-                .into_iter()
-                .map(|(l, w)| (l, w.0))
+                .iter()
+                .map(|(l, w)| (l.clone(), w.0))
                 .collect(),
             variables: their_display
                 .variables
@@ -101,63 +111,164 @@ impl Env for RiscVEnv {
                 .collect(),
         };
 
-        use riscvy::StepResult;
+        // The program is run with the wrapping arithmetic of RISC-V, and the
+        // reference assembly is run where that gets stuck or does not apply,
+        // such as when dividing by zero or with exponentiation, on which the
+        // reference compilation halts
+        let cmd = cmd.resolve_types();
+        let input = zero_memory(&cmd);
+        let expected = match wrapping_run(&cmd, &input) {
+            Some(run) if run.state == TerminationState::Terminated => run,
+            _ => assembly_run(&input, ref_res, &ref_display),
+        };
+        let got = assembly_run(&input, their_res, &their_display);
 
-        match (their_res, ref_res) {
-            (StepResult::Exit, StepResult::Exit) => {}
-            (StepResult::Stuck, StepResult::Stuck) => {}
-            (StepResult::Ok, _) | (_, StepResult::Ok) => {
-                return Ok((
-                    ValidationResult::Unknown {
-                        reason: format!(
-                            "programs did terminate. got: {their_res}, expected: {ref_res}"
-                        ),
-                    },
-                    ann,
-                ));
+        let observed = cmd
+            .fv()
+            .into_iter()
+            .filter(|t| matches!(t, Target::Variable(_)))
+            .collect();
+        let result = match Equivalence::of_runs(input, expected, got, &observed) {
+            Equivalence::Counterexample(cx) => ValidationResult::Mismatch {
+                reason: format!("the assembly does not behave like the program: {cx}"),
+            },
+            result => result.into(),
+        };
+
+        Ok((result, ann))
+    }
+}
+
+/// The memory of the compiled program at the start, where every variable is
+/// zero.
+fn zero_memory(cmd: &Commands) -> InterpreterMemory {
+    let mut memory = InterpreterMemory::default();
+    for def in cmd.target_defs() {
+        match (def.kind, def.name) {
+            (TargetKind::Variable, Target::Variable(v)) => {
+                memory.variables.insert(v, 0);
             }
-            (_, _) => {
-                return Ok((
-                    ValidationResult::Mismatch {
-                        reason: format!(
-                            "programs stopped at different times. got: {their_res}, expected: {ref_res}",
-                        ),
-                    },
-                    ann,
-                ));
+            (TargetKind::BoolVariable, Target::Variable(v)) => {
+                memory.bools.insert(v, false);
             }
+            _ => {}
         }
+    }
+    memory
+}
 
-        for v in cmd.fv() {
-            let (_, x) = their_display
-                .variables
-                .get(&format!("v{v}"))
-                .copied()
-                .unwrap_or_default();
-            let (_, y) = ref_display
-                .variables
-                .get(&format!("v{v}"))
-                .copied()
-                .unwrap_or_default();
-            if x != y {
-                return Ok((
-                    ValidationResult::Mismatch {
-                        reason: format!(
-                            "variable '{v}' has different value at end. got: {y}, expected: {x}",
-                        ),
-                    },
-                    ann,
-                ));
-            }
+/// Runs the program with the wrapping arithmetic of RISC-V.
+///
+/// Returns `None` if the program uses exponentiation, on which the reference
+/// compilation halts.
+fn wrapping_run(cmd: &Commands, input: &InterpreterMemory) -> Option<Run> {
+    let pg = ProgramGraph::new(gcl::pg::Determinism::Deterministic, cmd);
+    if pg.edges().iter().any(|e| e.action().uses_pow()) {
+        return None;
+    }
+    Some(Run::with_arithmetic(
+        Arithmetic::Wrapping,
+        &pg,
+        input,
+        STEPS,
+    ))
+}
+
+/// The run of an assembly program started from `input`, reading the variables
+/// of `input` from their labels when it stopped with `res`.
+fn assembly_run(input: &InterpreterMemory, res: StepResult, display: &RiscVVMDisplay) -> Run {
+    let state = match res {
+        StepResult::Exit => TerminationState::Terminated,
+        StepResult::Stuck => TerminationState::Stuck,
+        StepResult::Ok => TerminationState::Running,
+    };
+    let word = |v: &Variable| {
+        let (_, value) = display
+            .variables
+            .get(&v.to_label().0)
+            .copied()
+            .unwrap_or_default();
+        value.0
+    };
+    let mut memory = input.clone();
+    for (v, value) in &mut memory.variables {
+        *value = word(v);
+    }
+    // A word other than 0 and 1 is no boolean, so it is left out, which
+    // disagrees with any value expected
+    memory.bools = memory
+        .bools
+        .keys()
+        .filter_map(|v| match word(v) {
+            0 => Some((v.clone(), false)),
+            1 => Some((v.clone(), true)),
+            _ => None,
+        })
+        .collect();
+    Run { state, memory }
+}
+
+trait UsesPow {
+    fn uses_pow(&self) -> bool;
+}
+
+impl UsesPow for Action {
+    fn uses_pow(&self) -> bool {
+        match self {
+            Action::Assignment(t, a) => t.uses_pow() || a.uses_pow(),
+            Action::BoolAssignment(t, b) => t.uses_pow() || b.uses_pow(),
+            Action::ArrayAssignment(_, elems) => elems.iter().any(|a| a.uses_pow()),
+            Action::Skip => false,
+            Action::Condition(b) => b.uses_pow(),
         }
+    }
+}
 
-        Ok((ValidationResult::Correct, ann))
+impl UsesPow for Target<Box<AExpr>> {
+    fn uses_pow(&self) -> bool {
+        match self {
+            Target::Variable(_) => false,
+            Target::Array(_, idx) => idx.uses_pow(),
+        }
+    }
+}
+
+impl UsesPow for AExpr {
+    fn uses_pow(&self) -> bool {
+        match &self.kind {
+            AExprKind::Number(_) => false,
+            AExprKind::Reference(t) => t.uses_pow(),
+            AExprKind::Binary(l, op, r) => *op == AOp::Pow || l.uses_pow() || r.uses_pow(),
+            AExprKind::Minus(x) => x.uses_pow(),
+            AExprKind::Function(f) => f.args().any(|a| a.uses_pow()),
+        }
+    }
+}
+
+impl UsesPow for BExpr {
+    fn uses_pow(&self) -> bool {
+        match &self.kind {
+            BExprKind::Bool(_) => false,
+            BExprKind::Reference(t) => t.uses_pow(),
+            BExprKind::Rel(l, _, r) => l.uses_pow() || r.uses_pow(),
+            BExprKind::Logic(l, _, r) => l.uses_pow() || r.uses_pow(),
+            BExprKind::Not(x) => x.uses_pow(),
+        }
     }
 }
 
-fn compile(input: &Input, cmd: &Commands) -> RiscVFile {
+/// Compiles the program, which must not use arrays, as the compiled program
+/// starts from a memory where they have no length.
+fn compile(input: &Input, cmd: &Commands) -> ce_core::Result<RiscVFile> {
+    if let Some(arr) = cmd.fv().iter().find(|t| matches!(t, Target::Array(_, _))) {
+        return Err(ce_core::EnvError::InvalidInputForProgram {
+            message: format!("the RISC-V compilation does not support arrays, such as '{arr}'"),
+            source: None,
+        });
+    }
+
     let mut ctx = ce_bigcl::Ctx::new(cmd.fv().into_iter().map(|t| t.name().to_string()).collect());
-    let cmd = cmd.binify(&mut ctx);
+    let cmd = cmd.resolve_types().binify(&mut ctx);
     let fv = cmd.fv();
     let pg = ProgramGraph::new(gcl::pg::Determinism::NonDeterministic, &cmd);
 
@@ -185,7 +296,33 @@ fn compile(input: &Input, cmd: &Commands) -> RiscVFile {
                         file.push_aexp(Reg::t1(), e);
                         file.push_inst(Instruction::sw(Reg::t1(), Word(0), Reg::t0()));
                     }
-                    Target::Array(_, _) => todo!(),
+                    Target::Array(_, _) => unreachable!("arrays are rejected before compilation"),
+                }
+                file.push_inst(Instruction::j(t.to_label()));
+            }
+            [Edge(_, Action::BoolAssignment(x, b), t)] => {
+                match x {
+                    Target::Variable(v) => {
+                        file.push_inst(la(Reg::t0(), v.to_label()));
+                        match &b.kind {
+                            BExprKind::Bool(b) => file.push_inst(li(Reg::t1(), Word(*b as _))),
+                            BExprKind::Reference(Target::Variable(y)) => {
+                                file.push_inst(lw(Reg::t1(), y.to_label()))
+                            }
+                            BExprKind::Reference(Target::Array(_, _)) => {
+                                unreachable!("arrays are rejected before compilation")
+                            }
+                            BExprKind::Rel(_, _, _)
+                            | BExprKind::Logic(_, _, _)
+                            | BExprKind::Not(_) => {
+                                unreachable!(
+                                    "binification assigns other boolean expressions through a conditional"
+                                )
+                            }
+                        }
+                        file.push_inst(Instruction::sw(Reg::t1(), Word(0), Reg::t0()));
+                    }
+                    Target::Array(_, _) => unreachable!("arrays are rejected before compilation"),
                 }
                 file.push_inst(Instruction::j(t.to_label()));
             }
@@ -194,10 +331,19 @@ fn compile(input: &Input, cmd: &Commands) -> RiscVFile {
                 Edge(_, Action::Condition(b), f),
             ] => {
                 // NOTE: we know that b is !a
-                match a {
-                    BExpr::Bool(true) => file.push_inst(j(t.to_label())),
-                    BExpr::Bool(false) => file.push_inst(j(f.to_label())),
-                    BExpr::Rel(l, op, r) => {
+                match &a.kind {
+                    BExprKind::Bool(true) => file.push_inst(j(t.to_label())),
+                    BExprKind::Bool(false) => file.push_inst(j(f.to_label())),
+                    BExprKind::Reference(Target::Variable(v)) => {
+                        file.push_inst(lw(Reg::t0(), v.to_label()));
+                        file.push_inst(li(Reg::t1(), Word(1)));
+                        file.push_inst(beq(Reg::t0(), Reg::t1(), t.to_label()));
+                        file.push_inst(j(f.to_label()));
+                    }
+                    BExprKind::Reference(Target::Array(_, _)) => {
+                        unreachable!("arrays are rejected before compilation")
+                    }
+                    BExprKind::Rel(l, op, r) => {
                         match op {
                             RelOp::Lt => {
                                 file.push_aexp(Reg::t0(), l);
@@ -239,14 +385,14 @@ fn compile(input: &Input, cmd: &Commands) -> RiscVFile {
                             }
                         }
                     }
-                    BExpr::Logic(_, _, _) => unreachable!(),
-                    BExpr::Not(_) => unreachable!("found edge with ¬. they were: {a} and {b}"),
+                    BExprKind::Logic(_, _, _) => unreachable!(),
+                    BExprKind::Not(_) => unreachable!("found edge with ¬. they were: {a} and {b}"),
                 }
             }
             edges => todo!("\n\n{}\n\n{cmd}\n\n{edges:?}", input.commands),
         }
     }
-    file
+    Ok(file)
 }
 
 trait RiscVEncoding {
@@ -256,15 +402,17 @@ trait RiscVEncoding {
 impl RiscVEncoding for RiscVFile {
     fn push_aexp(&mut self, reg: Reg, a: &AExpr) {
         use Instruction::*;
-        match a {
-            AExpr::Number(n) => {
+        match &a.kind {
+            AExprKind::Number(n) => {
                 self.push_inst(li(reg, Word(*n)));
             }
-            AExpr::Reference(Target::Array(_, _)) => todo!(),
-            AExpr::Reference(Target::Variable(y)) => {
+            AExprKind::Reference(Target::Array(_, _)) => {
+                unreachable!("arrays are rejected before compilation")
+            }
+            AExprKind::Reference(Target::Variable(y)) => {
                 self.push_inst(lw(reg, y.to_label()));
             }
-            AExpr::Binary(l, op, r) => {
+            AExprKind::Binary(l, op, r) => {
                 self.push_aexp(Reg::t1(), l);
                 self.push_aexp(Reg::t2(), r);
                 match op {
@@ -275,10 +423,16 @@ impl RiscVEncoding for RiscVFile {
                     AOp::Pow => self.push_halt(),
                 }
             }
-            AExpr::Minus(x) => {
+            AExprKind::Minus(x) => {
                 self.push_aexp(reg.clone(), x);
                 self.push_inst(neg(reg.clone(), reg));
             }
+            AExprKind::Function(Function::Min(_, _) | Function::Max(_, _)) => {
+                unreachable!("binification turns min and max into conditionals")
+            }
+            AExprKind::Function(Function::Length(_) | Function::Count(_, _)) => {
+                unreachable!("arrays are rejected before compilation")
+            }
         }
     }
 }
@@ -330,3 +484,31 @@ impl Generate for Input {
         }
     }
 }
+
+impl Shrink for Input {
+    fn shrink(&self) -> Vec<Self> {
+        self.commands
+            .shrink()
+            .into_iter()
+            .map(|commands| Input { commands })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bool_assignment_from_a_comparison() {
+        let input = Input {
+            commands: Stringify::Unparsed(
+                "x := 2; y := 3; b := x < y && !(y = 4); if b -> z := 1 [] !b -> z := 2 fi"
+                    .to_string(),
+            ),
+        };
+        let output = RiscVEnv::run(&input).unwrap();
+        let (result, _) = RiscVEnv::validate(&input, &output).unwrap();
+        assert_eq!(result, ValidationResult::Correct);
+    }
+}
@@ -1,6 +1,6 @@
 use std::collections::{BTreeMap, BTreeSet};
 
-use gcl::ast::{Command, Commands, Guard, Target};
+use gcl::ast::{Command, CommandKind, Commands, Guard, Target, Variable};
 use itertools::{Itertools, chain};
 use serde::{Deserialize, Serialize};
 
@@ -73,14 +73,43 @@ pub(crate) trait Security {
 
 impl Security for Commands {
     fn sec(&self, implicit: &BTreeSet<Target>) -> BTreeSet<Flow> {
+        if self.has_procedures() {
+            return bypass_locals(self.inline_procedures().sec(implicit));
+        }
         self.0.iter().flat_map(|c| c.sec(implicit)).collect()
     }
 }
 
+/// The flows between the targets of the program, where the parameters of
+/// procedures are left out, as they merely pass information on from the
+/// targets flowing into them to those they flow into.
+fn bypass_locals(mut flows: BTreeSet<Flow>) -> BTreeSet<Flow> {
+    let is_local = |name: &str| Variable(name.to_string()).is_local();
+    loop {
+        let through: BTreeSet<Flow> = flows
+            .iter()
+            .filter(|f| is_local(&f.into))
+            .flat_map(|f| {
+                flows
+                    .iter()
+                    .filter(|g| g.from == f.into)
+                    .map(|g| flow(&f.from, &g.into))
+            })
+            .filter(|f| !flows.contains(f))
+            .collect();
+        if through.is_empty() {
+            break;
+        }
+        flows.extend(through);
+    }
+    flows.retain(|f| !is_local(&f.from) && !is_local(&f.into));
+    flows
+}
+
 impl Security for Command {
     fn sec(&self, implicit: &BTreeSet<Target>) -> BTreeSet<Flow> {
-        match self {
-            Command::Assignment(t, a) => chain!(
+        match &self.kind {
+            CommandKind::Assignment(t, a) => chain!(
                 implicit.iter().cloned(),
                 match t {
                     Target::Variable(_) => BTreeSet::default(),
@@ -90,8 +119,23 @@ impl Security for Command {
             )
             .map(|i| flow(i, t.clone().unit()))
             .collect(),
-            Command::Skip => BTreeSet::default(),
-            Command::If(c) | Command::Loop(c) => {
+            CommandKind::BoolAssignment(t, b) => chain!(
+                implicit.iter().cloned(),
+                match t {
+                    Target::Variable(_) => BTreeSet::default(),
+                    Target::Array(_, idx) => idx.fv().into_iter().collect(),
+                },
+                b.fv()
+            )
+            .map(|i| flow(i, t.clone().unit()))
+            .collect(),
+            CommandKind::ArrayAssignment(arr, elems) => {
+                chain!(implicit.iter().cloned(), elems.iter().flat_map(|a| a.fv()))
+                    .map(|i| flow(i, Target::Array(arr.clone(), ())))
+                    .collect()
+            }
+            CommandKind::Skip | CommandKind::Assert(_) | CommandKind::Abort => BTreeSet::default(),
+            CommandKind::If(c) | CommandKind::Loop(c) => {
                 c.iter()
                     .fold(
                         (implicit.clone(), BTreeSet::default()),
@@ -106,6 +150,12 @@ impl Security for Command {
                     )
                     .1
             }
+            CommandKind::Par(components) => {
+                components.iter().flat_map(|c| c.sec(implicit)).collect()
+            }
+            CommandKind::Proc(_) | CommandKind::Call(_, _) => {
+                unreachable!("procedures are inlined before the analysis")
+            }
         }
     }
 }
@@ -116,8 +166,8 @@ trait Security2 {
 
 impl Security2 for Guard {
     fn sec2(&self, implicit: &BTreeSet<Target>) -> (BTreeSet<Target>, BTreeSet<Flow>) {
-        let implicit = implicit.iter().cloned().chain(self.0.fv()).collect();
-        let flows = self.1.sec(&implicit);
+        let implicit = implicit.iter().cloned().chain(self.guard.fv()).collect();
+        let flows = self.cmds.sec(&implicit);
         (implicit, flows)
     }
 }
@@ -4,7 +4,8 @@ use std::collections::{BTreeMap, BTreeSet};
 
 use analysis::{Security, SecurityLattice};
 use ce_core::{
-    Env, Generate, ValidationResult, define_env,
+    Coverage, Env, Generate, Shrink, ValidationResult, define_env,
+    gn::GclGenContext,
     rand::{self, seq::IndexedRandom},
 };
 use gcl::{
@@ -85,7 +86,7 @@ impl Env for SecurityEnv {
 
         Meta {
             lattice: SecurityLattice::new(&input.lattice.rules),
-            targets: commands.fv().into_iter().map(|t| t.def()).collect(),
+            targets: commands.target_defs(),
         }
     }
 
@@ -97,6 +98,7 @@ impl Env for SecurityEnv {
                 .map_err(ce_core::EnvError::invalid_input_for_program(
                     "failed to parse commands",
                 ))?;
+        commands.check(None)?;
 
         let lattice = SecurityLattice::new(&input.lattice.rules);
 
@@ -182,13 +184,40 @@ impl Env for SecurityEnv {
 
         Ok((ValidationResult::Correct, ()))
     }
+
+    /// The pairs of classes which information flows between, along with
+    /// which of those flows are violations.
+    fn coverage(input: &Self::Input, output: &Self::Output) -> Coverage {
+        let class = |t: &String| input.classification.get(t).map_or("unclassified", |c| c);
+        let flows = output
+            .actual
+            .iter()
+            .map(|f| format!("flow {} -> {}", class(&f.from), class(&f.into)));
+        let violations = output
+            .violations
+            .iter()
+            .map(|f| format!("violation {} -> {}", class(&f.from), class(&f.into)));
+        let mut coverage: Coverage = flows.chain(violations).collect();
+        coverage.insert(if output.is_secure {
+            "secure"
+        } else {
+            "insecure"
+        });
+        coverage
+    }
 }
 
 impl Generate for Input {
     type Context = ();
 
     fn gn<R: rand::Rng>(_cx: &mut Self::Context, rng: &mut R) -> Self {
-        let commands = Commands::gn(&mut Default::default(), rng);
+        let commands = Commands::gn(
+            &mut GclGenContext {
+                arrays: true,
+                ..Default::default()
+            },
+            rng,
+        );
 
         let lattice_options = [
             // public < private
@@ -244,3 +273,18 @@ impl Generate for Input {
         }
     }
 }
+
+/// Only the commands are shrunk, as the classification and lattice are
+/// already small.
+impl Shrink for Input {
+    fn shrink(&self) -> Vec<Self> {
+        self.commands
+            .shrink()
+            .into_iter()
+            .map(|commands| Input {
+                commands,
+                ..self.clone()
+            })
+            .collect()
+    }
+}
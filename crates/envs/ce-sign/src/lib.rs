@@ -2,18 +2,19 @@
 
 mod semantics;
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
 use ce_core::{
-    Env, EnvError, Generate, ValidationResult, define_env,
+    Coverage, Env, Generate, Shrink, ValidationResult, define_env,
+    gn::GclGenContext,
     rand::{self, seq::IndexedRandom},
 };
 use gcl::{
-    ast::{Commands, Target, TargetDef},
+    ast::{Commands, TargetDef},
     memory::Memory,
     pg::{
         Determinism, Node, ProgramGraph,
-        analysis::{FiFo, mono_analysis},
+        analysis::{FiFo, WorklistKind, mono_analysis, semantic_calls},
     },
 };
 use indexmap::{IndexMap, IndexSet};
@@ -39,6 +40,9 @@ pub struct Output {
     pub final_node: String,
     pub nodes: IndexMap<String, IndexSet<SignMemory>>,
     pub dot: String,
+    /// The work needed to reach the solution with each worklist strategy
+    #[serde(default)]
+    pub semantic_calls: BTreeMap<WorklistKind, usize>,
 }
 
 impl Env for SignEnv {
@@ -52,57 +56,38 @@ impl Env for SignEnv {
 
     fn meta(input: &Self::Input) -> Self::Meta {
         if let Ok(commands) = input.commands.try_parse() {
-            commands.fv().into_iter().map(|t| t.def()).collect()
+            commands.target_defs()
         } else {
             Default::default()
         }
     }
 
     fn run(input: &Self::Input) -> ce_core::Result<Self::Output> {
-        let pg =
-            ProgramGraph::new(
-                input.determinism,
-                &input.commands.try_parse().map_err(
-                    ce_core::EnvError::invalid_input_for_program("failed to parse commands"),
-                )?,
-            );
-
-        for t in pg.fv() {
-            match t {
-                Target::Variable(var) => {
-                    if input.assignment.get_var(&var).is_none() {
-                        return Err(EnvError::InvalidInputForProgram {
-                            message: format!("variable `{var}` was not in the given input"),
-                            source: None,
-                        });
-                    }
-                }
-                Target::Array(arr, _) => {
-                    if input.assignment.get_arr(&arr).is_none() {
-                        return Err(EnvError::InvalidInputForProgram {
-                            message: format!("array `{arr}` was not in the given input"),
-                            source: None,
-                        });
-                    }
-                }
-            }
-        }
-
-        let nodes = mono_analysis::<_, FiFo>(
-            SignAnalysis {
-                assignment: input.assignment.clone(),
-            },
-            &pg,
-        )
-        .facts
-        .into_iter()
-        .map(|(k, v)| (format!("{k}"), v))
-        .collect();
+        let commands =
+            input
+                .commands
+                .try_parse()
+                .map_err(ce_core::EnvError::invalid_input_for_program(
+                    "failed to parse commands",
+                ))?;
+        commands.check(Some(&input.assignment.target_defs()))?;
+        let pg = ProgramGraph::new(input.determinism, &commands);
+
+        let analysis = SignAnalysis {
+            assignment: input.assignment.clone(),
+        };
+        let semantic_calls = semantic_calls(&analysis, &pg);
+        let nodes = mono_analysis::<_, FiFo>(analysis, &pg)
+            .facts
+            .into_iter()
+            .map(|(k, v)| (format!("{k}"), v))
+            .collect();
         Ok(Output {
             initial_node: Node::Start.to_string(),
             final_node: Node::End.to_string(),
             nodes,
             dot: pg.dot(),
+            semantic_calls,
         })
     }
 
@@ -142,13 +127,36 @@ impl Env for SignEnv {
             ))
         }
     }
+
+    /// The combinations of signs of the variables and arrays in the abstract
+    /// memories, such as `x+ y0 A{-, 0}`, and whether a node is unreachable.
+    fn coverage(_input: &Self::Input, output: &Self::Output) -> Coverage {
+        let mut coverage = Coverage::default();
+        for memories in output.nodes.values() {
+            if memories.is_empty() {
+                coverage.insert("unreachable node");
+            }
+            for mem in memories {
+                let variables = mem.variables.iter().map(|(x, s)| format!("{x}{s}"));
+                let arrays = mem.arrays.iter().map(|(a, s)| format!("{a}{s}"));
+                coverage.insert(variables.chain(arrays).join(" "));
+            }
+        }
+        coverage
+    }
 }
 
 impl Generate for Input {
     type Context = ();
 
     fn gn<R: rand::Rng>(_cx: &mut Self::Context, mut rng: &mut R) -> Self {
-        let commands = Commands::gn(&mut Default::default(), rng);
+        let commands = Commands::gn(
+            &mut GclGenContext {
+                arrays: true,
+                ..Default::default()
+            },
+            rng,
+        );
         let assignment: SignMemory = Memory::from_targets_with(
             commands.fv(),
             &mut rng,
@@ -169,6 +177,21 @@ impl Generate for Input {
     }
 }
 
+/// Only the commands are shrunk, as the initial abstract memory is already
+/// made of the few values of the domain.
+impl Shrink for Input {
+    fn shrink(&self) -> Vec<Self> {
+        self.commands
+            .shrink()
+            .into_iter()
+            .map(|commands| Input {
+                commands,
+                ..self.clone()
+            })
+            .collect()
+    }
+}
+
 impl Generate for Sign {
     type Context = ();
 
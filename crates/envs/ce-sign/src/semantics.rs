@@ -1,7 +1,9 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use gcl::{
-    ast::{AExpr, Array, BExpr, Int, Target, Variable},
+    ast::{
+        AExpr, AExprKind, Array, BExpr, BExprKind, Function, Int, Target, TargetDef, Type, Variable,
+    },
     pg::{
         Action, Edge, ProgramGraph,
         analysis::{Direction, MonotoneFramework},
@@ -9,7 +11,7 @@ use gcl::{
     semantics::SemanticsError,
 };
 use indexmap::IndexSet;
-use itertools::{Either, Itertools};
+use itertools::{Either, Itertools, chain};
 use serde::{Deserialize, Serialize};
 
 // use crate::analysis::{Direction, MonotoneFramework};
@@ -181,7 +183,6 @@ impl From<Vec<bool>> for Bools {
 
 #[test]
 fn bools_as_json() {
-    use std::collections::BTreeSet;
     assert_eq!(
         serde_json::to_string(&Bools::ALL).unwrap(),
         serde_json::to_string(&Bools::ALL.bools().collect::<BTreeSet<_>>()).unwrap()
@@ -219,6 +220,10 @@ impl FromIterator<bool> for Bools {
 pub struct SignMemory {
     pub variables: BTreeMap<Variable, Sign>,
     pub arrays: BTreeMap<Array, Signs>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub bools: BTreeMap<Variable, bool>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub bool_arrays: BTreeMap<Array, Bools>,
 }
 impl SignMemory {
     pub fn with_var(mut self, var: &Variable, value: Sign) -> Self {
@@ -234,12 +239,44 @@ impl SignMemory {
     pub fn get_arr(&self, arr: &Array) -> Option<Signs> {
         self.arrays.get(arr).copied()
     }
+    /// The variables and arrays in memory along with their types.
+    pub fn target_defs(&self) -> BTreeSet<TargetDef> {
+        chain!(
+            self.variables
+                .keys()
+                .map(|v| Target::<()>::Variable(v.clone()).typed_def(Type::Int)),
+            self.arrays
+                .keys()
+                .map(|a| Target::Array(a.clone(), ()).typed_def(Type::Int)),
+            self.bools
+                .keys()
+                .map(|v| Target::<()>::Variable(v.clone()).typed_def(Type::Bool)),
+            self.bool_arrays
+                .keys()
+                .map(|a| Target::Array(a.clone(), ()).typed_def(Type::Bool)),
+        )
+        .collect()
+    }
+    pub fn with_bool(mut self, var: &Variable, value: bool) -> Self {
+        *self
+            .bools
+            .get_mut(var)
+            .unwrap_or_else(|| panic!("boolean `{var}` not declared")) = value;
+        self
+    }
+    pub fn get_bool(&self, var: &Variable) -> Option<bool> {
+        self.bools.get(var).copied()
+    }
+    pub fn get_bool_arr(&self, arr: &Array) -> Option<Bools> {
+        self.bool_arrays.get(arr).copied()
+    }
 }
 impl From<gcl::memory::Memory<Sign, Signs>> for SignMemory {
     fn from(mem: gcl::memory::Memory<Sign, Signs>) -> Self {
         Self {
             variables: mem.variables,
             arrays: mem.arrays,
+            ..Default::default()
         }
     }
 }
@@ -287,6 +324,59 @@ impl MonotoneFramework for SignAnalysis {
                     }
                 })
                 .collect(),
+            Action::BoolAssignment(Target::Variable(var), b) => prev
+                .iter()
+                .flat_map(|mem| b.semantics_sign(mem).bools().map(move |v| (mem, v)))
+                .map(|(mem, v)| mem.clone().with_bool(var, v))
+                .collect(),
+            Action::BoolAssignment(Target::Array(arr, idx), b) => prev
+                .iter()
+                .flat_map(|mem| {
+                    let idx_signs = idx.semantics_sign(mem);
+                    if idx_signs.intersects(Signs::ZERO | Signs::POSITIVE) {
+                        let array_bools = mem
+                            .get_bool_arr(arr)
+                            .unwrap_or_else(|| panic!("could not get bools of array '{arr}'"));
+
+                        let mut new_possible = IndexSet::new();
+
+                        for v in std::iter::once(None).chain(array_bools.iter().map(Some)) {
+                            let mut bools = array_bools;
+                            if let Some(v) = v {
+                                bools.remove(v);
+                            }
+                            for new_bool in b.semantics_sign(mem).iter() {
+                                let mut new_mem = mem.clone();
+                                new_mem.bool_arrays.insert(arr.clone(), bools | new_bool);
+                                new_possible.insert(new_mem);
+                            }
+                        }
+
+                        new_possible
+                    } else {
+                        Default::default()
+                    }
+                })
+                .collect(),
+            Action::ArrayAssignment(arr, elems) => prev
+                .iter()
+                .flat_map(|mem| {
+                    // The signs the elements can have together
+                    let mut possible: IndexSet<Signs> = [Signs::empty()].into_iter().collect();
+                    for a in elems {
+                        let signs = a.semantics_sign(mem);
+                        possible = possible
+                            .iter()
+                            .flat_map(|s| signs.iter().map(move |x| *s | x))
+                            .collect();
+                    }
+                    possible.into_iter().map(|signs| {
+                        let mut new_mem = mem.clone();
+                        new_mem.arrays.insert(arr.clone(), signs);
+                        new_mem
+                    })
+                })
+                .collect(),
             Action::Skip => prev.clone(),
             Action::Condition(b) => prev
                 .iter()
@@ -337,9 +427,22 @@ impl SemanticSign for BExpr {
     type Items = Bools;
 
     fn semantics_sign(&self, mem: &SignMemory) -> Bools {
-        match self {
-            BExpr::Bool(b) => [*b].into_iter().collect(),
-            BExpr::Rel(l, op, r) => {
+        match &self.kind {
+            BExprKind::Bool(b) => [*b].into_iter().collect(),
+            BExprKind::Reference(Target::Variable(x)) => [mem
+                .get_bool(x)
+                .unwrap_or_else(|| panic!("could not get value of '{x}'"))]
+            .into_iter()
+            .collect(),
+            BExprKind::Reference(Target::Array(arr, idx)) => {
+                let idx_signs = idx.semantics_sign(mem);
+                if idx_signs.intersects(Signs::ZERO | Signs::POSITIVE) {
+                    mem.get_bool_arr(arr).unwrap_or_default()
+                } else {
+                    Default::default()
+                }
+            }
+            BExprKind::Rel(l, op, r) => {
                 let l = l.semantics_sign(mem);
                 let r = r.semantics_sign(mem);
                 cartesian_flat_map(
@@ -350,7 +453,7 @@ impl SemanticSign for BExpr {
                 .flatten()
                 .collect()
             }
-            BExpr::Logic(l, op, r) => {
+            BExprKind::Logic(l, op, r) => {
                 let l = l.semantics_sign(mem);
                 let r = r.semantics_sign(mem);
                 cartesian_flat_map(l.bools(), r.bools(), |l, r| {
@@ -359,7 +462,7 @@ impl SemanticSign for BExpr {
                 .flatten()
                 .collect()
             }
-            BExpr::Not(b) => b.semantics_sign(mem).map(|i| !i),
+            BExprKind::Not(b) => b.semantics_sign(mem).map(|i| !i),
         }
     }
 }
@@ -388,14 +491,14 @@ impl SemanticSign for AExpr {
     type Items = Signs;
 
     fn semantics_sign(&self, mem: &SignMemory) -> Signs {
-        match self {
-            AExpr::Number(n) => [sign_of(*n)].into_iter().collect(),
-            AExpr::Reference(Target::Variable(x)) => [mem
+        match &self.kind {
+            AExprKind::Number(n) => [sign_of(*n)].into_iter().collect(),
+            AExprKind::Reference(Target::Variable(x)) => [mem
                 .get_var(x)
                 .unwrap_or_else(|| panic!("could not get sign of '{x}'"))]
             .into_iter()
             .collect(),
-            AExpr::Binary(l, op, r) => cartesian_flat_map(
+            AExprKind::Binary(l, op, r) => cartesian_flat_map(
                 l.semantics_sign(mem)
                     .signs()
                     .flat_map(|x| x.representative()),
@@ -421,7 +524,7 @@ impl SemanticSign for AExpr {
             })
             .map(sign_of)
             .collect(),
-            AExpr::Reference(Target::Array(arr, idx)) => {
+            AExprKind::Reference(Target::Array(arr, idx)) => {
                 let idx_signs = idx.semantics_sign(mem);
                 if idx_signs.intersects(Signs::ZERO | Signs::POSITIVE) {
                     if let Some(arr) = mem.arrays.get(arr) {
@@ -433,7 +536,87 @@ impl SemanticSign for AExpr {
                     Default::default()
                 }
             }
-            AExpr::Minus(n) => n.semantics_sign(mem).map(|x| -x),
+            AExprKind::Minus(n) => n.semantics_sign(mem).map(|x| -x),
+            AExprKind::Function(f @ (Function::Min(l, r) | Function::Max(l, r))) => {
+                cartesian_flat_map(
+                    l.semantics_sign(mem)
+                        .signs()
+                        .flat_map(|x| x.representative()),
+                    r.semantics_sign(mem)
+                        .signs()
+                        .flat_map(|x| x.representative()),
+                    |l, r| match f {
+                        Function::Min(_, _) => Some(l.min(r?)),
+                        _ => Some(l.max(r?)),
+                    },
+                )
+                .flatten()
+                .map(sign_of)
+                .collect()
+            }
+            // The signs of an array are those of its elements, so an array
+            // without signs is empty
+            AExprKind::Function(Function::Length(arr)) => {
+                let empty = match (mem.arrays.get(arr), mem.bool_arrays.get(arr)) {
+                    (Some(signs), _) => signs.is_empty(),
+                    (None, Some(bools)) => bools.is_empty(),
+                    (None, None) => panic!("could not get sign of array '{arr}'"),
+                };
+                if empty { Signs::ZERO } else { Signs::POSITIVE }
+            }
+            AExprKind::Function(Function::Count(arr, x)) => {
+                let elements = mem
+                    .arrays
+                    .get(arr)
+                    .copied()
+                    .unwrap_or_else(|| panic!("could not get sign of array '{arr}'"));
+                if x.semantics_sign(mem).intersects(elements) {
+                    Signs::ZERO | Signs::POSITIVE
+                } else {
+                    Signs::ZERO
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn worklists_agree() {
+    use gcl::pg::{
+        Determinism,
+        analysis::{FiFo, WorklistKind, mono_analysis, mono_analysis_with},
+    };
+
+    let commands = "i := 0 ;
+        do i < n ->
+           if x > 0 -> x := -x
+           [] x <= 0 -> x := x - i
+           fi ;
+           i := i + 1
+        od"
+    .parse()
+    .unwrap();
+    let pg = ProgramGraph::new(Determinism::Deterministic, &commands);
+    let analysis = SignAnalysis {
+        assignment: SignMemory {
+            variables: [
+                ("i", Sign::Zero),
+                ("n", Sign::Positive),
+                ("x", Sign::Positive),
+            ]
+            .into_iter()
+            .map(|(v, s)| (Variable(v.to_string()), s))
+            .collect(),
+            ..Default::default()
+        },
+    };
+
+    let reference = mono_analysis::<_, FiFo>(analysis.clone(), &pg);
+    for kind in WorklistKind::ALL {
+        let results = mono_analysis_with(kind, analysis.clone(), &pg);
+        for (node, facts) in &reference.facts {
+            assert_eq!(&results.facts[node], facts, "{kind:?} differs at {node}");
         }
+        assert!(results.semantic_calls >= pg.edges().len(), "{kind:?}");
     }
 }
@@ -2,6 +2,8 @@ use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 
+use crate::parse::SourceSpan;
+
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Target<Idx = ()> {
     Variable(Variable),
@@ -14,6 +16,18 @@ pub enum Target<Idx = ()> {
 pub enum TargetKind {
     Variable,
     Array,
+    BoolVariable,
+    BoolArray,
+}
+
+/// The type of the values stored in a variable or in the elements of an array.
+#[derive(
+    tapi::Tapi, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[tapi(path = "GCL")]
+pub enum Type {
+    Int,
+    Bool,
 }
 
 #[derive(tapi::Tapi, Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Serialize, Deserialize)]
@@ -34,7 +48,7 @@ pub struct Variable(pub String);
 pub struct Array(pub String);
 
 impl FromStr for Commands {
-    type Err = crate::parse::ParseError;
+    type Err = crate::parse::ParseErrors;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         crate::parse::parse_commands(s)
     }
@@ -55,25 +69,161 @@ impl FromStr for AExpr {
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Commands(pub Vec<Command>);
 
+/// A command along with where it is in the source.
+///
+/// Commands are compared and hashed by their kind alone, such that programs
+/// which only differ in layout are equal.
+#[derive(Debug, Clone)]
+pub struct Command {
+    pub kind: CommandKind,
+    pub span: SourceSpan,
+}
+
+impl PartialEq for Command {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+impl Eq for Command {}
+impl PartialOrd for Command {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Command {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.kind.cmp(&other.kind)
+    }
+}
+impl std::hash::Hash for Command {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.kind.hash(state);
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub enum Command {
+pub enum CommandKind {
     Assignment(Target<Box<AExpr>>, AExpr),
+    BoolAssignment(Target<Box<AExpr>>, BExpr),
+    /// `A := [a1, ..., an]`, replacing the elements of the array
+    ArrayAssignment(Array, Vec<AExpr>),
     Skip,
     If(Vec<Guard>),
     Loop(Vec<Guard>),
+    /// `assert b`, failing the execution if `b` does not hold
+    Assert(BExpr),
+    /// `abort`, unconditionally failing the execution
+    Abort,
+    Proc(ProcDecl),
+    Call(ProcName, Vec<AExpr>),
+    /// `par C1 [] C2 rap`, running the components concurrently by
+    /// interleaving their steps
+    Par(Vec<Commands>),
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProcName(pub String);
+
+/// A procedure declaration `proc name(in x, out y) { body }`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProcDecl {
+    pub name: ProcName,
+    pub params: Vec<Param>,
+    pub body: Commands,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Guard(pub BExpr, pub Commands);
+pub struct Param {
+    pub mode: ParamMode,
+    pub var: Variable,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ParamMode {
+    In,
+    Out,
+}
+
+/// Like [`Command`], guards are compared and hashed without their span.
+#[derive(Debug, Clone)]
+pub struct Guard {
+    pub guard_span: SourceSpan,
+    pub guard: BExpr,
+    pub cmds: Commands,
+}
+
+impl PartialEq for Guard {
+    fn eq(&self, other: &Self) -> bool {
+        (&self.guard, &self.cmds) == (&other.guard, &other.cmds)
+    }
+}
+impl Eq for Guard {}
+impl PartialOrd for Guard {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Guard {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (&self.guard, &self.cmds).cmp(&(&other.guard, &other.cmds))
+    }
+}
+impl std::hash::Hash for Guard {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.guard.hash(state);
+        self.cmds.hash(state);
+    }
+}
 
 pub type Int = i32;
 
+/// An arithmetic expression along with where it is in the source. Like
+/// [`Command`], expressions are compared and hashed by their kind alone.
+#[derive(Debug, Clone)]
+pub struct AExpr {
+    pub kind: AExprKind,
+    pub span: SourceSpan,
+}
+
+impl PartialEq for AExpr {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+impl Eq for AExpr {}
+impl PartialOrd for AExpr {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for AExpr {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.kind.cmp(&other.kind)
+    }
+}
+impl std::hash::Hash for AExpr {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.kind.hash(state);
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub enum AExpr {
+pub enum AExprKind {
     Number(Int),
     Reference(Target<Box<AExpr>>),
     Binary(Box<AExpr>, AOp, Box<AExpr>),
     Minus(Box<AExpr>),
+    Function(Function),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Function {
+    Min(Box<AExpr>, Box<AExpr>),
+    Max(Box<AExpr>, Box<AExpr>),
+    /// `len(A)`, the number of elements in `A`
+    Length(Array),
+    /// `count(A, a)`, the number of elements in `A` equal to `a`
+    Count(Array, Box<AExpr>),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -85,9 +235,39 @@ pub enum AOp {
     Pow,
 }
 
+/// A boolean expression along with where it is in the source.
+#[derive(Debug, Clone)]
+pub struct BExpr {
+    pub kind: BExprKind,
+    pub span: SourceSpan,
+}
+
+impl PartialEq for BExpr {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+impl Eq for BExpr {}
+impl PartialOrd for BExpr {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for BExpr {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.kind.cmp(&other.kind)
+    }
+}
+impl std::hash::Hash for BExpr {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.kind.hash(state);
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub enum BExpr {
+pub enum BExprKind {
     Bool(bool),
+    Reference(Target<Box<AExpr>>),
     Rel(AExpr, RelOp, AExpr),
     Logic(Box<BExpr>, LogicOp, Box<BExpr>),
     Not(Box<BExpr>),
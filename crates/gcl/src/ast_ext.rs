@@ -4,9 +4,11 @@ use indexmap::IndexSet;
 
 use crate::{
     ast::{
-        AExpr, AOp, Array, BExpr, Command, Commands, Flow, Guard, LogicOp, RelOp, Target,
-        TargetDef, TargetKind, Variable,
+        AExpr, AExprKind, AOp, Array, BExpr, BExprKind, Command, CommandKind, Commands, Flow,
+        Function, Guard, Int, LogicOp, ProcName, RelOp, Target, TargetDef, TargetKind, Type,
+        Variable,
     },
+    parse::SourceSpan,
     semantics::EmptySemanticsContext,
 };
 
@@ -48,19 +50,38 @@ impl<Idx> Target<Idx> {
         }
     }
     pub fn def(&self) -> TargetDef {
+        self.typed_def(Type::Int)
+    }
+    pub fn typed_def(&self, ty: Type) -> TargetDef {
         match self {
             Target::Variable(v) => TargetDef {
                 name: Target::Variable(v.clone()),
-                kind: TargetKind::Variable,
+                kind: match ty {
+                    Type::Int => TargetKind::Variable,
+                    Type::Bool => TargetKind::BoolVariable,
+                },
             },
             Target::Array(a, _) => TargetDef {
                 name: Target::Array(a.clone(), ()),
-                kind: TargetKind::Array,
+                kind: match ty {
+                    Type::Int => TargetKind::Array,
+                    Type::Bool => TargetKind::BoolArray,
+                },
             },
         }
     }
 }
 
+impl TargetKind {
+    /// The type of the values stored in targets of this kind.
+    pub fn ty(&self) -> Type {
+        match self {
+            TargetKind::Variable | TargetKind::Array => Type::Int,
+            TargetKind::BoolVariable | TargetKind::BoolArray => Type::Bool,
+        }
+    }
+}
+
 impl<Idx> Debug for Target<Idx>
 where
     Idx: Debug,
@@ -143,6 +164,12 @@ impl FromStr for Array {
     }
 }
 
+impl Debug for ProcName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 impl<Idx> From<Variable> for Target<Idx> {
     fn from(value: Variable) -> Self {
         Target::Variable(value)
@@ -155,25 +182,58 @@ impl From<Array> for Target<()> {
 }
 
 impl Commands {
+    /// The free variables of the commands. Calls are inlined prior to
+    /// computing these, such that the globals used by procedures are
+    /// included, while their parameters are not.
     pub fn fv(&self) -> IndexSet<Target> {
+        if self.has_procedures() {
+            let mut fv = self.inline_procedures().fv();
+            fv.retain(|t| !t.is_local());
+            return fv;
+        }
         self.0.iter().flat_map(|c| c.fv()).collect()
     }
     pub fn concat(mut self, others: &Commands) -> Commands {
         self.0.extend(others.0.iter().cloned());
         self
     }
-    pub fn extend(mut self, cmd: Command) -> Commands {
-        self.0.push(cmd);
+    pub fn extend(mut self, cmd: impl Into<Command>) -> Commands {
+        self.0.push(cmd.into());
         self
     }
 }
 impl Command {
+    pub fn new(kind: CommandKind, span: SourceSpan) -> Command {
+        Command { kind, span }
+    }
+    pub fn fv(&self) -> IndexSet<Target> {
+        self.kind.fv()
+    }
+}
+/// Commands constructed outside of the parser do not originate from any
+/// source, and thus get an empty span.
+impl From<CommandKind> for Command {
+    fn from(kind: CommandKind) -> Self {
+        Command::new(kind, SourceSpan::default())
+    }
+}
+impl CommandKind {
     pub fn fv(&self) -> IndexSet<Target> {
         match self {
-            Command::Assignment(x, a) => x.fv().union(&a.fv()).cloned().collect(),
-            Command::Skip => IndexSet::default(),
-            Command::If(c) => guards_fv(c),
-            Command::Loop(c) => guards_fv(c),
+            CommandKind::Assignment(x, a) => x.fv().union(&a.fv()).cloned().collect(),
+            CommandKind::BoolAssignment(x, b) => x.fv().union(&b.fv()).cloned().collect(),
+            CommandKind::ArrayAssignment(arr, elems) => {
+                std::iter::once(Target::Array(arr.clone(), ()))
+                    .chain(elems.iter().flat_map(|a| a.fv()))
+                    .collect()
+            }
+            CommandKind::Skip | CommandKind::Abort => IndexSet::default(),
+            CommandKind::Assert(b) => b.fv(),
+            CommandKind::If(c) => guards_fv(c),
+            CommandKind::Loop(c) => guards_fv(c),
+            CommandKind::Proc(decl) => decl.body.fv(),
+            CommandKind::Call(_, args) => args.iter().flat_map(|a| a.fv()).collect(),
+            CommandKind::Par(components) => components.iter().flat_map(|c| c.fv()).collect(),
         }
     }
 }
@@ -181,8 +241,15 @@ fn guards_fv(guards: &[Guard]) -> IndexSet<Target> {
     guards.iter().flat_map(|g| g.fv()).collect()
 }
 impl Guard {
+    pub fn new(guard: BExpr, cmds: Commands) -> Guard {
+        Guard {
+            guard_span: SourceSpan::default(),
+            guard,
+            cmds,
+        }
+    }
     pub fn fv(&self) -> IndexSet<Target> {
-        self.0.fv().union(&self.1.fv()).cloned().collect()
+        self.guard.fv().union(&self.cmds.fv()).cloned().collect()
     }
 }
 impl Target<Box<AExpr>> {
@@ -198,108 +265,203 @@ impl Target<Box<AExpr>> {
     }
 }
 impl AExpr {
+    pub fn new(kind: AExprKind, span: SourceSpan) -> AExpr {
+        AExpr { kind, span }
+    }
+    pub fn number(n: Int) -> AExpr {
+        AExprKind::Number(n).into()
+    }
+    pub fn reference(t: Target<Box<AExpr>>) -> AExpr {
+        AExprKind::Reference(t).into()
+    }
     pub fn binary(lhs: Self, op: AOp, rhs: Self) -> Self {
-        Self::Binary(Box::new(lhs), op, Box::new(rhs))
+        AExprKind::Binary(Box::new(lhs), op, Box::new(rhs)).into()
+    }
+    pub fn minus(e: Self) -> Self {
+        AExprKind::Minus(Box::new(e)).into()
+    }
+    pub fn function(f: Function) -> Self {
+        AExprKind::Function(f).into()
+    }
+    /// The expression with its kind replaced, keeping its span.
+    pub fn with_kind(&self, kind: AExprKind) -> AExpr {
+        AExpr::new(kind, self.span)
     }
     pub fn fv(&self) -> IndexSet<Target> {
+        match &self.kind {
+            AExprKind::Number(_) => Default::default(),
+            AExprKind::Reference(v) => v.fv(),
+            AExprKind::Binary(l, _, r) => l.fv().union(&r.fv()).cloned().collect(),
+            AExprKind::Minus(x) => x.fv(),
+            AExprKind::Function(f) => f.fv(),
+        }
+    }
+}
+/// Like commands, expressions constructed outside of the parser get an empty
+/// span.
+impl From<AExprKind> for AExpr {
+    fn from(kind: AExprKind) -> Self {
+        AExpr::new(kind, SourceSpan::default())
+    }
+}
+impl Function {
+    /// The array the function is applied to, if any.
+    pub fn array(&self) -> Option<&Array> {
+        match self {
+            Function::Min(_, _) | Function::Max(_, _) => None,
+            Function::Length(arr) | Function::Count(arr, _) => Some(arr),
+        }
+    }
+    /// The arithmetic arguments of the function.
+    pub fn args(&self) -> impl Iterator<Item = &AExpr> {
         match self {
-            AExpr::Number(_) => Default::default(),
-            AExpr::Reference(v) => v.fv(),
-            AExpr::Binary(l, _, r) => l.fv().union(&r.fv()).cloned().collect(),
-            AExpr::Minus(x) => x.fv(),
+            Function::Min(a, b) | Function::Max(a, b) => vec![&**a, &**b],
+            Function::Length(_) => vec![],
+            Function::Count(_, a) => vec![&**a],
         }
+        .into_iter()
+    }
+    /// The function with `f` applied to each of its arithmetic arguments.
+    pub fn map(&self, mut f: impl FnMut(&AExpr) -> AExpr) -> Function {
+        match self {
+            Function::Min(a, b) => Function::Min(Box::new(f(a)), Box::new(f(b))),
+            Function::Max(a, b) => Function::Max(Box::new(f(a)), Box::new(f(b))),
+            Function::Length(arr) => Function::Length(arr.clone()),
+            Function::Count(arr, a) => Function::Count(arr.clone(), Box::new(f(a))),
+        }
+    }
+    pub fn fv(&self) -> IndexSet<Target> {
+        self.array()
+            .map(|arr| Target::Array(arr.clone(), ()))
+            .into_iter()
+            .chain(self.args().flat_map(|a| a.fv()))
+            .collect()
     }
 }
 impl BExpr {
+    pub fn new(kind: BExprKind, span: SourceSpan) -> BExpr {
+        BExpr { kind, span }
+    }
+    pub fn bool(b: bool) -> BExpr {
+        BExprKind::Bool(b).into()
+    }
+    pub fn reference(t: Target<Box<AExpr>>) -> BExpr {
+        BExprKind::Reference(t).into()
+    }
     pub fn logic(lhs: Self, op: LogicOp, rhs: Self) -> Self {
-        Self::Logic(Box::new(lhs), op, Box::new(rhs))
+        BExprKind::Logic(Box::new(lhs), op, Box::new(rhs)).into()
     }
     pub fn rel(lhs: AExpr, op: RelOp, rhs: AExpr) -> Self {
-        Self::Rel(lhs, op, rhs)
+        BExprKind::Rel(lhs, op, rhs).into()
+    }
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(b: Self) -> Self {
+        BExprKind::Not(Box::new(b)).into()
+    }
+    /// The expression with its kind replaced, keeping its span.
+    pub fn with_kind(&self, kind: BExprKind) -> BExpr {
+        BExpr::new(kind, self.span)
     }
     pub fn fv(&self) -> IndexSet<Target> {
-        match self {
-            BExpr::Bool(_) => Default::default(),
-            BExpr::Rel(l, _, r) => l.fv().union(&r.fv()).cloned().collect(),
-            BExpr::Logic(l, _, r) => l.fv().union(&r.fv()).cloned().collect(),
-            BExpr::Not(x) => x.fv(),
+        match &self.kind {
+            BExprKind::Bool(_) => Default::default(),
+            BExprKind::Reference(v) => v.fv(),
+            BExprKind::Rel(l, _, r) => l.fv().union(&r.fv()).cloned().collect(),
+            BExprKind::Logic(l, _, r) => l.fv().union(&r.fv()).cloned().collect(),
+            BExprKind::Not(x) => x.fv(),
         }
     }
 }
+impl From<BExprKind> for BExpr {
+    fn from(kind: BExprKind) -> Self {
+        BExpr::new(kind, SourceSpan::default())
+    }
+}
 
 impl BExpr {
     pub fn subst_var<T>(&self, t: &Target<T>, x: &AExpr) -> BExpr {
-        match self {
-            BExpr::Bool(b) => BExpr::Bool(*b),
-            BExpr::Rel(l, op, r) => BExpr::Rel(l.subst_var(t, x), *op, r.subst_var(t, x)),
-            BExpr::Logic(l, op, r) => BExpr::logic(l.subst_var(t, x), *op, r.subst_var(t, x)),
-            BExpr::Not(e) => BExpr::Not(Box::new(e.subst_var(t, x))),
-        }
+        self.with_kind(match &self.kind {
+            BExprKind::Bool(b) => BExprKind::Bool(*b),
+            BExprKind::Reference(v) => BExprKind::Reference(v.clone()),
+            BExprKind::Rel(l, op, r) => BExprKind::Rel(l.subst_var(t, x), *op, r.subst_var(t, x)),
+            BExprKind::Logic(l, op, r) => BExprKind::Logic(
+                Box::new(l.subst_var(t, x)),
+                *op,
+                Box::new(r.subst_var(t, x)),
+            ),
+            BExprKind::Not(e) => BExprKind::Not(Box::new(e.subst_var(t, x))),
+        })
     }
 
     pub fn simplify(&self) -> BExpr {
-        match self
-            .semantics(&EmptySemanticsContext)
-            .map(BExpr::Bool)
-            .unwrap_or_else(|_| self.clone())
-        {
-            BExpr::Bool(b) => BExpr::Bool(b),
-            BExpr::Rel(l, op, r) => BExpr::Rel(l.simplify(), op, r.simplify()),
-            BExpr::Logic(l, op, r) => {
-                let l = l.simplify();
-                let r = r.simplify();
+        let simplified = match self.semantics(&EmptySemanticsContext) {
+            Ok(b) => BExprKind::Bool(b),
+            Err(_) => match &self.kind {
+                BExprKind::Bool(b) => BExprKind::Bool(*b),
+                BExprKind::Reference(v) => BExprKind::Reference(v.simplify()),
+                BExprKind::Rel(l, op, r) => BExprKind::Rel(l.simplify(), *op, r.simplify()),
+                BExprKind::Logic(l, op, r) => {
+                    let l = l.simplify();
+                    let r = r.simplify();
 
-                match (l, op, r) {
-                    (BExpr::Bool(true), LogicOp::And, x) | (x, LogicOp::And, BExpr::Bool(true)) => {
-                        x
+                    match (&l.kind, op, &r.kind) {
+                        (BExprKind::Bool(true), LogicOp::And, _)
+                        | (BExprKind::Bool(false), LogicOp::Or, _) => return r,
+                        (_, LogicOp::And, BExprKind::Bool(true))
+                        | (_, LogicOp::Or, BExprKind::Bool(false)) => return l,
+                        (BExprKind::Bool(false), LogicOp::And, _)
+                        | (_, LogicOp::And, BExprKind::Bool(false)) => BExprKind::Bool(false),
+                        (BExprKind::Bool(true), LogicOp::Or, _)
+                        | (_, LogicOp::Or, BExprKind::Bool(true)) => BExprKind::Bool(true),
+                        _ => BExprKind::Logic(Box::new(l), *op, Box::new(r)),
                     }
-                    (BExpr::Bool(false), LogicOp::And, _)
-                    | (_, LogicOp::And, BExpr::Bool(false)) => BExpr::Bool(false),
-                    (BExpr::Bool(false), LogicOp::Or, x) | (x, LogicOp::Or, BExpr::Bool(false)) => {
-                        x
-                    }
-                    (BExpr::Bool(true), LogicOp::Or, _) | (_, LogicOp::Or, BExpr::Bool(true)) => {
-                        BExpr::Bool(true)
-                    }
-                    (l, op, r) => BExpr::logic(l, op, r),
                 }
-            }
-            BExpr::Not(x) => {
-                let x = x.simplify();
-                match x {
-                    BExpr::Bool(b) => BExpr::Bool(!b),
-                    x => BExpr::Not(Box::new(x)),
+                BExprKind::Not(x) => {
+                    let x = x.simplify();
+                    match x.kind {
+                        BExprKind::Bool(b) => BExprKind::Bool(!b),
+                        _ => BExprKind::Not(Box::new(x)),
+                    }
                 }
-            }
-        }
+            },
+        };
+        self.with_kind(simplified)
     }
 }
 
 impl AExpr {
     pub fn subst_var<T>(&self, t: &Target<T>, x: &AExpr) -> AExpr {
-        match self {
-            AExpr::Number(n) => AExpr::Number(*n),
-            AExpr::Reference(v) if v.same_name(t) => x.clone(),
-            AExpr::Reference(v) => AExpr::Reference(v.clone()),
-            AExpr::Binary(l, op, r) => AExpr::binary(l.subst_var(t, x), *op, r.subst_var(t, x)),
-            AExpr::Minus(e) => AExpr::Minus(Box::new(e.subst_var(t, x))),
-        }
+        self.with_kind(match &self.kind {
+            AExprKind::Number(n) => AExprKind::Number(*n),
+            AExprKind::Reference(v) if v.same_name(t) => return x.clone(),
+            AExprKind::Reference(v) => AExprKind::Reference(v.clone()),
+            AExprKind::Binary(l, op, r) => AExprKind::Binary(
+                Box::new(l.subst_var(t, x)),
+                *op,
+                Box::new(r.subst_var(t, x)),
+            ),
+            AExprKind::Minus(e) => AExprKind::Minus(Box::new(e.subst_var(t, x))),
+            AExprKind::Function(f) => AExprKind::Function(f.map(|a| a.subst_var(t, x))),
+        })
     }
 
     pub fn simplify(&self) -> AExpr {
-        match self
-            .semantics(&EmptySemanticsContext)
-            .map(AExpr::Number)
-            .unwrap_or_else(|_| self.clone())
-        {
-            AExpr::Number(n) => AExpr::Number(n),
-            AExpr::Reference(v) => AExpr::Reference(v.simplify()),
-            AExpr::Binary(l, op, r) => AExpr::binary(l.simplify(), op, r.simplify()),
-            AExpr::Minus(e) => match &*e {
-                AExpr::Minus(inner) => inner.simplify(),
-                _ => AExpr::Minus(Box::new(e.simplify())),
+        let simplified = match self.semantics(&EmptySemanticsContext) {
+            Ok(n) => AExprKind::Number(n),
+            Err(_) => match &self.kind {
+                AExprKind::Number(n) => AExprKind::Number(*n),
+                AExprKind::Reference(v) => AExprKind::Reference(v.simplify()),
+                AExprKind::Binary(l, op, r) => {
+                    AExprKind::Binary(Box::new(l.simplify()), *op, Box::new(r.simplify()))
+                }
+                AExprKind::Minus(e) => match &e.kind {
+                    AExprKind::Minus(inner) => return inner.simplify(),
+                    _ => AExprKind::Minus(Box::new(e.simplify())),
+                },
+                AExprKind::Function(f) => AExprKind::Function(f.map(|a| a.simplify())),
             },
-        }
+        };
+        self.with_kind(simplified)
     }
 }
 
@@ -0,0 +1,125 @@
+//! Encoding of expressions as SMT terms.
+//!
+//! Variables are encoded as constants of the same name, and array elements as
+//! constants named by the array and the index, such as `A.2` for `A[2]`. The
+//! indices of arrays and the exponents of powers must therefore be numbers,
+//! and functions of arrays must be evaluated, as they are in the values of a
+//! [`SymbolicMemory`](crate::symbolic::SymbolicMemory).
+
+use smtlib::prelude::*;
+
+use crate::ast::{
+    AExpr, AExprKind, AOp, Array, BExpr, BExprKind, Function, Int, LogicOp, RelOp, Target, Variable,
+};
+
+/// The name of the constant holding the initial value of `x`.
+pub fn variable_symbol(x: &Variable) -> String {
+    x.to_string()
+}
+
+/// The name of the constant holding the initial value of `A[idx]`.
+pub fn element_symbol(arr: &Array, idx: usize) -> String {
+    format!("{arr}.{idx}")
+}
+
+fn target_symbol(t: &Target<Box<AExpr>>) -> String {
+    match t {
+        Target::Variable(x) => variable_symbol(x),
+        Target::Array(arr, idx) => match idx.simplify() {
+            AExpr {
+                kind: AExprKind::Number(n),
+                ..
+            } if n >= 0 => element_symbol(arr, n as usize),
+            idx => panic!("the index of `{arr}[{idx}]` must be a number to be encoded"),
+        },
+    }
+}
+
+impl BExpr {
+    pub fn smt<'st>(&self, st: &'st smtlib::Storage) -> smtlib::Bool<'st> {
+        match &self.kind {
+            BExprKind::Bool(b) => smtlib::Bool::new(st, *b),
+            BExprKind::Reference(t) => smtlib::Bool::new_const(st, target_symbol(t)).into(),
+            BExprKind::Rel(lhs, op, rhs) => {
+                let lhs = lhs.smt(st);
+                let rhs = rhs.smt(st);
+                match op {
+                    RelOp::Eq => lhs._eq(rhs),
+                    RelOp::Ne => lhs._neq(rhs),
+                    RelOp::Lt => lhs.lt(rhs),
+                    RelOp::Le => lhs.le(rhs),
+                    RelOp::Gt => lhs.gt(rhs),
+                    RelOp::Ge => lhs.ge(rhs),
+                }
+            }
+            BExprKind::Logic(lhs, op, rhs) => {
+                let lhs = lhs.smt(st);
+                let rhs = rhs.smt(st);
+                match op {
+                    LogicOp::And | LogicOp::Land => lhs & rhs,
+                    LogicOp::Or | LogicOp::Lor => lhs | rhs,
+                }
+            }
+            BExprKind::Not(b) => !b.smt(st),
+        }
+    }
+}
+
+impl AExpr {
+    pub fn smt<'st>(&self, st: &'st smtlib::Storage) -> smtlib::Int<'st> {
+        match &self.kind {
+            AExprKind::Number(n) => smtlib::Int::new(st, *n as i64),
+            AExprKind::Reference(t) => smtlib::Int::new_const(st, target_symbol(t)).into(),
+            AExprKind::Binary(lhs, AOp::Pow, rhs) => match rhs.simplify() {
+                AExpr {
+                    kind: AExprKind::Number(n),
+                    ..
+                } if n >= 0 => {
+                    let base = lhs.smt(st);
+                    (0..n).fold(smtlib::Int::new(st, 1), |acc, _| acc * base)
+                }
+                rhs => panic!("the exponent of `{lhs} ^ {rhs}` must be a number to be encoded"),
+            },
+            AExprKind::Binary(lhs, op, rhs) => {
+                let lhs = lhs.smt(st);
+                let rhs = rhs.smt(st);
+                match op {
+                    AOp::Plus => lhs + rhs,
+                    AOp::Minus => lhs - rhs,
+                    AOp::Times => lhs * rhs,
+                    AOp::Divide => truncated_division(st, lhs, rhs),
+                    AOp::Pow => unreachable!(),
+                }
+            }
+            AExprKind::Minus(e) => -e.smt(st),
+            AExprKind::Function(Function::Min(lhs, rhs)) => {
+                let (lhs, rhs) = (lhs.smt(st), rhs.smt(st));
+                lhs.le(rhs).ite(lhs, rhs)
+            }
+            AExprKind::Function(Function::Max(lhs, rhs)) => {
+                let (lhs, rhs) = (lhs.smt(st), rhs.smt(st));
+                lhs.ge(rhs).ite(lhs, rhs)
+            }
+            AExprKind::Function(f) => panic!("`{f}` must be evaluated to be encoded"),
+        }
+    }
+}
+
+/// Division rounding towards zero, like the division of GCL, in terms of the
+/// division of SMT-LIB, which rounds such that the remainder is non-negative.
+fn truncated_division<'st>(
+    st: &'st smtlib::Storage,
+    lhs: smtlib::Int<'st>,
+    rhs: smtlib::Int<'st>,
+) -> smtlib::Int<'st> {
+    let zero = smtlib::Int::new(st, 0);
+    let one = smtlib::Int::new(st, 1);
+    let q = lhs / rhs;
+    let exact = (lhs - rhs * q)._eq(zero);
+    (lhs.ge(zero) | exact).ite(q, rhs.gt(zero).ite(q + one, q - one))
+}
+
+/// The condition that `x` fits in the integers of GCL.
+pub fn in_range<'st>(st: &'st smtlib::Storage, x: smtlib::Int<'st>) -> smtlib::Bool<'st> {
+    x.ge(smtlib::Int::new(st, Int::MIN as i64)) & x.le(smtlib::Int::new(st, Int::MAX as i64))
+}
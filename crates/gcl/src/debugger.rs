@@ -0,0 +1,266 @@
+//! Stepping through the execution of a program graph.
+//!
+//! A [`Session`] advances an [`Execution`] one edge at a time, and keeps every
+//! step it took, such that the execution can be stepped back through as well.
+//! Running stops at [breakpoints](Breakpoint), which are nodes, conditions or
+//! both, and [watches](Watch) are evaluated in the memory of the current step.
+
+use std::fmt;
+
+use crate::{
+    ast::{AExpr, BExpr, Int},
+    interpreter::{Execution, InterpreterMemory, TerminationState},
+    parse::ParseError,
+    pg::{Action, Node, ProgramGraph},
+    semantics::SemanticsError,
+};
+
+/// A node, a condition on the memory, or both, at which running stops. A
+/// breakpoint with a node stops every time the node is reached while the
+/// condition holds, whereas a breakpoint with only a condition stops when the
+/// condition starts to hold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub node: Option<Node>,
+    pub condition: Option<BExpr>,
+}
+
+impl Breakpoint {
+    /// Whether the breakpoint is hit at `node` with `memory`, where a
+    /// condition which cannot be evaluated is not hit.
+    pub fn is_hit(&self, node: Node, memory: &InterpreterMemory) -> bool {
+        self.node.is_none_or(|n| n == node)
+            && self
+                .condition
+                .as_ref()
+                .is_none_or(|b| b.semantics(memory) == Ok(true))
+    }
+}
+
+/// An expression evaluated at every step. As variables and array elements
+/// parse as both arithmetic and boolean expressions, the expression is kept
+/// as either which it parses as, and evaluated as a boolean expression if it
+/// cannot be evaluated as an arithmetic one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watch {
+    int: Option<AExpr>,
+    bool: Option<BExpr>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Value {
+    Int(Int),
+    Bool(bool),
+}
+
+impl Watch {
+    pub fn parse(src: &str) -> Result<Watch, ParseError> {
+        let int = src.parse::<AExpr>();
+        let bool = src.parse::<BExpr>();
+        match (int, bool) {
+            (Err(err), Err(_)) => Err(err),
+            (int, bool) => Ok(Watch {
+                int: int.ok(),
+                bool: bool.ok(),
+            }),
+        }
+    }
+
+    pub fn evaluate(&self, memory: &InterpreterMemory) -> Result<Value, SemanticsError> {
+        let int = self
+            .int
+            .as_ref()
+            .map(|a| a.semantics(memory).map(Value::Int));
+        match (int, &self.bool) {
+            (Some(Ok(value)), _) => Ok(value),
+            (int, Some(b)) => b
+                .semantics(memory)
+                .map(Value::Bool)
+                .or_else(|err| int.unwrap_or(Err(err))),
+            (int, None) => int.expect("a watch parses as an expression"),
+        }
+    }
+}
+
+/// Why running a [`Session`] stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// The breakpoint with the given index was hit
+    Breakpoint(usize),
+    /// The node which was run to was reached
+    Reached,
+    /// No step could be taken, as the execution ended in the given state
+    Ended(TerminationState),
+    /// The start of the execution was reached when stepping back
+    Start,
+    /// The number of steps ran out
+    StepLimit,
+}
+
+#[derive(Debug, Clone)]
+pub struct Session {
+    pg: ProgramGraph,
+    execution: Execution,
+    pub breakpoints: Vec<Breakpoint>,
+    pub watches: Vec<Watch>,
+}
+
+impl Session {
+    pub fn new(pg: ProgramGraph, initial_memory: InterpreterMemory) -> Session {
+        Session {
+            pg,
+            execution: Execution::new(initial_memory),
+            breakpoints: vec![],
+            watches: vec![],
+        }
+    }
+    pub fn pg(&self) -> &ProgramGraph {
+        &self.pg
+    }
+    pub fn execution(&self) -> &Execution {
+        &self.execution
+    }
+    pub fn node(&self) -> Node {
+        self.execution.current_node()
+    }
+    pub fn memory(&self) -> &InterpreterMemory {
+        self.execution.current_mem()
+    }
+    pub fn state(&self) -> TerminationState {
+        self.execution.state(&self.pg)
+    }
+    /// The actions which can be taken from the current step, and the nodes
+    /// they lead to.
+    pub fn choices(&self) -> Vec<(Action, Node)> {
+        self.node()
+            .nexts(&self.pg, self.memory())
+            .map(|(node, action, _)| (action.clone(), node))
+            .collect()
+    }
+    /// The values of the watches in the memory of the current step.
+    pub fn watch_values(&self) -> Vec<Result<Value, SemanticsError>> {
+        self.watches
+            .iter()
+            .map(|w| w.evaluate(self.memory()))
+            .collect()
+    }
+    /// The first breakpoint hit at the current step.
+    pub fn breakpoint(&self) -> Option<usize> {
+        self.hits().iter().position(|hit| *hit)
+    }
+    fn hits(&self) -> Vec<bool> {
+        let (node, memory) = (self.node(), self.memory());
+        self.breakpoints
+            .iter()
+            .map(|b| b.is_hit(node, memory))
+            .collect()
+    }
+    /// The first breakpoint to stop at after a step, given the breakpoints
+    /// which were hit before it.
+    fn stopping_breakpoint(&self, before: &[bool]) -> Option<usize> {
+        let hits = self.hits();
+        (0..hits.len()).find(|i| hits[*i] && (self.breakpoints[*i].node.is_some() || !before[*i]))
+    }
+
+    /// Takes the step with index `choice` among the [`choices`](Self::choices),
+    /// modulo the number of choices, or returns `false` if no step can be
+    /// taken.
+    pub fn step(&mut self, choice: usize) -> bool {
+        let mut nexts = self.execution.nexts(&self.pg);
+        if nexts.is_empty() {
+            return false;
+        }
+        let n = nexts.len();
+        self.execution = nexts.swap_remove(choice % n);
+        true
+    }
+    /// Undoes the last step, or returns `false` at the start of the
+    /// execution.
+    pub fn step_back(&mut self) -> bool {
+        self.execution.step_back()
+    }
+    pub fn restart(&mut self) {
+        while self.step_back() {}
+    }
+
+    /// Steps until a breakpoint is hit, for at most `steps` steps. Whenever
+    /// more than one step is possible, `choose` picks the one to take as in
+    /// [`Execution::run`].
+    pub fn resume(&mut self, steps: usize, choose: impl FnMut(&[(Action, Node)]) -> usize) -> Stop {
+        self.run_until(steps, choose, |_| false)
+    }
+    /// Steps until `node` is reached or a breakpoint is hit, for at most
+    /// `steps` steps.
+    pub fn run_to(
+        &mut self,
+        node: Node,
+        steps: usize,
+        choose: impl FnMut(&[(Action, Node)]) -> usize,
+    ) -> Stop {
+        self.run_until(steps, choose, |s| s.node() == node)
+    }
+    /// Steps back until a breakpoint is hit or the start is reached, for at
+    /// most `steps` steps.
+    pub fn reverse(&mut self, steps: usize) -> Stop {
+        for _ in 0..steps {
+            let before = self.hits();
+            if !self.step_back() {
+                return Stop::Start;
+            }
+            if let Some(b) = self.stopping_breakpoint(&before) {
+                return Stop::Breakpoint(b);
+            }
+        }
+        Stop::StepLimit
+    }
+
+    fn run_until(
+        &mut self,
+        steps: usize,
+        mut choose: impl FnMut(&[(Action, Node)]) -> usize,
+        reached: impl Fn(&Session) -> bool,
+    ) -> Stop {
+        for _ in 0..steps {
+            let choices = self.choices();
+            let choice = if choices.len() > 1 {
+                choose(&choices)
+            } else {
+                0
+            };
+            let before = self.hits();
+            if !self.step(choice) {
+                return Stop::Ended(self.state());
+            }
+            if let Some(b) = self.stopping_breakpoint(&before) {
+                return Stop::Breakpoint(b);
+            }
+            if reached(self) {
+                return Stop::Reached;
+            }
+        }
+        Stop::StepLimit
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(n) => write!(f, "{n}"),
+            Value::Bool(b) => write!(f, "{b}"),
+        }
+    }
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stop::Breakpoint(b) => write!(f, "hit breakpoint {}", b + 1),
+            Stop::Reached => write!(f, "reached the node"),
+            Stop::Ended(TerminationState::Terminated) => write!(f, "terminated"),
+            Stop::Ended(TerminationState::AssertionFailed) => write!(f, "an assertion failed"),
+            Stop::Ended(_) => write!(f, "got stuck"),
+            Stop::Start => write!(f, "reached the start"),
+            Stop::StepLimit => write!(f, "ran out of steps"),
+        }
+    }
+}
@@ -0,0 +1,379 @@
+//! Checking that two program graphs compute the same thing.
+//!
+//! Two program graphs are equivalent if, started from the same memory, they
+//! end in the same way with memories which agree on the observed variables.
+//! The check first compares the paths of the two graphs found by
+//! [symbolic execution](crate::symbolic), which proves the graphs equivalent
+//! when no path is cut off by the bound on unrollings and no arrays are
+//! involved. Otherwise, or if the solver can not be run, it falls back to
+//! comparing runs on random inputs.
+
+use std::fmt;
+
+use indexmap::IndexSet;
+use itertools::Itertools;
+use rand::{Rng, SeedableRng, rngs::SmallRng};
+
+use crate::{
+    ast::{AExpr, BExpr, LogicOp, RelOp, Target},
+    interpreter::{InterpreterMemory, TerminationState},
+    pg::{Edge, Node, ProgramGraph},
+    semantics::{Arithmetic, SemanticsContext, WithArithmetic},
+    symbolic::{Path, Solution, SymbolicError, SymbolicExecutor, SymbolicMemory, conjunction},
+};
+
+#[derive(Debug, Clone)]
+pub struct EquivalenceChecker {
+    pub symbolic: SymbolicExecutor,
+    /// The number of random inputs to compare the graphs on, if symbolic
+    /// execution does not decide equivalence
+    pub samples: usize,
+    /// The number of steps after which a run is considered not to terminate
+    pub steps: usize,
+    pub seed: u64,
+}
+
+impl Default for EquivalenceChecker {
+    fn default() -> Self {
+        EquivalenceChecker {
+            symbolic: SymbolicExecutor::default(),
+            samples: 10,
+            steps: 1000,
+            seed: 0xCEC34,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Equivalence {
+    Equivalent,
+    Counterexample(Box<Counterexample>),
+    Unknown { reason: String },
+}
+
+/// An input on which two program graphs end differently.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Counterexample {
+    pub input: InterpreterMemory,
+    pub left: Run,
+    pub right: Run,
+}
+
+/// The end of running a program graph from some input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Run {
+    /// How the run ended, where [`TerminationState::Running`] means that it
+    /// did not terminate within the number of steps
+    pub state: TerminationState,
+    pub memory: InterpreterMemory,
+}
+
+impl Run {
+    pub fn new(pg: &ProgramGraph, input: &InterpreterMemory, steps: usize) -> Run {
+        Run::with_arithmetic(Arithmetic::Checked, pg, input, steps)
+    }
+
+    /// Like [`Run::new`], but evaluating expressions with `arithmetic`.
+    pub fn with_arithmetic(
+        arithmetic: Arithmetic,
+        pg: &ProgramGraph,
+        input: &InterpreterMemory,
+        steps: usize,
+    ) -> Run {
+        let mut node = Node::Start;
+        let mut memory = WithArithmetic::new(arithmetic, input.clone());
+        for _ in 0..steps {
+            let next = pg.outgoing(node).iter().find_map(|Edge(_, action, next)| {
+                action.semantics(&memory).ok().map(|memory| (*next, memory))
+            });
+            match next {
+                Some(next) => (node, memory) = next,
+                None => {
+                    let state = match node {
+                        Node::End => TerminationState::Terminated,
+                        Node::Error => TerminationState::AssertionFailed,
+                        Node::Start | Node::Node(_) => TerminationState::Stuck,
+                    };
+                    return Run {
+                        state,
+                        memory: memory.cx,
+                    };
+                }
+            }
+        }
+        Run {
+            state: TerminationState::Running,
+            memory: memory.cx,
+        }
+    }
+
+    pub fn agrees_with(&self, other: &Run, observed: &IndexSet<Target>) -> bool {
+        self.state == other.state && self.memory.agrees_on(observed, &other.memory)
+    }
+}
+
+impl Equivalence {
+    /// Compares two runs from `input`, for programs which can be run but not
+    /// analysed, such as compiled ones. Runs agreeing on this one input are
+    /// taken as equivalent.
+    pub fn of_runs(
+        input: InterpreterMemory,
+        left: Run,
+        right: Run,
+        observed: &IndexSet<Target>,
+    ) -> Equivalence {
+        match (left.state, right.state) {
+            (TerminationState::Running, _) => Equivalence::Unknown {
+                reason: "the first program did not terminate".to_string(),
+            },
+            (_, TerminationState::Running) => Equivalence::Unknown {
+                reason: "the second program did not terminate".to_string(),
+            },
+            _ if left.agrees_with(&right, observed) => Equivalence::Equivalent,
+            _ => Equivalence::Counterexample(Box::new(Counterexample { input, left, right })),
+        }
+    }
+}
+
+impl EquivalenceChecker {
+    /// Checks that `pg1` and `pg2` are equivalent when observing the
+    /// variables and arrays in `observed`.
+    pub fn check(
+        &self,
+        pg1: &ProgramGraph,
+        pg2: &ProgramGraph,
+        observed: &IndexSet<Target>,
+    ) -> Equivalence {
+        let mut initial = self.symbolic.initial_memory(pg1);
+        initial.extend(self.symbolic.initial_memory(pg2));
+
+        match self.check_symbolically(pg1, pg2, &initial, observed) {
+            Ok(Some(result)) => result,
+            Ok(None) => self.check_randomly(pg1, pg2, &initial, observed),
+            // The solver not being available is no reason not to test, though
+            // an inconclusive test then also says that the solver failed
+            Err(err @ SymbolicError::Solver(_)) => {
+                match self.check_randomly(pg1, pg2, &initial, observed) {
+                    Equivalence::Unknown { reason } => Equivalence::Unknown {
+                        reason: format!("{reason}, and {err}"),
+                    },
+                    result => result,
+                }
+            }
+            Err(err) => Equivalence::Unknown {
+                reason: err.to_string(),
+            },
+        }
+    }
+
+    /// Decides equivalence by comparing the paths through the graphs, or
+    /// returns `None` if the paths considered do not cover every input.
+    fn check_symbolically(
+        &self,
+        pg1: &ProgramGraph,
+        pg2: &ProgramGraph,
+        initial: &SymbolicMemory,
+        observed: &IndexSet<Target>,
+    ) -> Result<Option<Equivalence>, SymbolicError> {
+        let st = smtlib::Storage::new();
+        let mut solver = self.symbolic.solver(&st, initial)?;
+        let paths1 = self.symbolic.paths_with(&mut solver, pg1, initial)?;
+        let paths2 = self.symbolic.paths_with(&mut solver, pg2, initial)?;
+
+        // Every array has the same length in the inputs of symbolic
+        // execution, so only inputs with that length are covered
+        let mut complete = initial.arrays.is_empty() && initial.bool_arrays.is_empty();
+
+        for p1 in &paths1 {
+            if p1.state == TerminationState::Running {
+                complete = false;
+                continue;
+            }
+            // An input taking `p1` and a path of `pg2` which it ends
+            // differently from
+            let differ = paths2
+                .iter()
+                .filter(|p2| {
+                    let cut_off = p2.state == TerminationState::Running;
+                    complete &= !cut_off;
+                    !cut_off
+                })
+                .map(|p2| {
+                    BExpr::logic(
+                        p2.condition.clone(),
+                        LogicOp::Land,
+                        BExpr::not(agreement(p1, p2, observed)),
+                    )
+                })
+                .reduce(|a, b| BExpr::logic(a, LogicOp::Lor, b))
+                .unwrap_or(BExpr::bool(false));
+
+            let input = match solver.solve(&[p1.condition.clone(), differ])? {
+                Solution::Unsat => continue,
+                Solution::Unknown => {
+                    complete = false;
+                    continue;
+                }
+                Solution::Sat(input) => input,
+            };
+            let left = Run::new(pg1, &input, self.steps);
+            let right = Run::new(pg2, &input, self.steps);
+            if left.agrees_with(&right, observed) {
+                // The solver found an input which does not actually take the
+                // paths, so the paths can not be trusted to decide
+                complete = false;
+            } else {
+                return Ok(Some(Equivalence::Counterexample(Box::new(
+                    Counterexample { input, left, right },
+                ))));
+            }
+        }
+
+        Ok(complete.then_some(Equivalence::Equivalent))
+    }
+
+    fn check_randomly(
+        &self,
+        pg1: &ProgramGraph,
+        pg2: &ProgramGraph,
+        initial: &SymbolicMemory,
+        observed: &IndexSet<Target>,
+    ) -> Equivalence {
+        let mut rng = SmallRng::seed_from_u64(self.seed);
+        let mut terminated = false;
+
+        for _ in 0..self.samples {
+            let input = random_input(initial, &mut rng);
+            let left = Run::new(pg1, &input, self.steps);
+            let right = Run::new(pg2, &input, self.steps);
+
+            match (left.state, right.state) {
+                (TerminationState::Running, TerminationState::Running) => continue,
+                (TerminationState::Running, _) => {
+                    return Equivalence::Unknown {
+                        reason: "the first program did not terminate".to_string(),
+                    };
+                }
+                (_, TerminationState::Running) => {
+                    return Equivalence::Unknown {
+                        reason: "the second program did not terminate".to_string(),
+                    };
+                }
+                _ => terminated = true,
+            }
+
+            if !left.agrees_with(&right, observed) {
+                return Equivalence::Counterexample(Box::new(Counterexample {
+                    input,
+                    left,
+                    right,
+                }));
+            }
+        }
+
+        if terminated {
+            Equivalence::Equivalent
+        } else {
+            Equivalence::Unknown {
+                reason: "neither program terminated".to_string(),
+            }
+        }
+    }
+}
+
+/// The condition that the graphs end the same way along `p1` and `p2`.
+fn agreement(p1: &Path, p2: &Path, observed: &IndexSet<Target>) -> BExpr {
+    if p1.state != p2.state {
+        return BExpr::bool(false);
+    }
+
+    let (m1, m2) = (&p1.memory, &p2.memory);
+    let ints = |a: &AExpr, b: &AExpr| BExpr::rel(a.clone(), RelOp::Eq, b.clone());
+    let bools = |a: &BExpr, b: &BExpr| {
+        BExpr::logic(
+            BExpr::logic(a.clone(), LogicOp::Land, b.clone()),
+            LogicOp::Lor,
+            BExpr::logic(BExpr::not(a.clone()), LogicOp::Land, BExpr::not(b.clone())),
+        )
+    };
+
+    conjunction(observed.iter().flat_map(|t| match t {
+        Target::Variable(x) => {
+            let int = m1.variables.get(x).zip(m2.variables.get(x));
+            let bool = m1.bools.get(x).zip(m2.bools.get(x));
+            int.map(|(a, b)| ints(a, b))
+                .into_iter()
+                .chain(bool.map(|(a, b)| bools(a, b)))
+                .collect_vec()
+        }
+        Target::Array(arr, ()) => {
+            let int = m1.arrays.get(arr).zip(m2.arrays.get(arr));
+            let bool = m1.bool_arrays.get(arr).zip(m2.bool_arrays.get(arr));
+            int.into_iter()
+                .flat_map(|(a, b)| a.iter().zip(b).map(|(a, b)| ints(a, b)))
+                .chain(
+                    bool.into_iter()
+                        .flat_map(|(a, b)| a.iter().zip(b).map(|(a, b)| bools(a, b))),
+                )
+                .collect_vec()
+        }
+    }))
+}
+
+/// A random input for the variables and arrays of `initial`, with arrays of
+/// random length.
+fn random_input(initial: &SymbolicMemory, rng: &mut SmallRng) -> InterpreterMemory {
+    InterpreterMemory {
+        variables: initial
+            .variables
+            .keys()
+            .map(|x| (x.clone(), rng.random_range(-10..=10)))
+            .collect(),
+        arrays: initial
+            .arrays
+            .keys()
+            .map(|arr| {
+                let len = rng.random_range(5..=10);
+                (
+                    arr.clone(),
+                    (0..len).map(|_| rng.random_range(-10..=10)).collect(),
+                )
+            })
+            .collect(),
+        bools: initial
+            .bools
+            .keys()
+            .map(|x| (x.clone(), rng.random()))
+            .collect(),
+        bool_arrays: initial
+            .bool_arrays
+            .keys()
+            .map(|arr| {
+                let len = rng.random_range(5..=10);
+                (arr.clone(), (0..len).map(|_| rng.random()).collect())
+            })
+            .collect(),
+    }
+}
+
+impl fmt::Display for Run {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match self.state {
+            TerminationState::Running => "does not terminate, reaching",
+            TerminationState::Stuck => "gets stuck with",
+            TerminationState::Terminated => "terminates with",
+            TerminationState::AssertionFailed => "fails an assertion with",
+        };
+        write!(f, "{state} {:?}", self.memory)
+    }
+}
+
+impl fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "starting from {:?}, the first program {}, while the second program {}",
+            self.input, self.left, self.right
+        )
+    }
+}
@@ -1,17 +1,96 @@
+//! Printing of programs.
+//!
+//! The [`Display`] implementations of the AST print every compound expression
+//! in parentheses and do not know about comments. [`format`] instead produces
+//! the canonical formatting of a program: expressions are printed with only
+//! the necessary parentheses and broken over multiple lines when they do not
+//! fit within the configured width, and the comments of the source are kept.
+
+mod comments;
+mod doc;
+mod printer;
+
 use std::fmt::{Debug, Display};
 
 use itertools::Itertools;
 
-use crate::ast::{
-    AExpr, AOp, Array, BExpr, Command, Commands, Flow, Guard, LogicOp, RelOp, SecurityClass,
-    Target, Variable,
+use crate::{
+    ast::{
+        AExpr, AExprKind, AOp, Array, BExpr, BExprKind, Command, CommandKind, Commands, Flow,
+        Function, Guard, LogicOp, Param, ParamMode, ProcDecl, ProcName, RelOp, SecurityClass,
+        Target, TargetKind, Type, Variable,
+    },
+    parse::ParseErrors,
 };
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FormatOptions {
+    /// The number of columns lines are kept within, where possible.
+    pub width: usize,
+    pub indent: Indent,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            width: 80,
+            indent: Indent::Spaces(3),
+        }
+    }
+}
+
+/// The indentation of the bodies of guards and procedures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Indent {
+    Spaces(usize),
+    /// Indents with tabs, which are counted as [`Indent::TAB_WIDTH`] columns.
+    Tabs,
+}
+
+impl Indent {
+    pub const TAB_WIDTH: usize = 4;
+
+    fn render(self, level: usize) -> String {
+        match self {
+            Indent::Spaces(n) => " ".repeat(n * level),
+            Indent::Tabs => "\t".repeat(level),
+        }
+    }
+    fn width(self, level: usize) -> usize {
+        match self {
+            Indent::Spaces(n) => n * level,
+            Indent::Tabs => Self::TAB_WIDTH * level,
+        }
+    }
+}
+
+/// Formats the program in `src`, keeping its comments and blank lines
+/// between commands.
+pub fn format(src: &str, options: &FormatOptions) -> Result<String, ParseErrors> {
+    let cmds = crate::parse::parse_commands(src)?;
+    Ok(printer::Printer::new(src)
+        .program(&cmds)
+        .render(options.width, options.indent))
+}
+
+/// Formats `cmds` like [`format`]. Since commands do not carry their comments,
+/// use [`format`] when the source is available.
+pub fn format_commands(cmds: &Commands, options: &FormatOptions) -> String {
+    printer::Printer::new("")
+        .program(cmds)
+        .render(options.width, options.indent)
+}
+
 impl Display for Variable {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0)
     }
 }
+impl Display for ProcName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
 impl Display for Array {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0)
@@ -35,13 +114,78 @@ impl std::fmt::Display for Target<()> {
     }
 }
 
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Bool => write!(f, "bool"),
+        }
+    }
+}
+
+impl Display for TargetKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TargetKind::Variable => write!(f, "an integer variable"),
+            TargetKind::Array => write!(f, "an integer array"),
+            TargetKind::BoolVariable => write!(f, "a boolean variable"),
+            TargetKind::BoolArray => write!(f, "a boolean array"),
+        }
+    }
+}
+
 impl Display for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.kind, f)
+    }
+}
+impl Display for CommandKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Command::Assignment(target, expr) => write!(f, "{target} := {expr}"),
-            Command::Skip => write!(f, "skip"),
-            Command::If(guards) => write!(f, "if {}\nfi", guards.iter().format("\n[] ")),
-            Command::Loop(guards) => write!(f, "do {}\nod", guards.iter().format("\n[] ")),
+            CommandKind::Assignment(target, expr) => write!(f, "{target} := {expr}"),
+            CommandKind::BoolAssignment(target, expr) => write!(f, "{target} := {expr}"),
+            CommandKind::ArrayAssignment(arr, elems) => {
+                write!(f, "{arr} := [{}]", elems.iter().format(", "))
+            }
+            CommandKind::Skip => write!(f, "skip"),
+            CommandKind::If(guards) => write!(f, "if {}\nfi", guards.iter().format("\n[] ")),
+            CommandKind::Loop(guards) => write!(f, "do {}\nod", guards.iter().format("\n[] ")),
+            CommandKind::Assert(b) => write!(f, "assert {b}"),
+            CommandKind::Abort => write!(f, "abort"),
+            CommandKind::Proc(decl) => Display::fmt(decl, f),
+            CommandKind::Call(name, args) => write!(f, "{name}({})", args.iter().format(", ")),
+            CommandKind::Par(components) => write!(
+                f,
+                "par\n{}\nrap",
+                components
+                    .iter()
+                    .map(|c| c.to_string().lines().map(|l| format!("   {l}")).join("\n"))
+                    .format("\n[]\n")
+            ),
+        }
+    }
+}
+
+impl Display for ProcDecl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "proc {}({}) {{\n{}\n}}",
+            self.name,
+            self.params.iter().format(", "),
+            self.body
+                .to_string()
+                .lines()
+                .map(|l| format!("   {l}"))
+                .format("\n")
+        )
+    }
+}
+impl Display for Param {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.mode {
+            ParamMode::In => write!(f, "in {}", self.var),
+            ParamMode::Out => write!(f, "out {}", self.var),
         }
     }
 }
@@ -57,8 +201,8 @@ impl Display for Guard {
         write!(
             f,
             "{} ->\n{}",
-            self.0,
-            self.1
+            self.guard,
+            self.cmds
                 .to_string()
                 .lines()
                 .map(|l| format!("   {l}"))
@@ -68,12 +212,23 @@ impl Display for Guard {
 }
 
 impl Display for AExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            AExprKind::Number(n) => write!(f, "{n}"),
+            AExprKind::Reference(x) => write!(f, "{x}"),
+            AExprKind::Binary(l, op, r) => write!(f, "({l} {op} {r})"),
+            AExprKind::Minus(m) => write!(f, "-{m}"),
+            AExprKind::Function(func) => write!(f, "{func}"),
+        }
+    }
+}
+impl Display for Function {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            AExpr::Number(n) => write!(f, "{n}"),
-            AExpr::Reference(x) => write!(f, "{x}"),
-            AExpr::Binary(l, op, r) => write!(f, "({l} {op} {r})"),
-            AExpr::Minus(m) => write!(f, "-{m}"),
+            Function::Min(a, b) => write!(f, "min({a}, {b})"),
+            Function::Max(a, b) => write!(f, "max({a}, {b})"),
+            Function::Length(arr) => write!(f, "len({arr})"),
+            Function::Count(arr, a) => write!(f, "count({arr}, {a})"),
         }
     }
 }
@@ -90,11 +245,12 @@ impl Display for AOp {
 }
 impl Display for BExpr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            BExpr::Bool(b) => write!(f, "{b}"),
-            BExpr::Rel(l, op, r) => write!(f, "({l} {op} {r})"),
-            BExpr::Logic(l, op, r) => write!(f, "({l} {op} {r})"),
-            BExpr::Not(b) => write!(f, "!{b}"),
+        match &self.kind {
+            BExprKind::Bool(b) => write!(f, "{b}"),
+            BExprKind::Reference(x) => write!(f, "{x}"),
+            BExprKind::Rel(l, op, r) => write!(f, "({l} {op} {r})"),
+            BExprKind::Logic(l, op, r) => write!(f, "({l} {op} {r})"),
+            BExprKind::Not(b) => write!(f, "!{b}"),
         }
     }
 }
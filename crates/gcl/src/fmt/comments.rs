@@ -0,0 +1,52 @@
+//! The parser skips comments along with whitespace, so to preserve them when
+//! formatting they are recovered from the source by a separate scan, and then
+//! placed by their position relative to the spans of the parsed commands.
+
+use crate::parse::SourceSpan;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CommentKind {
+    /// `// comment`, running until the end of the line
+    Line,
+    /// `/* comment */`
+    Block,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Comment<'a> {
+    pub kind: CommentKind,
+    pub span: SourceSpan,
+    pub text: &'a str,
+}
+
+/// Finds all comments in `src`, in the order they appear.
+pub(crate) fn comments(src: &str) -> Vec<Comment<'_>> {
+    let mut comments = Vec::new();
+    let mut offset = 0;
+
+    while let Some(start) = src[offset..].find('/').map(|i| offset + i) {
+        let rest = &src[start..];
+        let (kind, len) = if rest.starts_with("//") {
+            let len = rest.find(['\n', '\r']).unwrap_or(rest.len());
+            (CommentKind::Line, len)
+        } else if let Some(body) = rest.strip_prefix("/*") {
+            match body.find("*/") {
+                Some(end) => (CommentKind::Block, end + 4),
+                // An unterminated comment is a syntax error, so it never
+                // reaches the formatter
+                None => break,
+            }
+        } else {
+            offset = start + 1;
+            continue;
+        };
+        comments.push(Comment {
+            kind,
+            span: (start, len).into(),
+            text: rest[..len].trim_end(),
+        });
+        offset = start + len;
+    }
+
+    comments
+}
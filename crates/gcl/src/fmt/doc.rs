@@ -0,0 +1,122 @@
+//! A small document algebra for width-aware layout, in the style of Wadler's
+//! "A prettier printer".
+//!
+//! A [`Doc`] describes text along with the places it may be broken into
+//! multiple lines. Each [`Doc::Group`] is laid out on a single line if it fits
+//! within the remaining width, and otherwise has all of its direct line breaks
+//! turned into newlines.
+
+use super::Indent;
+
+#[derive(Debug, Clone)]
+pub(crate) enum Doc {
+    Text(String),
+    /// A space, or a newline if the enclosing group is broken
+    Line,
+    /// Nothing, or a newline if the enclosing group is broken
+    SoftLine,
+    /// A newline, which breaks all enclosing groups
+    HardLine,
+    /// Increases the indentation of lines in the document by one level
+    Nest(Box<Doc>),
+    Group(Box<Doc>),
+    Concat(Vec<Doc>),
+}
+
+impl Doc {
+    pub fn text(s: impl Into<String>) -> Doc {
+        Doc::Text(s.into())
+    }
+    pub fn nest(doc: Doc) -> Doc {
+        Doc::Nest(Box::new(doc))
+    }
+    pub fn group(doc: Doc) -> Doc {
+        Doc::Group(Box::new(doc))
+    }
+    pub fn concat(docs: impl IntoIterator<Item = Doc>) -> Doc {
+        Doc::Concat(docs.into_iter().collect())
+    }
+
+    /// Lays out the document within `width` columns.
+    pub fn render(&self, width: usize, indent: Indent) -> String {
+        let mut out = String::new();
+        let mut col = 0;
+        // Indentation is written lazily before the next text, such that empty
+        // lines do not contain trailing whitespace
+        let mut pending_indent = None;
+        let mut stack = vec![(0, Mode::Break, self)];
+
+        while let Some((level, mode, doc)) = stack.pop() {
+            match doc {
+                Doc::Text(s) if s.is_empty() => {}
+                Doc::Text(s) => {
+                    if let Some(level) = pending_indent.take() {
+                        out += &indent.render(level);
+                        col = indent.width(level);
+                    }
+                    out += s;
+                    col += s.chars().count();
+                }
+                Doc::Line if mode == Mode::Flat => {
+                    out.push(' ');
+                    col += 1;
+                }
+                Doc::SoftLine if mode == Mode::Flat => {}
+                Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                    out.push('\n');
+                    col = 0;
+                    pending_indent = Some(level);
+                }
+                Doc::Nest(doc) => stack.push((level + 1, mode, doc)),
+                Doc::Group(doc) => {
+                    let col = pending_indent.map_or(col, |level| indent.width(level));
+                    let mode = if mode == Mode::Flat
+                        || fits(width.saturating_sub(col), (level, Mode::Flat, doc), &stack)
+                    {
+                        Mode::Flat
+                    } else {
+                        Mode::Break
+                    };
+                    stack.push((level, mode, doc));
+                }
+                Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|d| (level, mode, d))),
+            }
+        }
+
+        out
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+/// Determines if `next` fits within `remaining` columns when laid out flat,
+/// including whatever follows it on the same line.
+fn fits(mut remaining: usize, next: (usize, Mode, &Doc), rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut rest = rest.iter().rev().copied();
+    let mut stack = vec![next];
+
+    loop {
+        let Some((level, mode, doc)) = stack.pop().or_else(|| rest.next()) else {
+            return true;
+        };
+        match doc {
+            Doc::Text(s) => match remaining.checked_sub(s.chars().count()) {
+                Some(r) => remaining = r,
+                None => return false,
+            },
+            Doc::Line if mode == Mode::Flat => match remaining.checked_sub(1) {
+                Some(r) => remaining = r,
+                None => return false,
+            },
+            Doc::SoftLine if mode == Mode::Flat => {}
+            Doc::HardLine if mode == Mode::Flat => return false,
+            Doc::Line | Doc::SoftLine | Doc::HardLine => return true,
+            Doc::Nest(doc) | Doc::Group(doc) => stack.push((level, mode, doc)),
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|d| (level, mode, d))),
+        }
+    }
+}
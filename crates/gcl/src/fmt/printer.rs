@@ -0,0 +1,391 @@
+use std::collections::VecDeque;
+
+use itertools::Itertools;
+
+use super::{
+    comments::{Comment, CommentKind, comments},
+    doc::Doc,
+};
+use crate::ast::{
+    AExpr, AExprKind, AOp, BExpr, BExprKind, Command, CommandKind, Commands, Function, Guard,
+    LogicOp, Target,
+};
+
+/// Builds the layout of a program, interleaving the comments of its source.
+pub(super) struct Printer<'a> {
+    src: &'a str,
+    comments: VecDeque<Comment<'a>>,
+    /// The end of what was last printed, in the source. Used to preserve
+    /// blank lines between commands.
+    last: usize,
+}
+
+impl<'a> Printer<'a> {
+    pub fn new(src: &'a str) -> Self {
+        Printer {
+            src,
+            comments: comments(src).into(),
+            last: 0,
+        }
+    }
+
+    pub fn program(mut self, cmds: &Commands) -> Doc {
+        Doc::concat([self.block(cmds, usize::MAX), Doc::HardLine])
+    }
+
+    /// Lays out a sequence of commands along with the comments before `end`,
+    /// one command per line.
+    fn block(&mut self, cmds: &Commands, end: usize) -> Doc {
+        let mut docs = Vec::new();
+
+        for (idx, cmd) in cmds.0.iter().enumerate() {
+            if idx > 0 {
+                docs.push(Doc::HardLine);
+            }
+            while let Some(c) = self.take_comment_before(cmd.span.offset()) {
+                self.blank_line(&mut docs, c.span.offset());
+                docs.push(self.comment(c));
+                docs.push(Doc::HardLine);
+            }
+            self.blank_line(&mut docs, cmd.span.offset());
+            docs.push(self.command(cmd));
+            self.last = cmd.span.end();
+
+            let next = cmds.0.get(idx + 1).map_or(end, |c| c.span.offset());
+            if idx + 1 < cmds.0.len() {
+                docs.push(Doc::text(" ;"));
+            }
+            // Comments on the same line as the end of the command, along with
+            // those inside of expressions, are kept after the command
+            while let Some(c) = self.take_comment_before(self.line_end(self.last).min(next)) {
+                docs.push(Doc::text(" "));
+                docs.push(self.comment(c));
+            }
+        }
+
+        while let Some(c) = self.take_comment_before(end) {
+            docs.push(Doc::HardLine);
+            self.blank_line(&mut docs, c.span.offset());
+            docs.push(self.comment(c));
+        }
+
+        Doc::concat(docs)
+    }
+
+    fn command(&mut self, cmd: &Command) -> Doc {
+        match &cmd.kind {
+            CommandKind::Assignment(t, a) => {
+                Doc::concat([self.target(t), Doc::text(" := "), self.aexpr(a)])
+            }
+            CommandKind::BoolAssignment(t, b) => {
+                Doc::concat([self.target(t), Doc::text(" := "), self.bexpr(b)])
+            }
+            CommandKind::ArrayAssignment(arr, elems) => list(
+                format!("{arr} := ["),
+                elems.iter().map(|a| self.aexpr(a)),
+                "]",
+            ),
+            CommandKind::Skip => Doc::text("skip"),
+            CommandKind::Assert(b) => Doc::concat([Doc::text("assert "), self.bexpr(b)]),
+            CommandKind::Abort => Doc::text("abort"),
+            CommandKind::If(guards) => self.guards("if", guards, "fi", cmd.span.end()),
+            CommandKind::Loop(guards) => self.guards("do", guards, "od", cmd.span.end()),
+            CommandKind::Proc(decl) => Doc::concat([
+                Doc::text(format!(
+                    "proc {}({}) {{",
+                    decl.name,
+                    decl.params.iter().format(", ")
+                )),
+                Doc::nest(Doc::concat([
+                    Doc::HardLine,
+                    self.block(&decl.body, cmd.span.end()),
+                ])),
+                Doc::HardLine,
+                Doc::text("}"),
+            ]),
+            CommandKind::Call(name, args) => {
+                list(format!("{name}("), args.iter().map(|a| self.aexpr(a)), ")")
+            }
+            CommandKind::Par(components) => self.par(components, cmd.span.end()),
+        }
+    }
+
+    fn par(&mut self, components: &[Commands], end: usize) -> Doc {
+        let mut docs = vec![Doc::text("par")];
+        for (idx, cmds) in components.iter().enumerate() {
+            if idx > 0 {
+                docs.push(Doc::HardLine);
+                docs.push(Doc::text("[]"));
+            }
+            let body_end = match (cmds.0.last(), components.get(idx + 1)) {
+                (Some(last), Some(next)) => {
+                    let next = next.0.first().map_or(end, |c| c.span.offset());
+                    self.separator(last.span.end(), next)
+                }
+                _ => end,
+            };
+            docs.push(Doc::nest(Doc::concat([
+                Doc::HardLine,
+                self.block(cmds, body_end),
+            ])));
+        }
+        docs.push(Doc::HardLine);
+        docs.push(Doc::text("rap"));
+
+        Doc::concat(docs)
+    }
+
+    fn guards(&mut self, open: &str, guards: &[Guard], close: &str, end: usize) -> Doc {
+        if guards.is_empty() {
+            return Doc::text(format!("{open} {close}"));
+        }
+
+        let mut docs = vec![Doc::text(format!("{open} "))];
+        for (idx, g) in guards.iter().enumerate() {
+            if idx > 0 {
+                docs.push(Doc::HardLine);
+                docs.push(Doc::text("[] "));
+            }
+            while let Some(c) = self.take_comment_before(g.guard_span.offset()) {
+                docs.push(self.comment(c));
+                docs.push(match c.kind {
+                    CommentKind::Line => Doc::nest(Doc::HardLine),
+                    CommentKind::Block => Doc::text(" "),
+                });
+            }
+            docs.push(Doc::group(self.bexpr(&g.guard)));
+            docs.push(Doc::text(" ->"));
+            self.last = g.guard_span.end();
+
+            let body_end = guards.get(idx + 1).map_or(end, |g| g.guard_span.offset());
+            let first = g.cmds.0.first().map_or(body_end, |c| c.span.offset());
+            while let Some(c) = self.take_comment_before(self.line_end(self.last).min(first)) {
+                docs.push(Doc::text(" "));
+                docs.push(self.comment(c));
+            }
+            docs.push(Doc::nest(Doc::concat([
+                Doc::HardLine,
+                self.block(&g.cmds, body_end),
+            ])));
+        }
+        docs.push(Doc::HardLine);
+        docs.push(Doc::text(close));
+
+        Doc::concat(docs)
+    }
+
+    /// The offset of the `[]` between `from` and `to` which is not part of a
+    /// comment, as the separators of `par` components have no span.
+    fn separator(&self, from: usize, to: usize) -> usize {
+        self.src
+            .get(from..to)
+            .into_iter()
+            .flat_map(|gap| gap.match_indices("[]"))
+            .map(|(i, _)| from + i)
+            .find(|&i| {
+                !self
+                    .comments
+                    .iter()
+                    .any(|c| c.span.offset() <= i && i < c.span.end())
+            })
+            .unwrap_or(to)
+    }
+
+    fn target(&self, t: &Target<Box<AExpr>>) -> Doc {
+        match t {
+            Target::Variable(v) => Doc::text(v.to_string()),
+            Target::Array(arr, idx) => Doc::concat([
+                Doc::text(format!("{arr}[")),
+                self.aexpr(idx),
+                Doc::text("]"),
+            ]),
+        }
+    }
+
+    fn aexpr(&self, a: &AExpr) -> Doc {
+        match &a.kind {
+            AExprKind::Number(n) => Doc::text(n.to_string()),
+            AExprKind::Reference(t) => self.target(t),
+            AExprKind::Minus(x) => Doc::concat([
+                Doc::text("-"),
+                parens(self.aexpr(x), aexpr_precedence(x) > 1),
+            ]),
+            AExprKind::Function(f) => match f {
+                Function::Min(a, b) => list("min(", [self.aexpr(a), self.aexpr(b)], ")"),
+                Function::Max(a, b) => list("max(", [self.aexpr(a), self.aexpr(b)], ")"),
+                Function::Length(arr) => Doc::text(format!("len({arr})")),
+                Function::Count(arr, a) => {
+                    list("count(", [Doc::text(arr.to_string()), self.aexpr(a)], ")")
+                }
+            },
+            // Exponentiation is the only right associative operator
+            AExprKind::Binary(l, AOp::Pow, r) => chain(
+                parens(self.aexpr(l), aexpr_precedence(l) >= 2),
+                [(
+                    AOp::Pow.to_string(),
+                    parens(self.aexpr(r), aexpr_precedence(r) > 2),
+                )],
+            ),
+            AExprKind::Binary(..) => {
+                let level = aexpr_precedence(a);
+                let mut rest = Vec::new();
+                let mut first = a;
+                while let AExprKind::Binary(l, op, r) = &first.kind
+                    && aexpr_precedence(first) == level
+                {
+                    rest.push((
+                        op.to_string(),
+                        parens(self.aexpr(r), aexpr_precedence(r) >= level),
+                    ));
+                    first = l;
+                }
+                rest.reverse();
+                chain(
+                    parens(self.aexpr(first), aexpr_precedence(first) > level),
+                    rest,
+                )
+            }
+        }
+    }
+
+    fn bexpr(&self, b: &BExpr) -> Doc {
+        match &b.kind {
+            BExprKind::Bool(b) => Doc::text(b.to_string()),
+            BExprKind::Reference(t) => self.target(t),
+            BExprKind::Rel(l, op, r) => chain(self.aexpr(l), [(op.to_string(), self.aexpr(r))]),
+            // The negation binds looser than relations, but `!(x < y)` reads
+            // better than `!x < y`
+            BExprKind::Not(x) => Doc::concat([
+                Doc::text("!"),
+                parens(self.bexpr(x), bexpr_precedence(x) > 1),
+            ]),
+            BExprKind::Logic(..) => {
+                let level = bexpr_precedence(b);
+                let mut rest = Vec::new();
+                let mut first = b;
+                while let BExprKind::Logic(l, op, r) = &first.kind
+                    && bexpr_precedence(first) == level
+                {
+                    rest.push((
+                        op.to_string(),
+                        parens(self.bexpr(r), bexpr_precedence(r) >= level),
+                    ));
+                    first = l;
+                }
+                rest.reverse();
+                chain(
+                    parens(self.bexpr(first), bexpr_precedence(first) > level),
+                    rest,
+                )
+            }
+        }
+    }
+
+    fn comment(&mut self, c: Comment) -> Doc {
+        self.last = c.span.end();
+        if c.kind == CommentKind::Line {
+            return Doc::text(c.text);
+        }
+        // Continuation lines of block comments are kept at the same position
+        // relative to the start of the comment
+        let (_, col) = c.span.line_col(self.src);
+        let mut lines = c.text.lines();
+        let first = Doc::text(lines.next().unwrap_or_default());
+        Doc::concat(std::iter::once(first).chain(lines.flat_map(|line| {
+            let indent = line
+                .chars()
+                .take(col - 1)
+                .take_while(|c| c.is_whitespace())
+                .map(char::len_utf8)
+                .sum::<usize>();
+            [Doc::HardLine, Doc::text(line[indent..].trim_end())]
+        })))
+    }
+
+    fn take_comment_before(&mut self, offset: usize) -> Option<Comment<'a>> {
+        if self.comments.front()?.span.offset() < offset {
+            self.comments.pop_front()
+        } else {
+            None
+        }
+    }
+
+    /// Adds an empty line if there is one in the source between what was last
+    /// printed and `offset`.
+    fn blank_line(&self, docs: &mut Vec<Doc>, offset: usize) {
+        let gap = self.src.get(self.last..offset).unwrap_or_default();
+        if !docs.is_empty() && gap.matches('\n').count() > 1 {
+            docs.push(Doc::HardLine);
+        }
+    }
+
+    /// The offset of the end of the line containing `offset`.
+    fn line_end(&self, offset: usize) -> usize {
+        self.src
+            .get(offset..)
+            .and_then(|rest| rest.find(['\n', '\r']))
+            .map_or(usize::MAX, |i| offset + i)
+    }
+}
+
+/// Lays out `first op x op y ...`, breaking before every operator if it does
+/// not fit on the line.
+fn chain(first: Doc, rest: impl IntoIterator<Item = (String, Doc)>) -> Doc {
+    Doc::group(Doc::concat([
+        first,
+        Doc::nest(Doc::concat(
+            rest.into_iter()
+                .flat_map(|(op, x)| [Doc::Line, Doc::text(format!("{op} ")), x]),
+        )),
+    ]))
+}
+
+/// The items separated by commas between `open` and `close`, broken over
+/// one line each if they do not fit.
+fn list(open: impl Into<String>, items: impl IntoIterator<Item = Doc>, close: &str) -> Doc {
+    Doc::concat([
+        Doc::text(open.into()),
+        Doc::group(Doc::concat([
+            Doc::nest(Doc::concat([
+                Doc::SoftLine,
+                Doc::concat(Itertools::intersperse(
+                    items.into_iter(),
+                    Doc::concat([Doc::text(","), Doc::Line]),
+                )),
+            ])),
+            Doc::SoftLine,
+        ])),
+        Doc::text(close),
+    ])
+}
+
+fn parens(doc: Doc, needed: bool) -> Doc {
+    if needed {
+        Doc::concat([Doc::text("("), doc, Doc::text(")")])
+    } else {
+        doc
+    }
+}
+
+/// The precedence levels of the grammar, where lower levels bind tighter.
+fn aexpr_precedence(a: &AExpr) -> u8 {
+    match &a.kind {
+        AExprKind::Number(_)
+        | AExprKind::Reference(_)
+        | AExprKind::Minus(_)
+        | AExprKind::Function(_) => 1,
+        AExprKind::Binary(_, AOp::Pow, _) => 2,
+        AExprKind::Binary(_, AOp::Times | AOp::Divide, _) => 3,
+        AExprKind::Binary(_, AOp::Plus | AOp::Minus, _) => 4,
+    }
+}
+
+fn bexpr_precedence(b: &BExpr) -> u8 {
+    match &b.kind {
+        BExprKind::Bool(_) | BExprKind::Reference(_) => 1,
+        BExprKind::Rel(..) => 5,
+        BExprKind::Not(_) => 6,
+        BExprKind::Logic(_, LogicOp::And | LogicOp::Land, _) => 7,
+        BExprKind::Logic(_, LogicOp::Or | LogicOp::Lor, _) => 8,
+    }
+}
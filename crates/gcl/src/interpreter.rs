@@ -1,13 +1,14 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
-use itertools::Itertools;
+use itertools::{Itertools, chain};
 use serde::{Deserialize, Serialize};
 use stdx::stringify::Stringify;
 
 use crate::{
-    ast::{Array, Int, Variable},
+    ast::{Array, Commands, Int, Target, TargetDef, Type, Variable},
     pg::{Action, Edge, Node, ProgramGraph},
-    semantics::{SemanticsContext, SemanticsError},
+    procedures::MAX_CALL_DEPTH,
+    semantics::{LocatedSemanticsError, SemanticsContext, SemanticsError},
 };
 
 #[derive(
@@ -17,6 +18,10 @@ use crate::{
 pub struct InterpreterMemory {
     pub variables: BTreeMap<Variable, Int>,
     pub arrays: BTreeMap<Array, Vec<Int>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub bools: BTreeMap<Variable, bool>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub bool_arrays: BTreeMap<Array, Vec<bool>>,
 }
 
 #[derive(tapi::Tapi, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -33,13 +38,96 @@ pub enum TerminationState {
     Running,
     Stuck,
     Terminated,
+    /// An assertion failed or the program aborted
+    AssertionFailed,
 }
 
-fn lookup_array<'a>(
-    mem: &'a InterpreterMemory,
+/// The `assert` or `abort` command which failed an execution.
+#[derive(tapi::Tapi, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[tapi(path = "Interpreter")]
+pub struct FailedAssertion {
+    /// The step of the trace which failed, counting from 1
+    pub step: usize,
+    /// The failed command as written in the program
+    pub command: String,
+    /// The line and column of the failed command in the program
+    pub location: Option<(usize, usize)>,
+    #[serde(default)]
+    pub failure: Failure,
+    /// What went wrong, as shown to the user after the command
+    #[serde(default)]
+    pub message: String,
+}
+
+/// Why an execution failed.
+#[derive(tapi::Tapi, Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[tapi(path = "Interpreter")]
+pub enum Failure {
+    /// An assertion did not hold or the program aborted
+    #[default]
+    Assertion,
+    /// A call exceeded [`MAX_CALL_DEPTH`] activations of its procedure
+    CallDepthExceeded,
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Failure::Assertion => write!(f, "failed"),
+            // Not a failure of the program itself, so say whose limit it is
+            Failure::CallDepthExceeded => write!(
+                f,
+                "exceeded the interpreter's limit of {MAX_CALL_DEPTH} nested procedure calls"
+            ),
+        }
+    }
+}
+
+impl InterpreterMemory {
+    /// The memory without the parameters of procedure activations, which are
+    /// internal to the execution and not part of the state of the program.
+    pub fn without_locals(&self) -> InterpreterMemory {
+        InterpreterMemory {
+            variables: self
+                .variables
+                .iter()
+                .filter(|(v, _)| !v.is_local())
+                .map(|(v, n)| (v.clone(), *n))
+                .collect(),
+            bools: self
+                .bools
+                .iter()
+                .filter(|(v, _)| !v.is_local())
+                .map(|(v, b)| (v.clone(), *b))
+                .collect(),
+            ..self.clone()
+        }
+    }
+    /// The variables and arrays in memory along with their types.
+    pub fn target_defs(&self) -> BTreeSet<TargetDef> {
+        chain!(
+            self.variables
+                .keys()
+                .map(|v| Target::<()>::Variable(v.clone()).typed_def(Type::Int)),
+            self.arrays
+                .keys()
+                .map(|a| Target::Array(a.clone(), ()).typed_def(Type::Int)),
+            self.bools
+                .keys()
+                .map(|v| Target::<()>::Variable(v.clone()).typed_def(Type::Bool)),
+            self.bool_arrays
+                .keys()
+                .map(|a| Target::Array(a.clone(), ()).typed_def(Type::Bool)),
+        )
+        .collect()
+    }
+}
+
+fn lookup_array<'a, T>(
+    arrays: &'a BTreeMap<Array, Vec<T>>,
     array: &Array,
-) -> Result<&'a [Int], SemanticsError> {
-    mem.arrays
+) -> Result<&'a [T], SemanticsError> {
+    arrays
         .get(array)
         .ok_or_else(|| SemanticsError::ArrayNotFound {
             name: array.to_string(),
@@ -58,7 +146,7 @@ impl SemanticsContext for InterpreterMemory {
     }
 
     fn set_variable(&self, var: &Variable, value: Int) -> Result<Self, SemanticsError> {
-        if self.variables.contains_key(var) {
+        if self.variables.contains_key(var) || var.is_local() {
             let mut m2 = self.clone();
             m2.variables.insert(var.clone(), value);
             Ok(m2)
@@ -70,7 +158,7 @@ impl SemanticsContext for InterpreterMemory {
     }
 
     fn array_element(&self, array: &Array, index: Int) -> Result<Int, SemanticsError> {
-        let data = lookup_array(self, array)?;
+        let data = lookup_array(&self.arrays, array)?;
         data.get(index as usize)
             .ok_or_else(|| SemanticsError::IndexOutOfBound {
                 name: array.to_string(),
@@ -85,30 +173,85 @@ impl SemanticsContext for InterpreterMemory {
         index: Int,
         value: Int,
     ) -> Result<Self, SemanticsError> {
-        match self.arrays.get(array) {
-            Some(data) if 0 <= index && index < data.len() as _ => {
-                let mut m2 = self.clone();
-                let data = m2.arrays.get_mut(array).unwrap();
-                data[index as usize] = value;
-                Ok(m2)
-            }
-            Some(_) => Err(SemanticsError::ArrayNotFound {
+        let data = lookup_array(&self.arrays, array)?;
+        if 0 <= index && index < data.len() as _ {
+            let mut m2 = self.clone();
+            m2.arrays.get_mut(array).unwrap()[index as usize] = value;
+            Ok(m2)
+        } else {
+            Err(SemanticsError::IndexOutOfBound {
                 name: array.to_string(),
-            }),
-            None => Err(SemanticsError::IndexOutOfBound {
+                index,
+            })
+        }
+    }
+
+    fn set_array(&self, array: &Array, values: Vec<Int>) -> Result<Self, SemanticsError> {
+        lookup_array(&self.arrays, array)?;
+        let mut m2 = self.clone();
+        m2.arrays.insert(array.clone(), values);
+        Ok(m2)
+    }
+
+    fn bool_variable(&self, var: &Variable) -> Result<bool, SemanticsError> {
+        self.bools
+            .get(var)
+            .ok_or_else(|| SemanticsError::VariableNotFound {
+                name: var.to_string(),
+            })
+            .copied()
+    }
+
+    fn set_bool_variable(&self, var: &Variable, value: bool) -> Result<Self, SemanticsError> {
+        if self.bools.contains_key(var) || var.is_local() {
+            let mut m2 = self.clone();
+            m2.bools.insert(var.clone(), value);
+            Ok(m2)
+        } else {
+            Err(SemanticsError::VariableNotFound {
+                name: var.to_string(),
+            })
+        }
+    }
+
+    fn bool_array_element(&self, array: &Array, index: Int) -> Result<bool, SemanticsError> {
+        let data = lookup_array(&self.bool_arrays, array)?;
+        data.get(index as usize)
+            .ok_or_else(|| SemanticsError::IndexOutOfBound {
+                name: array.to_string(),
+                index,
+            })
+            .copied()
+    }
+
+    fn set_bool_array_element(
+        &self,
+        array: &Array,
+        index: Int,
+        value: bool,
+    ) -> Result<Self, SemanticsError> {
+        let data = lookup_array(&self.bool_arrays, array)?;
+        if 0 <= index && index < data.len() as _ {
+            let mut m2 = self.clone();
+            m2.bool_arrays.get_mut(array).unwrap()[index as usize] = value;
+            Ok(m2)
+        } else {
+            Err(SemanticsError::IndexOutOfBound {
                 name: array.to_string(),
                 index,
-            }),
+            })
         }
     }
 
     fn array_length(&self, array: &Array) -> Result<Int, SemanticsError> {
-        let data = lookup_array(self, array)?;
-        Ok(data.len() as _)
+        match lookup_array(&self.arrays, array) {
+            Ok(data) => Ok(data.len() as _),
+            Err(_) => Ok(lookup_array(&self.bool_arrays, array)?.len() as _),
+        }
     }
 
     fn array_count(&self, array: &Array, element: Int) -> Result<Int, SemanticsError> {
-        let data = lookup_array(self, array)?;
+        let data = lookup_array(&self.arrays, array)?;
         Ok(data.iter().filter(|e| **e == element).count() as _)
     }
 }
@@ -141,11 +284,26 @@ impl Execution {
     pub fn is_finished(&self) -> bool {
         self.current_node() == Node::End
     }
+    pub fn has_failed(&self) -> bool {
+        self.current_node() == Node::Error
+    }
     pub fn is_stuck(&self, pg: &ProgramGraph) -> bool {
         self.current_node().is_stuck(pg, self.current_mem())
     }
+    /// The edge taken by the last step of the execution.
+    pub fn last_edge(&self) -> Option<Edge> {
+        let (step, to) = self.trace.last()?;
+        let from = self
+            .trace
+            .iter()
+            .nth_back(1)
+            .map_or(Node::Start, |(_, n)| *n);
+        Some(Edge(from, step.action.try_parse().ok()?, *to))
+    }
     pub fn state(&self, pg: &ProgramGraph) -> TerminationState {
-        if self.is_stuck(pg) {
+        if self.has_failed() {
+            TerminationState::AssertionFailed
+        } else if self.is_stuck(pg) {
             if self.is_finished() {
                 TerminationState::Terminated
             } else {
@@ -155,6 +313,58 @@ impl Execution {
             TerminationState::Running
         }
     }
+    /// The command which failed the execution, if it has failed. The
+    /// command is looked up in `src`, the source of the program of `pg`.
+    ///
+    /// Calls are only inlined up to a fixed depth, beyond which they fail the
+    /// execution from the span of the call.
+    pub fn failed_assertion(&self, pg: &ProgramGraph, src: &str) -> Option<FailedAssertion> {
+        if !self.has_failed() {
+            return None;
+        }
+        let edge = self.last_edge()?;
+        let span = pg.span(&edge);
+        let is_call = |span| {
+            src.parse::<Commands>()
+                .is_ok_and(|cmds| cmds.call_spans().contains(&span))
+        };
+        let failure = if span.is_some_and(is_call) {
+            Failure::CallDepthExceeded
+        } else {
+            Failure::Assertion
+        };
+        Some(FailedAssertion {
+            failure,
+            message: failure.to_string(),
+            step: self.trace.len(),
+            command: span
+                .and_then(|span| src.get(span.offset()..span.end()))
+                .map_or_else(|| edge.action().to_string(), str::to_string),
+            location: span.map(|span| span.line_col(src)),
+        })
+    }
+    /// The errors raised by the actions leaving the current node, other than
+    /// by guards which do not hold, which is why an execution gets stuck.
+    pub fn errors(&self, pg: &ProgramGraph) -> Vec<LocatedSemanticsError> {
+        pg.outgoing(self.current_node())
+            .iter()
+            .filter_map(|edge| match edge.action().semantics(self.current_mem()) {
+                Ok(_) | Err(SemanticsError::NoProgression) => None,
+                Err(error) => Some(LocatedSemanticsError {
+                    error,
+                    span: edge
+                        .action()
+                        .error_span(self.current_mem())
+                        .or(pg.span(edge)),
+                }),
+            })
+            .collect()
+    }
+    /// Undoes the last step of the execution, or returns `false` if no step
+    /// has been taken.
+    pub fn step_back(&mut self) -> bool {
+        self.trace.pop().is_some()
+    }
     pub fn nexts(&self, pg: &ProgramGraph) -> Vec<Execution> {
         self.current_node()
             .nexts(pg, self.current_mem())
@@ -172,6 +382,60 @@ impl Execution {
             })
             .collect_vec()
     }
+
+    /// Runs the execution for at most `steps` steps. Whenever more than one
+    /// step is possible, such as between the interleavings of a `par`
+    /// command, `choose` is given the possible executions and picks the index
+    /// of the one to continue with, modulo the number of executions.
+    pub fn run(
+        self,
+        pg: &ProgramGraph,
+        steps: usize,
+        mut choose: impl FnMut(&[Execution]) -> usize,
+    ) -> Execution {
+        let mut exe = self;
+        for _ in 0..steps {
+            let mut nexts = exe.nexts(pg);
+            exe = match nexts.len() {
+                0 => break,
+                1 => nexts.pop().unwrap(),
+                n => nexts.swap_remove(choose(&nexts) % n),
+            };
+        }
+        exe
+    }
+
+    /// Explores all of the ways the execution can continue for at most
+    /// `steps` steps, returning the executions which cannot take another
+    /// step along with those which ran out of steps.
+    ///
+    /// Executions reaching a node and memory which was already reached are
+    /// not explored further, so each distinct outcome is found once, by
+    /// whichever of the shortest executions reaches it first.
+    pub fn explore(&self, pg: &ProgramGraph, steps: usize) -> Vec<Execution> {
+        let mut seen = BTreeSet::from([(self.current_node(), self.current_mem().clone())]);
+        let mut frontier = vec![self.clone()];
+        let mut done = vec![];
+
+        for _ in 0..steps {
+            let mut next_frontier = vec![];
+            for exe in frontier {
+                let nexts = exe.nexts(pg);
+                if nexts.is_empty() {
+                    done.push(exe);
+                }
+                next_frontier.extend(
+                    nexts.into_iter().filter(|next| {
+                        seen.insert((next.current_node(), next.current_mem().clone()))
+                    }),
+                );
+            }
+            frontier = next_frontier;
+        }
+
+        done.extend(frontier);
+        done
+    }
 }
 
 impl Node {
@@ -1,9 +1,19 @@
+#[cfg(test)]
+mod tests;
+
 pub mod ast;
 mod ast_ext;
-mod fmt;
+pub mod ast_smt;
+pub mod debugger;
+pub mod equivalence;
+pub mod fmt;
 mod gcl;
 pub mod interpreter;
 pub mod memory;
 pub mod parse;
 pub mod pg;
+pub mod procedures;
+pub mod reachability;
 pub mod semantics;
+pub mod symbolic;
+pub mod typeck;
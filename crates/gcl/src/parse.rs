@@ -2,9 +2,12 @@ use miette::Diagnostic;
 use once_cell::sync::Lazy;
 use thiserror::Error;
 
-use crate::ast::{BExpr, Commands, Flow, SecurityClass};
+use crate::ast::{
+    AExpr, AExprKind, AOp, BExpr, BExprKind, Commands, Flow, Function, Int, LogicOp, RelOp,
+    SecurityClass, Target, Type,
+};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct SourceSpan {
     /// The start of the span.
     offset: usize,
@@ -49,6 +52,18 @@ impl SourceSpan {
         span.into_iter()
             .fold(init, |a, b| b.map(|b| a.join(b)).unwrap_or(a))
     }
+
+    /// Computes the 1-indexed line and column of the start of the span in
+    /// `src`.
+    #[must_use]
+    pub fn line_col(&self, src: &str) -> (usize, usize) {
+        let before = &src[..self.offset.min(src.len())];
+        let line = before.matches('\n').count() + 1;
+        let col = before.rfind('\n').map_or(before.chars().count(), |nl| {
+            before[nl + 1..].chars().count()
+        }) + 1;
+        (line, col)
+    }
 }
 
 impl From<SourceSpan> for miette::SourceSpan {
@@ -62,35 +77,103 @@ impl From<(usize, usize)> for SourceSpan {
     }
 }
 
-pub fn parse_commands(src: &str) -> Result<Commands, ParseError> {
+type Recovered<'input> =
+    Vec<lalrpop_util::ErrorRecovery<usize, lalrpop_util::lexer::Token<'input>, CustomError>>;
+
+/// Parses `src` as a sequence of commands.
+///
+/// The parser recovers from errors in individual commands and guards, such
+/// that all of the syntax errors of the program are reported at once, rather
+/// than only the first.
+///
+/// Only the syntax of the program is checked. Use [`Commands::check`] to check
+/// its procedures and the types of its targets.
+pub fn parse_commands(src: &str) -> Result<Commands, ParseErrors> {
     static PARSER: Lazy<crate::gcl::CommandsParser> = Lazy::new(crate::gcl::CommandsParser::new);
 
-    PARSER.parse(src).map_err(|e| ParseError::new(src, e))
+    let mut recovered: Recovered = Vec::new();
+    let result = PARSER.parse(&mut recovered, src);
+    let mut errors = recovered
+        .into_iter()
+        .map(|r| ParseError::new(src, r.error))
+        .collect::<Vec<_>>();
+    match result {
+        Ok(cmds) if errors.is_empty() => Ok(cmds),
+        Ok(_) => Err(ParseErrors { errors }),
+        Err(e) => {
+            errors.push(ParseError::new(src, e));
+            Err(ParseErrors { errors })
+        }
+    }
+}
+
+/// Runs a parser which does not report recovered errors, returning the first
+/// error encountered, if any.
+fn parse_first<'input, T>(
+    src: &'input str,
+    parse: impl FnOnce(
+        &mut Recovered<'input>,
+    ) -> Result<
+        T,
+        lalrpop_util::ParseError<usize, lalrpop_util::lexer::Token<'input>, CustomError>,
+    >,
+) -> Result<T, ParseError> {
+    let mut recovered = Vec::new();
+    let result = parse(&mut recovered).map_err(|e| ParseError::new(src, e))?;
+    match recovered.into_iter().next() {
+        Some(r) => Err(ParseError::new(src, r.error)),
+        None => Ok(result),
+    }
 }
 
 pub fn parse_bexpr(src: &str) -> Result<BExpr, ParseError> {
     static PARSER: Lazy<crate::gcl::BExprParser> = Lazy::new(crate::gcl::BExprParser::new);
 
-    PARSER.parse(src).map_err(|e| ParseError::new(src, e))
+    parse_first(src, |errors| PARSER.parse(errors, src))
 }
 
 pub fn parse_aexpr(src: &str) -> Result<crate::ast::AExpr, ParseError> {
     static PARSER: Lazy<crate::gcl::AExprParser> = Lazy::new(crate::gcl::AExprParser::new);
 
-    PARSER.parse(src).map_err(|e| ParseError::new(src, e))
+    parse_first(src, |errors| PARSER.parse(errors, src))
 }
 
 pub fn parse_action(src: &str) -> Result<crate::pg::Action, ParseError> {
     static PARSER: Lazy<crate::gcl::ActionParser> = Lazy::new(crate::gcl::ActionParser::new);
 
-    PARSER.parse(src).map_err(|e| ParseError::new(src, e))
+    parse_first(src, |errors| PARSER.parse(errors, src))
 }
 
 pub fn parse_security_lattice(src: &str) -> Result<Vec<Flow<SecurityClass>>, ParseError> {
     static PARSER: Lazy<crate::gcl::SecurityLatticeParser> =
         Lazy::new(crate::gcl::SecurityLatticeParser::new);
 
-    PARSER.parse(src).map_err(|e| ParseError::new(src, e))
+    parse_first(src, |errors| PARSER.parse(errors, src))
+}
+
+/// All of the errors found while parsing a program.
+#[derive(Debug, Error, Diagnostic, Clone)]
+#[error("{}", self.summary())]
+pub struct ParseErrors {
+    #[related]
+    pub errors: Vec<ParseError>,
+}
+
+impl ParseErrors {
+    fn summary(&self) -> String {
+        let mut summary = match self.errors.len() {
+            1 => "found 1 syntax error".to_string(),
+            n => format!("found {n} syntax errors"),
+        };
+        for err in &self.errors {
+            let (line, col) = err.span().line_col(err.src());
+            summary += &format!("\n  {line}:{col}: {err}");
+            if let Some(hint) = err.hint() {
+                summary += &format!(" ({hint})");
+            }
+        }
+        summary
+    }
 }
 
 #[derive(Debug, Error, Diagnostic, Clone)]
@@ -115,13 +198,23 @@ pub enum ParseError {
         hint: Option<String>,
     },
     #[error("Unrecognized EOF")]
-    #[diagnostic(help("Expected tokens in this context are:\n{expected}"))]
+    #[diagnostic(help("Expected tokens in this context are:\n{expected}{}", if let Some(hint) = hint { format!("\n{hint}") } else { "".to_string() }))]
     UnrecognizedEof {
         #[source_code]
         src: String,
         #[label = "The document ends too early. Are you missing a token?"]
         err_span: SourceSpan,
         expected: String,
+        hint: Option<String>,
+    },
+    #[error("Type mismatch")]
+    #[diagnostic()]
+    TypeMismatch {
+        #[source_code]
+        src: String,
+        #[label("{message}")]
+        err_span: SourceSpan,
+        message: String,
     },
     #[error("Integer is too large")]
     #[diagnostic(help("The integer is too large to be represented"))]
@@ -132,21 +225,127 @@ pub enum ParseError {
         err_span: SourceSpan,
     },
 }
-// impl ParseError {
-//     pub fn span(&self) -> Span {
-//         match self {
-//             ParseError::InvalidToken { err_span, .. }
-//             | ParseError::UnrecognizedToken { err_span, .. }
-//             | ParseError::UnrecognizedEof { err_span, .. } => Span {
-//                 start: err_span.offset(),
-//                 end: err_span.offset() + err_span.len(),
-//             },
-//         }
-//     }
-// }
+impl ParseError {
+    pub fn span(&self) -> SourceSpan {
+        match self {
+            ParseError::InvalidToken { err_span, .. }
+            | ParseError::UnrecognizedToken { err_span, .. }
+            | ParseError::UnrecognizedEof { err_span, .. }
+            | ParseError::TypeMismatch { err_span, .. }
+            | ParseError::IntegerTooLarge { err_span, .. } => *err_span,
+        }
+    }
+    pub fn hint(&self) -> Option<&str> {
+        match self {
+            ParseError::UnrecognizedToken { hint, .. }
+            | ParseError::UnrecognizedEof { hint, .. } => hint.as_deref(),
+            ParseError::TypeMismatch { message, .. } => Some(message),
+            ParseError::InvalidToken { .. } | ParseError::IntegerTooLarge { .. } => None,
+        }
+    }
+    fn src(&self) -> &str {
+        match self {
+            ParseError::InvalidToken { src, .. }
+            | ParseError::UnrecognizedToken { src, .. }
+            | ParseError::UnrecognizedEof { src, .. }
+            | ParseError::TypeMismatch { src, .. }
+            | ParseError::IntegerTooLarge { src, .. } => src,
+        }
+    }
+}
 
 pub(crate) enum CustomError {
     IntegerTooLarge { from: usize, to: usize },
+    TypeMismatch { span: SourceSpan, expected: Type },
+}
+
+/// An expression as parsed, before it is determined to be either an
+/// arithmetic or a boolean expression.
+pub(crate) struct Expr {
+    kind: ExprKind,
+    span: SourceSpan,
+}
+
+pub(crate) enum ExprKind {
+    Number(Int),
+    Bool(bool),
+    Reference(Target<Box<AExpr>>),
+    Binary(Box<Expr>, AOp, Box<Expr>),
+    Minus(Box<Expr>),
+    Function(Function),
+    Rel(Box<Expr>, RelOp, Box<Expr>),
+    Logic(Box<Expr>, LogicOp, Box<Expr>),
+    Not(Box<Expr>),
+}
+
+type ExprError<T> = lalrpop_util::ParseError<usize, T, CustomError>;
+
+impl Expr {
+    pub(crate) fn new(kind: ExprKind, l: usize, r: usize) -> Expr {
+        Expr {
+            kind,
+            span: (l, r - l).into(),
+        }
+    }
+    pub(crate) fn arith(a: Expr, op: AOp, b: Expr, l: usize, r: usize) -> Expr {
+        Expr::new(ExprKind::Binary(Box::new(a), op, Box::new(b)), l, r)
+    }
+    pub(crate) fn logic(a: Expr, op: LogicOp, b: Expr, l: usize, r: usize) -> Expr {
+        Expr::new(ExprKind::Logic(Box::new(a), op, Box::new(b)), l, r)
+    }
+    /// Whether the expression is boolean by its outermost operator. References
+    /// are not, since their type depends on the target.
+    pub(crate) fn is_bool(&self) -> bool {
+        match self.kind {
+            ExprKind::Bool(_) | ExprKind::Rel(..) | ExprKind::Logic(..) | ExprKind::Not(_) => true,
+            ExprKind::Number(_)
+            | ExprKind::Reference(_)
+            | ExprKind::Binary(..)
+            | ExprKind::Minus(_)
+            | ExprKind::Function(_) => false,
+        }
+    }
+    fn mismatch<T>(&self, expected: Type) -> ExprError<T> {
+        lalrpop_util::ParseError::User {
+            error: CustomError::TypeMismatch {
+                span: self.span,
+                expected,
+            },
+        }
+    }
+    pub(crate) fn into_aexpr<T>(self) -> Result<AExpr, ExprError<T>> {
+        let kind = match self.kind {
+            ExprKind::Number(n) => AExprKind::Number(n),
+            ExprKind::Reference(t) => AExprKind::Reference(t),
+            ExprKind::Binary(l, op, r) => {
+                AExprKind::Binary(Box::new(l.into_aexpr()?), op, Box::new(r.into_aexpr()?))
+            }
+            ExprKind::Minus(e) => AExprKind::Minus(Box::new(e.into_aexpr()?)),
+            ExprKind::Function(f) => AExprKind::Function(f),
+            ExprKind::Bool(_) | ExprKind::Rel(..) | ExprKind::Logic(..) | ExprKind::Not(_) => {
+                return Err(self.mismatch(Type::Int));
+            }
+        };
+        Ok(AExpr::new(kind, self.span))
+    }
+    pub(crate) fn into_bexpr<T>(self) -> Result<BExpr, ExprError<T>> {
+        let kind = match self.kind {
+            ExprKind::Bool(b) => BExprKind::Bool(b),
+            ExprKind::Reference(t) => BExprKind::Reference(t),
+            ExprKind::Rel(l, op, r) => BExprKind::Rel(l.into_aexpr()?, op, r.into_aexpr()?),
+            ExprKind::Logic(l, op, r) => {
+                BExprKind::Logic(Box::new(l.into_bexpr()?), op, Box::new(r.into_bexpr()?))
+            }
+            ExprKind::Not(e) => BExprKind::Not(Box::new(e.into_bexpr()?)),
+            ExprKind::Number(_)
+            | ExprKind::Binary(..)
+            | ExprKind::Minus(_)
+            | ExprKind::Function(_) => {
+                return Err(self.mismatch(Type::Bool));
+            }
+        };
+        Ok(BExpr::new(kind, self.span))
+    }
 }
 
 impl ParseError {
@@ -165,6 +364,7 @@ impl ParseError {
                 ParseError::UnrecognizedEof {
                     src: prep_src(),
                     err_span: (location, 0).into(),
+                    hint: eof_hint(&expected),
                     expected: expected.join(", "),
                 }
             }
@@ -173,17 +373,71 @@ impl ParseError {
                     src: prep_src(),
                     err_span: (token.0, token.2 - token.0).into(),
                     token: token.1.to_string(),
+                    hint: token_hint(src, token.0, token.1.1, &expected),
                     expected: expected.join(", "),
-                    hint: None,
                 }
             }
-            lalrpop_util::ParseError::ExtraToken { .. } => todo!(),
+            lalrpop_util::ParseError::ExtraToken { token } => ParseError::UnrecognizedToken {
+                src: prep_src(),
+                err_span: (token.0, token.2 - token.0).into(),
+                token: token.1.to_string(),
+                expected: "end of input".to_string(),
+                hint: None,
+            },
             lalrpop_util::ParseError::User { error } => match error {
                 CustomError::IntegerTooLarge { from, to } => ParseError::IntegerTooLarge {
                     src: prep_src(),
-                    err_span: (from, to).into(),
+                    err_span: (from, to - from).into(),
+                },
+                CustomError::TypeMismatch { span, expected } => ParseError::TypeMismatch {
+                    src: prep_src(),
+                    err_span: span,
+                    message: format!("expected an expression of type {expected}"),
                 },
             },
         }
     }
 }
+
+fn expects(expected: &[String], token: &str) -> bool {
+    expected.iter().any(|e| e.trim_matches('"') == token)
+}
+
+/// Suggests a fix for common mistakes leading to an unexpected `token` at
+/// `offset`.
+fn token_hint(src: &str, offset: usize, token: &str, expected: &[String]) -> Option<String> {
+    // Keywords and targets alike start with a letter
+    let starts_command = token.starts_with(|c: char| c.is_ascii_alphabetic());
+
+    let hint = match token {
+        "=" if expects(expected, ":=") => {
+            "Did you mean `:=`? Assignments are written `x := a`, while `=` compares two values"
+        }
+        "=" if src[..offset].trim_end().ends_with('=') => "Equality is written with a single `=`",
+        "fi" if expects(expected, "od") => "Did you mean `od`? Loops started by `do` end with `od`",
+        "od" if expects(expected, "fi") => {
+            "Did you mean `fi`? Conditionals started by `if` end with `fi`"
+        }
+        "[" if expects(expected, "[]") => {
+            "Guards are separated by `[]`, without any whitespace between the brackets"
+        }
+        "|" | "||" if expects(expected, "[]") => "Guards are separated by `[]`",
+        _ if starts_command && expects(expected, ";") => {
+            "Did you forget a `;` between two commands?"
+        }
+        _ => return None,
+    };
+    Some(hint.to_string())
+}
+
+/// Suggests a fix when the program ends while `expected` tokens are still
+/// expected.
+fn eof_hint(expected: &[String]) -> Option<String> {
+    if expects(expected, "fi") {
+        Some("Are you missing a `fi` to close an `if`?".to_string())
+    } else if expects(expected, "od") {
+        Some("Are you missing an `od` to close a `do`?".to_string())
+    } else {
+        None
+    }
+}
@@ -1,4 +1,10 @@
 pub mod analysis;
+pub mod dominators;
+pub mod dot;
+pub mod graphml;
+pub mod optimize;
+pub mod serialize;
+pub mod ssa;
 
 use std::{
     collections::{BTreeMap, VecDeque},
@@ -9,13 +15,23 @@ use indexmap::{IndexMap, IndexSet};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
-use crate::ast::{AExpr, BExpr, Command, Commands, Guard, LogicOp, Target};
+use crate::{
+    ast::{AExpr, Array, BExpr, Command, CommandKind, Commands, Guard, LogicOp, Target},
+    parse::SourceSpan,
+};
 
-#[derive(Debug, Clone)]
+/// Serialized as a [`GraphDocument`](serialize::GraphDocument).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    into = "serialize::GraphDocument",
+    try_from = "serialize::GraphDocument"
+)]
 pub struct ProgramGraph {
     edges: Vec<Edge>,
     nodes: IndexSet<Node>,
     outgoing: IndexMap<Node, Vec<Edge>>,
+    /// The span in the source program each edge originates from
+    source_map: IndexMap<Edge, SourceSpan>,
 }
 
 #[derive(
@@ -35,6 +51,8 @@ pub enum Node {
     Start,
     Node(NodeId),
     End,
+    /// Reached by failing an assertion or aborting
+    Error,
 }
 
 impl std::fmt::Debug for Node {
@@ -43,6 +61,7 @@ impl std::fmt::Debug for Node {
             Node::Start => write!(f, "qStart"),
             Node::Node(n) => write!(f, "q{}", n.0),
             Node::End => write!(f, "qFinal"),
+            Node::Error => write!(f, "qError"),
         }
     }
 }
@@ -72,6 +91,27 @@ impl std::fmt::Display for Node {
                 //     .format("")
             ),
             Node::End => write!(f, "q◀"),
+            Node::Error => write!(f, "q⊥"),
+        }
+    }
+}
+/// Parses the names produced by both the [`Display`](std::fmt::Display) and
+/// [`Debug`] implementations.
+impl FromStr for Node {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "q▷" | "qStart" => Ok(Node::Start),
+            "q◀" | "qFinal" => Ok(Node::End),
+            "q⊥" | "qError" => Ok(Node::Error),
+            _ => {
+                let id = s.strip_prefix('q').ok_or(())?;
+                if !id.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(());
+                }
+                id.parse().map(|id| Node::Node(NodeId(id))).map_err(|_| ())
+            }
         }
     }
 }
@@ -79,6 +119,8 @@ impl std::fmt::Display for Node {
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Action {
     Assignment(Target<Box<AExpr>>, AExpr),
+    BoolAssignment(Target<Box<AExpr>>, BExpr),
+    ArrayAssignment(Array, Vec<AExpr>),
     Skip,
     Condition(BExpr),
 }
@@ -86,6 +128,10 @@ impl Action {
     fn fv(&self) -> IndexSet<Target> {
         match self {
             Action::Assignment(x, a) => x.fv().union(&a.fv()).cloned().collect(),
+            Action::BoolAssignment(x, b) => x.fv().union(&b.fv()).cloned().collect(),
+            Action::ArrayAssignment(arr, elems) => std::iter::once(Target::Array(arr.clone(), ()))
+                .chain(elems.iter().flat_map(|a| a.fv()))
+                .collect(),
             Action::Skip => Default::default(),
             Action::Condition(b) => b.fv(),
         }
@@ -118,6 +164,10 @@ impl std::fmt::Display for Action {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Action::Assignment(v, x) => write!(f, "{v} := {x}"),
+            Action::BoolAssignment(v, b) => write!(f, "{v} := {b}"),
+            Action::ArrayAssignment(arr, elems) => {
+                write!(f, "{arr} := [{}]", elems.iter().format(", "))
+            }
             Action::Skip => write!(f, "skip"),
             Action::Condition(b) => write!(f, "{b}"),
         }
@@ -127,6 +177,7 @@ impl std::fmt::Display for Action {
 #[derive(Default)]
 struct EdgesCtx {
     next: u64,
+    source_map: IndexMap<Edge, SourceSpan>,
 }
 
 impl EdgesCtx {
@@ -135,6 +186,14 @@ impl EdgesCtx {
         self.next += 1;
         Node::Node(NodeId(id))
     }
+    /// Constructs a new edge, remembering the span it originated from
+    fn edge(&mut self, s: Node, action: Action, t: Node, span: SourceSpan) -> Edge {
+        let e = Edge(s, action, t);
+        if !span.is_empty() {
+            self.source_map.insert(e.clone(), span);
+        }
+        e
+    }
 }
 
 trait Edges {
@@ -168,36 +227,43 @@ fn guard_edges(
     match det {
         Determinism::Deterministic => {
             // See the "if" and "do" Commands on Page 25 of Formal Methods
-            let mut prev = BExpr::Bool(false);
+            let mut prev = BExpr::bool(false);
 
             let mut edges = vec![];
 
-            for Guard(b, c) in guards {
+            for Guard {
+                guard_span,
+                guard: b,
+                cmds: c,
+            } in guards
+            {
                 let q = ctx.fresh();
 
-                edges.push(Edge(
+                let e = ctx.edge(
                     s,
                     Action::Condition(BExpr::logic(
                         b.clone(),
                         LogicOp::Land,
-                        BExpr::Not(Box::new(prev.clone())),
+                        BExpr::not(prev.clone()),
                     )),
                     q,
-                ));
+                    *guard_span,
+                );
+                edges.push(e);
                 edges.extend(c.edges(ctx, det, q, t));
                 prev = BExpr::logic(b.to_owned().clone(), LogicOp::Lor, prev);
             }
 
             // Wraps in "not" so that the "d" part can be used directly by "do"
-            (edges, BExpr::Not(Box::new(prev)))
+            (edges, BExpr::not(prev))
         }
         Determinism::NonDeterministic => {
             let e = guards
                 .iter()
-                .flat_map(|Guard(b, c)| {
+                .flat_map(|g| {
                     let q = ctx.fresh();
-                    let mut edges = c.edges(ctx, det, q, t);
-                    edges.push(Edge(s, Action::Condition(b.clone()), q));
+                    let mut edges = g.cmds.edges(ctx, det, q, t);
+                    edges.push(ctx.edge(s, Action::Condition(g.guard.clone()), q, g.guard_span));
                     edges
                 })
                 .collect();
@@ -208,32 +274,129 @@ fn guard_edges(
 
 impl Edges for Command {
     fn edges(&self, ctx: &mut EdgesCtx, det: Determinism, s: Node, t: Node) -> Vec<Edge> {
-        match self {
-            Command::Assignment(v, expr) => {
-                vec![Edge(s, Action::Assignment(v.clone(), expr.clone()), t)]
+        match &self.kind {
+            CommandKind::Assignment(v, expr) => {
+                vec![ctx.edge(s, Action::Assignment(v.clone(), expr.clone()), t, self.span)]
             }
-            Command::Skip => vec![Edge(s, Action::Skip, t)],
-            Command::If(guards) => guard_edges(ctx, det, guards, s, t).0,
-            Command::Loop(guards) => {
+            CommandKind::BoolAssignment(v, expr) => vec![ctx.edge(
+                s,
+                Action::BoolAssignment(v.clone(), expr.clone()),
+                t,
+                self.span,
+            )],
+            CommandKind::ArrayAssignment(arr, elems) => vec![ctx.edge(
+                s,
+                Action::ArrayAssignment(arr.clone(), elems.clone()),
+                t,
+                self.span,
+            )],
+            CommandKind::Skip => vec![ctx.edge(s, Action::Skip, t, self.span)],
+            CommandKind::Assert(b) => vec![
+                ctx.edge(s, Action::Condition(b.clone()), t, self.span),
+                ctx.edge(
+                    s,
+                    Action::Condition(BExpr::not(b.clone())),
+                    Node::Error,
+                    self.span,
+                ),
+            ],
+            CommandKind::Abort => vec![ctx.edge(
+                s,
+                Action::Condition(BExpr::bool(true)),
+                Node::Error,
+                self.span,
+            )],
+            CommandKind::If(guards) => guard_edges(ctx, det, guards, s, t).0,
+            CommandKind::Loop(guards) => {
                 let (mut edges, b) = guard_edges(ctx, det, guards, s, s);
-                edges.push(Edge(s, Action::Condition(b), t));
+                edges.push(ctx.edge(s, Action::Condition(b), t, self.span));
                 edges
             }
+            CommandKind::Par(components) => par_edges(ctx, det, components, s, t),
+            CommandKind::Proc(_) | CommandKind::Call(_, _) => {
+                unreachable!("procedures are inlined before constructing the program graph")
+            }
         }
     }
 }
 
+/// Computes the product of the program graphs of the components, where each
+/// step of the product is a step of one of the components. The product starts
+/// when all components are at their start, and ends when all are at their end.
+fn par_edges(
+    ctx: &mut EdgesCtx,
+    det: Determinism,
+    components: &[Commands],
+    s: Node,
+    t: Node,
+) -> Vec<Edge> {
+    let graphs = components
+        .iter()
+        .map(|c| {
+            let (cs, ct) = (ctx.fresh(), ctx.fresh());
+            let mut outgoing: IndexMap<Node, Vec<Edge>> = IndexMap::new();
+            for e in c.edges(ctx, det, cs, ct) {
+                outgoing.entry(e.0).or_default().push(e);
+            }
+            (cs, ct, outgoing)
+        })
+        .collect_vec();
+
+    let start = graphs.iter().map(|g| g.0).collect_vec();
+    let end = graphs.iter().map(|g| g.1).collect_vec();
+    let mut names: IndexMap<Vec<Node>, Node> = [(start.clone(), s), (end, t)].into_iter().collect();
+    let mut queue = VecDeque::from([start]);
+    let mut edges = vec![];
+
+    while let Some(state) = queue.pop_front() {
+        let from = names[&state];
+        for (idx, (_, _, outgoing)) in graphs.iter().enumerate() {
+            for e in outgoing.get(&state[idx]).into_iter().flatten() {
+                // Failing in any of the components fails the whole command
+                if e.2 == Node::Error {
+                    let span = ctx.source_map.get(e).copied().unwrap_or_default();
+                    edges.push(ctx.edge(from, e.1.clone(), Node::Error, span));
+                    continue;
+                }
+                let mut next = state.clone();
+                next[idx] = e.2;
+                let to = match names.get(&next) {
+                    Some(&n) => n,
+                    None => {
+                        let n = ctx.fresh();
+                        names.insert(next.clone(), n);
+                        queue.push_back(next);
+                        n
+                    }
+                };
+                let span = ctx.source_map.get(e).copied().unwrap_or_default();
+                edges.push(ctx.edge(from, e.1.clone(), to, span));
+            }
+        }
+    }
+
+    // The edges of the components themselves are not part of the graph
+    for e in graphs.iter().flat_map(|g| g.2.values().flatten()) {
+        ctx.source_map.shift_remove(e);
+    }
+
+    edges
+}
+
 fn done(guards: &[Guard]) -> BExpr {
     guards
         .iter()
-        .map(|Guard(b, _c)| BExpr::Not(Box::new(b.clone())))
+        .map(|g| BExpr::not(g.guard.clone()))
         .reduce(|a, b| BExpr::logic(a, LogicOp::Land, b))
-        .unwrap_or(BExpr::Bool(true))
+        .unwrap_or(BExpr::bool(true))
 }
 
 impl ProgramGraph {
     pub fn new(det: Determinism, cmds: &Commands) -> Self {
-        let edges = cmds.edges(&mut Default::default(), det, Node::Start, Node::End);
+        let mut ctx = EdgesCtx::default();
+        let edges = cmds
+            .resolve_types()
+            .edges(&mut ctx, det, Node::Start, Node::End);
         let mut outgoing: IndexMap<Node, Vec<Edge>> = Default::default();
         let mut nodes: IndexSet<Node> = Default::default();
 
@@ -247,6 +410,7 @@ impl ProgramGraph {
             outgoing,
             edges,
             nodes,
+            source_map: ctx.source_map,
         }
         .rename_with_reverse_post_order()
     }
@@ -263,21 +427,15 @@ impl ProgramGraph {
             .unwrap_or_default()
     }
 
-    pub fn fv(&self) -> IndexSet<Target> {
-        self.edges.iter().flat_map(|e| e.action().fv()).collect()
+    /// The span of the command or guard in the source program which gave rise
+    /// to the given edge. Graphs built from commands which were not parsed
+    /// from source have no spans.
+    pub fn span(&self, edge: &Edge) -> Option<SourceSpan> {
+        self.source_map.get(edge).copied()
     }
 
-    pub fn dot(&self) -> String {
-        format!(
-            "digraph G {{\n{}\n}}",
-            self.edges
-                .iter()
-                .map(|Edge(a, t, b)| format!(
-                    "  {a:?}[label=\"{a}\"]; {a:?} -> {b:?}[label={:?}]; {b:?}[label=\"{b}\"];",
-                    t.to_string(),
-                ))
-                .format("  \n")
-        )
+    pub fn fv(&self) -> IndexSet<Target> {
+        self.edges.iter().flat_map(|e| e.action().fv()).collect()
     }
 
     pub fn as_petgraph(
@@ -342,37 +500,35 @@ impl ProgramGraph {
                         node_mapping_new.insert(*n, Node::Node(NodeId(idx)));
                         NamingStage::Middle { idx: idx + 1 }
                     }
-                    Node::End => {
-                        node_mapping_new.insert(*n, Node::End);
+                    Node::End | Node::Error => {
+                        node_mapping_new.insert(*n, *n);
                         NamingStage::Middle { idx }
                     }
                 },
             }
         }
 
+        // NOTE: edges out of nodes which are unreachable from the start node,
+        // such as those following a stuck call, are dropped
+        let reachable = |Edge(a, _, _): &&Edge| node_mapping_new.contains_key(a);
+        let rename = |Edge(a, action, b): &Edge| {
+            Edge(node_mapping_new[a], action.clone(), node_mapping_new[b])
+        };
+
         Self {
-            edges: self
-                .edges
-                .iter()
-                .map(|Edge(a, action, b)| {
-                    Edge(node_mapping_new[a], action.clone(), node_mapping_new[b])
-                })
-                .collect(),
+            edges: self.edges.iter().filter(reachable).map(rename).collect(),
             nodes: node_mapping_new.values().copied().collect(),
             outgoing: self
                 .outgoing
                 .iter()
-                .map(|(n, outgoing)| {
-                    (
-                        node_mapping_new[n],
-                        outgoing
-                            .iter()
-                            .map(|Edge(a, action, b)| {
-                                Edge(node_mapping_new[a], action.clone(), node_mapping_new[b])
-                            })
-                            .collect(),
-                    )
-                })
+                .filter(|(n, _)| node_mapping_new.contains_key(n))
+                .map(|(n, outgoing)| (node_mapping_new[n], outgoing.iter().map(rename).collect()))
+                .collect(),
+            source_map: self
+                .source_map
+                .iter()
+                .filter(|(e, _)| reachable(e))
+                .map(|(e, span)| (rename(e), *span))
                 .collect(),
         }
     }
@@ -1,10 +1,11 @@
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 
 use indexmap::{IndexMap, IndexSet};
 use serde::{Deserialize, Serialize};
 
 use crate::pg::{Edge, Node, ProgramGraph};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Direction {
     Forward,
     Backward,
@@ -16,6 +17,18 @@ pub trait MonotoneFramework {
     fn direction() -> Direction;
     fn initial(&self, pg: &ProgramGraph) -> Self::Domain;
     fn debug(&self, _item: &Self::Domain) {}
+    /// Combines the facts `prev` at a loop head with the new facts `next`,
+    /// such that every increasing sequence of facts stabilizes. Domains of
+    /// infinite height must provide this for the analysis to terminate, while
+    /// `None` falls back to the least upper bound.
+    fn widen(&self, _prev: &Self::Domain, _next: &Self::Domain) -> Option<Self::Domain> {
+        None
+    }
+    /// Refines the facts `prev` at a loop head, which were found by widening,
+    /// using the facts `next` computed from them. `None` keeps `prev`.
+    fn narrow(&self, _prev: &Self::Domain, _next: &Self::Domain) -> Option<Self::Domain> {
+        None
+    }
 }
 
 pub trait Lattice: Sized + Clone {
@@ -28,14 +41,15 @@ pub trait Lattice: Sized + Clone {
 }
 
 pub trait Worklist {
-    fn empty() -> Self;
+    /// An empty worklist for an analysis of `pg` in the given direction.
+    fn empty(pg: &ProgramGraph, direction: Direction) -> Self;
     fn insert(&mut self, n: Node);
     fn extract(&mut self, pg: &ProgramGraph) -> Option<Node>;
 }
 
 pub struct FiFo(VecDeque<Node>);
 impl Worklist for FiFo {
-    fn empty() -> Self {
+    fn empty(_pg: &ProgramGraph, _direction: Direction) -> Self {
         FiFo(Default::default())
     }
 
@@ -50,7 +64,7 @@ impl Worklist for FiFo {
 
 pub struct LiFo(Vec<Node>);
 impl Worklist for LiFo {
-    fn empty() -> Self {
+    fn empty(_pg: &ProgramGraph, _direction: Direction) -> Self {
         LiFo(Default::default())
     }
 
@@ -63,32 +77,166 @@ impl Worklist for LiFo {
     }
 }
 
-// pub struct RoundRobin(VecDeque<Node>, IndexSet<Node>);
-// impl Worklist for RoundRobin {
-//     fn empty() -> Self {
-//         RoundRobin(Default::default(), Default::default())
-//     }
-
-//     fn insert(&mut self, n: Node) {
-//         let RoundRobin(v, p) = self;
-//         if !v.contains(&n) {
-//             p.insert(n);
-//         }
-//     }
-
-//     fn extract(&mut self, pg: &ProgramGraph) -> Option<Node> {
-//         let RoundRobin(v, p) = self;
-//         match (v.is_empty(), p.is_empty()) {
-//             (true, true) => return None,
-//             (true, false) => {
-//                 *v = pg.reverse_post_order().filter(|n|
-// p.contains(n)).collect();                 p.clear();
-//                 v.pop_back()
-//             }
-//             _ => v.pop_back(),
-//         }
-//     }
-// }
+/// Processes nodes in rounds, where each round visits the nodes inserted
+/// during the previous round in reverse post-order.
+pub struct RoundRobin {
+    order: IndexMap<Node, usize>,
+    current: VecDeque<Node>,
+    pending: IndexSet<Node>,
+}
+impl Worklist for RoundRobin {
+    fn empty(pg: &ProgramGraph, direction: Direction) -> Self {
+        RoundRobin {
+            order: rank(&reverse_post_order(pg, direction)),
+            current: Default::default(),
+            pending: Default::default(),
+        }
+    }
+
+    fn insert(&mut self, n: Node) {
+        if !self.current.contains(&n) {
+            self.pending.insert(n);
+        }
+    }
+
+    fn extract(&mut self, _pg: &ProgramGraph) -> Option<Node> {
+        if self.current.is_empty() {
+            let mut round = self.pending.drain(..).collect::<Vec<_>>();
+            round.sort_by_key(|n| self.order[n]);
+            self.current = round.into();
+        }
+        self.current.pop_front()
+    }
+}
+
+/// Always extracts the node which comes first in reverse post-order.
+pub struct ReversePostOrder {
+    order: IndexMap<Node, usize>,
+    pending: BTreeSet<(usize, Node)>,
+}
+impl Worklist for ReversePostOrder {
+    fn empty(pg: &ProgramGraph, direction: Direction) -> Self {
+        ReversePostOrder {
+            order: rank(&reverse_post_order(pg, direction)),
+            pending: Default::default(),
+        }
+    }
+
+    fn insert(&mut self, n: Node) {
+        self.pending.insert((self.order[&n], n));
+    }
+
+    fn extract(&mut self, _pg: &ProgramGraph) -> Option<Node> {
+        self.pending.pop_first().map(|(_, n)| n)
+    }
+}
+
+/// Stabilizes one strongly connected component at a time, taking the
+/// components in topological order and the nodes within a component in
+/// reverse post-order.
+pub struct Scc {
+    order: IndexMap<Node, usize>,
+    component: IndexMap<Node, usize>,
+    current: BTreeSet<(usize, Node)>,
+    pending: BTreeSet<(usize, usize, Node)>,
+}
+impl Worklist for Scc {
+    fn empty(pg: &ProgramGraph, direction: Direction) -> Self {
+        let (g, _, node_mapping_rev) = &pg.as_petgraph();
+        // The components are found in reverse topological order of the edges
+        let mut components = match direction {
+            Direction::Forward => petgraph::algo::tarjan_scc(g),
+            Direction::Backward => petgraph::algo::tarjan_scc(petgraph::visit::Reversed(g)),
+        };
+        components.reverse();
+        Scc {
+            order: rank(&reverse_post_order(pg, direction)),
+            component: components
+                .iter()
+                .enumerate()
+                .flat_map(|(idx, c)| c.iter().map(move |n| (node_mapping_rev[n], idx)))
+                .collect(),
+            current: Default::default(),
+            pending: Default::default(),
+        }
+    }
+
+    fn insert(&mut self, n: Node) {
+        self.pending.insert((self.component[&n], self.order[&n], n));
+    }
+
+    fn extract(&mut self, _pg: &ProgramGraph) -> Option<Node> {
+        if self.current.is_empty() {
+            let &(component, _, _) = self.pending.first()?;
+            while let Some(&(c, order, n)) = self.pending.first()
+                && c == component
+            {
+                self.pending.pop_first();
+                self.current.insert((order, n));
+            }
+        }
+        self.current.pop_first().map(|(_, n)| n)
+    }
+}
+
+/// The nodes of `pg` in reverse post-order of a depth-first traversal from
+/// the extremal nodes of an analysis in `direction`. Nodes which are not
+/// reachable in that direction come last, in the order of the graph.
+pub fn reverse_post_order(pg: &ProgramGraph, direction: Direction) -> Vec<Node> {
+    let mut successors: IndexMap<Node, Vec<Node>> = IndexMap::new();
+    for e in pg.edges() {
+        let (from, to) = match direction {
+            Direction::Forward => (e.from(), e.to()),
+            Direction::Backward => (e.to(), e.from()),
+        };
+        successors.entry(from).or_default().push(to);
+    }
+    let extremal = extremal_nodes(pg, direction);
+
+    let mut order = vec![];
+    let mut visited = IndexSet::new();
+    for root in extremal.into_iter().chain(pg.nodes().iter().copied()) {
+        if !visited.insert(root) {
+            continue;
+        }
+        let mut post_order = vec![];
+        let mut stack = vec![(root, 0)];
+        while let Some((n, idx)) = stack.pop() {
+            match successors.get(&n).and_then(|s| s.get(idx)) {
+                Some(&next) => {
+                    stack.push((n, idx + 1));
+                    if visited.insert(next) {
+                        stack.push((next, 0));
+                    }
+                }
+                None => post_order.push(n),
+            }
+        }
+        order.extend(post_order.into_iter().rev());
+    }
+
+    order
+}
+
+/// The nodes of `pg` which are the target of an edge going backwards in
+/// reverse post-order, following edges in `direction`. Every cycle of the graph
+/// passes through at least one of them.
+pub fn loop_heads(pg: &ProgramGraph, direction: Direction) -> IndexSet<Node> {
+    let order = rank(&reverse_post_order(pg, direction));
+    pg.edges()
+        .iter()
+        .map(|e| match direction {
+            Direction::Forward => (e.from(), e.to()),
+            Direction::Backward => (e.to(), e.from()),
+        })
+        .filter(|(from, to)| order[to] <= order[from])
+        .map(|(_, to)| to)
+        .collect()
+}
+
+fn rank(nodes: &[Node]) -> IndexMap<Node, usize> {
+    nodes.iter().enumerate().map(|(idx, n)| (*n, idx)).collect()
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct AnalysisResults<A: MonotoneFramework> {
@@ -100,7 +248,7 @@ pub fn mono_analysis<A: MonotoneFramework, W: Worklist>(
     a: A,
     pg: &ProgramGraph,
 ) -> AnalysisResults<A> {
-    let mut worklist = W::empty();
+    let mut worklist = W::empty(pg, A::direction());
 
     let bot = A::Domain::bottom();
 
@@ -110,13 +258,14 @@ pub fn mono_analysis<A: MonotoneFramework, W: Worklist>(
         worklist.insert(n);
     }
 
+    let extremal = extremal_nodes(pg, A::direction());
     let initial = a.initial(pg);
-    let initial_node = match A::direction() {
-        Direction::Forward => Node::Start,
-        Direction::Backward => Node::End,
-    };
-    facts.insert(initial_node, initial);
+    for &n in &extremal {
+        facts.insert(n, initial.clone());
+    }
 
+    let heads = loop_heads(pg, A::direction());
+    let mut widened = false;
     let mut calls = 0;
 
     while let Some(n) = worklist.extract(pg) {
@@ -135,18 +284,156 @@ pub fn mono_analysis<A: MonotoneFramework, W: Worklist>(
             let target = facts.get_mut(&to).unwrap();
 
             if !target.contains(&constraint) {
-                target.lub_extend(&constraint);
+                match heads
+                    .contains(&to)
+                    .then(|| a.widen(target, &constraint))
+                    .flatten()
+                {
+                    Some(w) => {
+                        *target = w;
+                        widened = true;
+                    }
+                    None => target.lub_extend(&constraint),
+                }
                 worklist.insert(to);
             }
         }
     }
 
+    if widened {
+        calls += narrow(&a, pg, &heads, &extremal, &mut facts);
+    }
+
     AnalysisResults {
         facts,
         semantic_calls: calls,
     }
 }
 
+/// The nodes where an analysis in the given direction begins, and which get
+/// their facts from [`MonotoneFramework::initial`]. A backward analysis begins
+/// at every node without outgoing edges, such as [`Node::End`] and
+/// [`Node::Error`].
+pub fn extremal_nodes(pg: &ProgramGraph, direction: Direction) -> Vec<Node> {
+    match direction {
+        Direction::Forward => vec![Node::Start],
+        Direction::Backward => std::iter::once(Node::End)
+            .chain(
+                pg.nodes()
+                    .iter()
+                    .copied()
+                    .filter(|&n| n != Node::End && pg.outgoing(n).is_empty()),
+            )
+            .collect(),
+    }
+}
+
+/// The upper bound on the number of rounds of narrowing, for narrowing
+/// operators which do not stabilize by themselves.
+pub const MAX_NARROWING_ROUNDS: usize = 32;
+
+/// Improves the facts found using widening by repeatedly recomputing the facts
+/// of every node from its predecessors, applying [`MonotoneFramework::narrow`]
+/// at loop heads. Returns the number of semantic calls made.
+fn narrow<A: MonotoneFramework>(
+    a: &A,
+    pg: &ProgramGraph,
+    heads: &IndexSet<Node>,
+    extremal: &[Node],
+    facts: &mut IndexMap<Node, A::Domain>,
+) -> usize {
+    let mut calls = 0;
+
+    for _ in 0..MAX_NARROWING_ROUNDS {
+        let mut next: IndexMap<Node, A::Domain> =
+            facts.keys().map(|&n| (n, A::Domain::bottom())).collect();
+        for &n in extremal {
+            next.insert(n, a.initial(pg));
+        }
+        for e in pg.edges() {
+            let (from, to) = match A::direction() {
+                Direction::Forward => (e.from(), e.to()),
+                Direction::Backward => (e.to(), e.from()),
+            };
+            let constraint = a.semantic(pg, e, &facts[&from]);
+            calls += 1;
+            next[&to].lub_extend(&constraint);
+        }
+
+        let mut changed = false;
+        for (n, new) in next {
+            let new = if heads.contains(&n) {
+                match a.narrow(&facts[&n], &new) {
+                    Some(narrowed) => narrowed,
+                    None => continue,
+                }
+            } else {
+                new
+            };
+            if facts[&n].contains(&new) && new.contains(&facts[&n]) {
+                continue;
+            }
+            facts.insert(n, new);
+            changed = true;
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    calls
+}
+
+/// The worklist strategies available to [`mono_analysis`].
+#[derive(
+    tapi::Tapi, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[tapi(path = "GCL")]
+pub enum WorklistKind {
+    FiFo,
+    LiFo,
+    RoundRobin,
+    ReversePostOrder,
+    Scc,
+}
+
+impl WorklistKind {
+    pub const ALL: [WorklistKind; 5] = [
+        WorklistKind::FiFo,
+        WorklistKind::LiFo,
+        WorklistKind::RoundRobin,
+        WorklistKind::ReversePostOrder,
+        WorklistKind::Scc,
+    ];
+}
+
+/// Runs [`mono_analysis`] with the worklist of the given kind.
+pub fn mono_analysis_with<A: MonotoneFramework>(
+    kind: WorklistKind,
+    a: A,
+    pg: &ProgramGraph,
+) -> AnalysisResults<A> {
+    match kind {
+        WorklistKind::FiFo => mono_analysis::<A, FiFo>(a, pg),
+        WorklistKind::LiFo => mono_analysis::<A, LiFo>(a, pg),
+        WorklistKind::RoundRobin => mono_analysis::<A, RoundRobin>(a, pg),
+        WorklistKind::ReversePostOrder => mono_analysis::<A, ReversePostOrder>(a, pg),
+        WorklistKind::Scc => mono_analysis::<A, Scc>(a, pg),
+    }
+}
+
+/// The number of calls to [`MonotoneFramework::semantic`] needed to reach
+/// the solution with each worklist strategy.
+pub fn semantic_calls<A: MonotoneFramework + Clone>(
+    a: &A,
+    pg: &ProgramGraph,
+) -> BTreeMap<WorklistKind, usize> {
+    WorklistKind::ALL
+        .into_iter()
+        .map(|kind| (kind, mono_analysis_with(kind, a.clone(), pg).semantic_calls))
+        .collect()
+}
+
 impl<T> Lattice for IndexSet<T>
 where
     T: std::hash::Hash + PartialEq + Eq + Clone,
@@ -168,6 +455,69 @@ where
     }
 }
 
+impl<T> Lattice for BTreeSet<T>
+where
+    T: Ord + Clone,
+{
+    fn bottom() -> Self {
+        Default::default()
+    }
+
+    fn lub_extend(&mut self, other: &Self) {
+        self.extend(other.iter().cloned());
+    }
+
+    fn lub(&self, other: &Self) -> Self {
+        self.union(other).cloned().collect()
+    }
+
+    fn contains(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+}
+
+/// Sets ordered by reverse inclusion, for analyses of facts which must hold
+/// along every path. The least element is the set of all facts, which is
+/// represented by `None` as it depends on the program being analyzed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Must<T: Ord>(pub Option<BTreeSet<T>>);
+
+impl<T: Ord + Clone> Must<T> {
+    /// The facts, where `all` are the facts of the least element.
+    pub fn facts(&self, all: impl FnOnce() -> BTreeSet<T>) -> BTreeSet<T> {
+        match &self.0 {
+            Some(facts) => facts.clone(),
+            None => all(),
+        }
+    }
+}
+
+impl<T> Lattice for Must<T>
+where
+    T: Ord + Clone,
+{
+    fn bottom() -> Self {
+        Must(None)
+    }
+
+    fn lub(&self, other: &Self) -> Self {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => Must(Some(a.intersection(b).cloned().collect())),
+            (Some(a), None) | (None, Some(a)) => Must(Some(a.clone())),
+            (None, None) => Must(None),
+        }
+    }
+
+    fn contains(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(a), Some(b)) => a.is_subset(b),
+        }
+    }
+}
+
 impl<K, V> Lattice for IndexMap<K, V>
 where
     K: std::hash::Hash + PartialEq + Eq + Clone,
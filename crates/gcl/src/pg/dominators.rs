@@ -0,0 +1,227 @@
+use indexmap::{IndexMap, IndexSet};
+
+use crate::pg::{
+    Edge, Node, ProgramGraph,
+    analysis::{Direction, extremal_nodes, reverse_post_order},
+};
+
+/// The dominator tree of a program graph, following edges in a direction. In
+/// the forward direction a node `d` dominates `n` if every path from the start
+/// node to `n` passes through `d`, and in the backward direction, `d`
+/// post-dominates `n` if every path from `n` to a final node passes through
+/// `d`.
+///
+/// Every node dominates itself. Nodes which cannot be reached from the roots
+/// of the tree are not part of it, and are dominated by nothing.
+#[derive(Debug, Clone)]
+pub struct DominatorTree {
+    direction: Direction,
+    roots: Vec<Node>,
+    /// The immediate dominator of each node in the tree, where `None` marks a
+    /// root, or a node which is only dominated by several roots together
+    idom: IndexMap<Node, Option<Node>>,
+    /// The predecessors of each node, following edges in `direction`
+    predecessors: IndexMap<Node, Vec<Node>>,
+}
+
+impl ProgramGraph {
+    /// The dominator tree of the graph, rooted at [`Node::Start`].
+    pub fn dominators(&self) -> DominatorTree {
+        DominatorTree::new(self, Direction::Forward)
+    }
+    /// The post-dominator tree of the graph. A graph can have several final
+    /// nodes, such as [`Node::End`] and [`Node::Error`], each of which is a
+    /// root of the tree.
+    pub fn post_dominators(&self) -> DominatorTree {
+        DominatorTree::new(self, Direction::Backward)
+    }
+    /// The natural loops of the graph, one per loop header, in the order of the
+    /// nodes of the graph.
+    pub fn natural_loops(&self) -> Vec<NaturalLoop> {
+        let dom = self.dominators();
+        let mut loops: IndexMap<Node, NaturalLoop> = IndexMap::new();
+        for e in &self.edges {
+            if !dom.dominates(e.to(), e.from()) {
+                continue;
+            }
+            let l = loops.entry(e.to()).or_insert_with(|| NaturalLoop {
+                header: e.to(),
+                back_edges: vec![],
+                nodes: [e.to()].into_iter().collect(),
+            });
+            l.back_edges.push(e.clone());
+            // The body consists of the nodes which reach the back edge without
+            // passing through the header
+            let mut pending = vec![e.from()];
+            while let Some(n) = pending.pop() {
+                if l.nodes.insert(n) {
+                    pending.extend(dom.predecessors(n));
+                }
+            }
+        }
+        loops.sort_by_cached_key(|h, _| self.nodes.get_index_of(h));
+        loops.into_values().collect()
+    }
+}
+
+impl DominatorTree {
+    /// Computes the tree using the algorithm of Cooper, Harvey and Kennedy,
+    /// which iterates over the nodes in reverse post-order until the
+    /// immediate dominators stabilize.
+    fn new(pg: &ProgramGraph, direction: Direction) -> DominatorTree {
+        let mut predecessors: IndexMap<Node, Vec<Node>> = IndexMap::new();
+        for e in pg.edges() {
+            let (from, to) = match direction {
+                Direction::Forward => (e.from(), e.to()),
+                Direction::Backward => (e.to(), e.from()),
+            };
+            predecessors.entry(to).or_default().push(from);
+        }
+
+        let roots = extremal_nodes(pg, direction);
+        let order = reverse_post_order(pg, direction);
+        let rank: IndexMap<Node, usize> = order.iter().enumerate().map(|(i, &n)| (n, i)).collect();
+
+        let mut idom: IndexMap<Node, Option<Node>> = roots
+            .iter()
+            .filter(|n| pg.nodes().contains(*n))
+            .map(|&n| (n, None))
+            .collect();
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &n in &order {
+                if roots.contains(&n) {
+                    continue;
+                }
+                let mut processed = predecessors
+                    .get(&n)
+                    .into_iter()
+                    .flatten()
+                    .filter(|p| idom.contains_key(*p));
+                let Some(&first) = processed.next() else {
+                    continue;
+                };
+                let mut new = Some(first);
+                for &p in processed {
+                    new = intersect(&idom, &rank, Some(p), new);
+                }
+                if idom.get(&n) != Some(&new) {
+                    idom.insert(n, new);
+                    changed = true;
+                }
+            }
+        }
+
+        idom.sort_by_cached_key(|n, _| rank[n]);
+
+        DominatorTree {
+            direction,
+            roots: roots.into_iter().filter(|n| idom.contains_key(n)).collect(),
+            idom,
+            predecessors,
+        }
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+    /// The nodes of the tree, such that every node comes after its dominators.
+    pub fn nodes(&self) -> impl Iterator<Item = Node> + '_ {
+        self.idom.keys().copied()
+    }
+    pub fn roots(&self) -> impl Iterator<Item = Node> + '_ {
+        self.roots.iter().copied()
+    }
+    /// The closest strict dominator of `n`, which is `None` for roots and for
+    /// nodes outside of the tree. It is also `None` for nodes which reach
+    /// several roots of a post-dominator tree, as no single node is passed
+    /// through on the way to all of them.
+    pub fn immediate_dominator(&self, n: Node) -> Option<Node> {
+        self.idom.get(&n).copied().flatten()
+    }
+    /// The nodes immediately dominated by `n`.
+    pub fn children(&self, n: Node) -> impl Iterator<Item = Node> + '_ {
+        self.idom
+            .iter()
+            .filter(move |(_, d)| **d == Some(n))
+            .map(|(&c, _)| c)
+    }
+    /// The dominators of `n`, starting with `n` itself and ending at a root.
+    pub fn dominators(&self, n: Node) -> impl Iterator<Item = Node> + '_ {
+        std::iter::successors(self.idom.contains_key(&n).then_some(n), move |&d| {
+            self.immediate_dominator(d)
+        })
+    }
+    pub fn dominates(&self, d: Node, n: Node) -> bool {
+        self.dominators(n).any(|m| m == d)
+    }
+    pub fn strictly_dominates(&self, d: Node, n: Node) -> bool {
+        d != n && self.dominates(d, n)
+    }
+    /// The dominance frontier of every node in the tree: the nodes where the
+    /// dominance of `n` ends, as they have a predecessor dominated by `n`
+    /// without being strictly dominated by `n` themselves.
+    pub fn frontiers(&self) -> IndexMap<Node, IndexSet<Node>> {
+        let mut frontiers: IndexMap<Node, IndexSet<Node>> =
+            self.nodes().map(|n| (n, IndexSet::new())).collect();
+        for (&n, &idom) in &self.idom {
+            let preds = self
+                .predecessors(n)
+                .filter(|p| self.idom.contains_key(p))
+                .collect::<Vec<_>>();
+            // Roots are also entered from outside of the graph
+            let entries = preds.len() + usize::from(self.roots.contains(&n));
+            if entries < 2 {
+                continue;
+            }
+            for p in preds {
+                let mut runner = Some(p);
+                while let Some(r) = runner {
+                    if Some(r) == idom {
+                        break;
+                    }
+                    frontiers[&r].insert(n);
+                    runner = self.immediate_dominator(r);
+                }
+            }
+        }
+        frontiers
+    }
+
+    fn predecessors(&self, n: Node) -> impl Iterator<Item = Node> + '_ {
+        self.predecessors.get(&n).into_iter().flatten().copied()
+    }
+}
+
+/// The common dominator of `a` and `b` closest to them, where `None` is a
+/// virtual node dominating every root.
+fn intersect(
+    idom: &IndexMap<Node, Option<Node>>,
+    rank: &IndexMap<Node, usize>,
+    mut a: Option<Node>,
+    mut b: Option<Node>,
+) -> Option<Node> {
+    let rank = |n: Option<Node>| n.map_or(0, |n| rank[&n] + 1);
+    while a != b {
+        while rank(a) > rank(b) {
+            a = idom[&a.unwrap()];
+        }
+        while rank(b) > rank(a) {
+            b = idom[&b.unwrap()];
+        }
+    }
+    a
+}
+
+/// A loop of the graph with a single entry point, the header, which dominates
+/// every other node of the loop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NaturalLoop {
+    pub header: Node,
+    /// The edges going from the body of the loop back to the header
+    pub back_edges: Vec<Edge>,
+    /// The nodes of the loop, including the header
+    pub nodes: IndexSet<Node>,
+}
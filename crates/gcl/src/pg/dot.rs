@@ -0,0 +1,570 @@
+//! The dot language of Graphviz.
+//!
+//! The parser accepts the full language as described in the Graphviz
+//! documentation: quoted, numeral and HTML identifiers, attribute lists,
+//! default attributes, subgraphs and chains of edges, along with comments.
+//! Ports and compass points are accepted but ignored.
+
+use std::fmt;
+
+use indexmap::{IndexMap, IndexSet};
+
+pub type Attributes = IndexMap<String, String>;
+
+/// A graph written in the dot language, where the default attributes are
+/// applied to the nodes and edges they affect.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DotGraph {
+    pub name: Option<String>,
+    pub directed: bool,
+    pub attributes: Attributes,
+    pub nodes: IndexMap<String, Attributes>,
+    pub edges: Vec<DotEdge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DotEdge {
+    pub from: String,
+    pub to: String,
+    pub attributes: Attributes,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{message} on line {line}")]
+pub struct DotError {
+    pub message: String,
+    pub line: usize,
+}
+
+impl DotGraph {
+    pub fn parse(src: &str) -> Result<DotGraph, DotError> {
+        let tokens = lex(src)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            graph: DotGraph::default(),
+        };
+        parser.graph()?;
+        Ok(parser.graph)
+    }
+
+    /// Adds the node if it is not already in the graph.
+    pub fn node(&mut self, id: impl Into<String>) -> &mut Attributes {
+        self.nodes.entry(id.into()).or_default()
+    }
+
+    pub fn edge(&mut self, from: impl Into<String>, to: impl Into<String>) -> &mut Attributes {
+        let from = from.into();
+        let to = to.into();
+        self.node(from.clone());
+        self.node(to.clone());
+        self.edges.push(DotEdge {
+            from,
+            to,
+            attributes: Default::default(),
+        });
+        &mut self.edges.last_mut().unwrap().attributes
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// An identifier, and whether it was quoted, which keeps it from being a
+    /// keyword
+    Id(String, bool),
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Semi,
+    Comma,
+    Colon,
+    Equals,
+    EdgeOp,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Id(id, _) => write!(f, "'{id}'"),
+            Token::LBrace => write!(f, "'{{'"),
+            Token::RBrace => write!(f, "'}}'"),
+            Token::LBracket => write!(f, "'['"),
+            Token::RBracket => write!(f, "']'"),
+            Token::Semi => write!(f, "';'"),
+            Token::Comma => write!(f, "','"),
+            Token::Colon => write!(f, "':'"),
+            Token::Equals => write!(f, "'='"),
+            Token::EdgeOp => write!(f, "an edge operator"),
+        }
+    }
+}
+
+fn lex(src: &str) -> Result<Vec<(Token, usize)>, DotError> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens: Vec<(Token, usize)> = vec![];
+    let mut line = 1;
+    let mut i = 0;
+    let mut at_line_start = true;
+    let error = |message: &str, line| DotError {
+        message: message.to_string(),
+        line,
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let line_start = at_line_start;
+        at_line_start = c == '\n' || (at_line_start && c.is_whitespace());
+        match c {
+            '\n' => {
+                line += 1;
+                i += 1;
+            }
+            c if c.is_whitespace() => i += 1,
+            // Lines output by the C preprocessor
+            '#' if line_start => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                let start = line;
+                i += 2;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(error("unterminated comment", start)),
+                        Some('*') if chars.get(i + 1) == Some(&'/') => {
+                            i += 2;
+                            break;
+                        }
+                        Some(c) => {
+                            line += (*c == '\n') as usize;
+                            i += 1;
+                        }
+                    }
+                }
+            }
+            '{' | '}' | '[' | ']' | ';' | ',' | ':' | '=' => {
+                let token = match c {
+                    '{' => Token::LBrace,
+                    '}' => Token::RBrace,
+                    '[' => Token::LBracket,
+                    ']' => Token::RBracket,
+                    ';' => Token::Semi,
+                    ',' => Token::Comma,
+                    ':' => Token::Colon,
+                    _ => Token::Equals,
+                };
+                tokens.push((token, line));
+                i += 1;
+            }
+            '-' if matches!(chars.get(i + 1), Some('>' | '-')) => {
+                tokens.push((Token::EdgeOp, line));
+                i += 2;
+            }
+            '"' => {
+                let start = line;
+                let mut s = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(error("unterminated string", start)),
+                        Some('"') => {
+                            i += 1;
+                            break;
+                        }
+                        Some('\\') if chars.get(i + 1) == Some(&'\n') => {
+                            line += 1;
+                            i += 2;
+                        }
+                        Some('\\') if matches!(chars.get(i + 1), Some('"' | '\\')) => {
+                            s.push(chars[i + 1]);
+                            i += 2;
+                        }
+                        Some(c) => {
+                            line += (*c == '\n') as usize;
+                            s.push(*c);
+                            i += 1;
+                        }
+                    }
+                }
+                // Quoted strings can be concatenated with '+'
+                if let [.., (Token::Id(prev, true), _), (Token::Id(plus, false), _)] =
+                    tokens.as_mut_slice()
+                    && plus == "+"
+                {
+                    prev.push_str(&s);
+                    tokens.pop();
+                } else {
+                    tokens.push((Token::Id(s, true), start));
+                }
+            }
+            '<' => {
+                let start = line;
+                let mut depth = 0;
+                let mut s = String::new();
+                loop {
+                    match chars.get(i) {
+                        None => return Err(error("unterminated HTML string", start)),
+                        Some(c) => {
+                            match c {
+                                '<' => depth += 1,
+                                '>' => depth -= 1,
+                                '\n' => line += 1,
+                                _ => {}
+                            }
+                            i += 1;
+                            if depth == 0 {
+                                break;
+                            }
+                            if depth > 1 || *c != '<' {
+                                s.push(*c);
+                            }
+                        }
+                    }
+                }
+                tokens.push((Token::Id(s, true), start));
+            }
+            '+' => {
+                tokens.push((Token::Id("+".to_string(), false), line));
+                i += 1;
+            }
+            c if c == '-' || c == '.' || c.is_ascii_digit() => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && (chars[i] == '.' || chars[i].is_ascii_digit()) {
+                    i += 1;
+                }
+                let numeral: String = chars[start..i].iter().collect();
+                if numeral == "-" || numeral == "." || numeral == "-." {
+                    return Err(error(&format!("unexpected '{numeral}'"), line));
+                }
+                tokens.push((Token::Id(numeral, false), line));
+            }
+            c if c == '_' || c.is_alphabetic() || !c.is_ascii() => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i] == '_' || chars[i].is_alphanumeric() || !chars[i].is_ascii())
+                    && !chars[i].is_whitespace()
+                {
+                    i += 1;
+                }
+                tokens.push((Token::Id(chars[start..i].iter().collect(), false), line));
+            }
+            c => return Err(error(&format!("unexpected '{c}'"), line)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// The default attributes in effect in a graph or subgraph.
+#[derive(Debug, Clone, Default)]
+struct Scope {
+    node: Attributes,
+    edge: Attributes,
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    graph: DotGraph,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or(self.tokens.last())
+            .map_or(1, |(_, line)| *line)
+    }
+    fn error<T>(&self, expected: &str) -> Result<T, DotError> {
+        let message = match self.peek() {
+            Some(t) => format!("expected {expected}, found {t}"),
+            None => format!("expected {expected}, found the end of the input"),
+        };
+        Err(DotError {
+            message,
+            line: self.line(),
+        })
+    }
+    fn eat(&mut self, token: Token) -> bool {
+        if self.peek() == Some(&token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+    fn expect(&mut self, token: Token, expected: &str) -> Result<(), DotError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            self.error(expected)
+        }
+    }
+    fn keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Id(id, false)) if id.eq_ignore_ascii_case(keyword))
+    }
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if self.keyword(keyword) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+    fn id(&mut self) -> Option<String> {
+        const KEYWORDS: [&str; 6] = ["node", "edge", "graph", "digraph", "subgraph", "strict"];
+        match self.peek() {
+            Some(Token::Id(id, quoted))
+                if *quoted || !KEYWORDS.iter().any(|k| id.eq_ignore_ascii_case(k)) =>
+            {
+                let id = id.clone();
+                self.pos += 1;
+                Some(id)
+            }
+            _ => None,
+        }
+    }
+
+    fn graph(&mut self) -> Result<(), DotError> {
+        self.eat_keyword("strict");
+        if self.eat_keyword("digraph") {
+            self.graph.directed = true;
+        } else if !self.eat_keyword("graph") {
+            return self.error("'graph' or 'digraph'");
+        }
+        self.graph.name = self.id();
+        self.expect(Token::LBrace, "'{'")?;
+        self.statements(&mut Scope::default(), &mut IndexSet::new())?;
+        self.expect(Token::RBrace, "'}'")?;
+        if self.peek().is_some() {
+            return self.error("the end of the input");
+        }
+        Ok(())
+    }
+
+    /// Parses statements until a closing brace, adding the nodes they mention
+    /// to `mentioned`.
+    fn statements(
+        &mut self,
+        scope: &mut Scope,
+        mentioned: &mut IndexSet<String>,
+    ) -> Result<(), DotError> {
+        while self.peek().is_some() && self.peek() != Some(&Token::RBrace) {
+            self.statement(scope, mentioned)?;
+            self.eat(Token::Semi);
+        }
+        Ok(())
+    }
+
+    fn statement(
+        &mut self,
+        scope: &mut Scope,
+        mentioned: &mut IndexSet<String>,
+    ) -> Result<(), DotError> {
+        if self.eat_keyword("graph") {
+            let attributes = self.attribute_lists()?;
+            self.graph.attributes.extend(attributes);
+            return Ok(());
+        }
+        if self.eat_keyword("node") {
+            let attributes = self.attribute_lists()?;
+            scope.node.extend(attributes);
+            return Ok(());
+        }
+        if self.eat_keyword("edge") {
+            let attributes = self.attribute_lists()?;
+            scope.edge.extend(attributes);
+            return Ok(());
+        }
+
+        let operand = self.operand(scope, mentioned)?;
+        if let Operand::Node(id) = &operand
+            && self.eat(Token::Equals)
+        {
+            let Some(value) = self.id() else {
+                return self.error("a value");
+            };
+            self.graph.attributes.insert(id.clone(), value);
+            return Ok(());
+        }
+
+        let mut operands = vec![operand];
+        while self.eat(Token::EdgeOp) {
+            operands.push(self.operand(scope, mentioned)?);
+        }
+        let attributes = self.attribute_lists()?;
+
+        if let [Operand::Node(id)] = operands.as_slice() {
+            self.add_node(scope, mentioned, id).extend(attributes);
+            return Ok(());
+        }
+        for (from, to) in operands.iter().zip(&operands[1..]) {
+            for from in from.nodes() {
+                for to in to.nodes() {
+                    let mut edge_attributes = scope.edge.clone();
+                    edge_attributes.extend(attributes.clone());
+                    self.graph.edges.push(DotEdge {
+                        from: from.clone(),
+                        to: to.clone(),
+                        attributes: edge_attributes,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn operand(
+        &mut self,
+        scope: &Scope,
+        mentioned: &mut IndexSet<String>,
+    ) -> Result<Operand, DotError> {
+        if self.keyword("subgraph") || self.peek() == Some(&Token::LBrace) {
+            if self.eat_keyword("subgraph") {
+                self.id();
+            }
+            self.expect(Token::LBrace, "'{'")?;
+            let mut inner = scope.clone();
+            let mut nodes = IndexSet::new();
+            self.statements(&mut inner, &mut nodes)?;
+            self.expect(Token::RBrace, "'}'")?;
+            mentioned.extend(nodes.iter().cloned());
+            return Ok(Operand::Subgraph(nodes));
+        }
+
+        let Some(id) = self.id() else {
+            return self.error("a node, a subgraph or an attribute statement");
+        };
+        // Ports and compass points do not matter to the structure of the
+        // graph
+        while self.eat(Token::Colon) {
+            if self.id().is_none() {
+                return self.error("a port");
+            }
+        }
+        if self.peek() != Some(&Token::Equals) {
+            self.add_node(scope, mentioned, &id);
+        }
+        Ok(Operand::Node(id))
+    }
+
+    fn add_node(
+        &mut self,
+        scope: &Scope,
+        mentioned: &mut IndexSet<String>,
+        id: &str,
+    ) -> &mut Attributes {
+        mentioned.insert(id.to_string());
+        self.graph
+            .nodes
+            .entry(id.to_string())
+            .or_insert_with(|| scope.node.clone())
+    }
+
+    fn attribute_lists(&mut self) -> Result<Attributes, DotError> {
+        let mut attributes = Attributes::new();
+        while self.eat(Token::LBracket) {
+            while !self.eat(Token::RBracket) {
+                let Some(key) = self.id() else {
+                    return self.error("an attribute or ']'");
+                };
+                let value = if self.eat(Token::Equals) {
+                    match self.id() {
+                        Some(value) => value,
+                        None => return self.error("a value"),
+                    }
+                } else {
+                    "true".to_string()
+                };
+                attributes.insert(key, value);
+                if !self.eat(Token::Comma) {
+                    self.eat(Token::Semi);
+                }
+            }
+        }
+        Ok(attributes)
+    }
+}
+
+enum Operand {
+    Node(String),
+    Subgraph(IndexSet<String>),
+}
+
+impl Operand {
+    fn nodes(&self) -> Vec<&String> {
+        match self {
+            Operand::Node(id) => vec![id],
+            Operand::Subgraph(nodes) => nodes.iter().collect(),
+        }
+    }
+}
+
+/// Quotes `id` unless it is a plain identifier.
+fn quote(id: &str) -> String {
+    let plain = id.chars().next().is_some_and(|c| !c.is_ascii_digit())
+        && id.chars().all(|c| c == '_' || c.is_ascii_alphanumeric());
+    if plain {
+        id.to_string()
+    } else {
+        let escaped = id
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n");
+        format!("\"{escaped}\"")
+    }
+}
+
+fn write_attributes(f: &mut fmt::Formatter<'_>, attributes: &Attributes) -> fmt::Result {
+    if attributes.is_empty() {
+        return Ok(());
+    }
+    write!(f, "[")?;
+    for (i, (key, value)) in attributes.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}={}", quote(key), quote(value))?;
+    }
+    write!(f, "]")
+}
+
+impl fmt::Display for DotGraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, op) = if self.directed {
+            ("digraph", "->")
+        } else {
+            ("graph", "--")
+        };
+        write!(f, "{kind} ")?;
+        if let Some(name) = &self.name {
+            write!(f, "{} ", quote(name))?;
+        }
+        writeln!(f, "{{")?;
+        for (key, value) in &self.attributes {
+            writeln!(f, "  {}={};", quote(key), quote(value))?;
+        }
+        for (id, attributes) in &self.nodes {
+            write!(f, "  {}", quote(id))?;
+            write_attributes(f, attributes)?;
+            writeln!(f, ";")?;
+        }
+        for edge in &self.edges {
+            write!(f, "  {} {op} {}", quote(&edge.from), quote(&edge.to))?;
+            write_attributes(f, &edge.attributes)?;
+            writeln!(f, ";")?;
+        }
+        write!(f, "}}")
+    }
+}
@@ -0,0 +1,381 @@
+//! The GraphML format for graphs.
+//!
+//! Only the parts of XML which GraphML documents are made of are supported:
+//! elements, attributes, text, character references, comments, CDATA
+//! sections and processing instructions. The data of nodes and edges is
+//! looked up by the name of its key, falling back to the id of the key.
+
+use std::fmt::Write;
+
+use indexmap::IndexMap;
+
+use super::dot::Attributes;
+
+/// A graph read from or written as GraphML, with the data of its nodes and
+/// edges named by their keys.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GraphMl {
+    pub directed: bool,
+    pub nodes: IndexMap<String, Attributes>,
+    pub edges: Vec<GraphMlEdge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphMlEdge {
+    pub source: String,
+    pub target: String,
+    pub data: Attributes,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{message} on line {line}")]
+pub struct GraphMlError {
+    pub message: String,
+    pub line: usize,
+}
+
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attributes: Attributes,
+    children: Vec<Element>,
+    text: String,
+    line: usize,
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(|s| s.as_str())
+    }
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+    fn required(&self, name: &str) -> Result<&str, GraphMlError> {
+        self.attribute(name).ok_or_else(|| GraphMlError {
+            message: format!("<{}> is missing the attribute '{name}'", self.name),
+            line: self.line,
+        })
+    }
+}
+
+struct Reader<'a> {
+    src: &'a str,
+    pos: usize,
+    line: usize,
+}
+
+impl Reader<'_> {
+    fn rest(&self) -> &str {
+        &self.src[self.pos..]
+    }
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, GraphMlError> {
+        Err(GraphMlError {
+            message: message.into(),
+            line: self.line,
+        })
+    }
+    fn advance(&mut self, n: usize) {
+        self.line += self.src[self.pos..self.pos + n].matches('\n').count();
+        self.pos += n;
+    }
+    /// Skips past the next occurrence of `end`.
+    fn skip_past(&mut self, end: &str, what: &str) -> Result<(), GraphMlError> {
+        match self.rest().find(end) {
+            Some(n) => {
+                self.advance(n + end.len());
+                Ok(())
+            }
+            None => self.error(format!("unterminated {what}")),
+        }
+    }
+    fn skip_whitespace(&mut self) {
+        let n = self.rest().len() - self.rest().trim_start().len();
+        self.advance(n);
+    }
+    fn name(&mut self) -> Result<String, GraphMlError> {
+        let n = self
+            .rest()
+            .find(|c: char| c.is_whitespace() || matches!(c, '=' | '/' | '>'))
+            .unwrap_or(self.rest().len());
+        if n == 0 {
+            return self.error("expected a name");
+        }
+        let name = self.rest()[..n].to_string();
+        self.advance(n);
+        Ok(name)
+    }
+
+    /// Skips the declaration, comments and processing instructions which can
+    /// appear between elements.
+    fn skip_misc(&mut self) -> Result<(), GraphMlError> {
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("<?") {
+                self.skip_past("?>", "processing instruction")?;
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->", "comment")?;
+            } else if self.rest().starts_with("<!") {
+                self.skip_past(">", "declaration")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn element(&mut self) -> Result<Element, GraphMlError> {
+        if !self.rest().starts_with('<') {
+            return self.error("expected an element");
+        }
+        self.advance(1);
+        let mut element = Element {
+            line: self.line,
+            name: local_name(&self.name()?).to_string(),
+            ..Default::default()
+        };
+
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.advance(2);
+                return Ok(element);
+            }
+            if self.rest().starts_with('>') {
+                self.advance(1);
+                break;
+            }
+            let name = self.name()?;
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                return self.error(format!("expected a value for the attribute '{name}'"));
+            }
+            self.advance(1);
+            self.skip_whitespace();
+            let Some(quote) = self
+                .rest()
+                .chars()
+                .next()
+                .filter(|c| matches!(c, '"' | '\''))
+            else {
+                return self.error(format!("expected a quoted value for '{name}'"));
+            };
+            self.advance(1);
+            let Some(n) = self.rest().find(quote) else {
+                return self.error("unterminated attribute value");
+            };
+            let value = unescape(&self.rest()[..n]);
+            self.advance(n + 1);
+            element
+                .attributes
+                .insert(local_name(&name).to_string(), value);
+        }
+
+        loop {
+            if self.rest().starts_with("</") {
+                self.advance(2);
+                let name = self.name()?;
+                if local_name(&name) != element.name {
+                    return self.error(format!("expected </{}>, found </{name}>", element.name));
+                }
+                self.skip_whitespace();
+                self.skip_past(">", "closing tag")?;
+                return Ok(element);
+            } else if self.rest().starts_with("<![CDATA[") {
+                self.advance("<![CDATA[".len());
+                let Some(n) = self.rest().find("]]>") else {
+                    return self.error("unterminated CDATA section");
+                };
+                element.text.push_str(&self.rest()[..n]);
+                self.advance(n + 3);
+            } else if self.rest().starts_with("<!--") {
+                // Whitespace around comments is part of the text
+                self.skip_past("-->", "comment")?;
+            } else if self.rest().starts_with("<?") {
+                self.skip_past("?>", "processing instruction")?;
+            } else if self.rest().starts_with('<') {
+                let child = self.element()?;
+                element.children.push(child);
+            } else if self.rest().is_empty() {
+                return self.error(format!("unterminated <{}>", element.name));
+            } else {
+                let n = self.rest().find('<').unwrap_or(self.rest().len());
+                element.text.push_str(&unescape(&self.rest()[..n]));
+                self.advance(n);
+            }
+        }
+    }
+}
+
+/// The name without its namespace prefix.
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::new();
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let Some(end) = rest.find(';') else { break };
+        let entity = &rest[1..end];
+        let c = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(|dec| dec.parse()))
+                .and_then(|n| n.ok())
+                .and_then(char::from_u32),
+        };
+        match c {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl GraphMl {
+    pub fn parse(src: &str) -> Result<GraphMl, GraphMlError> {
+        let mut reader = Reader {
+            src,
+            pos: 0,
+            line: 1,
+        };
+        reader.skip_misc()?;
+        let root = reader.element()?;
+        reader.skip_misc()?;
+        if !reader.rest().is_empty() {
+            return reader.error("expected the end of the document");
+        }
+        if root.name != "graphml" {
+            return reader.error(format!("expected <graphml>, found <{}>", root.name));
+        }
+
+        // Data refers to keys by their id, but is named by the name of the key
+        let keys: IndexMap<&str, &str> = root
+            .children("key")
+            .filter_map(|k| {
+                let id = k.attribute("id")?;
+                Some((id, k.attribute("attr.name").unwrap_or(id)))
+            })
+            .collect();
+        let data = |e: &Element| -> Result<Attributes, GraphMlError> {
+            e.children("data")
+                .map(|d| {
+                    let key = d.required("key")?;
+                    let name = keys.get(key).copied().unwrap_or(key);
+                    Ok((name.to_string(), d.text.clone()))
+                })
+                .collect()
+        };
+
+        let Some(graph) = root.children("graph").next() else {
+            return reader.error("<graphml> does not contain a <graph>");
+        };
+        let mut result = GraphMl {
+            directed: graph.attribute("edgedefault") != Some("undirected"),
+            ..Default::default()
+        };
+        for node in graph.children("node") {
+            result
+                .nodes
+                .insert(node.required("id")?.to_string(), data(node)?);
+        }
+        for edge in graph.children("edge") {
+            let source = edge.required("source")?.to_string();
+            let target = edge.required("target")?.to_string();
+            result.nodes.entry(source.clone()).or_default();
+            result.nodes.entry(target.clone()).or_default();
+            result.edges.push(GraphMlEdge {
+                source,
+                target,
+                data: data(edge)?,
+            });
+        }
+        Ok(result)
+    }
+}
+
+impl std::fmt::Display for GraphMl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut keys: IndexMap<(&str, &str), ()> = IndexMap::new();
+        for data in self.nodes.values() {
+            keys.extend(data.keys().map(|k| (("node", k.as_str()), ())));
+        }
+        for edge in &self.edges {
+            keys.extend(edge.data.keys().map(|k| (("edge", k.as_str()), ())));
+        }
+
+        let mut out = String::new();
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            out,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        for (for_, name) in keys.keys() {
+            writeln!(
+                out,
+                r#"  <key id="{for_}_{0}" for="{for_}" attr.name="{0}" attr.type="string"/>"#,
+                escape(name)
+            )?;
+        }
+        let edgedefault = if self.directed {
+            "directed"
+        } else {
+            "undirected"
+        };
+        writeln!(out, r#"  <graph id="G" edgedefault="{edgedefault}">"#)?;
+        let data = |out: &mut String, for_: &str, data: &Attributes| -> std::fmt::Result {
+            for (key, value) in data {
+                writeln!(
+                    out,
+                    r#"      <data key="{for_}_{}">{}</data>"#,
+                    escape(key),
+                    escape(value)
+                )?;
+            }
+            Ok(())
+        };
+        for (id, attributes) in &self.nodes {
+            if attributes.is_empty() {
+                writeln!(out, r#"    <node id="{}"/>"#, escape(id))?;
+            } else {
+                writeln!(out, r#"    <node id="{}">"#, escape(id))?;
+                data(&mut out, "node", attributes)?;
+                writeln!(out, "    </node>")?;
+            }
+        }
+        for edge in &self.edges {
+            let (source, target) = (escape(&edge.source), escape(&edge.target));
+            if edge.data.is_empty() {
+                writeln!(out, r#"    <edge source="{source}" target="{target}"/>"#)?;
+            } else {
+                writeln!(out, r#"    <edge source="{source}" target="{target}">"#)?;
+                data(&mut out, "edge", &edge.data)?;
+                writeln!(out, "    </edge>")?;
+            }
+        }
+        writeln!(out, "  </graph>")?;
+        write!(out, "</graphml>")?;
+        f.write_str(&out)
+    }
+}
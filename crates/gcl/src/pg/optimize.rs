@@ -0,0 +1,406 @@
+//! Optimizations of program graphs.
+//!
+//! Each [`Pass`] rewrites the edges of a graph to a smaller graph with the
+//! same behavior: every execution of the optimized graph ends in the same
+//! memory as the corresponding execution of the original graph, and gets stuck
+//! or fails exactly when the original does.
+
+use std::collections::BTreeMap;
+
+use indexmap::{IndexMap, IndexSet};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ast::{AExpr, AExprKind, BExpr, BExprKind, Int, LogicOp, Target, Variable},
+    pg::{
+        Action, Edge, Node, ProgramGraph,
+        analysis::{Direction, FiFo, Lattice, MonotoneFramework, mono_analysis},
+    },
+    semantics::EmptySemanticsContext,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Pass {
+    /// Replaces variables known to hold a constant by their value, evaluates
+    /// constant expressions, and removes conditions which can never hold
+    ConstantFolding,
+    /// Removes edges out of nodes which cannot be reached from the start node
+    UnreachableNodes,
+    /// Merges the nodes of `skip` and `true` edges which are the only edge
+    /// out of their node
+    TrivialEdges,
+    /// Combines two conditions in sequence into one, when the node between
+    /// them has no other edges
+    ChainContraction,
+}
+
+impl Pass {
+    pub const ALL: [Pass; 4] = [
+        Pass::ConstantFolding,
+        Pass::UnreachableNodes,
+        Pass::TrivialEdges,
+        Pass::ChainContraction,
+    ];
+
+    pub fn apply(self, pg: &ProgramGraph) -> ProgramGraph {
+        match self {
+            Pass::ConstantFolding => constant_folding(pg),
+            Pass::UnreachableNodes => unreachable_nodes(pg),
+            Pass::TrivialEdges => trivial_edges(pg),
+            Pass::ChainContraction => chain_contraction(pg),
+        }
+    }
+}
+
+impl ProgramGraph {
+    /// Constructs a graph from its edges, with the nodes in the order they
+    /// first appear in. The start node is always part of the graph.
+    pub fn from_edges(edges: impl IntoIterator<Item = Edge>) -> ProgramGraph {
+        let edges: Vec<Edge> = edges.into_iter().collect();
+        let mut outgoing: IndexMap<Node, Vec<Edge>> = Default::default();
+        let mut nodes: IndexSet<Node> = [Node::Start].into_iter().collect();
+
+        for e in &edges {
+            outgoing.entry(e.0).or_default().push(e.clone());
+            nodes.insert(e.0);
+            nodes.insert(e.2);
+        }
+
+        ProgramGraph {
+            edges,
+            nodes,
+            outgoing,
+            source_map: Default::default(),
+        }
+    }
+
+    /// Applies the passes in order, repeating them until the graph no longer
+    /// changes. The nodes of the result are renamed in reverse post-order.
+    pub fn optimize(&self, passes: &[Pass]) -> ProgramGraph {
+        let mut pg = self.clone();
+        loop {
+            let next = passes.iter().fold(pg.clone(), |pg, pass| pass.apply(&pg));
+            if next.edges == pg.edges {
+                return next.rename_with_reverse_post_order();
+            }
+            pg = next;
+        }
+    }
+
+    /// A graph with the given edges, keeping the spans of those which are
+    /// also edges of `self`.
+    fn with_edges(&self, edges: Vec<Edge>) -> ProgramGraph {
+        let mut pg = ProgramGraph::from_edges(edges);
+        pg.source_map = pg
+            .edges
+            .iter()
+            .filter_map(|e| Some((e.clone(), *self.source_map.get(e)?)))
+            .collect();
+        pg
+    }
+}
+
+/// The integer variables known to hold a constant value at a node, where
+/// `None` marks nodes which can never be reached.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Constants(Option<BTreeMap<Variable, Int>>);
+
+impl Lattice for Constants {
+    fn bottom() -> Self {
+        Constants(None)
+    }
+
+    fn lub(&self, other: &Self) -> Self {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => Constants(Some(
+                a.iter()
+                    .filter(|(x, n)| b.get(*x) == Some(*n))
+                    .map(|(x, n)| (x.clone(), *n))
+                    .collect(),
+            )),
+            (Some(a), None) | (None, Some(a)) => Constants(Some(a.clone())),
+            (None, None) => Constants(None),
+        }
+    }
+
+    fn contains(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(a), Some(b)) => a.iter().all(|(x, n)| b.get(x) == Some(n)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ConstantPropagation;
+
+impl MonotoneFramework for ConstantPropagation {
+    type Domain = Constants;
+
+    fn semantic(&self, _pg: &ProgramGraph, e: &Edge, prev: &Self::Domain) -> Self::Domain {
+        let Some(known) = &prev.0 else {
+            return Constants(None);
+        };
+        let mut known = known.clone();
+        match e.action() {
+            Action::Assignment(Target::Variable(x), a) => match fold_aexpr(a, &known).kind {
+                AExprKind::Number(n) => {
+                    known.insert(x.clone(), n);
+                }
+                _ => {
+                    known.remove(x);
+                }
+            },
+            Action::Condition(b) => {
+                if fold_bexpr(b, &known) == BExpr::bool(false) {
+                    return Constants(None);
+                }
+            }
+            Action::Assignment(Target::Array(_, _), _)
+            | Action::BoolAssignment(_, _)
+            | Action::ArrayAssignment(_, _) => {}
+            Action::Skip => {}
+        }
+        Constants(Some(known))
+    }
+
+    fn direction() -> Direction {
+        Direction::Forward
+    }
+
+    fn initial(&self, _pg: &ProgramGraph) -> Self::Domain {
+        Constants(Some(BTreeMap::new()))
+    }
+}
+
+fn constant_folding(pg: &ProgramGraph) -> ProgramGraph {
+    let facts = mono_analysis::<_, FiFo>(ConstantPropagation, pg).facts;
+    let edges = pg
+        .edges
+        .iter()
+        .filter_map(|e| {
+            let known = facts.get(&e.from())?.0.as_ref()?;
+            let action = match e.action() {
+                Action::Assignment(t, a) => {
+                    Action::Assignment(fold_target(t, known), fold_aexpr(a, known))
+                }
+                Action::BoolAssignment(t, b) => {
+                    Action::BoolAssignment(fold_target(t, known), fold_bexpr(b, known))
+                }
+                Action::ArrayAssignment(arr, elems) => Action::ArrayAssignment(
+                    arr.clone(),
+                    elems.iter().map(|a| fold_aexpr(a, known)).collect(),
+                ),
+                Action::Skip => Action::Skip,
+                Action::Condition(b) => match fold_bexpr(b, known) {
+                    b if b.kind == BExprKind::Bool(false) => return None,
+                    b => Action::Condition(b),
+                },
+            };
+            Some(Edge(e.from(), action, e.to()))
+        })
+        .collect();
+    pg.with_edges(edges)
+}
+
+fn fold_target(t: &Target<Box<AExpr>>, known: &BTreeMap<Variable, Int>) -> Target<Box<AExpr>> {
+    match t {
+        Target::Variable(x) => Target::Variable(x.clone()),
+        Target::Array(arr, idx) => Target::Array(arr.clone(), Box::new(fold_aexpr(idx, known))),
+    }
+}
+
+/// Substitutes the known constants into the expression and evaluates the
+/// operations on constants, leaving those which would fail in place.
+fn fold_aexpr(a: &AExpr, known: &BTreeMap<Variable, Int>) -> AExpr {
+    match &a.kind {
+        AExprKind::Number(n) => AExpr::number(*n),
+        AExprKind::Reference(Target::Variable(x)) => match known.get(x) {
+            Some(n) => AExpr::number(*n),
+            None => AExpr::reference(Target::Variable(x.clone())),
+        },
+        AExprKind::Reference(t) => AExpr::reference(fold_target(t, known)),
+        AExprKind::Binary(l, op, r) => {
+            let (l, r) = (fold_aexpr(l, known), fold_aexpr(r, known));
+            if let (AExprKind::Number(l), AExprKind::Number(r)) = (&l.kind, &r.kind)
+                && let Ok(n) = op.semantic(*l, *r)
+            {
+                return AExpr::number(n);
+            }
+            AExpr::binary(l, *op, r)
+        }
+        AExprKind::Minus(x) => {
+            let x = fold_aexpr(x, known);
+            if let AExprKind::Number(n) = x.kind
+                && let Some(n) = n.checked_neg()
+            {
+                return AExpr::number(n);
+            }
+            AExpr::minus(x)
+        }
+        // Only functions of constants can be evaluated, as the arrays are not
+        // tracked
+        AExprKind::Function(f) => {
+            let f = AExpr::function(f.map(|a| fold_aexpr(a, known)));
+            f.semantics(&EmptySemanticsContext)
+                .map(AExpr::number)
+                .unwrap_or(f)
+        }
+    }
+}
+
+fn fold_bexpr(b: &BExpr, known: &BTreeMap<Variable, Int>) -> BExpr {
+    match &b.kind {
+        BExprKind::Bool(b) => BExpr::bool(*b),
+        BExprKind::Reference(t) => BExpr::reference(fold_target(t, known)),
+        BExprKind::Rel(l, op, r) => {
+            let (l, r) = (fold_aexpr(l, known), fold_aexpr(r, known));
+            match (&l.kind, &r.kind) {
+                (AExprKind::Number(l), AExprKind::Number(r)) => BExpr::bool(op.semantic(*l, *r)),
+                _ => BExpr::rel(l, *op, r),
+            }
+        }
+        BExprKind::Logic(l, op, r) => {
+            let (l, r) = (fold_bexpr(l, known), fold_bexpr(r, known));
+            match (op, &l.kind, &r.kind) {
+                (_, BExprKind::Bool(l), BExprKind::Bool(r)) => BExpr::bool(match op {
+                    LogicOp::And | LogicOp::Land => *l && *r,
+                    LogicOp::Or | LogicOp::Lor => *l || *r,
+                }),
+                // The left operand is evaluated first, so it can decide the
+                // result on its own, while the right operand can only be
+                // dropped when evaluating it cannot change the outcome
+                (LogicOp::And | LogicOp::Land, BExprKind::Bool(true), _)
+                | (LogicOp::Or | LogicOp::Lor, BExprKind::Bool(false), _) => r,
+                (LogicOp::And | LogicOp::Land, _, BExprKind::Bool(true))
+                | (LogicOp::Or | LogicOp::Lor, _, BExprKind::Bool(false)) => l,
+                (LogicOp::And, BExprKind::Bool(false), _) => BExpr::bool(false),
+                (LogicOp::Or, BExprKind::Bool(true), _) => BExpr::bool(true),
+                (op, _, _) => BExpr::logic(l, *op, r),
+            }
+        }
+        BExprKind::Not(x) => match fold_bexpr(x, known) {
+            BExpr {
+                kind: BExprKind::Bool(b),
+                ..
+            } => BExpr::bool(!b),
+            x => BExpr::not(x),
+        },
+    }
+}
+
+fn unreachable_nodes(pg: &ProgramGraph) -> ProgramGraph {
+    let mut reachable: IndexSet<Node> = [Node::Start].into_iter().collect();
+    let mut pending = vec![Node::Start];
+    while let Some(n) = pending.pop() {
+        for e in pg.outgoing(n) {
+            if reachable.insert(e.to()) {
+                pending.push(e.to());
+            }
+        }
+    }
+    pg.with_edges(
+        pg.edges
+            .iter()
+            .filter(|e| reachable.contains(&e.from()))
+            .cloned()
+            .collect(),
+    )
+}
+
+fn is_trivial(action: &Action) -> bool {
+    matches!(
+        action,
+        Action::Skip
+            | Action::Condition(BExpr {
+                kind: BExprKind::Bool(true),
+                ..
+            })
+    )
+}
+
+fn trivial_edges(pg: &ProgramGraph) -> ProgramGraph {
+    let mut pg = pg.clone();
+    loop {
+        // The start node keeps its name, so it absorbs the node it leads to,
+        // unless that is a final node, which must remain distinguishable
+        let merge = pg.edges.iter().find_map(|e| {
+            let (p, q) = (e.from(), e.to());
+            if !is_trivial(e.action()) || p == q || pg.outgoing(p).len() != 1 {
+                return None;
+            }
+            match (p, q) {
+                (Node::Start, Node::Node(_)) => Some((e.clone(), q, p)),
+                (Node::Node(_), _) => Some((e.clone(), p, q)),
+                _ => None,
+            }
+        });
+        let Some((trivial, from, into)) = merge else {
+            return pg;
+        };
+        let rename = |n: Node| if n == from { into } else { n };
+        let edges = pg
+            .edges
+            .iter()
+            .filter(|e| **e != trivial)
+            .map(|Edge(a, action, b)| Edge(rename(*a), action.clone(), rename(*b)))
+            .collect();
+        pg = pg.with_edges(edges);
+    }
+}
+
+fn chain_contraction(pg: &ProgramGraph) -> ProgramGraph {
+    let mut pg = pg.clone();
+    loop {
+        let mut incoming: IndexMap<Node, Vec<&Edge>> = IndexMap::new();
+        for e in &pg.edges {
+            incoming.entry(e.to()).or_default().push(e);
+        }
+        // Getting stuck on the second condition is only the same as getting
+        // stuck on the combined condition if the first edge is the only way
+        // out of its node, as another edge could be taken otherwise
+        let chain = pg.edges.iter().find_map(|first| {
+            let (p, q) = (first.from(), first.to());
+            let (Action::Condition(b1), [second], [_]) = (
+                first.action(),
+                pg.outgoing(q),
+                incoming.get(&q).map_or(&[][..], |es| es.as_slice()),
+            ) else {
+                return None;
+            };
+            let Action::Condition(b2) = second.action() else {
+                return None;
+            };
+            if !matches!(q, Node::Node(_))
+                || pg.outgoing(p).len() != 1
+                || p == q
+                || second.to() == q
+            {
+                return None;
+            }
+            let combined = Edge(
+                p,
+                Action::Condition(BExpr::logic(b1.clone(), LogicOp::And, b2.clone())),
+                second.to(),
+            );
+            Some((first.clone(), second.clone(), combined))
+        });
+        let Some((first, second, combined)) = chain else {
+            return pg;
+        };
+        let edges = pg
+            .edges
+            .iter()
+            .filter(|e| **e != second)
+            .map(|e| {
+                if *e == first {
+                    combined.clone()
+                } else {
+                    e.clone()
+                }
+            })
+            .collect();
+        pg = pg.with_edges(edges);
+    }
+}
@@ -0,0 +1,214 @@
+//! Program graphs as JSON, dot and GraphML.
+//!
+//! Nodes are identified by their [`Debug`] names `qStart`, `qFinal`, `qError`
+//! and `qN`, which need no quoting in any of the formats, and are labelled by
+//! their [`Display`](std::fmt::Display) names. As the nodes of the graph of a
+//! program are numbered in reverse post-order, a program always gives the same
+//! names. When reading a graph, a node is recognised by either of its names,
+//! given as its id or as its label.
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    Action, Edge, Node, ProgramGraph,
+    dot::{DotError, DotGraph},
+    graphml::{GraphMl, GraphMlEdge, GraphMlError},
+};
+
+/// The JSON form of a program graph, with nodes named as in [`Node`]'s
+/// [`Debug`] implementation and actions written as in the program graph.
+#[derive(tapi::Tapi, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[tapi(path = "GCL")]
+pub struct GraphDocument {
+    pub nodes: Vec<String>,
+    pub edges: Vec<GraphDocumentEdge>,
+}
+
+#[derive(tapi::Tapi, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[tapi(path = "GCL")]
+pub struct GraphDocumentEdge {
+    pub from: String,
+    pub action: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum GraphError {
+    #[error("failed to parse dot: {0}")]
+    Dot(#[from] DotError),
+    #[error("failed to parse GraphML: {0}")]
+    GraphMl(#[from] GraphMlError),
+    #[error("'{0}' is not the name of a node")]
+    Node(String),
+    #[error("the edge from '{from}' to '{to}' has no label")]
+    MissingLabel { from: String, to: String },
+    #[error("failed to parse the action '{action}': {message}")]
+    Action { action: String, message: String },
+}
+
+/// Resolves a node by its id, or by its label if the id is not a name of a
+/// node.
+fn node(id: &str, label: Option<&String>) -> Result<Node, GraphError> {
+    id.parse()
+        .or_else(|()| label.ok_or(())?.trim().parse())
+        .map_err(|()| GraphError::Node(label.cloned().unwrap_or_else(|| id.to_string())))
+}
+
+fn action(label: &str) -> Result<Action, GraphError> {
+    label.trim().parse().map_err(|err| GraphError::Action {
+        action: label.to_string(),
+        message: format!("{err}"),
+    })
+}
+
+impl ProgramGraph {
+    pub fn document(&self) -> GraphDocument {
+        GraphDocument {
+            nodes: self.nodes.iter().map(|n| format!("{n:?}")).collect(),
+            edges: self
+                .edges
+                .iter()
+                .map(|Edge(from, action, to)| GraphDocumentEdge {
+                    from: format!("{from:?}"),
+                    action: action.to_string(),
+                    to: format!("{to:?}"),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn from_document(document: &GraphDocument) -> Result<ProgramGraph, GraphError> {
+        let edges = document
+            .edges
+            .iter()
+            .map(|e| {
+                Ok(Edge(
+                    node(&e.from, None)?,
+                    action(&e.action)?,
+                    node(&e.to, None)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, GraphError>>()?;
+        let mut pg = ProgramGraph::from_edges(edges);
+        for n in &document.nodes {
+            pg.nodes.insert(node(n, None)?);
+        }
+        Ok(pg)
+    }
+
+    /// The graph in the dot language, with nodes and edges labelled.
+    pub fn dot_graph(&self) -> DotGraph {
+        let mut graph = DotGraph {
+            name: Some("G".to_string()),
+            directed: true,
+            ..Default::default()
+        };
+        for n in &self.nodes {
+            graph
+                .node(format!("{n:?}"))
+                .insert("label".to_string(), n.to_string());
+        }
+        for Edge(from, action, to) in &self.edges {
+            graph
+                .edge(format!("{from:?}"), format!("{to:?}"))
+                .insert("label".to_string(), action.to_string());
+        }
+        graph
+    }
+
+    pub fn dot(&self) -> String {
+        self.dot_graph().to_string()
+    }
+
+    /// Reads a graph written in the dot language, where every edge is labelled
+    /// by its action.
+    pub fn from_dot(src: &str) -> Result<ProgramGraph, GraphError> {
+        let graph = DotGraph::parse(src)?;
+        let node = |id: &String| node(id, graph.nodes.get(id).and_then(|a| a.get("label")));
+        let edges = graph
+            .edges
+            .iter()
+            .map(|e| {
+                let label = e
+                    .attributes
+                    .get("label")
+                    .ok_or_else(|| GraphError::MissingLabel {
+                        from: e.from.clone(),
+                        to: e.to.clone(),
+                    })?;
+                Ok(Edge(node(&e.from)?, action(label)?, node(&e.to)?))
+            })
+            .collect::<Result<Vec<_>, GraphError>>()?;
+        let mut pg = ProgramGraph::from_edges(edges);
+        for id in graph.nodes.keys() {
+            pg.nodes.insert(node(id)?);
+        }
+        Ok(pg)
+    }
+
+    pub fn graphml(&self) -> String {
+        GraphMl {
+            directed: true,
+            nodes: self
+                .nodes
+                .iter()
+                .map(|n| {
+                    let label = [("label".to_string(), n.to_string())];
+                    (format!("{n:?}"), label.into_iter().collect())
+                })
+                .collect(),
+            edges: self
+                .edges
+                .iter()
+                .map(|Edge(from, action, to)| GraphMlEdge {
+                    source: format!("{from:?}"),
+                    target: format!("{to:?}"),
+                    data: [("label".to_string(), action.to_string())]
+                        .into_iter()
+                        .collect(),
+                })
+                .collect(),
+        }
+        .to_string()
+    }
+
+    /// Reads a graph written in GraphML, where every edge has its action as
+    /// the data named `label`.
+    pub fn from_graphml(src: &str) -> Result<ProgramGraph, GraphError> {
+        let graph = GraphMl::parse(src)?;
+        let node = |id: &String| node(id, graph.nodes.get(id).and_then(|a| a.get("label")));
+        let edges = graph
+            .edges
+            .iter()
+            .map(|e| {
+                let label = e
+                    .data
+                    .get("label")
+                    .ok_or_else(|| GraphError::MissingLabel {
+                        from: e.source.clone(),
+                        to: e.target.clone(),
+                    })?;
+                Ok(Edge(node(&e.source)?, action(label)?, node(&e.target)?))
+            })
+            .collect::<Result<Vec<_>, GraphError>>()?;
+        let mut pg = ProgramGraph::from_edges(edges);
+        for id in graph.nodes.keys() {
+            pg.nodes.insert(node(id)?);
+        }
+        Ok(pg)
+    }
+}
+
+impl From<ProgramGraph> for GraphDocument {
+    fn from(pg: ProgramGraph) -> Self {
+        pg.document()
+    }
+}
+
+impl TryFrom<GraphDocument> for ProgramGraph {
+    type Error = GraphError;
+
+    fn try_from(document: GraphDocument) -> Result<Self, Self::Error> {
+        ProgramGraph::from_document(&document)
+    }
+}
@@ -0,0 +1,327 @@
+//! Static single assignment form of program graphs.
+//!
+//! Every assignment to a variable in SSA form introduces a new version of it,
+//! such that each version is assigned at most once. Where the versions of
+//! several paths meet, a [`Phi`] at the node picks the version of the edge the
+//! node was entered by.
+//!
+//! Versions are named by subscripting the variable, such as `x₁`, which can
+//! never clash with a name of the source program. The version `x₀` is the
+//! initial value of `x`. Arrays are left as they are, as assigning to an
+//! element does not replace the array, but variables in their indices are
+//! renamed.
+
+use indexmap::{IndexMap, IndexSet};
+use itertools::Itertools;
+
+use crate::{
+    ast::{AExpr, AExprKind, BExpr, BExprKind, Target, Variable},
+    pg::{Action, Edge, Node, ProgramGraph, dot::DotGraph},
+};
+
+/// Chooses the version of `variable` to use at a node, depending on which
+/// edge the node was entered by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Phi {
+    pub variable: Variable,
+    pub sources: Vec<PhiSource>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhiSource {
+    /// The node the edge comes from, which is `None` when entering the
+    /// program at the start node
+    pub from: Option<Node>,
+    pub value: Variable,
+}
+
+impl std::fmt::Display for Phi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} := φ({})",
+            self.variable,
+            self.sources.iter().map(|s| &s.value).format(", ")
+        )
+    }
+}
+
+/// A program graph in SSA form, with the same nodes and edges as the graph it
+/// was constructed from.
+#[derive(Debug, Clone)]
+pub struct Ssa {
+    edges: Vec<Edge>,
+    phis: IndexMap<Node, Vec<Phi>>,
+}
+
+impl Ssa {
+    /// The edges of the graph, in the order of the original graph.
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+    /// The phis evaluated when entering `n`.
+    pub fn phis(&self, n: Node) -> &[Phi] {
+        self.phis.get(&n).map(|p| p.as_slice()).unwrap_or_default()
+    }
+    pub fn dot(&self) -> String {
+        let mut graph = DotGraph {
+            name: Some("G".to_string()),
+            directed: true,
+            ..Default::default()
+        };
+        for Edge(a, t, b) in &self.edges {
+            for n in [a, b] {
+                let label = std::iter::once(n.to_string())
+                    .chain(self.phis(*n).iter().map(|p| p.to_string()))
+                    .join("\n");
+                graph
+                    .node(format!("{n:?}"))
+                    .insert("label".to_string(), label);
+            }
+            graph
+                .edge(format!("{a:?}"), format!("{b:?}"))
+                .insert("label".to_string(), t.to_string());
+        }
+        graph.to_string()
+    }
+}
+
+fn version(x: &Variable, version: usize) -> Variable {
+    let subscript = version
+        .to_string()
+        .chars()
+        .map(|c| char::from_u32('₀' as u32 + c.to_digit(10).unwrap()).unwrap())
+        .collect::<String>();
+    Variable(format!("{x}{subscript}"))
+}
+
+/// The variable assigned by the action, if any.
+fn assigned(action: &Action) -> Option<&Variable> {
+    match action {
+        Action::Assignment(Target::Variable(x), _)
+        | Action::BoolAssignment(Target::Variable(x), _) => Some(x),
+        _ => None,
+    }
+}
+
+impl ProgramGraph {
+    /// Converts the graph to SSA form, placing phis at the iterated dominance
+    /// frontiers of the assignments to each variable, following Cytron et al.
+    pub fn ssa(&self) -> Ssa {
+        let dom = self.dominators();
+        let frontiers = dom.frontiers();
+
+        // The start node is also entered from outside of the graph
+        let mut entries: IndexMap<Node, usize> = [(Node::Start, 1)].into_iter().collect();
+        for e in &self.edges {
+            *entries.entry(e.to()).or_default() += 1;
+        }
+
+        let variables: IndexSet<Variable> = self
+            .fv()
+            .into_iter()
+            .filter_map(|t| match t {
+                Target::Variable(x) => Some(x),
+                Target::Array(_, ()) => None,
+            })
+            .collect();
+
+        let mut placed: IndexMap<Node, IndexSet<Variable>> = IndexMap::new();
+        for x in &variables {
+            let assignments = self
+                .edges
+                .iter()
+                .filter(|e| assigned(e.action()) == Some(x))
+                .collect_vec();
+            // An assignment on an edge into a node with several entries is
+            // only seen on that edge, and thus requires a phi at the node
+            let mut phis: IndexSet<Node> = assignments
+                .iter()
+                .map(|e| e.to())
+                .filter(|n| entries[n] > 1)
+                .collect();
+            let mut pending = std::iter::once(Node::Start)
+                .chain(assignments.iter().map(|e| e.to()))
+                .collect_vec();
+            pending.extend(phis.iter().copied());
+            while let Some(d) = pending.pop() {
+                for &f in frontiers.get(&d).into_iter().flatten() {
+                    if phis.insert(f) {
+                        pending.push(f);
+                    }
+                }
+            }
+            for n in phis {
+                placed.entry(n).or_default().insert(x.clone());
+            }
+        }
+
+        let mut renamer = Renamer {
+            pg: self,
+            edges: vec![None; self.edges.len()],
+            phis: placed
+                .into_iter()
+                .map(|(n, xs)| {
+                    let phis = xs
+                        .into_iter()
+                        .map(|x| {
+                            let phi = Phi {
+                                variable: x.clone(),
+                                sources: vec![],
+                            };
+                            (x, phi)
+                        })
+                        .collect();
+                    (n, phis)
+                })
+                .collect(),
+            versions: variables.iter().map(|x| (x.clone(), 0)).collect(),
+            current: variables
+                .iter()
+                .map(|x| (x.clone(), vec![version(x, 0)]))
+                .collect(),
+            entry: Default::default(),
+            entries,
+        };
+        for (x, phi) in renamer.phis.get_mut(&Node::Start).into_iter().flatten() {
+            phi.sources.push(PhiSource {
+                from: None,
+                value: version(x, 0),
+            });
+        }
+        renamer.visit(&dom, Node::Start);
+
+        Ssa {
+            edges: renamer.edges.into_iter().flatten().collect(),
+            phis: renamer
+                .phis
+                .into_iter()
+                .map(|(n, phis)| (n, phis.into_iter().map(|(_, phi)| phi).collect()))
+                .collect(),
+        }
+    }
+}
+
+struct Renamer<'a> {
+    pg: &'a ProgramGraph,
+    /// The renamed edges, by their index in the original graph
+    edges: Vec<Option<Edge>>,
+    /// The phis of each node along with the variable they are for, which are
+    /// given their versions and sources as the nodes are visited
+    phis: IndexMap<Node, Vec<(Variable, Phi)>>,
+    /// The number of versions introduced for each variable
+    versions: IndexMap<Variable, usize>,
+    /// The versions in scope, with the current one last
+    current: IndexMap<Variable, Vec<Variable>>,
+    /// The versions assigned on the only edge into a node
+    entry: IndexMap<Node, Vec<(Variable, Variable)>>,
+    entries: IndexMap<Node, usize>,
+}
+
+impl Renamer<'_> {
+    /// Renames the edges out of `n` and the nodes dominated by it.
+    fn visit(&mut self, dom: &crate::pg::dominators::DominatorTree, n: Node) {
+        let mut introduced = vec![];
+        for (x, v) in self.entry.shift_remove(&n).into_iter().flatten() {
+            self.current[&x].push(v);
+            introduced.push(x);
+        }
+        for (x, phi) in self.phis.get_mut(&n).into_iter().flatten() {
+            let count = &mut self.versions[&*x];
+            *count += 1;
+            phi.variable = version(x, *count);
+            self.current[&*x].push(phi.variable.clone());
+            introduced.push(x.clone());
+        }
+
+        for (idx, e) in self.pg.edges.iter().enumerate() {
+            if e.from() != n {
+                continue;
+            }
+            let mut action = self.rename_action(e.action());
+            let defined = assigned(e.action()).map(|x| {
+                let v = self.fresh(x);
+                match &mut action {
+                    Action::Assignment(t, _) | Action::BoolAssignment(t, _) => {
+                        *t = Target::Variable(v.clone());
+                    }
+                    Action::ArrayAssignment(_, _) | Action::Skip | Action::Condition(_) => {
+                        unreachable!()
+                    }
+                }
+                (x.clone(), v)
+            });
+            self.edges[idx] = Some(Edge(n, action, e.to()));
+
+            let after = |x: &Variable| match &defined {
+                Some((y, v)) if y == x => v.clone(),
+                _ => self.current[x].last().unwrap().clone(),
+            };
+            for (x, phi) in self.phis.get_mut(&e.to()).into_iter().flatten() {
+                phi.sources.push(PhiSource {
+                    from: Some(n),
+                    value: after(x),
+                });
+            }
+            if let Some(def) = defined
+                && self.entries[&e.to()] == 1
+            {
+                self.entry.entry(e.to()).or_default().push(def);
+            }
+        }
+
+        for c in dom.children(n).collect_vec() {
+            self.visit(dom, c);
+        }
+
+        for x in introduced {
+            self.current[&x].pop();
+        }
+    }
+
+    fn fresh(&mut self, x: &Variable) -> Variable {
+        let n = &mut self.versions[x];
+        *n += 1;
+        version(x, *n)
+    }
+
+    fn rename_action(&self, action: &Action) -> Action {
+        match action {
+            Action::Assignment(t, a) => Action::Assignment(self.target(t), self.aexpr(a)),
+            Action::BoolAssignment(t, b) => Action::BoolAssignment(self.target(t), self.bexpr(b)),
+            Action::ArrayAssignment(arr, elems) => {
+                Action::ArrayAssignment(arr.clone(), elems.iter().map(|a| self.aexpr(a)).collect())
+            }
+            Action::Skip => Action::Skip,
+            Action::Condition(b) => Action::Condition(self.bexpr(b)),
+        }
+    }
+    fn target(&self, t: &Target<Box<AExpr>>) -> Target<Box<AExpr>> {
+        match t {
+            Target::Variable(x) => Target::Variable(self.current[x].last().unwrap().clone()),
+            Target::Array(a, idx) => Target::Array(a.clone(), Box::new(self.aexpr(idx))),
+        }
+    }
+    fn aexpr(&self, a: &AExpr) -> AExpr {
+        a.with_kind(match &a.kind {
+            AExprKind::Number(n) => AExprKind::Number(*n),
+            AExprKind::Reference(t) => AExprKind::Reference(self.target(t)),
+            AExprKind::Binary(l, op, r) => {
+                AExprKind::Binary(Box::new(self.aexpr(l)), *op, Box::new(self.aexpr(r)))
+            }
+            AExprKind::Minus(x) => AExprKind::Minus(Box::new(self.aexpr(x))),
+            AExprKind::Function(f) => AExprKind::Function(f.map(|a| self.aexpr(a))),
+        })
+    }
+    fn bexpr(&self, b: &BExpr) -> BExpr {
+        b.with_kind(match &b.kind {
+            BExprKind::Bool(b) => BExprKind::Bool(*b),
+            BExprKind::Reference(t) => BExprKind::Reference(self.target(t)),
+            BExprKind::Rel(l, op, r) => BExprKind::Rel(self.aexpr(l), *op, self.aexpr(r)),
+            BExprKind::Logic(l, op, r) => {
+                BExprKind::Logic(Box::new(self.bexpr(l)), *op, Box::new(self.bexpr(r)))
+            }
+            BExprKind::Not(x) => BExprKind::Not(Box::new(self.bexpr(x))),
+        })
+    }
+}
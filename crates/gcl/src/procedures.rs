@@ -0,0 +1,496 @@
+//! Procedures are given semantics by inlining every call at its call site,
+//! yielding a program consisting only of the core commands.
+//!
+//! Parameters are local to each activation of a procedure, and are thus
+//! renamed to `{param}@{proc}/{depth}`, where `depth` is the number of
+//! activations of the procedure at the time of the call. As `@` cannot occur
+//! in the names of a program, locals never clash with its variables, and they
+//! are left out of its [free variables](Commands::fv) and the targets it is
+//! [checked](Commands::check) against. All other variables and arrays
+//! referenced in the body of a procedure are global. Calls in the components
+//! of a `par` command are active at the same time, so each component numbers
+//! its activations after those of the components before it.
+//!
+//! A call `p(a1, ..., an)` is expanded into assignments of the arguments to
+//! the `in` parameters, followed by the body of `p`, followed by assignments of
+//! the `out` parameters to the arguments in their positions. Recursive calls
+//! are inlined up to [`MAX_CALL_DEPTH`] activations, after which the call
+//! fails the execution like `abort`. As how deep a program recurses depends on
+//! its input, the limit cannot be checked up front, and the interpreter instead
+//! reports such failures as
+//! [`Failure::CallDepthExceeded`](crate::interpreter::Failure::CallDepthExceeded),
+//! telling the user that the limit was reached rather than that the program
+//! failed.
+
+use indexmap::{IndexMap, IndexSet};
+use itertools::Itertools;
+use thiserror::Error;
+
+use crate::{
+    ast::{
+        AExpr, AExprKind, BExpr, BExprKind, Command, CommandKind, Commands, Guard, ParamMode,
+        ProcDecl, ProcName, Target, Variable,
+    },
+    parse::SourceSpan,
+};
+
+/// The maximum number of simultaneous activations of a single procedure.
+pub const MAX_CALL_DEPTH: usize = 8;
+
+impl Variable {
+    /// Whether this is a parameter of a procedure activation, introduced by
+    /// inlining the procedure.
+    pub fn is_local(&self) -> bool {
+        self.0.contains('@')
+    }
+}
+impl<Idx> Target<Idx> {
+    pub fn is_local(&self) -> bool {
+        match self {
+            Target::Variable(v) => v.is_local(),
+            Target::Array(_, _) => false,
+        }
+    }
+}
+
+fn local(name: &ProcName, var: &Variable, depth: usize) -> Variable {
+    Variable(format!("{var}@{name}/{depth}"))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ProcedureError {
+    #[error("procedure '{name}' is declared more than once")]
+    Redeclared { name: ProcName, span: SourceSpan },
+    #[error("parameter '{param}' of procedure '{name}' is declared more than once")]
+    DuplicateParameter {
+        name: ProcName,
+        param: Variable,
+        span: SourceSpan,
+    },
+    #[error("procedure '{name}' is not declared")]
+    Undeclared { name: ProcName, span: SourceSpan },
+    #[error("procedure '{name}' takes {expected} arguments, but {found} were given")]
+    ArityMismatch {
+        name: ProcName,
+        expected: usize,
+        found: usize,
+        span: SourceSpan,
+    },
+    #[error(
+        "argument {position} of '{name}' is passed to an out parameter, and must be a variable or an array element"
+    )]
+    OutArgumentNotTarget {
+        name: ProcName,
+        position: usize,
+        span: SourceSpan,
+    },
+}
+
+impl ProcedureError {
+    pub fn span(&self) -> SourceSpan {
+        match self {
+            ProcedureError::Redeclared { span, .. }
+            | ProcedureError::DuplicateParameter { span, .. }
+            | ProcedureError::Undeclared { span, .. }
+            | ProcedureError::ArityMismatch { span, .. }
+            | ProcedureError::OutArgumentNotTarget { span, .. } => *span,
+        }
+    }
+}
+
+impl Commands {
+    /// The procedures declared at the top-level of the program.
+    pub fn procedures(&self) -> IndexMap<&ProcName, &ProcDecl> {
+        self.0
+            .iter()
+            .filter_map(|cmd| match &cmd.kind {
+                CommandKind::Proc(decl) => Some((&decl.name, decl)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Whether the program declares or calls any procedures.
+    pub fn has_procedures(&self) -> bool {
+        self.0.iter().any(|cmd| match &cmd.kind {
+            CommandKind::Proc(_) | CommandKind::Call(_, _) => true,
+            CommandKind::If(guards) | CommandKind::Loop(guards) => {
+                guards.iter().any(|g| g.cmds.has_procedures())
+            }
+            CommandKind::Par(components) => components.iter().any(|c| c.has_procedures()),
+            CommandKind::Assignment(_, _)
+            | CommandKind::BoolAssignment(_, _)
+            | CommandKind::ArrayAssignment(_, _)
+            | CommandKind::Skip
+            | CommandKind::Assert(_)
+            | CommandKind::Abort => false,
+        })
+    }
+
+    /// Checks that procedures are declared once, and that all calls refer to
+    /// a declared procedure with the right number and kind of arguments.
+    pub fn check_procedures(&self) -> Vec<ProcedureError> {
+        let mut errors = Vec::new();
+        let mut procs = IndexMap::new();
+
+        for cmd in &self.0 {
+            let CommandKind::Proc(decl) = &cmd.kind else {
+                continue;
+            };
+            if procs.insert(&decl.name, decl).is_some() {
+                errors.push(ProcedureError::Redeclared {
+                    name: decl.name.clone(),
+                    span: cmd.span,
+                });
+            }
+            for param in decl.params.iter().duplicates_by(|p| &p.var) {
+                errors.push(ProcedureError::DuplicateParameter {
+                    name: decl.name.clone(),
+                    param: param.var.clone(),
+                    span: cmd.span,
+                });
+            }
+        }
+
+        check_calls(&procs, self, &mut errors);
+        errors
+    }
+
+    /// The spans of all calls in the program, including those in the bodies
+    /// of procedures.
+    pub fn call_spans(&self) -> IndexSet<SourceSpan> {
+        let mut spans = IndexSet::new();
+        for cmd in &self.0 {
+            match &cmd.kind {
+                CommandKind::Call(_, _) => {
+                    spans.insert(cmd.span);
+                }
+                CommandKind::If(guards) | CommandKind::Loop(guards) => {
+                    spans.extend(guards.iter().flat_map(|g| g.cmds.call_spans()));
+                }
+                CommandKind::Par(components) => {
+                    spans.extend(components.iter().flat_map(|c| c.call_spans()));
+                }
+                CommandKind::Proc(decl) => spans.extend(decl.body.call_spans()),
+                CommandKind::Assignment(_, _)
+                | CommandKind::BoolAssignment(_, _)
+                | CommandKind::ArrayAssignment(_, _)
+                | CommandKind::Skip
+                | CommandKind::Assert(_)
+                | CommandKind::Abort => {}
+            }
+        }
+        spans
+    }
+
+    /// Replaces all calls by the bodies of the called procedures, and removes
+    /// all procedure declarations.
+    ///
+    /// Calls which exceed [`MAX_CALL_DEPTH`] are replaced by `abort`, and calls
+    /// which are invalid according to [`Commands::check_procedures`] by a
+    /// stuck command.
+    pub fn inline_procedures(&self) -> Commands {
+        let mut inliner = Inliner {
+            procs: self.procedures(),
+            depth: IndexMap::new(),
+            deepest: IndexMap::new(),
+        };
+        let cmds = inliner.commands(self);
+        if cmds.0.is_empty() {
+            Commands(vec![CommandKind::Skip.into()])
+        } else {
+            cmds
+        }
+    }
+}
+
+fn check_calls(
+    procs: &IndexMap<&ProcName, &ProcDecl>,
+    cmds: &Commands,
+    errors: &mut Vec<ProcedureError>,
+) {
+    for cmd in &cmds.0 {
+        match &cmd.kind {
+            CommandKind::Assignment(_, _)
+            | CommandKind::BoolAssignment(_, _)
+            | CommandKind::ArrayAssignment(_, _)
+            | CommandKind::Skip
+            | CommandKind::Assert(_)
+            | CommandKind::Abort => {}
+            CommandKind::If(guards) | CommandKind::Loop(guards) => {
+                for g in guards {
+                    check_calls(procs, &g.cmds, errors);
+                }
+            }
+            CommandKind::Par(components) => {
+                for c in components {
+                    check_calls(procs, c, errors);
+                }
+            }
+            CommandKind::Proc(decl) => check_calls(procs, &decl.body, errors),
+            CommandKind::Call(name, args) => {
+                let Some(decl) = procs.get(name) else {
+                    errors.push(ProcedureError::Undeclared {
+                        name: name.clone(),
+                        span: cmd.span,
+                    });
+                    continue;
+                };
+                if decl.params.len() != args.len() {
+                    errors.push(ProcedureError::ArityMismatch {
+                        name: name.clone(),
+                        expected: decl.params.len(),
+                        found: args.len(),
+                        span: cmd.span,
+                    });
+                    continue;
+                }
+                for (idx, (param, arg)) in decl.params.iter().zip(args).enumerate() {
+                    if param.mode == ParamMode::Out && !matches!(&arg.kind, AExprKind::Reference(_))
+                    {
+                        errors.push(ProcedureError::OutArgumentNotTarget {
+                            name: name.clone(),
+                            position: idx + 1,
+                            span: cmd.span,
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+struct Inliner<'a> {
+    procs: IndexMap<&'a ProcName, &'a ProcDecl>,
+    depth: IndexMap<ProcName, usize>,
+    /// The deepest activation of each procedure inlined so far
+    deepest: IndexMap<ProcName, usize>,
+}
+
+impl Inliner<'_> {
+    fn commands(&mut self, cmds: &Commands) -> Commands {
+        Commands(cmds.0.iter().flat_map(|cmd| self.command(cmd)).collect())
+    }
+
+    fn guards(&mut self, guards: &[Guard]) -> Vec<Guard> {
+        guards
+            .iter()
+            .map(|g| Guard {
+                guard_span: g.guard_span,
+                guard: g.guard.clone(),
+                cmds: self.commands(&g.cmds),
+            })
+            .collect()
+    }
+
+    fn command(&mut self, cmd: &Command) -> Vec<Command> {
+        match &cmd.kind {
+            CommandKind::Assignment(_, _)
+            | CommandKind::BoolAssignment(_, _)
+            | CommandKind::ArrayAssignment(_, _)
+            | CommandKind::Skip
+            | CommandKind::Assert(_)
+            | CommandKind::Abort => {
+                vec![cmd.clone()]
+            }
+            CommandKind::If(guards) => {
+                vec![Command::new(CommandKind::If(self.guards(guards)), cmd.span)]
+            }
+            CommandKind::Loop(guards) => {
+                vec![Command::new(
+                    CommandKind::Loop(self.guards(guards)),
+                    cmd.span,
+                )]
+            }
+            CommandKind::Par(components) => {
+                vec![Command::new(
+                    CommandKind::Par(self.par(components)),
+                    cmd.span,
+                )]
+            }
+            CommandKind::Proc(_) => vec![],
+            CommandKind::Call(name, args) => match self.call(name, args, cmd.span) {
+                Ok(cmds) => cmds,
+                Err(CallError::Exceeded) => vec![Command::new(CommandKind::Abort, cmd.span)],
+                Err(CallError::Invalid) => vec![Command::new(CommandKind::If(vec![]), cmd.span)],
+            },
+        }
+    }
+
+    fn par(&mut self, components: &[Commands]) -> Vec<Commands> {
+        let outer = self.depth.clone();
+        let outer_deepest = std::mem::take(&mut self.deepest);
+        let mut taken = outer.clone();
+
+        let components = components
+            .iter()
+            .map(|c| {
+                self.depth = taken.clone();
+                self.deepest = taken.clone();
+                let cmds = self.commands(c);
+                taken = std::mem::take(&mut self.deepest);
+                cmds
+            })
+            .collect();
+
+        self.depth = outer;
+        self.deepest = outer_deepest;
+        for (name, depth) in taken {
+            self.reached(&name, depth);
+        }
+        components
+    }
+
+    fn reached(&mut self, name: &ProcName, depth: usize) {
+        let deepest = self.deepest.entry(name.clone()).or_default();
+        *deepest = (*deepest).max(depth);
+    }
+
+    fn call(
+        &mut self,
+        name: &ProcName,
+        args: &[AExpr],
+        span: SourceSpan,
+    ) -> Result<Vec<Command>, CallError> {
+        let decl = *self.procs.get(name).ok_or(CallError::Invalid)?;
+        if decl.params.len() != args.len() {
+            return Err(CallError::Invalid);
+        }
+        let depth = self.depth.get(name).copied().unwrap_or_default() + 1;
+        if depth > MAX_CALL_DEPTH {
+            return Err(CallError::Exceeded);
+        }
+
+        let params: IndexMap<&Variable, Variable> = decl
+            .params
+            .iter()
+            .map(|p| (&p.var, local(name, &p.var, depth)))
+            .collect();
+
+        let mut cmds = Vec::new();
+        for (param, arg) in decl.params.iter().zip(args) {
+            if param.mode == ParamMode::In {
+                cmds.push(Command::new(
+                    CommandKind::Assignment(
+                        Target::Variable(params[&param.var].clone()),
+                        arg.clone(),
+                    ),
+                    span,
+                ));
+            }
+        }
+
+        self.depth.insert(name.clone(), depth);
+        self.reached(name, depth);
+        cmds.extend(self.commands(&decl.body.rename(&params)).0);
+        self.depth.insert(name.clone(), depth - 1);
+
+        for (param, arg) in decl.params.iter().zip(args) {
+            if param.mode == ParamMode::Out {
+                let AExprKind::Reference(target) = &arg.kind else {
+                    return Err(CallError::Invalid);
+                };
+                cmds.push(Command::new(
+                    CommandKind::Assignment(
+                        target.clone(),
+                        AExpr::reference(Target::Variable(params[&param.var].clone())),
+                    ),
+                    span,
+                ));
+            }
+        }
+
+        Ok(cmds)
+    }
+}
+
+/// Why a call cannot be inlined.
+enum CallError {
+    /// The call does not match the declaration of the procedure
+    Invalid,
+    /// The procedure already has [`MAX_CALL_DEPTH`] activations
+    Exceeded,
+}
+
+type Renaming<'a> = IndexMap<&'a Variable, Variable>;
+
+impl Commands {
+    fn rename(&self, r: &Renaming) -> Commands {
+        Commands(
+            self.0
+                .iter()
+                .map(|cmd| Command::new(cmd.kind.rename(r), cmd.span))
+                .collect(),
+        )
+    }
+}
+impl CommandKind {
+    fn rename(&self, r: &Renaming) -> CommandKind {
+        let guards = |guards: &[Guard]| {
+            guards
+                .iter()
+                .map(|g| Guard {
+                    guard_span: g.guard_span,
+                    guard: g.guard.rename(r),
+                    cmds: g.cmds.rename(r),
+                })
+                .collect()
+        };
+        match self {
+            CommandKind::Assignment(t, a) => CommandKind::Assignment(t.rename(r), a.rename(r)),
+            CommandKind::BoolAssignment(t, b) => {
+                CommandKind::BoolAssignment(t.rename(r), b.rename(r))
+            }
+            CommandKind::ArrayAssignment(arr, elems) => CommandKind::ArrayAssignment(
+                arr.clone(),
+                elems.iter().map(|a| a.rename(r)).collect(),
+            ),
+            CommandKind::Skip => CommandKind::Skip,
+            CommandKind::Assert(b) => CommandKind::Assert(b.rename(r)),
+            CommandKind::Abort => CommandKind::Abort,
+            CommandKind::If(gs) => CommandKind::If(guards(gs)),
+            CommandKind::Loop(gs) => CommandKind::Loop(guards(gs)),
+            CommandKind::Proc(decl) => CommandKind::Proc(decl.clone()),
+            CommandKind::Call(name, args) => {
+                CommandKind::Call(name.clone(), args.iter().map(|a| a.rename(r)).collect())
+            }
+            CommandKind::Par(components) => {
+                CommandKind::Par(components.iter().map(|c| c.rename(r)).collect())
+            }
+        }
+    }
+}
+impl Target<Box<AExpr>> {
+    fn rename(&self, r: &Renaming) -> Target<Box<AExpr>> {
+        match self {
+            Target::Variable(v) => Target::Variable(r.get(v).cloned().unwrap_or_else(|| v.clone())),
+            Target::Array(a, idx) => Target::Array(a.clone(), Box::new(idx.rename(r))),
+        }
+    }
+}
+impl AExpr {
+    fn rename(&self, r: &Renaming) -> AExpr {
+        self.with_kind(match &self.kind {
+            AExprKind::Number(n) => AExprKind::Number(*n),
+            AExprKind::Reference(t) => AExprKind::Reference(t.rename(r)),
+            AExprKind::Binary(l, op, rhs) => {
+                AExprKind::Binary(Box::new(l.rename(r)), *op, Box::new(rhs.rename(r)))
+            }
+            AExprKind::Minus(x) => AExprKind::Minus(Box::new(x.rename(r))),
+            AExprKind::Function(f) => AExprKind::Function(f.map(|a| a.rename(r))),
+        })
+    }
+}
+impl BExpr {
+    fn rename(&self, r: &Renaming) -> BExpr {
+        self.with_kind(match &self.kind {
+            BExprKind::Bool(b) => BExprKind::Bool(*b),
+            BExprKind::Reference(t) => BExprKind::Reference(t.rename(r)),
+            BExprKind::Rel(l, op, rhs) => BExprKind::Rel(l.rename(r), *op, rhs.rename(r)),
+            BExprKind::Logic(l, op, rhs) => {
+                BExprKind::Logic(Box::new(l.rename(r)), *op, Box::new(rhs.rename(r)))
+            }
+            BExprKind::Not(x) => BExprKind::Not(Box::new(x.rename(r))),
+        })
+    }
+}
@@ -0,0 +1,257 @@
+//! Exploring the configurations a program graph can reach.
+//!
+//! A configuration is a node along with the memory at it. The [`StateSpace`]
+//! of a program graph is the transition system of the configurations reachable
+//! from an initial one, where each configuration is identified by its hash, so
+//! that it is only expanded once however many executions reach it. As memories
+//! can grow without bound, exploration is limited by fuel, which is spent on
+//! every transition.
+
+use std::collections::{HashMap, VecDeque};
+
+use itertools::Itertools;
+
+use crate::{
+    interpreter::{InterpreterMemory, TerminationState},
+    pg::{Action, Node, ProgramGraph, dot::DotGraph},
+};
+
+pub type ConfigurationId = usize;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Configuration {
+    pub node: Node,
+    pub memory: InterpreterMemory,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transition {
+    pub action: Action,
+    pub to: ConfigurationId,
+}
+
+/// The fuel ran out before every reachable configuration was explored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfFuel;
+
+#[derive(Debug, Clone)]
+pub struct StateSpace<'a> {
+    pg: &'a ProgramGraph,
+    configurations: Vec<Configuration>,
+    ids: HashMap<Configuration, ConfigurationId>,
+    /// The transitions from each configuration, or `None` if it has not been
+    /// expanded yet
+    transitions: Vec<Option<Vec<Transition>>>,
+    /// The configuration each configuration was first reached from
+    parents: Vec<Option<ConfigurationId>>,
+}
+
+impl<'a> StateSpace<'a> {
+    /// The id of the initial configuration.
+    pub const INITIAL: ConfigurationId = 0;
+
+    /// The state space with only the initial configuration, at the start node
+    /// with `initial_memory`, which is explored as it is asked for.
+    pub fn new(pg: &'a ProgramGraph, initial_memory: InterpreterMemory) -> StateSpace<'a> {
+        let mut space = StateSpace {
+            pg,
+            configurations: vec![],
+            ids: HashMap::new(),
+            transitions: vec![],
+            parents: vec![],
+        };
+        space.insert(
+            Configuration {
+                node: Node::Start,
+                memory: initial_memory,
+            },
+            None,
+        );
+        space
+    }
+
+    pub fn pg(&self) -> &'a ProgramGraph {
+        self.pg
+    }
+    pub fn configuration(&self, id: ConfigurationId) -> &Configuration {
+        &self.configurations[id]
+    }
+    /// The configurations found so far, indexed by their id.
+    pub fn configurations(&self) -> &[Configuration] {
+        &self.configurations
+    }
+    pub fn id(&self, configuration: &Configuration) -> Option<ConfigurationId> {
+        self.ids.get(configuration).copied()
+    }
+
+    fn insert(
+        &mut self,
+        configuration: Configuration,
+        parent: Option<ConfigurationId>,
+    ) -> ConfigurationId {
+        if let Some(id) = self.ids.get(&configuration) {
+            return *id;
+        }
+        let id = self.configurations.len();
+        self.ids.insert(configuration.clone(), id);
+        self.configurations.push(configuration);
+        self.transitions.push(None);
+        self.parents.push(parent);
+        id
+    }
+
+    /// The transitions from `id`, which is expanded if it has not been
+    /// already.
+    pub fn successors(&mut self, id: ConfigurationId) -> &[Transition] {
+        if self.transitions[id].is_none() {
+            let Configuration { node, memory } = &self.configurations[id];
+            let nexts = node
+                .nexts(self.pg, memory)
+                .map(|(node, action, memory)| (action.clone(), Configuration { node, memory }))
+                .collect_vec();
+            let transitions = nexts
+                .into_iter()
+                .map(|(action, next)| Transition {
+                    action,
+                    to: self.insert(next, Some(id)),
+                })
+                .collect();
+            self.transitions[id] = Some(transitions);
+        }
+        self.transitions[id].as_deref().unwrap()
+    }
+
+    /// Explores every configuration reachable from the initial one, breadth
+    /// first, spending one unit of `fuel` on every transition.
+    pub fn explore(&mut self, mut fuel: usize) -> Result<(), OutOfFuel> {
+        let mut queue = (0..self.configurations.len())
+            .filter(|id| self.transitions[*id].is_none())
+            .collect::<VecDeque<_>>();
+        while let Some(id) = queue.pop_front() {
+            let known = self.configurations.len();
+            let transitions = self.successors(id).len();
+            fuel = fuel.checked_sub(transitions).ok_or(OutOfFuel)?;
+            queue.extend(known..self.configurations.len());
+        }
+        Ok(())
+    }
+
+    /// Whether every configuration found has been expanded, such that the
+    /// state space holds every reachable configuration.
+    pub fn is_complete(&self) -> bool {
+        self.transitions.iter().all(|t| t.is_some())
+    }
+
+    /// How an execution in configuration `id` stands, where a configuration
+    /// from which a step can be taken is [`TerminationState::Running`].
+    pub fn state(&mut self, id: ConfigurationId) -> TerminationState {
+        if !self.successors(id).is_empty() {
+            return TerminationState::Running;
+        }
+        match self.configurations[id].node {
+            Node::End => TerminationState::Terminated,
+            Node::Error => TerminationState::AssertionFailed,
+            Node::Start | Node::Node(_) => TerminationState::Stuck,
+        }
+    }
+
+    /// The explored configurations from which no step can be taken.
+    pub fn terminal(&self) -> impl Iterator<Item = ConfigurationId> + '_ {
+        (0..self.configurations.len())
+            .filter(|id| self.transitions[*id].as_ref().is_some_and(|t| t.is_empty()))
+    }
+
+    /// The explored configurations in which an execution is stuck, as it can
+    /// take no step and is neither at the final nor the error node.
+    pub fn stuck(&self) -> impl Iterator<Item = ConfigurationId> + '_ {
+        self.terminal()
+            .filter(|id| matches!(self.configurations[*id].node, Node::Start | Node::Node(_)))
+    }
+
+    /// A cycle among the explored transitions, along which an execution can
+    /// run forever. The cycle starts and ends at its first configuration,
+    /// which is not repeated.
+    pub fn cycle(&self) -> Option<Vec<ConfigurationId>> {
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        enum Mark {
+            New,
+            Active,
+            Done,
+        }
+
+        let mut marks = vec![Mark::New; self.configurations.len()];
+        // The configurations being visited, and the index of the next
+        // transition to follow from each
+        let mut stack = vec![(Self::INITIAL, 0)];
+        marks[Self::INITIAL] = Mark::Active;
+        while let Some(&(id, i)) = stack.last() {
+            let transitions = self.transitions[id].as_deref().unwrap_or_default();
+            let Some(t) = transitions.get(i) else {
+                marks[id] = Mark::Done;
+                stack.pop();
+                continue;
+            };
+            stack.last_mut().unwrap().1 += 1;
+            match marks[t.to] {
+                Mark::New => {
+                    marks[t.to] = Mark::Active;
+                    stack.push((t.to, 0));
+                }
+                Mark::Active => {
+                    let start = stack.iter().position(|(c, _)| *c == t.to).unwrap();
+                    return Some(stack[start..].iter().map(|(c, _)| *c).collect());
+                }
+                Mark::Done => {}
+            }
+        }
+        None
+    }
+
+    /// The configurations along a shortest path from the initial
+    /// configuration to `id`, both included.
+    pub fn path_to(&self, id: ConfigurationId) -> Vec<ConfigurationId> {
+        let mut path = std::iter::successors(Some(id), |id| self.parents[*id]).collect_vec();
+        path.reverse();
+        path
+    }
+
+    /// The explored transition system in the dot language, with
+    /// configurations labelled by their node and memory.
+    pub fn dot(&self) -> String {
+        let mut graph = DotGraph {
+            name: Some("StateSpace".to_string()),
+            directed: true,
+            ..Default::default()
+        };
+        for (id, c) in self.configurations.iter().enumerate() {
+            graph.node(format!("c{id}")).insert(
+                "label".to_string(),
+                format!("{}\n{}", c.node, memory_label(&c.memory)),
+            );
+        }
+        for (id, transitions) in self.transitions.iter().enumerate() {
+            for t in transitions.iter().flatten() {
+                graph
+                    .edge(format!("c{id}"), format!("c{}", t.to))
+                    .insert("label".to_string(), t.action.to_string());
+            }
+        }
+        graph.to_string()
+    }
+}
+
+fn memory_label(memory: &InterpreterMemory) -> String {
+    itertools::chain!(
+        memory.variables.iter().map(|(x, v)| format!("{x} = {v}")),
+        memory.bools.iter().map(|(x, v)| format!("{x} = {v}")),
+        memory
+            .arrays
+            .iter()
+            .map(|(a, vs)| format!("{a} = [{}]", vs.iter().format(", "))),
+        memory
+            .bool_arrays
+            .iter()
+            .map(|(a, vs)| format!("{a} = [{}]", vs.iter().format(", "))),
+    )
+    .join(", ")
+}
@@ -1,8 +1,13 @@
 use crate::{
-    ast::{AExpr, AOp, Array, BExpr, Int, LogicOp, RelOp, Target, Variable},
+    ast::{
+        AExpr, AExprKind, AOp, Array, BExpr, BExprKind, Function, Int, LogicOp, RelOp, Target,
+        Variable,
+    },
+    parse::SourceSpan,
     pg::Action,
 };
 use indexmap::IndexSet;
+use num_bigint::{BigInt, Sign};
 
 #[derive(Debug, PartialEq, Eq, thiserror::Error)]
 pub enum SemanticsError {
@@ -26,7 +31,42 @@ pub enum SemanticsError {
     OutsideFunctionDomain,
 }
 
+/// A [`SemanticsError`] raised by the action of an edge, along with the span of
+/// the expression which failed, or otherwise of the command or guard the edge
+/// comes from.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+#[error("{error}")]
+pub struct LocatedSemanticsError {
+    pub error: SemanticsError,
+    pub span: Option<SourceSpan>,
+}
+
+/// How integer arithmetic behaves when a result does not fit in an [`Int`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Arithmetic {
+    /// Overflowing operations fail with [`SemanticsError::ArithmeticOverflow`].
+    #[default]
+    Checked,
+    /// Overflowing operations wrap around in two's complement, matching the
+    /// 32-bit integer instructions of RISC-V.
+    Wrapping,
+    /// Expressions are evaluated with arbitrary precision, and only fail if
+    /// the final value does not fit in an [`Int`]. Literals and the values
+    /// stored in memory are still [`Int`]s, so
+    /// [`AExpr::unbounded_semantics`] is used to get a larger result.
+    Unbounded,
+}
+
+/// The largest number of bits of an intermediate result in
+/// [`Arithmetic::Unbounded`], beyond which the operation is considered to
+/// overflow. This keeps repeated exponentiation from running away.
+pub const UNBOUNDED_MAX_BITS: u64 = 1 << 16;
+
 pub trait SemanticsContext: Sized + Clone {
+    /// The arithmetic used when evaluating expressions in this context.
+    fn arithmetic(&self) -> Arithmetic {
+        Arithmetic::Checked
+    }
     fn variable(&self, var: &Variable) -> Result<Int, SemanticsError>;
     fn array_element(&self, array: &Array, index: Int) -> Result<Int, SemanticsError>;
     fn set_variable(&self, var: &Variable, value: Int) -> Result<Self, SemanticsError>;
@@ -36,14 +76,32 @@ pub trait SemanticsContext: Sized + Clone {
         index: Int,
         value: Int,
     ) -> Result<Self, SemanticsError>;
+    /// Replaces the elements of an integer array, changing its length to the
+    /// number of `values`.
+    fn set_array(&self, array: &Array, values: Vec<Int>) -> Result<Self, SemanticsError>;
+    fn bool_variable(&self, var: &Variable) -> Result<bool, SemanticsError>;
+    fn bool_array_element(&self, array: &Array, index: Int) -> Result<bool, SemanticsError>;
+    fn set_bool_variable(&self, var: &Variable, value: bool) -> Result<Self, SemanticsError>;
+    fn set_bool_array_element(
+        &self,
+        array: &Array,
+        index: Int,
+        value: bool,
+    ) -> Result<Self, SemanticsError>;
+    /// The length of the array, regardless of the type of its elements.
     fn array_length(&self, array: &Array) -> Result<Int, SemanticsError>;
     fn array_count(&self, array: &Array, element: Int) -> Result<Int, SemanticsError>;
 
     fn agrees_on(&self, vars: &IndexSet<Target>, other: &Self) -> bool {
         vars.iter().all(|t| match t {
-            Target::Variable(v) => self.variable(v) == other.variable(v),
-            Target::Array(a, ()) => (0..self.array_length(a).unwrap_or_default())
-                .all(|idx| self.array_element(a, idx) == other.array_element(a, idx)),
+            Target::Variable(v) => {
+                self.variable(v) == other.variable(v)
+                    && self.bool_variable(v) == other.bool_variable(v)
+            }
+            Target::Array(a, ()) => (0..self.array_length(a).unwrap_or_default()).all(|idx| {
+                self.array_element(a, idx) == other.array_element(a, idx)
+                    && self.bool_array_element(a, idx) == other.bool_array_element(a, idx)
+            }),
         })
     }
 }
@@ -77,6 +135,35 @@ impl SemanticsContext for EmptySemanticsContext {
         Ok(self.clone())
     }
 
+    fn set_array(&self, _array: &Array, _values: Vec<Int>) -> Result<Self, SemanticsError> {
+        Ok(self.clone())
+    }
+
+    fn bool_variable(&self, var: &Variable) -> Result<bool, SemanticsError> {
+        Err(SemanticsError::VariableNotFound {
+            name: var.to_string(),
+        })
+    }
+
+    fn set_bool_variable(&self, _var: &Variable, _value: bool) -> Result<Self, SemanticsError> {
+        Ok(self.clone())
+    }
+
+    fn bool_array_element(&self, array: &Array, _index: Int) -> Result<bool, SemanticsError> {
+        Err(SemanticsError::ArrayNotFound {
+            name: array.to_string(),
+        })
+    }
+
+    fn set_bool_array_element(
+        &self,
+        _array: &Array,
+        _index: Int,
+        _value: bool,
+    ) -> Result<Self, SemanticsError> {
+        Ok(self.clone())
+    }
+
     fn array_length(&self, array: &Array) -> Result<Int, SemanticsError> {
         Err(SemanticsError::ArrayNotFound {
             name: array.to_string(),
@@ -90,62 +177,256 @@ impl SemanticsContext for EmptySemanticsContext {
     }
 }
 
+/// Evaluates in the context `cx`, but with the given arithmetic.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WithArithmetic<S> {
+    pub arithmetic: Arithmetic,
+    pub cx: S,
+}
+
+impl<S> WithArithmetic<S> {
+    pub fn new(arithmetic: Arithmetic, cx: S) -> Self {
+        Self { arithmetic, cx }
+    }
+
+    fn lift(&self, cx: Result<S, SemanticsError>) -> Result<Self, SemanticsError> {
+        cx.map(|cx| Self::new(self.arithmetic, cx))
+    }
+}
+
+impl<S: SemanticsContext> SemanticsContext for WithArithmetic<S> {
+    fn arithmetic(&self) -> Arithmetic {
+        self.arithmetic
+    }
+
+    fn variable(&self, var: &Variable) -> Result<Int, SemanticsError> {
+        self.cx.variable(var)
+    }
+
+    fn array_element(&self, array: &Array, index: Int) -> Result<Int, SemanticsError> {
+        self.cx.array_element(array, index)
+    }
+
+    fn set_variable(&self, var: &Variable, value: Int) -> Result<Self, SemanticsError> {
+        self.lift(self.cx.set_variable(var, value))
+    }
+
+    fn set_array_element(
+        &self,
+        array: &Array,
+        index: Int,
+        value: Int,
+    ) -> Result<Self, SemanticsError> {
+        self.lift(self.cx.set_array_element(array, index, value))
+    }
+
+    fn set_array(&self, array: &Array, values: Vec<Int>) -> Result<Self, SemanticsError> {
+        self.lift(self.cx.set_array(array, values))
+    }
+
+    fn bool_variable(&self, var: &Variable) -> Result<bool, SemanticsError> {
+        self.cx.bool_variable(var)
+    }
+
+    fn bool_array_element(&self, array: &Array, index: Int) -> Result<bool, SemanticsError> {
+        self.cx.bool_array_element(array, index)
+    }
+
+    fn set_bool_variable(&self, var: &Variable, value: bool) -> Result<Self, SemanticsError> {
+        self.lift(self.cx.set_bool_variable(var, value))
+    }
+
+    fn set_bool_array_element(
+        &self,
+        array: &Array,
+        index: Int,
+        value: bool,
+    ) -> Result<Self, SemanticsError> {
+        self.lift(self.cx.set_bool_array_element(array, index, value))
+    }
+
+    fn array_length(&self, array: &Array) -> Result<Int, SemanticsError> {
+        self.cx.array_length(array)
+    }
+
+    fn array_count(&self, array: &Array, element: Int) -> Result<Int, SemanticsError> {
+        self.cx.array_count(array, element)
+    }
+}
+
 impl AExpr {
     pub fn semantics<S: SemanticsContext>(&self, cx: &S) -> Result<Int, SemanticsError> {
-        Ok(match self {
-            AExpr::Number(n) => *n,
-            AExpr::Reference(Target::Variable(x)) => cx.variable(x)?,
-            AExpr::Reference(Target::Array(arr, idx)) => {
+        let arithmetic = cx.arithmetic();
+        if arithmetic == Arithmetic::Unbounded {
+            return Int::try_from(self.unbounded_semantics(cx)?)
+                .map_err(|_| SemanticsError::ArithmeticOverflow);
+        }
+        Ok(match &self.kind {
+            AExprKind::Number(n) => *n,
+            AExprKind::Reference(Target::Variable(x)) => cx.variable(x)?,
+            AExprKind::Reference(Target::Array(arr, idx)) => {
                 let idx = idx.semantics(cx)?;
                 cx.array_element(arr, idx)?
             }
-            AExpr::Binary(l, op, r) => op.semantic(l.semantics(cx)?, r.semantics(cx)?)?,
-            AExpr::Minus(n) => n
-                .semantics(cx)?
-                .checked_neg()
-                .ok_or(SemanticsError::ArithmeticOverflow)?,
+            AExprKind::Binary(l, op, r) => {
+                op.semantic_with(arithmetic, l.semantics(cx)?, r.semantics(cx)?)?
+            }
+            AExprKind::Minus(n) => {
+                let n = n.semantics(cx)?;
+                match arithmetic {
+                    Arithmetic::Wrapping => n.wrapping_neg(),
+                    _ => n.checked_neg().ok_or(SemanticsError::ArithmeticOverflow)?,
+                }
+            }
+            AExprKind::Function(f) => f.semantics(cx)?,
+        })
+    }
+
+    /// Evaluates the expression with arbitrary precision, regardless of the
+    /// arithmetic of the context. Array indices are evaluated using
+    /// [`AExpr::semantics`].
+    pub fn unbounded_semantics<S: SemanticsContext>(
+        &self,
+        cx: &S,
+    ) -> Result<BigInt, SemanticsError> {
+        Ok(match &self.kind {
+            AExprKind::Number(n) => BigInt::from(*n),
+            AExprKind::Reference(Target::Variable(x)) => cx.variable(x)?.into(),
+            AExprKind::Reference(Target::Array(arr, idx)) => {
+                let idx = idx.semantics(cx)?;
+                cx.array_element(arr, idx)?.into()
+            }
+            AExprKind::Binary(l, op, r) => {
+                op.unbounded_semantic(l.unbounded_semantics(cx)?, r.unbounded_semantics(cx)?)?
+            }
+            AExprKind::Minus(n) => -n.unbounded_semantics(cx)?,
+            AExprKind::Function(Function::Min(a, b)) => {
+                a.unbounded_semantics(cx)?.min(b.unbounded_semantics(cx)?)
+            }
+            AExprKind::Function(Function::Max(a, b)) => {
+                a.unbounded_semantics(cx)?.max(b.unbounded_semantics(cx)?)
+            }
+            AExprKind::Function(f) => f.semantics(cx)?.into(),
         })
     }
 }
 
+impl Function {
+    pub fn semantics<S: SemanticsContext>(&self, cx: &S) -> Result<Int, SemanticsError> {
+        match self {
+            Function::Min(a, b) => Ok(a.semantics(cx)?.min(b.semantics(cx)?)),
+            Function::Max(a, b) => Ok(a.semantics(cx)?.max(b.semantics(cx)?)),
+            Function::Length(arr) => cx.array_length(arr),
+            Function::Count(arr, a) => {
+                let element = a.semantics(cx)?;
+                cx.array_count(arr, element)
+            }
+        }
+    }
+}
+
 impl AOp {
     pub fn semantic(&self, l: Int, r: Int) -> Result<Int, SemanticsError> {
-        Ok(match self {
-            AOp::Plus => l.checked_add(r).ok_or(SemanticsError::ArithmeticOverflow)?,
-            AOp::Minus => l.checked_sub(r).ok_or(SemanticsError::ArithmeticOverflow)?,
-            AOp::Times => l.checked_mul(r).ok_or(SemanticsError::ArithmeticOverflow)?,
+        self.semantic_with(Arithmetic::Checked, l, r)
+    }
+
+    pub fn semantic_with(
+        &self,
+        arithmetic: Arithmetic,
+        l: Int,
+        r: Int,
+    ) -> Result<Int, SemanticsError> {
+        if arithmetic == Arithmetic::Unbounded {
+            return Int::try_from(self.unbounded_semantic(l.into(), r.into())?)
+                .map_err(|_| SemanticsError::ArithmeticOverflow);
+        }
+        let wrapping = arithmetic == Arithmetic::Wrapping;
+        let checked = |res: Option<Int>, wrapped: fn(Int, Int) -> Int| match res {
+            Some(res) => Ok(res),
+            None if wrapping => Ok(wrapped(l, r)),
+            None => Err(SemanticsError::ArithmeticOverflow),
+        };
+        match self {
+            AOp::Plus => checked(l.checked_add(r), Int::wrapping_add),
+            AOp::Minus => checked(l.checked_sub(r), Int::wrapping_sub),
+            AOp::Times => checked(l.checked_mul(r), Int::wrapping_mul),
             AOp::Divide => {
                 if r != 0 {
-                    l / r
+                    checked(l.checked_div(r), Int::wrapping_div)
                 } else {
-                    return Err(SemanticsError::DivisionByZero);
+                    Err(SemanticsError::DivisionByZero)
                 }
             }
             AOp::Pow => {
                 if r >= 0 {
-                    l.checked_pow(r as _)
-                        .ok_or(SemanticsError::ArithmeticOverflow)?
+                    checked(l.checked_pow(r as _), |l, r| l.wrapping_pow(r as _))
                 } else {
+                    Err(SemanticsError::NegativeExponent)
+                }
+            }
+        }
+    }
+
+    pub fn unbounded_semantic(&self, l: BigInt, r: BigInt) -> Result<BigInt, SemanticsError> {
+        let res = match self {
+            AOp::Plus => l + r,
+            AOp::Minus => l - r,
+            AOp::Times => l * r,
+            AOp::Divide => {
+                if r.sign() != Sign::NoSign {
+                    l / r
+                } else {
+                    return Err(SemanticsError::DivisionByZero);
+                }
+            }
+            AOp::Pow => {
+                if r.sign() == Sign::Minus {
                     return Err(SemanticsError::NegativeExponent);
                 }
+                // Powers of 0, 1 and -1 never grow, regardless of the exponent
+                if l.bits() <= 1 {
+                    return Ok(
+                        if r.sign() == Sign::NoSign || (l.sign() == Sign::Minus && !r.bit(0)) {
+                            BigInt::from(1)
+                        } else {
+                            l
+                        },
+                    );
+                }
+                match u32::try_from(&r) {
+                    Ok(r) if l.bits().saturating_mul(r as u64) <= UNBOUNDED_MAX_BITS => l.pow(r),
+                    _ => return Err(SemanticsError::ArithmeticOverflow),
+                }
             }
-        })
+        };
+        if res.bits() > UNBOUNDED_MAX_BITS {
+            return Err(SemanticsError::ArithmeticOverflow);
+        }
+        Ok(res)
     }
 }
 
 impl BExpr {
     pub fn semantics<S: SemanticsContext>(&self, cx: &S) -> Result<bool, SemanticsError> {
-        Ok(match self {
-            BExpr::Bool(b) => *b,
-            BExpr::Rel(l, op, r) => op.semantic(l.semantics(cx)?, r.semantics(cx)?),
-            BExpr::Logic(l, op, r) => op.semantic(l.semantics(cx)?, || r.semantics(cx))?,
-            BExpr::Not(b) => !b.semantics(cx)?,
+        Ok(match &self.kind {
+            BExprKind::Bool(b) => *b,
+            BExprKind::Reference(Target::Variable(x)) => cx.bool_variable(x)?,
+            BExprKind::Reference(Target::Array(arr, idx)) => {
+                let idx = idx.semantics(cx)?;
+                cx.bool_array_element(arr, idx)?
+            }
+            BExprKind::Rel(l, op, r) if cx.arithmetic() == Arithmetic::Unbounded => {
+                op.semantic(l.unbounded_semantics(cx)?, r.unbounded_semantics(cx)?)
+            }
+            BExprKind::Rel(l, op, r) => op.semantic(l.semantics(cx)?, r.semantics(cx)?),
+            BExprKind::Logic(l, op, r) => op.semantic(l.semantics(cx)?, || r.semantics(cx))?,
+            BExprKind::Not(b) => !b.semantics(cx)?,
         })
     }
 }
 
 impl RelOp {
-    pub fn semantic(&self, l: Int, r: Int) -> bool {
+    pub fn semantic<T: Ord>(&self, l: T, r: T) -> bool {
         match self {
             RelOp::Eq => l == r,
             RelOp::Ne => l != r,
@@ -190,6 +471,22 @@ impl Action {
                 let value = a.semantics(cx)?;
                 cx.set_array_element(arr, idx, value)
             }
+            Action::ArrayAssignment(arr, elems) => {
+                let values = elems
+                    .iter()
+                    .map(|a| a.semantics(cx))
+                    .collect::<Result<_, _>>()?;
+                cx.set_array(arr, values)
+            }
+            Action::BoolAssignment(Target::Variable(x), b) => {
+                let value = b.semantics(cx)?;
+                cx.set_bool_variable(x, value)
+            }
+            Action::BoolAssignment(Target::Array(arr, idx), b) => {
+                let idx = idx.semantics(cx)?;
+                let value = b.semantics(cx)?;
+                cx.set_bool_array_element(arr, idx, value)
+            }
             Action::Skip => Ok(cx.clone()),
             Action::Condition(b) => {
                 if b.semantics(cx)? {
@@ -201,3 +498,55 @@ impl Action {
         }
     }
 }
+
+impl AExpr {
+    /// The span of the innermost subexpression whose evaluation fails in `cx`,
+    /// or `None` if the expression evaluates or was not parsed from a source.
+    pub fn error_span<S: SemanticsContext>(&self, cx: &S) -> Option<SourceSpan> {
+        self.semantics(cx).err()?;
+        let inner = match &self.kind {
+            AExprKind::Number(_) | AExprKind::Reference(Target::Variable(_)) => None,
+            AExprKind::Reference(Target::Array(_, idx)) => idx.error_span(cx),
+            AExprKind::Binary(l, _, r) => l.error_span(cx).or_else(|| r.error_span(cx)),
+            AExprKind::Minus(x) => x.error_span(cx),
+            AExprKind::Function(f) => f.args().find_map(|a| a.error_span(cx)),
+        };
+        inner.or(Some(self.span).filter(|span| !span.is_empty()))
+    }
+}
+
+impl BExpr {
+    /// Like [`AExpr::error_span`], the span of the innermost failing
+    /// subexpression. The right operand of a logical operator only fails if
+    /// it is evaluated, which is when the left operand does not fail.
+    pub fn error_span<S: SemanticsContext>(&self, cx: &S) -> Option<SourceSpan> {
+        self.semantics(cx).err()?;
+        let inner = match &self.kind {
+            BExprKind::Bool(_) | BExprKind::Reference(Target::Variable(_)) => None,
+            BExprKind::Reference(Target::Array(_, idx)) => idx.error_span(cx),
+            BExprKind::Rel(l, _, r) => l.error_span(cx).or_else(|| r.error_span(cx)),
+            BExprKind::Logic(l, _, r) => l.error_span(cx).or_else(|| r.error_span(cx)),
+            BExprKind::Not(x) => x.error_span(cx),
+        };
+        inner.or(Some(self.span).filter(|span| !span.is_empty()))
+    }
+}
+
+impl Action {
+    /// The span of the expression of the action whose evaluation fails in
+    /// `cx`, if any. Failures to store the result, such as an index out of
+    /// bounds in the target of an assignment, are not located by this.
+    pub fn error_span<S: SemanticsContext>(&self, cx: &S) -> Option<SourceSpan> {
+        let index = |t: &Target<Box<AExpr>>| match t {
+            Target::Variable(_) => None,
+            Target::Array(_, idx) => idx.error_span(cx),
+        };
+        match self {
+            Action::Assignment(t, a) => index(t).or_else(|| a.error_span(cx)),
+            Action::BoolAssignment(t, b) => index(t).or_else(|| b.error_span(cx)),
+            Action::ArrayAssignment(_, elems) => elems.iter().find_map(|a| a.error_span(cx)),
+            Action::Skip => None,
+            Action::Condition(b) => b.error_span(cx),
+        }
+    }
+}
@@ -0,0 +1,879 @@
+//! Symbolic execution of program graphs.
+//!
+//! Instead of running a program on concrete values, the symbolic executor runs
+//! it on the unknown initial values of its variables, and collects the
+//! condition under which each path through the program graph is taken. The
+//! conditions are decided by the SMT solver Z3, which also finds an initial
+//! memory taking each of the paths.
+//!
+//! Paths are explored the way [`Node::next`] executes a program: the first
+//! edge out of a node whose action succeeds is taken, and an action fails if
+//! it divides by zero, indexes outside of an array, or overflows. Arrays have
+//! the same, fixed length in every initial memory, and an index which is not
+//! known is split into a path for each possible value.
+
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+use smtlib::backend::z3_binary::Z3Binary;
+
+use crate::{
+    ast::{
+        AExpr, AExprKind, AOp, Array, BExpr, BExprKind, Function, Int, LogicOp, RelOp, Target,
+        Variable,
+    },
+    ast_smt::{element_symbol, in_range, variable_symbol},
+    interpreter::{InterpreterMemory, TerminationState},
+    pg::{Action, Edge, Node, ProgramGraph},
+};
+
+/// A memory whose values are expressions over the initial values of the
+/// program. The initial value of a variable `x` is written `x`, and that of
+/// an array element `A[2]` is written `A[2]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolicMemory {
+    pub variables: BTreeMap<Variable, AExpr>,
+    pub arrays: BTreeMap<Array, Vec<AExpr>>,
+    pub bools: BTreeMap<Variable, BExpr>,
+    pub bool_arrays: BTreeMap<Array, Vec<BExpr>>,
+}
+
+impl SymbolicMemory {
+    /// Adds the variables and arrays of `other` which are not in `self`.
+    pub fn extend(&mut self, other: SymbolicMemory) {
+        for (x, v) in other.variables {
+            self.variables.entry(x).or_insert(v);
+        }
+        for (arr, vs) in other.arrays {
+            self.arrays.entry(arr).or_insert(vs);
+        }
+        for (x, v) in other.bools {
+            self.bools.entry(x).or_insert(v);
+        }
+        for (arr, vs) in other.bool_arrays {
+            self.bool_arrays.entry(arr).or_insert(vs);
+        }
+    }
+}
+
+/// A path through the program graph, from the start node to where the
+/// execution ends.
+#[derive(Debug, Clone)]
+pub struct Path {
+    pub edges: Vec<Edge>,
+    /// The condition on the initial values under which the path is taken
+    pub condition: BExpr,
+    /// The memory at the end of the path
+    pub memory: SymbolicMemory,
+    /// How the path ends, where [`TerminationState::Running`] marks paths
+    /// which were cut off by the bound on unrollings
+    pub state: TerminationState,
+    /// An initial memory taking the path, unless the solver could not find
+    /// one
+    pub input: Option<InterpreterMemory>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SymbolicError {
+    #[error("failed to run the SMT solver: {0}")]
+    Solver(#[from] smtlib::Error),
+    #[error("the exponent of `{0}` must be a constant between 0 and 64")]
+    UnsupportedExponent(AExpr),
+    #[error("the program has more than {0} paths")]
+    TooManyPaths(usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct SymbolicExecutor {
+    /// How many times a path may return to a node it has already visited
+    pub unrollings: usize,
+    /// The length of every array of the initial memory
+    pub array_length: usize,
+    /// The number of paths after which exploration gives up with
+    /// [`SymbolicError::TooManyPaths`]
+    pub max_paths: usize,
+}
+
+impl Default for SymbolicExecutor {
+    fn default() -> Self {
+        SymbolicExecutor {
+            unrollings: 2,
+            array_length: 3,
+            max_paths: 128,
+        }
+    }
+}
+
+/// One of the ways an expression can be evaluated, which is taken when all of
+/// the `conditions` hold, including those for the evaluation to succeed.
+#[derive(Debug, Clone)]
+struct Case<T> {
+    conditions: Vec<BExpr>,
+    value: T,
+}
+
+impl<T> Case<T> {
+    fn new(value: T) -> Case<T> {
+        Case {
+            conditions: vec![],
+            value,
+        }
+    }
+    /// Adds `b` to the conditions of the case, returning `None` if it can
+    /// never hold.
+    fn require(mut self, b: BExpr) -> Option<Case<T>> {
+        let b = b.simplify();
+        match b.kind {
+            BExprKind::Bool(true) => Some(self),
+            BExprKind::Bool(false) => None,
+            _ => {
+                self.conditions.push(b);
+                Some(self)
+            }
+        }
+    }
+    fn map<S>(self, f: impl FnOnce(T) -> S) -> Case<S> {
+        Case {
+            conditions: self.conditions,
+            value: f(self.value),
+        }
+    }
+    fn and<S>(&self, other: Case<S>) -> Case<(T, S)>
+    where
+        T: Clone,
+    {
+        Case {
+            conditions: self
+                .conditions
+                .iter()
+                .cloned()
+                .chain(other.conditions)
+                .collect(),
+            value: (self.value.clone(), other.value),
+        }
+    }
+}
+
+pub(crate) fn conjunction(bs: impl IntoIterator<Item = BExpr>) -> BExpr {
+    bs.into_iter()
+        .reduce(|a, b| BExpr::logic(a, LogicOp::Land, b))
+        .unwrap_or(BExpr::bool(true))
+        .simplify()
+}
+
+fn disjunction(bs: impl IntoIterator<Item = BExpr>) -> BExpr {
+    bs.into_iter()
+        .reduce(|a, b| BExpr::logic(a, LogicOp::Lor, b))
+        .unwrap_or(BExpr::bool(false))
+        .simplify()
+}
+
+fn in_bounds(x: &AExpr) -> BExpr {
+    BExpr::logic(
+        BExpr::rel(AExpr::number(Int::MIN), RelOp::Le, x.clone()),
+        LogicOp::Land,
+        BExpr::rel(x.clone(), RelOp::Le, AExpr::number(Int::MAX)),
+    )
+}
+
+/// The state of a path being explored.
+#[derive(Debug, Clone)]
+struct State {
+    node: Node,
+    memory: SymbolicMemory,
+    conditions: Vec<BExpr>,
+    edges: Vec<Edge>,
+    visits: BTreeMap<Node, usize>,
+}
+
+/// The result of deciding a condition.
+pub(crate) enum Solution {
+    Sat(InterpreterMemory),
+    Unsat,
+    Unknown,
+}
+
+impl SymbolicExecutor {
+    /// The initial memory, holding the initial value of every variable and
+    /// array of `pg`.
+    pub fn initial_memory(&self, pg: &ProgramGraph) -> SymbolicMemory {
+        let (ints, bools) = typed_targets(pg);
+        let mut mem = SymbolicMemory::default();
+        for t in ints {
+            match t {
+                Target::Variable(x) => {
+                    let value = AExpr::reference(Target::Variable(x.clone()));
+                    mem.variables.insert(x, value);
+                }
+                Target::Array(arr, ()) => {
+                    let values = (0..self.array_length)
+                        .map(|k| AExpr::reference(element(&arr, k)))
+                        .collect();
+                    mem.arrays.insert(arr, values);
+                }
+            }
+        }
+        for t in bools {
+            match t {
+                Target::Variable(x) => {
+                    let value = BExpr::reference(Target::Variable(x.clone()));
+                    mem.bools.insert(x, value);
+                }
+                Target::Array(arr, ()) => {
+                    let values = (0..self.array_length)
+                        .map(|k| BExpr::reference(element(&arr, k)))
+                        .collect();
+                    mem.bool_arrays.insert(arr, values);
+                }
+            }
+        }
+        mem
+    }
+
+    /// The feasible paths through `pg` which return to each node at most
+    /// [`unrollings`](Self::unrollings) times.
+    pub fn paths(&self, pg: &ProgramGraph) -> Result<Vec<Path>, SymbolicError> {
+        self.paths_from(pg, &self.initial_memory(pg))
+    }
+
+    /// Like [`paths`](Self::paths), but starting from `initial`, which must
+    /// hold the initial value of every variable and array of `pg`.
+    pub fn paths_from(
+        &self,
+        pg: &ProgramGraph,
+        initial: &SymbolicMemory,
+    ) -> Result<Vec<Path>, SymbolicError> {
+        let st = smtlib::Storage::new();
+        let mut solver = self.solver(&st, initial)?;
+        self.paths_with(&mut solver, pg, initial)
+    }
+
+    /// Like [`paths_from`](Self::paths_from), but deciding the conditions
+    /// with `solver`, which must be made for `initial`.
+    pub(crate) fn paths_with(
+        &self,
+        solver: &mut Solver,
+        pg: &ProgramGraph,
+        initial: &SymbolicMemory,
+    ) -> Result<Vec<Path>, SymbolicError> {
+        let mut paths = vec![];
+        let mut pending = vec![State {
+            node: Node::Start,
+            memory: initial.clone(),
+            conditions: vec![],
+            edges: vec![],
+            visits: [(Node::Start, 1)].into_iter().collect(),
+        }];
+
+        while let Some(state) = pending.pop() {
+            if paths.len() + pending.len() >= self.max_paths {
+                return Err(SymbolicError::TooManyPaths(self.max_paths));
+            }
+            // The negated conditions of the edges before the current one,
+            // which must not be taken for the current one to be
+            let mut disabled = vec![];
+            let mut next_states = vec![];
+            for e in pg.outgoing(state.node) {
+                let cases = self.action(&state.memory, e.action())?;
+                for case in &cases {
+                    let conditions = state
+                        .conditions
+                        .iter()
+                        .chain(&disabled)
+                        .chain(&case.conditions)
+                        .cloned()
+                        .collect_vec();
+                    // The condition only needs to be decided if it is
+                    // stronger than that of the path so far
+                    let stronger = conditions.len() > state.conditions.len();
+                    if stronger && matches!(solver.solve(&conditions)?, Solution::Unsat) {
+                        continue;
+                    }
+                    let mut next = State {
+                        node: e.to(),
+                        memory: case.value.clone(),
+                        conditions,
+                        edges: state.edges.clone(),
+                        visits: state.visits.clone(),
+                    };
+                    next.edges.push(e.clone());
+                    let visits = next.visits.entry(e.to()).or_default();
+                    *visits += 1;
+                    if *visits > self.unrollings + 1 {
+                        paths.push(finish(solver, pg, next, TerminationState::Running)?);
+                    } else {
+                        next_states.push(next);
+                    }
+                }
+                disabled.push(BExpr::not(disjunction(
+                    cases.into_iter().map(|c| conjunction(c.conditions)),
+                )));
+            }
+            // Explore the paths in the order of the edges
+            pending.extend(next_states.into_iter().rev());
+
+            let mut end = state;
+            let stronger = !disabled.is_empty();
+            end.conditions.extend(disabled);
+            if stronger && matches!(solver.solve(&end.conditions)?, Solution::Unsat) {
+                continue;
+            }
+            let outcome = match end.node {
+                Node::End => TerminationState::Terminated,
+                Node::Error => TerminationState::AssertionFailed,
+                Node::Start | Node::Node(_) => TerminationState::Stuck,
+            };
+            paths.push(finish(solver, pg, end, outcome)?);
+        }
+
+        Ok(paths)
+    }
+
+    /// Initial memories which together take every feasible path through
+    /// `pg`.
+    pub fn test_inputs(&self, pg: &ProgramGraph) -> Result<Vec<InterpreterMemory>, SymbolicError> {
+        Ok(self
+            .paths(pg)?
+            .into_iter()
+            .filter_map(|p| p.input)
+            .unique()
+            .collect())
+    }
+
+    /// Initial memories for which the execution gets stuck before reaching
+    /// the final node.
+    pub fn stuck_inputs(&self, pg: &ProgramGraph) -> Result<Vec<InterpreterMemory>, SymbolicError> {
+        Ok(self
+            .paths(pg)?
+            .into_iter()
+            .filter(|p| p.state == TerminationState::Stuck)
+            .filter_map(|p| p.input)
+            .unique()
+            .collect())
+    }
+
+    /// A solver for conditions over the initial values of `initial`.
+    pub(crate) fn solver<'st>(
+        &self,
+        st: &'st smtlib::Storage,
+        initial: &SymbolicMemory,
+    ) -> Result<Solver<'st>, SymbolicError> {
+        let mut solver = smtlib::Solver::new(st, Z3Binary::new("z3")?)?;
+
+        let ints = initial
+            .variables
+            .keys()
+            .map(|x| {
+                let c = smtlib::Int::new_const(st, variable_symbol(x));
+                (Target::Variable(x.clone()), c)
+            })
+            .chain(initial.arrays.keys().flat_map(|arr| {
+                (0..self.array_length).map(|k| {
+                    let c = smtlib::Int::new_const(st, element_symbol(arr, k));
+                    (Target::Array(arr.clone(), k), c)
+                })
+            }))
+            .collect_vec();
+        let bools = initial
+            .bools
+            .keys()
+            .map(|x| {
+                let c = smtlib::Bool::new_const(st, variable_symbol(x));
+                (Target::Variable(x.clone()), c)
+            })
+            .chain(initial.bool_arrays.keys().flat_map(|arr| {
+                (0..self.array_length).map(|k| {
+                    let c = smtlib::Bool::new_const(st, element_symbol(arr, k));
+                    (Target::Array(arr.clone(), k), c)
+                })
+            }))
+            .collect_vec();
+
+        // Every constant is mentioned outside of the scopes of the queries,
+        // such that it stays declared when they are popped
+        for (_, c) in &ints {
+            solver.assert(in_range(st, (*c).into()))?;
+        }
+        for (_, c) in &bools {
+            solver.assert(**c | !**c)?;
+        }
+
+        // Values left open by a model can be anything, so they are zero
+        let base = InterpreterMemory {
+            arrays: initial
+                .arrays
+                .keys()
+                .map(|arr| (arr.clone(), vec![0; self.array_length]))
+                .collect(),
+            bool_arrays: initial
+                .bool_arrays
+                .keys()
+                .map(|arr| (arr.clone(), vec![false; self.array_length]))
+                .collect(),
+            ..Default::default()
+        };
+
+        Ok(Solver {
+            st,
+            solver,
+            ints,
+            bools,
+            base,
+        })
+    }
+
+    /// The ways the action can be taken, along with the memory after it.
+    fn action(
+        &self,
+        mem: &SymbolicMemory,
+        action: &Action,
+    ) -> Result<Vec<Case<SymbolicMemory>>, SymbolicError> {
+        Ok(match action {
+            Action::Assignment(Target::Variable(x), a) => self
+                .aexpr(mem, a)?
+                .into_iter()
+                .map(|c| {
+                    c.map(|value| {
+                        let mut mem = mem.clone();
+                        mem.variables.insert(x.clone(), value);
+                        mem
+                    })
+                })
+                .collect(),
+            Action::Assignment(Target::Array(arr, idx), a) => {
+                let values = self.aexpr(mem, a)?;
+                self.index(mem, arr, idx)?
+                    .into_iter()
+                    .flat_map(|k| values.iter().map(move |v| k.and(v.clone())))
+                    .map(|c| {
+                        c.map(|(k, value)| {
+                            let mut mem = mem.clone();
+                            if let Some(data) = mem.arrays.get_mut(arr) {
+                                data[k] = value;
+                            }
+                            mem
+                        })
+                    })
+                    .collect()
+            }
+            Action::BoolAssignment(Target::Variable(x), b) => self
+                .bexpr(mem, b)?
+                .into_iter()
+                .map(|c| {
+                    c.map(|value| {
+                        let mut mem = mem.clone();
+                        mem.bools.insert(x.clone(), value);
+                        mem
+                    })
+                })
+                .collect(),
+            Action::BoolAssignment(Target::Array(arr, idx), b) => {
+                let values = self.bexpr(mem, b)?;
+                self.index(mem, arr, idx)?
+                    .into_iter()
+                    .flat_map(|k| values.iter().map(move |v| k.and(v.clone())))
+                    .map(|c| {
+                        c.map(|(k, value)| {
+                            let mut mem = mem.clone();
+                            if let Some(data) = mem.bool_arrays.get_mut(arr) {
+                                data[k] = value;
+                            }
+                            mem
+                        })
+                    })
+                    .collect()
+            }
+            Action::ArrayAssignment(arr, elems) => {
+                let mut cases = vec![Case::new(vec![])];
+                for a in elems {
+                    let values = self.aexpr(mem, a)?;
+                    cases = cases
+                        .iter()
+                        .flat_map(|c| values.iter().map(|v| c.and(v.clone())))
+                        .map(|c| {
+                            c.map(|(mut values, value)| {
+                                values.push(value);
+                                values
+                            })
+                        })
+                        .collect();
+                }
+                cases
+                    .into_iter()
+                    .map(|c| {
+                        c.map(|values| {
+                            let mut mem = mem.clone();
+                            mem.arrays.insert(arr.clone(), values);
+                            mem
+                        })
+                    })
+                    .collect()
+            }
+            Action::Skip => vec![Case::new(mem.clone())],
+            Action::Condition(b) => self
+                .bexpr(mem, b)?
+                .into_iter()
+                .filter_map(|c| {
+                    let holds = c.value.clone();
+                    c.map(|_| mem.clone()).require(holds)
+                })
+                .collect(),
+        })
+    }
+
+    /// The indices of `arr` which `idx` can evaluate to.
+    fn index(
+        &self,
+        mem: &SymbolicMemory,
+        arr: &Array,
+        idx: &AExpr,
+    ) -> Result<Vec<Case<usize>>, SymbolicError> {
+        let len = mem
+            .arrays
+            .get(arr)
+            .map(|data| data.len())
+            .or_else(|| mem.bool_arrays.get(arr).map(|data| data.len()))
+            .unwrap_or_default();
+        Ok(self
+            .aexpr(mem, idx)?
+            .into_iter()
+            .flat_map(|c| {
+                (0..len).filter_map(move |k| {
+                    let is_k = BExpr::rel(c.value.clone(), RelOp::Eq, AExpr::number(k as Int));
+                    c.clone().map(|_| k).require(is_k)
+                })
+            })
+            .collect())
+    }
+
+    fn aexpr(&self, mem: &SymbolicMemory, a: &AExpr) -> Result<Vec<Case<AExpr>>, SymbolicError> {
+        Ok(match &a.kind {
+            AExprKind::Number(n) => vec![Case::new(AExpr::number(*n))],
+            AExprKind::Reference(Target::Variable(x)) => mem
+                .variables
+                .get(x)
+                .map(|v| Case::new(v.clone()))
+                .into_iter()
+                .collect(),
+            AExprKind::Reference(Target::Array(arr, idx)) => self
+                .index(mem, arr, idx)?
+                .into_iter()
+                .filter_map(|c| {
+                    let value = mem.arrays.get(arr)?.get(c.value)?.clone();
+                    Some(c.map(|_| value))
+                })
+                .collect(),
+            AExprKind::Binary(l, op, r) => {
+                let rs = self.aexpr(mem, r)?;
+                let mut cases = vec![];
+                for l in self.aexpr(mem, l)? {
+                    for r in &rs {
+                        let c = l.and(r.clone());
+                        let (l, r) = &c.value;
+                        let c =
+                            match op {
+                                AOp::Divide => c.clone().require(BExpr::rel(
+                                    r.clone(),
+                                    RelOp::Ne,
+                                    AExpr::number(0),
+                                )),
+                                AOp::Pow => match r.simplify().kind {
+                                    AExprKind::Number(n) if n < 0 => None,
+                                    AExprKind::Number(n) if n <= 64 => Some(c.clone()),
+                                    _ => {
+                                        return Err(SymbolicError::UnsupportedExponent(
+                                            AExpr::binary(l.clone(), *op, r.clone()),
+                                        ));
+                                    }
+                                },
+                                AOp::Plus | AOp::Minus | AOp::Times => Some(c.clone()),
+                            };
+                        let Some(c) = c else { continue };
+                        let c = c.map(|(l, r)| AExpr::binary(l, *op, r).simplify());
+                        let value = c.value.clone();
+                        cases.extend(c.require(in_bounds(&value)));
+                    }
+                }
+                cases
+            }
+            AExprKind::Minus(x) => self
+                .aexpr(mem, x)?
+                .into_iter()
+                .filter_map(|c| {
+                    let c = c.map(|x| AExpr::minus(x).simplify());
+                    let value = c.value.clone();
+                    c.require(in_bounds(&value))
+                })
+                .collect(),
+            AExprKind::Function(Function::Min(l, r)) => self.extremum(mem, l, r, Function::Min)?,
+            AExprKind::Function(Function::Max(l, r)) => self.extremum(mem, l, r, Function::Max)?,
+            AExprKind::Function(Function::Length(arr)) => {
+                let len = mem
+                    .arrays
+                    .get(arr)
+                    .map(|data| data.len())
+                    .or_else(|| mem.bool_arrays.get(arr).map(|data| data.len()));
+                len.map(|len| Case::new(AExpr::number(len as Int)))
+                    .into_iter()
+                    .collect()
+            }
+            // Each element is split into a case where it equals the counted
+            // value and one where it does not
+            AExprKind::Function(Function::Count(arr, x)) => {
+                let Some(data) = mem.arrays.get(arr) else {
+                    return Ok(vec![]);
+                };
+                let mut cases = vec![];
+                for x in self.aexpr(mem, x)? {
+                    let value = x.value.clone();
+                    let mut counts = vec![x.map(|_| 0)];
+                    for e in data {
+                        let is_x = BExpr::rel(e.clone(), RelOp::Eq, value.clone());
+                        counts = counts
+                            .into_iter()
+                            .flat_map(|c| {
+                                let n = c.value;
+                                [
+                                    c.clone().map(|_| n + 1).require(is_x.clone()),
+                                    c.require(BExpr::not(is_x.clone())),
+                                ]
+                            })
+                            .flatten()
+                            .collect();
+                    }
+                    cases.extend(counts.into_iter().map(|c| c.map(AExpr::number)));
+                }
+                cases
+            }
+        })
+    }
+
+    /// The cases of `min(l, r)` or `max(l, r)`, as given by `f`.
+    fn extremum(
+        &self,
+        mem: &SymbolicMemory,
+        l: &AExpr,
+        r: &AExpr,
+        f: fn(Box<AExpr>, Box<AExpr>) -> Function,
+    ) -> Result<Vec<Case<AExpr>>, SymbolicError> {
+        let rs = self.aexpr(mem, r)?;
+        Ok(self
+            .aexpr(mem, l)?
+            .into_iter()
+            .flat_map(|l| {
+                rs.iter().map(move |r| l.and(r.clone())).map(|c| {
+                    c.map(|(l, r)| AExpr::function(f(Box::new(l), Box::new(r))).simplify())
+                })
+            })
+            .collect())
+    }
+
+    fn bexpr(&self, mem: &SymbolicMemory, b: &BExpr) -> Result<Vec<Case<BExpr>>, SymbolicError> {
+        Ok(match &b.kind {
+            BExprKind::Bool(b) => vec![Case::new(BExpr::bool(*b))],
+            BExprKind::Reference(Target::Variable(x)) => mem
+                .bools
+                .get(x)
+                .map(|v| Case::new(v.clone()))
+                .into_iter()
+                .collect(),
+            BExprKind::Reference(Target::Array(arr, idx)) => self
+                .index(mem, arr, idx)?
+                .into_iter()
+                .filter_map(|c| {
+                    let value = mem.bool_arrays.get(arr)?.get(c.value)?.clone();
+                    Some(c.map(|_| value))
+                })
+                .collect(),
+            BExprKind::Rel(l, op, r) => {
+                let rs = self.aexpr(mem, r)?;
+                self.aexpr(mem, l)?
+                    .into_iter()
+                    .flat_map(|l| {
+                        rs.iter()
+                            .map(move |r| l.and(r.clone()))
+                            .map(|c| c.map(|(l, r)| BExpr::rel(l, *op, r).simplify()))
+                    })
+                    .collect()
+            }
+            // The right operand of a short-circuiting operator is only
+            // evaluated, and can only fail, if the left operand does not
+            // decide the result
+            BExprKind::Logic(l, op @ (LogicOp::And | LogicOp::Or), r) => {
+                let decides = *op == LogicOp::Or;
+                let rs = self.bexpr(mem, r)?;
+                let mut cases = vec![];
+                for l in self.bexpr(mem, l)? {
+                    let holds = l.value.clone();
+                    let decided = if decides {
+                        holds.clone()
+                    } else {
+                        BExpr::not(holds.clone())
+                    };
+                    cases.extend(l.clone().map(|_| BExpr::bool(decides)).require(decided));
+                    let undecided = if decides { BExpr::not(holds) } else { holds };
+                    let Some(l) = l.require(undecided) else {
+                        continue;
+                    };
+                    cases.extend(rs.iter().map(|r| l.and(r.clone()).map(|(_, r)| r)));
+                }
+                cases
+            }
+            BExprKind::Logic(l, op, r) => {
+                let rs = self.bexpr(mem, r)?;
+                self.bexpr(mem, l)?
+                    .into_iter()
+                    .flat_map(|l| {
+                        rs.iter()
+                            .map(move |r| l.and(r.clone()))
+                            .map(|c| c.map(|(l, r)| BExpr::logic(l, *op, r).simplify()))
+                    })
+                    .collect()
+            }
+            BExprKind::Not(x) => self
+                .bexpr(mem, x)?
+                .into_iter()
+                .map(|c| c.map(|x| BExpr::not(x).simplify()))
+                .collect(),
+        })
+    }
+}
+
+impl Path {
+    /// Whether executing `pg` from `input` follows the path.
+    pub fn is_taken_by(&self, pg: &ProgramGraph, input: &InterpreterMemory) -> bool {
+        let mut node = Node::Start;
+        let mut mem = input.clone();
+        for e in &self.edges {
+            match node.next(pg, &mem) {
+                Some((next, next_mem)) if next == e.to() => {
+                    node = next;
+                    mem = next_mem;
+                }
+                _ => return false,
+            }
+        }
+        self.state == TerminationState::Running || node.next(pg, &mem).is_none()
+    }
+}
+
+/// Ends the path explored by `state` in `outcome`, along with an initial
+/// memory taking it if the solver finds one.
+fn finish(
+    solver: &mut Solver,
+    pg: &ProgramGraph,
+    state: State,
+    outcome: TerminationState,
+) -> Result<Path, SymbolicError> {
+    let input = match solver.solve(&state.conditions)? {
+        Solution::Sat(input) => Some(input),
+        Solution::Unsat | Solution::Unknown => None,
+    };
+    let path = Path {
+        condition: conjunction(state.conditions),
+        edges: state.edges,
+        memory: state.memory,
+        state: outcome,
+        input: None,
+    };
+    // The solver may find an input which does not take the path when the
+    // conditions are beyond what it can decide, so the input is checked
+    let input = input.filter(|input| path.is_taken_by(pg, input));
+    Ok(Path { input, ..path })
+}
+
+/// A running instance of Z3 deciding conditions over the initial values of a
+/// memory. Each condition is decided in a scope of its own, so one process
+/// serves every query of an exploration or equivalence check.
+pub(crate) struct Solver<'st> {
+    st: &'st smtlib::Storage,
+    solver: smtlib::Solver<'st, Z3Binary>,
+    ints: Vec<(Target<usize>, smtlib::Const<'st, smtlib::Int<'st>>)>,
+    bools: Vec<(Target<usize>, smtlib::Const<'st, smtlib::Bool<'st>>)>,
+    base: InterpreterMemory,
+}
+
+impl Solver<'_> {
+    /// Decides the conjunction of `conditions`, finding an initial memory for
+    /// which they hold if they are satisfiable.
+    pub(crate) fn solve(&mut self, conditions: &[BExpr]) -> Result<Solution, SymbolicError> {
+        let Solver {
+            st,
+            solver,
+            ints,
+            bools,
+            base,
+        } = self;
+        let solution = solver.scope(|solver| {
+            for b in conditions {
+                solver.assert(b.smt(st))?;
+            }
+            let model = match solver.check_sat_with_model()? {
+                smtlib::SatResultWithModel::Sat(model) => model,
+                smtlib::SatResultWithModel::Unsat => return Ok(Solution::Unsat),
+                smtlib::SatResultWithModel::Unknown => return Ok(Solution::Unknown),
+            };
+
+            let mut input = base.clone();
+            for (t, c) in ints.iter() {
+                let value = model
+                    .eval(*c)
+                    .and_then(|v| Int::try_from(v).ok())
+                    .unwrap_or_default();
+                match t {
+                    Target::Variable(x) => {
+                        input.variables.insert(x.clone(), value);
+                    }
+                    Target::Array(arr, k) => input.arrays.get_mut(arr).unwrap()[*k] = value,
+                }
+            }
+            for (t, c) in bools.iter() {
+                let value = model.eval(*c).unwrap_or_default();
+                match t {
+                    Target::Variable(x) => {
+                        input.bools.insert(x.clone(), value);
+                    }
+                    Target::Array(arr, k) => input.bool_arrays.get_mut(arr).unwrap()[*k] = value,
+                }
+            }
+            Ok(Solution::Sat(input))
+        })?;
+        Ok(solution)
+    }
+}
+
+/// The target holding the initial value of `A[k]` in a symbolic memory.
+fn element(arr: &Array, k: usize) -> Target<Box<AExpr>> {
+    Target::Array(arr.clone(), Box::new(AExpr::number(k as Int)))
+}
+
+/// The integer and boolean targets of the graph, where a target is boolean
+/// if it is assigned a boolean or used as one.
+pub(crate) fn typed_targets(pg: &ProgramGraph) -> (Vec<Target>, Vec<Target>) {
+    fn bexpr_refs(b: &BExpr, bools: &mut Vec<Target>) {
+        match &b.kind {
+            BExprKind::Bool(_) | BExprKind::Rel(_, _, _) => {}
+            BExprKind::Reference(t) => bools.push(t.clone().unit()),
+            BExprKind::Logic(l, _, r) => {
+                bexpr_refs(l, bools);
+                bexpr_refs(r, bools);
+            }
+            BExprKind::Not(x) => bexpr_refs(x, bools),
+        }
+    }
+
+    let mut bools = vec![];
+    for e in pg.edges() {
+        match e.action() {
+            Action::BoolAssignment(t, b) => {
+                bools.push(t.clone().unit());
+                bexpr_refs(b, &mut bools);
+            }
+            Action::Condition(b) => bexpr_refs(b, &mut bools),
+            Action::Assignment(_, _) | Action::ArrayAssignment(_, _) | Action::Skip => {}
+        }
+    }
+    let bools = bools.into_iter().unique().collect_vec();
+    let ints = pg.fv().into_iter().filter(|t| !bools.contains(t)).collect();
+    (ints, bools)
+}
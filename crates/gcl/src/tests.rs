@@ -0,0 +1,21 @@
+mod analysis;
+mod arithmetic;
+mod debugger;
+mod dominators;
+mod dot;
+mod equivalence;
+mod fmt;
+mod functions;
+mod graphml;
+mod parse;
+mod procedures;
+mod reachability;
+mod spans;
+mod ssa;
+mod symbolic;
+
+/// Whether Z3 can be started. Tests going through the solver return early
+/// where it is not installed.
+fn z3_available() -> bool {
+    smtlib::backend::z3_binary::Z3Binary::new("z3").is_ok()
+}
@@ -0,0 +1,252 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ast::{AExprKind, AOp, BExprKind, Commands, RelOp, Target},
+    pg::{
+        Action, Determinism, Edge, Node, ProgramGraph,
+        analysis::{
+            Direction, FiFo, Lattice, MAX_NARROWING_ROUNDS, MonotoneFramework, WorklistKind,
+            loop_heads, mono_analysis, mono_analysis_with,
+        },
+    },
+};
+
+fn pg(src: &str) -> ProgramGraph {
+    let cmds: Commands = src.parse().unwrap();
+    ProgramGraph::new(Determinism::NonDeterministic, &cmds)
+}
+
+/// Reaching definitions, with definitions named by the node they leave.
+#[derive(Debug, Clone)]
+struct ReachingDefinitions;
+
+impl MonotoneFramework for ReachingDefinitions {
+    type Domain = BTreeSet<(String, String)>;
+
+    fn semantic(&self, _pg: &ProgramGraph, e: &Edge, prev: &Self::Domain) -> Self::Domain {
+        match e.action() {
+            Action::Assignment(Target::Variable(v), _) => prev
+                .iter()
+                .filter(|(w, _)| *w != v.0)
+                .cloned()
+                .chain([(v.0.clone(), format!("{:?}", e.from()))])
+                .collect(),
+            _ => prev.clone(),
+        }
+    }
+
+    fn direction() -> Direction {
+        Direction::Forward
+    }
+
+    fn initial(&self, _pg: &ProgramGraph) -> Self::Domain {
+        BTreeSet::new()
+    }
+}
+
+const NESTED_LOOPS: &str = "
+    i := 0;
+    do i < 3 ->
+        j := 0;
+        do j < 3 ->
+            k := 0;
+            do k < 3 -> x := x + k; k := k + 1 od;
+            j := j + 1
+        od;
+        i := i + 1
+    od;
+    y := x
+";
+
+#[test]
+fn worklists_reach_the_same_fixpoint() {
+    let pg = pg(NESTED_LOOPS);
+    let fifo = mono_analysis_with(WorklistKind::FiFo, ReachingDefinitions, &pg);
+    for kind in WorklistKind::ALL {
+        let result = mono_analysis_with(kind, ReachingDefinitions, &pg);
+        assert_eq!(result.facts, fifo.facts, "{kind:?}");
+    }
+    // The definitions in the loops reach the end through the exits of the loops
+    let at_end = fifo.facts[&Node::End].iter().map(|(v, _)| v.as_str());
+    assert_eq!(
+        at_end.collect::<BTreeSet<_>>(),
+        BTreeSet::from(["i", "j", "k", "x", "y"])
+    );
+}
+
+#[test]
+fn ordered_worklists_make_fewer_semantic_calls() {
+    let pg = pg(NESTED_LOOPS);
+    let calls = |kind| mono_analysis_with(kind, ReachingDefinitions, &pg).semantic_calls;
+    let fifo = calls(WorklistKind::FiFo);
+    for kind in [WorklistKind::ReversePostOrder, WorklistKind::Scc] {
+        assert!(calls(kind) <= fifo, "{kind:?}: {} > {fifo}", calls(kind));
+    }
+}
+
+/// The values of `i` as an interval, where `i64::MAX` is unbounded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Range {
+    Empty,
+    Within(i64, i64),
+}
+
+impl Lattice for Range {
+    fn bottom() -> Self {
+        Range::Empty
+    }
+    fn lub(&self, other: &Self) -> Self {
+        match (*self, *other) {
+            (Range::Empty, r) | (r, Range::Empty) => r,
+            (Range::Within(a, b), Range::Within(c, d)) => Range::Within(a.min(c), b.max(d)),
+        }
+    }
+    fn contains(&self, other: &Self) -> bool {
+        self.lub(other) == *self
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Narrowing {
+    Off,
+    /// Replaces an unbounded upper bound by the one computed
+    Standard,
+    /// Lowers the upper bound by one every round, and thus never stabilizes
+    Unending,
+}
+
+/// The values of `i` in programs which only assign constants to it or
+/// increment it, and only compare it to constants.
+#[derive(Debug, Clone, Copy)]
+struct Counter(Narrowing);
+
+impl MonotoneFramework for Counter {
+    type Domain = Range;
+
+    fn semantic(&self, _pg: &ProgramGraph, e: &Edge, prev: &Range) -> Range {
+        let &Range::Within(lo, hi) = prev else {
+            return Range::Empty;
+        };
+        let (b, negated) = match e.action() {
+            Action::Assignment(Target::Variable(v), a) if v.0 == "i" => {
+                return match &a.kind {
+                    AExprKind::Number(n) => Range::Within(*n as _, *n as _),
+                    AExprKind::Binary(_, AOp::Plus, r) => match r.kind {
+                        AExprKind::Number(n) => {
+                            Range::Within(lo + n as i64, hi.saturating_add(n as _))
+                        }
+                        _ => unreachable!(),
+                    },
+                    _ => unreachable!(),
+                };
+            }
+            Action::Condition(b) => match &b.kind {
+                BExprKind::Not(b) => (b.as_ref(), true),
+                _ => (b, false),
+            },
+            _ => return *prev,
+        };
+        let BExprKind::Rel(_, RelOp::Lt, r) = &b.kind else {
+            unreachable!()
+        };
+        let AExprKind::Number(n) = r.kind else {
+            unreachable!()
+        };
+        let (lo, hi) = if negated {
+            (lo.max(n as _), hi)
+        } else {
+            (lo, hi.min(n as i64 - 1))
+        };
+        if lo <= hi {
+            Range::Within(lo, hi)
+        } else {
+            Range::Empty
+        }
+    }
+
+    fn direction() -> Direction {
+        Direction::Forward
+    }
+
+    fn initial(&self, _pg: &ProgramGraph) -> Range {
+        Range::Within(0, 0)
+    }
+
+    fn widen(&self, prev: &Range, next: &Range) -> Option<Range> {
+        match (prev, prev.lub(next)) {
+            (Range::Within(_, hi), Range::Within(lo, new)) if new > *hi => {
+                Some(Range::Within(lo, i64::MAX))
+            }
+            (_, r) => Some(r),
+        }
+    }
+
+    fn narrow(&self, prev: &Range, next: &Range) -> Option<Range> {
+        match (self.0, *prev) {
+            (Narrowing::Off, _) => None,
+            (Narrowing::Standard, Range::Within(lo, i64::MAX)) => match next {
+                Range::Within(_, hi) => Some(Range::Within(lo, *hi)),
+                Range::Empty => None,
+            },
+            (Narrowing::Standard, _) => None,
+            (Narrowing::Unending, Range::Within(lo, hi)) => Some(Range::Within(lo, hi - 1)),
+            (Narrowing::Unending, Range::Empty) => None,
+        }
+    }
+}
+
+const COUNTING_LOOP: &str = "i := 0; do i < 10 -> i := i + 1; j := i od";
+
+#[test]
+fn widening_only_happens_at_loop_heads() {
+    let pg = pg(COUNTING_LOOP);
+    let [head] = loop_heads(&pg, Direction::Forward)
+        .into_iter()
+        .collect::<Vec<_>>()[..]
+    else {
+        panic!("expected a single loop head")
+    };
+    let facts = mono_analysis::<_, FiFo>(Counter(Narrowing::Off), &pg).facts;
+    assert_eq!(facts[&head], Range::Within(0, i64::MAX));
+
+    // The rest of the loop is computed from the widened head without being
+    // widened itself
+    let after_increment = pg
+        .edges()
+        .iter()
+        .find(|e| matches!(e.action(), Action::Assignment(_, a) if matches!(a.kind, AExprKind::Binary(..))))
+        .unwrap()
+        .to();
+    assert_ne!(after_increment, head);
+    assert_eq!(facts[&after_increment], Range::Within(1, 10));
+    assert_eq!(facts[&Node::End], Range::Within(10, i64::MAX));
+}
+
+#[test]
+fn narrowing_recovers_the_loop_bound() {
+    let pg = pg(COUNTING_LOOP);
+    let facts = mono_analysis::<_, FiFo>(Counter(Narrowing::Standard), &pg).facts;
+    let [head] = loop_heads(&pg, Direction::Forward)
+        .into_iter()
+        .collect::<Vec<_>>()[..]
+    else {
+        panic!("expected a single loop head")
+    };
+    assert_eq!(facts[&head], Range::Within(0, 10));
+    assert_eq!(facts[&Node::End], Range::Within(10, 10));
+}
+
+#[test]
+fn narrowing_stops_after_a_bounded_number_of_rounds() {
+    let pg = pg(COUNTING_LOOP);
+    let calls = |narrowing| mono_analysis::<_, FiFo>(Counter(narrowing), &pg).semantic_calls;
+    // Without narrowing, a single round finds that nothing changes, while the
+    // unending narrowing runs every round
+    let extra_rounds = MAX_NARROWING_ROUNDS - 1;
+    assert_eq!(
+        calls(Narrowing::Unending) - calls(Narrowing::Off),
+        extra_rounds * pg.edges().len()
+    );
+}
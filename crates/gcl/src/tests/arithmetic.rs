@@ -0,0 +1,104 @@
+use num_bigint::BigInt;
+
+use crate::{
+    ast::{AExpr, BExpr, Int},
+    parse::ParseError,
+    semantics::{Arithmetic, EmptySemanticsContext, SemanticsError, WithArithmetic},
+};
+
+fn cx(arithmetic: Arithmetic) -> WithArithmetic<EmptySemanticsContext> {
+    WithArithmetic::new(arithmetic, EmptySemanticsContext)
+}
+
+fn eval(arithmetic: Arithmetic, src: &str) -> Result<Int, SemanticsError> {
+    src.parse::<AExpr>().unwrap().semantics(&cx(arithmetic))
+}
+
+fn eval_unbounded(src: &str) -> Result<BigInt, SemanticsError> {
+    src.parse::<AExpr>()
+        .unwrap()
+        .unbounded_semantics(&EmptySemanticsContext)
+}
+
+const PLUS: &str = "2147483647 + 1";
+const TIMES: &str = "65536 * 65536";
+const POW: &str = "2 ^ 31";
+const MINUS: &str = "-(-2147483647 - 1)";
+
+#[test]
+fn checked_arithmetic_fails_on_overflow() {
+    for src in [PLUS, TIMES, POW, MINUS] {
+        assert_eq!(
+            eval(Arithmetic::Checked, src),
+            Err(SemanticsError::ArithmeticOverflow),
+            "{src}"
+        );
+    }
+    assert_eq!(eval(Arithmetic::Checked, "2 ^ 30"), Ok(1 << 30));
+}
+
+#[test]
+fn wrapping_arithmetic_wraps_around() {
+    assert_eq!(eval(Arithmetic::Wrapping, PLUS), Ok(Int::MIN));
+    assert_eq!(eval(Arithmetic::Wrapping, TIMES), Ok(0));
+    assert_eq!(eval(Arithmetic::Wrapping, POW), Ok(Int::MIN));
+    assert_eq!(eval(Arithmetic::Wrapping, MINUS), Ok(Int::MIN));
+    assert_eq!(
+        eval(Arithmetic::Wrapping, "3 ^ 21"),
+        Ok(3i32.wrapping_pow(21))
+    );
+}
+
+#[test]
+fn unbounded_arithmetic_only_checks_the_final_value() {
+    for src in [PLUS, TIMES, POW, MINUS] {
+        assert_eq!(
+            eval(Arithmetic::Unbounded, src),
+            Err(SemanticsError::ArithmeticOverflow),
+            "{src}"
+        );
+    }
+    assert_eq!(
+        eval(Arithmetic::Unbounded, "2147483647 + 1 - 1"),
+        Ok(Int::MAX)
+    );
+    assert_eq!(
+        eval(Arithmetic::Unbounded, "65536 * 65536 / 65536"),
+        Ok(65536)
+    );
+    assert_eq!(eval(Arithmetic::Unbounded, "2 ^ 40 / 2 ^ 20"), Ok(1 << 20));
+    assert_eq!(
+        eval(Arithmetic::Unbounded, "-(-2147483647 - 1) - 1"),
+        Ok(Int::MAX)
+    );
+
+    let gt = "2147483647 + 1 > 2147483647".parse::<BExpr>().unwrap();
+    assert_eq!(gt.semantics(&cx(Arithmetic::Unbounded)), Ok(true));
+    assert_eq!(
+        gt.semantics(&cx(Arithmetic::Checked)),
+        Err(SemanticsError::ArithmeticOverflow)
+    );
+}
+
+#[test]
+fn unbounded_semantics_gives_large_results() {
+    assert_eq!(eval_unbounded(PLUS), Ok(BigInt::from(1i64 << 31)));
+    assert_eq!(eval_unbounded(TIMES), Ok(BigInt::from(1i64 << 32)));
+    assert_eq!(eval_unbounded(POW), Ok(BigInt::from(1i64 << 31)));
+    assert_eq!(eval_unbounded(MINUS), Ok(BigInt::from(1i64 << 31)));
+    assert_eq!(eval_unbounded("2 ^ 100"), Ok(BigInt::from(2).pow(100)));
+    assert_eq!(eval_unbounded("(-1) ^ 2147483647"), Ok(BigInt::from(-1)));
+    assert_eq!(
+        eval_unbounded("2 ^ 2147483647"),
+        Err(SemanticsError::ArithmeticOverflow)
+    );
+}
+
+#[test]
+fn literals_too_large_for_an_int_are_rejected() {
+    assert!(matches!(
+        "2147483648".parse::<AExpr>(),
+        Err(ParseError::IntegerTooLarge { .. })
+    ));
+    assert!("2147483647".parse::<AExpr>().is_ok());
+}
@@ -0,0 +1,116 @@
+use crate::{
+    ast::{Commands, Variable},
+    debugger::{Breakpoint, Session, Stop, Value, Watch},
+    interpreter::{InterpreterMemory, TerminationState},
+    pg::{Determinism, Node, ProgramGraph},
+};
+
+fn session(src: &str, x: i32) -> Session {
+    let cmds: Commands = src.parse().unwrap();
+    let pg = ProgramGraph::new(Determinism::Deterministic, &cmds);
+    let memory = InterpreterMemory {
+        variables: [(Variable("x".to_string()), x)].into_iter().collect(),
+        ..InterpreterMemory::default()
+    };
+    Session::new(pg, memory)
+}
+
+fn x(session: &Session) -> i32 {
+    session.memory().variables[&Variable("x".to_string())]
+}
+
+#[test]
+fn stepping_back_returns_to_the_same_state() {
+    let mut s = session("x := x + 1 ; x := x * 2 ; x := x - 3", 1);
+    let start = (s.node(), s.memory().clone());
+
+    assert!(s.step(0));
+    let first = (s.node(), s.memory().clone());
+    assert!(s.step(0));
+    assert_eq!(x(&s), 4);
+
+    assert!(s.step_back());
+    assert_eq!((s.node(), s.memory().clone()), first);
+    assert!(s.step(0));
+    assert_eq!(x(&s), 4);
+
+    s.restart();
+    assert_eq!((s.node(), s.memory().clone()), start);
+    assert!(!s.step_back());
+    assert_eq!(s.reverse(10), Stop::Start);
+}
+
+#[test]
+fn running_stops_at_breakpoints() {
+    let src = "do x < 10 -> x := x + 1 od";
+    let mut s = session(src, 0);
+    s.breakpoints.push(Breakpoint {
+        node: Some(Node::Start),
+        condition: Some("x = 3".parse().unwrap()),
+    });
+    assert_eq!(s.resume(100, |_| 0), Stop::Breakpoint(0));
+    assert_eq!((s.node(), x(&s)), (Node::Start, 3));
+
+    // A breakpoint with only a condition stops when it starts to hold
+    let mut s = session(src, 0);
+    s.breakpoints.push(Breakpoint {
+        node: None,
+        condition: Some("x >= 5".parse().unwrap()),
+    });
+    assert_eq!(s.resume(100, |_| 0), Stop::Breakpoint(0));
+    assert_eq!(x(&s), 5);
+    assert_eq!(
+        s.resume(100, |_| 0),
+        Stop::Ended(TerminationState::Terminated)
+    );
+    assert_eq!(x(&s), 10);
+
+    // Stepping back stops where a condition starts to hold going backwards
+    s.breakpoints[0].condition = Some("x < 3".parse().unwrap());
+    assert_eq!(s.reverse(100), Stop::Breakpoint(0));
+    assert_eq!(x(&s), 2);
+}
+
+#[test]
+fn running_ends_in_termination_or_a_failed_assertion() {
+    let src = "do x < 4 -> x := x + 1 ; assert x != 3 od";
+
+    let mut s = session(src, 3);
+    assert_eq!(
+        s.resume(100, |_| 0),
+        Stop::Ended(TerminationState::Terminated)
+    );
+    assert_eq!(s.node(), Node::End);
+    assert_eq!(x(&s), 4);
+
+    let mut s = session(src, 0);
+    assert_eq!(
+        s.resume(100, |_| 0),
+        Stop::Ended(TerminationState::AssertionFailed)
+    );
+    assert_eq!(s.node(), Node::Error);
+    assert_eq!(x(&s), 3);
+    assert!(!s.step(0));
+
+    let mut s = session(src, 0);
+    assert_eq!(s.resume(2, |_| 0), Stop::StepLimit);
+    assert_eq!(s.run_to(Node::Error, 100, |_| 0), Stop::Reached);
+}
+
+#[test]
+fn watches_are_evaluated_at_the_current_step() {
+    let mut s = session("x := x + 1", 1);
+    s.watches = vec![
+        Watch::parse("x * 2").unwrap(),
+        Watch::parse("x > 1").unwrap(),
+    ];
+    let values = |s: &Session| {
+        s.watch_values()
+            .into_iter()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(values(&s), [Value::Int(2), Value::Bool(false)]);
+    s.step(0);
+    assert_eq!(values(&s), [Value::Int(4), Value::Bool(true)]);
+}
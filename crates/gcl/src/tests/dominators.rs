@@ -0,0 +1,158 @@
+use indexmap::IndexSet;
+
+use crate::{
+    ast::Commands,
+    pg::{Action, Determinism, Edge, Node, ProgramGraph},
+};
+
+pub(super) fn node(name: &str) -> Node {
+    name.parse().unwrap()
+}
+
+fn nodes<const N: usize>(names: [&str; N]) -> IndexSet<Node> {
+    names.into_iter().map(node).collect()
+}
+
+/// A graph from its edges, written as `(from, action, to)`.
+pub(super) fn graph(edges: &[(&str, &str, &str)]) -> ProgramGraph {
+    ProgramGraph::from_edges(
+        edges
+            .iter()
+            .map(|(from, action, to)| Edge(node(from), action.parse().unwrap(), node(to))),
+    )
+}
+
+pub(super) fn diamond() -> ProgramGraph {
+    graph(&[
+        ("qStart", "x > 0", "q1"),
+        ("qStart", "!(x > 0)", "q2"),
+        ("q1", "y := 1", "q3"),
+        ("q2", "y := 2", "q3"),
+        ("q3", "z := y", "qFinal"),
+    ])
+}
+
+pub(super) fn nested_loops() -> ProgramGraph {
+    graph(&[
+        ("qStart", "i := 0", "q1"),
+        ("q1", "i < 3", "q2"),
+        ("q2", "j := 0", "q3"),
+        ("q3", "j < 3", "q4"),
+        ("q4", "j := j + 1", "q3"),
+        ("q3", "!(j < 3)", "q5"),
+        ("q5", "i := i + 1", "q1"),
+        ("q1", "!(i < 3)", "qFinal"),
+    ])
+}
+
+#[test]
+fn diamond_dominators() {
+    let dom = diamond().dominators();
+    for n in ["q1", "q2", "q3"] {
+        assert_eq!(dom.immediate_dominator(node(n)), Some(Node::Start), "{n}");
+    }
+    assert_eq!(dom.immediate_dominator(Node::End), Some(node("q3")));
+    // Neither branch dominates the join
+    assert!(!dom.dominates(node("q1"), node("q3")));
+    assert_eq!(
+        dom.dominators(Node::End).collect::<Vec<_>>(),
+        [Node::End, node("q3"), Node::Start]
+    );
+    assert_eq!(dom.roots().collect::<Vec<_>>(), [Node::Start]);
+
+    // The dominance of each branch ends at the join
+    let frontiers = dom.frontiers();
+    assert_eq!(frontiers[&node("q1")], nodes(["q3"]));
+    assert_eq!(frontiers[&node("q2")], nodes(["q3"]));
+    assert!(frontiers[&node("q3")].is_empty());
+}
+
+#[test]
+fn nested_loop_dominators() {
+    let dom = nested_loops().dominators();
+    let idom = |n| dom.immediate_dominator(node(n));
+    assert_eq!(idom("q1"), Some(Node::Start));
+    assert_eq!(idom("q2"), Some(node("q1")));
+    assert_eq!(idom("q3"), Some(node("q2")));
+    assert_eq!(idom("q4"), Some(node("q3")));
+    assert_eq!(idom("q5"), Some(node("q3")));
+    assert_eq!(idom("qFinal"), Some(node("q1")));
+    assert_eq!(
+        dom.children(node("q3")).collect::<IndexSet<_>>(),
+        nodes(["q4", "q5"])
+    );
+
+    // The back edges make each head part of its own frontier
+    let frontiers = dom.frontiers();
+    assert_eq!(frontiers[&node("q1")], nodes(["q1"]));
+    assert_eq!(frontiers[&node("q3")], nodes(["q1", "q3"]));
+    assert_eq!(frontiers[&node("q4")], nodes(["q3"]));
+}
+
+#[test]
+fn post_dominators_with_an_error_node() {
+    let pg = graph(&[
+        ("qStart", "x := 1", "q1"),
+        ("q1", "x > 0", "q2"),
+        ("q1", "!(x > 0)", "qError"),
+        ("q2", "y := x", "qFinal"),
+    ]);
+    let pdom = pg.post_dominators();
+    assert_eq!(pdom.roots().collect::<Vec<_>>(), [Node::End, Node::Error]);
+    assert_eq!(pdom.immediate_dominator(node("q2")), Some(Node::End));
+    assert_eq!(pdom.immediate_dominator(Node::Start), Some(node("q1")));
+    // From `q1` both final nodes can be reached, so it is post-dominated by
+    // neither, without being a root itself
+    assert_eq!(pdom.immediate_dominator(node("q1")), None);
+    assert!(!pdom.dominates(Node::End, node("q1")));
+    assert!(!pdom.dominates(Node::Error, node("q1")));
+    assert!(pdom.strictly_dominates(node("q1"), Node::Start));
+}
+
+#[test]
+fn natural_loops_of_nested_loops() {
+    let pg = nested_loops();
+    let [outer, inner] = pg.natural_loops().try_into().unwrap();
+    assert_eq!(outer.header, node("q1"));
+    assert_eq!(
+        outer.nodes.into_iter().collect::<IndexSet<_>>(),
+        nodes(["q1", "q2", "q3", "q4", "q5"])
+    );
+    assert_eq!(inner.header, node("q3"));
+    assert_eq!(inner.nodes, nodes(["q3", "q4"]));
+    assert_eq!(
+        inner
+            .back_edges
+            .iter()
+            .map(|e| e.from())
+            .collect::<Vec<_>>(),
+        [node("q4")]
+    );
+}
+
+#[test]
+fn natural_loop_of_a_do_loop() {
+    let cmds: Commands = "x := 0; do x < 10 -> x := x + 1; y := x od; z := y"
+        .parse()
+        .unwrap();
+    let pg = ProgramGraph::new(Determinism::NonDeterministic, &cmds);
+    let [l] = pg.natural_loops().try_into().unwrap();
+
+    // The header is where the guard is evaluated
+    assert!(
+        pg.outgoing(l.header)
+            .iter()
+            .any(|e| e.action() == &"x < 10".parse::<Action>().unwrap())
+    );
+    // The body is the header and the two assignments, but not the exit
+    assert_eq!(l.nodes.len(), 3);
+    let [back] = l.back_edges.try_into().unwrap();
+    assert_eq!(back.action(), &"y := x".parse::<Action>().unwrap());
+    assert!(l.nodes.contains(&back.from()));
+    let exit = pg
+        .edges()
+        .iter()
+        .find(|e| e.action() == &"z := y".parse::<Action>().unwrap())
+        .unwrap();
+    assert!(!l.nodes.contains(&exit.from()));
+}
@@ -0,0 +1,116 @@
+use crate::pg::dot::{Attributes, DotEdge, DotError, DotGraph};
+
+fn parse(src: &str) -> DotGraph {
+    DotGraph::parse(src).unwrap()
+}
+
+fn attributes<'a>(pairs: impl IntoIterator<Item = (&'a str, &'a str)>) -> Attributes {
+    pairs
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[test]
+fn quoted_ids_are_unescaped() {
+    let graph = parse(
+        r#"digraph "my graph" {
+            a [label="x := \"1\"\l"];
+            b [label="first \
+second"];
+            c [label="con" + "cat" + "enated"];
+            "multi
+line";
+            d [label=<<b>bold</b> &amp; more>];
+        }"#,
+    );
+    assert_eq!(graph.name.as_deref(), Some("my graph"));
+    let label = |node: &str| graph.nodes[node].get("label").map(String::as_str);
+    // Only quotes are escaped, other escapes are kept for Graphviz
+    assert_eq!(label("a"), Some(r#"x := "1"\l"#));
+    assert_eq!(label("b"), Some("first second"));
+    assert_eq!(label("c"), Some("concatenated"));
+    assert_eq!(label("d"), Some("<b>bold</b> &amp; more"));
+    assert!(graph.nodes.contains_key("multi\nline"));
+}
+
+#[test]
+fn comments_are_skipped() {
+    let graph = parse(
+        "// a line comment\n\
+         # a line of the preprocessor\n\
+         digraph { /* a block\n comment */ a -> b // trailing\n\
+         /* a [label=\"hidden\"] */ }",
+    );
+    assert_eq!(graph.nodes.keys().collect::<Vec<_>>(), ["a", "b"]);
+    assert!(graph.nodes.values().all(|a| a.is_empty()));
+
+    // Lines are still counted inside comments
+    let err = DotGraph::parse("digraph {\n/*\n\n*/\n a -> ;\n}").unwrap_err();
+    assert_eq!(err.line, 5);
+}
+
+#[test]
+fn edge_attributes_apply_to_every_edge_of_a_chain() {
+    let graph = parse(
+        r#"digraph {
+            edge [color=red];
+            a -> b -> c [label="x := 1", weight=2];
+            subgraph s { edge [style=dashed]; d -> e }
+            { f g } -> h;
+            i:port:n -> j;
+            k -> l;
+        }"#,
+    );
+    let chain = attributes([("color", "red"), ("label", "x := 1"), ("weight", "2")]);
+    let edge = |from: &str, to: &str, attributes| DotEdge {
+        from: from.to_string(),
+        to: to.to_string(),
+        attributes,
+    };
+    assert_eq!(
+        graph.edges,
+        [
+            edge("a", "b", chain.clone()),
+            edge("b", "c", chain),
+            edge(
+                "d",
+                "e",
+                attributes([("color", "red"), ("style", "dashed")])
+            ),
+            edge("f", "h", attributes([("color", "red")])),
+            edge("g", "h", attributes([("color", "red")])),
+            edge("i", "j", attributes([("color", "red")])),
+            // Defaults set in a subgraph do not leak out of it
+            edge("k", "l", attributes([("color", "red")])),
+        ]
+    );
+}
+
+#[test]
+fn malformed_input_is_an_error() {
+    let cases = [
+        ("", 1),
+        ("digraph {", 1),
+        ("digraph {\n a -> }", 2),
+        ("digraph { a [label=\"x] }", 1),
+        ("digraph { a [label=<x] }", 1),
+        ("digraph {\n /* x", 2),
+        ("digraph { a = }", 1),
+        ("digraph { a -> b ] }", 1),
+        ("digraph { a -> b } }", 1),
+        ("digraph { a -> - }", 1),
+    ];
+    for (src, line) in cases {
+        let err: DotError = DotGraph::parse(src).unwrap_err();
+        assert_eq!(err.line, line, "{src:?}: {err}");
+    }
+}
+
+#[test]
+fn truncated_input_does_not_panic() {
+    let src = r#"digraph G { node [shape=circle]; a [label="x := \"1\""]; a -> b -> c [label=<<i>y</i>>]; /* c */ { d e } -> f }"#;
+    for (i, _) in src.char_indices() {
+        let _ = DotGraph::parse(&src[..i]);
+    }
+}
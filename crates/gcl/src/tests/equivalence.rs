@@ -0,0 +1,104 @@
+use indexmap::IndexSet;
+
+use crate::{
+    ast::{Commands, Target, Variable},
+    equivalence::{Equivalence, EquivalenceChecker},
+    interpreter::TerminationState,
+    pg::{Determinism, ProgramGraph},
+};
+
+fn pg(src: &str) -> ProgramGraph {
+    let cmds: Commands = src.parse().unwrap();
+    ProgramGraph::new(Determinism::Deterministic, &cmds)
+}
+
+fn check(src1: &str, src2: &str, observed: &[&str]) -> Equivalence {
+    let observed: IndexSet<Target> = observed
+        .iter()
+        .map(|x| Target::Variable(Variable(x.to_string())))
+        .collect();
+    EquivalenceChecker::default().check(&pg(src1), &pg(src2), &observed)
+}
+
+#[test]
+fn equivalent_programs() {
+    let result = check("y := x + x", "y := 2 * x", &["y"]);
+    assert!(matches!(result, Equivalence::Equivalent), "{result:?}");
+
+    let result = check(
+        "if x >= 0 -> y := 1 [] x < 0 -> y := 0 fi",
+        "y := 0 ; if x >= 0 -> y := 1 [] x < 0 -> skip fi",
+        &["y"],
+    );
+    assert!(matches!(result, Equivalence::Equivalent), "{result:?}");
+}
+
+#[test]
+fn unobserved_variables_may_differ() {
+    let result = check("y := x ; z := 1", "y := x ; z := 2", &["y"]);
+    assert!(matches!(result, Equivalence::Equivalent), "{result:?}");
+}
+
+#[test]
+fn counterexamples_tell_the_programs_apart() {
+    let result = check(
+        "if x > 5 -> y := 1 [] x <= 5 -> y := 0 fi",
+        "y := 0",
+        &["y"],
+    );
+    let Equivalence::Counterexample(cx) = result else {
+        panic!("expected a counterexample, got {result:?}")
+    };
+    let x = cx.input.variables[&Variable("x".to_string())];
+    assert!(x > 5);
+    assert_eq!(cx.left.state, TerminationState::Terminated);
+    assert_eq!(cx.right.state, TerminationState::Terminated);
+    assert_eq!(cx.left.memory.variables[&Variable("y".to_string())], 1);
+    assert_eq!(cx.right.memory.variables[&Variable("y".to_string())], 0);
+}
+
+#[test]
+fn the_solver_finds_inputs_testing_would_miss() {
+    if !super::z3_available() {
+        return;
+    }
+    let result = check(
+        "if x = 1000 -> y := 1 [] x != 1000 -> y := 0 fi",
+        "y := 0",
+        &["y"],
+    );
+    let Equivalence::Counterexample(cx) = result else {
+        panic!("expected a counterexample, got {result:?}")
+    };
+    assert_eq!(cx.input.variables[&Variable("x".to_string())], 1000);
+}
+
+#[test]
+fn programs_which_do_not_terminate_are_unknown() {
+    let result = check("do x > 0 -> skip od", "skip", &["x"]);
+    let Equivalence::Unknown { reason } = result else {
+        panic!("expected an unknown result, got {result:?}")
+    };
+    assert!(reason.contains("the first program did not terminate"));
+
+    let result = check("do true -> skip od", "do true -> x := x + 1 od", &["x"]);
+    let Equivalence::Unknown { reason } = result else {
+        panic!("expected an unknown result, got {result:?}")
+    };
+    assert!(reason.contains("neither program terminated"));
+    if !super::z3_available() {
+        assert!(reason.contains("SMT solver"), "{reason}");
+    }
+}
+
+#[test]
+fn symbolic_errors_are_not_hidden_by_testing() {
+    if !super::z3_available() {
+        return;
+    }
+    let result = check("y := 2 ^ x", "y := 2 ^ x", &["y"]);
+    let Equivalence::Unknown { reason } = result else {
+        panic!("expected an unknown result, got {result:?}")
+    };
+    assert!(reason.contains("exponent"), "{reason}");
+}
@@ -0,0 +1,98 @@
+use crate::{
+    ast::Commands,
+    fmt::{FormatOptions, Indent, format},
+};
+
+const COMMENTED: &str = "// leading
+x := 1; // trailing
+// between
+y := 2;
+if x > 0 -> skip // in guard
+[] x <= 0 -> skip fi // after fi";
+
+const LONG_GUARD: &str = "if veryLongVariableName > anotherVeryLongVariableName && yetAnotherLongName < 100 || somethingElse = 7 -> skip fi";
+
+const PROGRAMS: &[&str] = &[
+    COMMENTED,
+    LONG_GUARD,
+    "x:=(1+2)*3; y := 1 + (2 * 3)",
+    "do x>0->x:=x-1 [] x<0 -> x := -x od",
+    "/* block */ A[i] := A[i + 1] /* after */; skip",
+];
+
+fn fmt(src: &str, width: usize) -> String {
+    let options = FormatOptions {
+        width,
+        ..Default::default()
+    };
+    format(src, &options).unwrap()
+}
+
+#[test]
+fn formatting_is_idempotent() {
+    for src in PROGRAMS {
+        for width in [20, 80] {
+            let once = fmt(src, width);
+            assert_eq!(fmt(&once, width), once, "{src:?} at width {width}");
+        }
+    }
+}
+
+#[test]
+fn formatting_preserves_the_program() {
+    for src in PROGRAMS {
+        let formatted = fmt(src, 20);
+        assert_eq!(
+            formatted.parse::<Commands>().unwrap(),
+            src.parse::<Commands>().unwrap(),
+            "{formatted}"
+        );
+    }
+}
+
+#[test]
+fn line_and_trailing_comments_are_kept() {
+    assert_eq!(
+        fmt(COMMENTED, 80),
+        "// leading
+x := 1 ; // trailing
+// between
+y := 2 ;
+if x > 0 ->
+   skip // in guard
+[] x <= 0 ->
+   skip
+fi // after fi
+"
+    );
+}
+
+#[test]
+fn long_guards_are_broken_at_operators() {
+    let formatted = fmt(LONG_GUARD, 80);
+    assert_eq!(
+        formatted,
+        "if veryLongVariableName > anotherVeryLongVariableName
+   && yetAnotherLongName < 100
+   || somethingElse = 7 ->
+   skip
+fi
+"
+    );
+    assert!(formatted.lines().all(|line| line.len() <= 80));
+
+    let short = "if x > 0 && y < 100 -> skip fi";
+    assert_eq!(fmt(short, 80), "if x > 0 && y < 100 ->\n   skip\nfi\n");
+}
+
+#[test]
+fn indentation_is_configurable() {
+    let options = FormatOptions {
+        indent: Indent::Tabs,
+        ..Default::default()
+    };
+    assert_eq!(
+        format("do x > 0 -> x := x - 1 od", &options).unwrap(),
+        "do x > 0 ->\n\tx := x - 1\nod\n"
+    );
+}
@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    ast::{AExpr, AOp, Array, CommandKind, Commands, Function, Target, Variable},
+    interpreter::{Execution, InterpreterMemory, TerminationState},
+    pg::{Determinism, ProgramGraph},
+    semantics::SemanticsError,
+};
+
+fn arr(name: &str) -> Array {
+    Array(name.to_string())
+}
+
+fn var(name: &str) -> AExpr {
+    AExpr::reference(Target::Variable(Variable(name.to_string())))
+}
+
+#[test]
+fn functions_parse() {
+    let cases = [
+        ("len(A)", AExpr::function(Function::Length(arr("A")))),
+        (
+            "count(A, x + 1)",
+            AExpr::function(Function::Count(
+                arr("A"),
+                Box::new(AExpr::binary(var("x"), AOp::Plus, AExpr::number(1))),
+            )),
+        ),
+        (
+            "min(x, 2)",
+            AExpr::function(Function::Min(
+                Box::new(var("x")),
+                Box::new(AExpr::number(2)),
+            )),
+        ),
+        (
+            "max(1, len(A))",
+            AExpr::function(Function::Max(
+                Box::new(AExpr::number(1)),
+                Box::new(AExpr::function(Function::Length(arr("A")))),
+            )),
+        ),
+    ];
+    for (src, expected) in cases {
+        let parsed = src.parse::<AExpr>().unwrap();
+        assert_eq!(parsed, expected, "{src}");
+        assert_eq!(
+            parsed.to_string().parse::<AExpr>().unwrap(),
+            parsed,
+            "{src}"
+        );
+    }
+
+    for src in [
+        "len(x + 1)",
+        "len(A, 1)",
+        "count(A)",
+        "min(1)",
+        "max(1, 2, 3)",
+    ] {
+        assert!(src.parse::<AExpr>().is_err(), "{src}");
+    }
+}
+
+#[test]
+fn array_literals_parse() {
+    let commands = "A := [1, x, 2 * 3]; B := []".parse::<Commands>().unwrap();
+    let kinds = commands.0.iter().map(|c| &c.kind).collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            &CommandKind::ArrayAssignment(
+                arr("A"),
+                vec![
+                    AExpr::number(1),
+                    var("x"),
+                    AExpr::binary(AExpr::number(2), AOp::Times, AExpr::number(3)),
+                ],
+            ),
+            &CommandKind::ArrayAssignment(arr("B"), vec![]),
+        ]
+    );
+}
+
+#[test]
+fn functions_and_array_literals_evaluate() {
+    let src = "n := len(A); c := count(A, 2); lo := min(n, c); hi := max(n, c); A := [n, c, \
+               lo + hi]; B := []";
+    let commands = src.parse::<Commands>().unwrap();
+    commands.check(None).unwrap();
+    let pg = ProgramGraph::new(Determinism::Deterministic, &commands);
+
+    let memory = InterpreterMemory {
+        variables: ["n", "c", "lo", "hi"]
+            .into_iter()
+            .map(|x| (Variable(x.to_string()), 0))
+            .collect(),
+        arrays: BTreeMap::from([(arr("A"), vec![2, 1, 2, 7]), (arr("B"), vec![5])]),
+        ..Default::default()
+    };
+    let exe = Execution::new(memory).run(&pg, 100, |_| 0);
+    assert_eq!(exe.state(&pg), TerminationState::Terminated);
+
+    let mem = exe.current_mem();
+    let value = |x: &str| mem.variables[&Variable(x.to_string())];
+    assert_eq!(
+        (value("n"), value("c"), value("lo"), value("hi")),
+        (4, 2, 2, 4)
+    );
+    assert_eq!(mem.arrays[&arr("A")], [4, 2, 6]);
+    assert_eq!(mem.arrays[&arr("B")], Vec::<i32>::new());
+}
+
+#[test]
+fn functions_of_missing_arrays_fail() {
+    let memory = InterpreterMemory::default();
+    for src in ["len(A)", "count(A, 1)"] {
+        assert_eq!(
+            src.parse::<AExpr>().unwrap().semantics(&memory),
+            Err(SemanticsError::ArrayNotFound {
+                name: "A".to_string()
+            }),
+            "{src}"
+        );
+    }
+}
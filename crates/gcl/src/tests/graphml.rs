@@ -0,0 +1,106 @@
+use crate::pg::graphml::{GraphMl, GraphMlEdge};
+
+fn document(graph: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- exported by hand -->
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns" xmlns:y="http://www.yworks.com/xml/graphml">
+  <key id="d0" for="edge" attr.name="label" attr.type="string"/>
+  <key id="d1" for="node"/>
+  <graph id="G" edgedefault="directed">{graph}</graph>
+</graphml>"#
+    )
+}
+
+fn label(graph: &GraphMl, i: usize) -> &str {
+    &graph.edges[i].data["label"]
+}
+
+#[test]
+fn entities_are_unescaped() {
+    let graph = GraphMl::parse(&document(
+        r#"
+        <node id="q&lt;0&gt;"><data key="d1">&quot;start&quot; &amp; &apos;go&apos;</data></node>
+        <edge source="q&lt;0&gt;" target="q1"><data key="d0">x := &#65;&#x42; + 1</data></edge>
+        <edge source="q1" target="q2"><data key="d0">&unknown; &#xFFFFFFFF; &amp</data></edge>
+        "#,
+    ))
+    .unwrap();
+    // Data of keys without a name is named by the id of the key
+    assert_eq!(graph.nodes["q<0>"]["d1"], r#""start" & 'go'"#);
+    assert_eq!(label(&graph, 0), "x := AB + 1");
+    // References which are not understood are kept as they are
+    assert_eq!(label(&graph, 1), "&unknown; &#xFFFFFFFF; &amp");
+}
+
+#[test]
+fn cdata_and_comments_in_data() {
+    let graph = GraphMl::parse(&document(
+        r#"
+        <edge source="q0" target="q1"><data key="d0"><![CDATA[x < 1 && y > 2]]></data></edge>
+        <edge source="q1" target="q2"><data key="d0"><![CDATA[&amp;]]></data></edge>
+        <edge source="q2" target="q3"><data key="d0">x := <!-- one --> 1<?pi ignored?></data></edge>
+        <edge source='q3' target='q4'><y:data key="d0">skip</y:data></edge>
+        "#,
+    ))
+    .unwrap();
+    assert_eq!(label(&graph, 0), "x < 1 && y > 2");
+    // The contents of CDATA sections are not unescaped
+    assert_eq!(label(&graph, 1), "&amp;");
+    assert_eq!(label(&graph, 2), "x :=  1");
+    // Namespace prefixes are dropped
+    assert_eq!(label(&graph, 3), "skip");
+    assert_eq!(
+        graph.nodes.keys().collect::<Vec<_>>(),
+        ["q0", "q1", "q2", "q3", "q4"]
+    );
+}
+
+#[test]
+fn written_graphs_are_read_back() {
+    let mut graph = GraphMl {
+        directed: true,
+        ..Default::default()
+    };
+    graph.nodes.insert("q<0>".to_string(), Default::default());
+    graph.nodes.insert("q\"1\"".to_string(), Default::default());
+    graph.edges.push(GraphMlEdge {
+        source: "q<0>".to_string(),
+        target: "q\"1\"".to_string(),
+        data: [("label".to_string(), "x < 1 && y > 2".to_string())]
+            .into_iter()
+            .collect(),
+    });
+    assert_eq!(GraphMl::parse(&graph.to_string()).unwrap(), graph);
+}
+
+#[test]
+fn malformed_input_is_an_error() {
+    let cases = [
+        ("", 1),
+        ("<graphml>", 1),
+        ("<graph/>", 1),
+        ("<graphml></graphml>", 1),
+        ("<graphml>\n<graph></graphml>", 2),
+        ("<graphml a=b></graphml>", 1),
+        ("<graphml><graph/></graphml> trailing", 1),
+        ("<graphml><graph>\n<node/></graph></graphml>", 2),
+        ("<graphml><graph><edge source=\"a\"/></graph></graphml>", 1),
+        ("<graphml><graph>\n\n<![CDATA[ </graph></graphml>", 3),
+        ("<graphml><!-- </graphml>", 1),
+    ];
+    for (src, line) in cases {
+        let err = GraphMl::parse(src).unwrap_err();
+        assert_eq!(err.line, line, "{src:?}: {err}");
+    }
+}
+
+#[test]
+fn truncated_input_does_not_panic() {
+    let src = document(
+        r#"<node id="q&lt;0&gt;"/><edge source="q&lt;0&gt;" target="q1"><data key="d0"><![CDATA[x < 1]]> &#65;</data></edge>"#,
+    );
+    for (i, _) in src.char_indices() {
+        let _ = GraphMl::parse(&src[..i]);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{
+    parse::{ParseError, parse_commands},
+    typeck::CheckError,
+};
+
+/// The errors of parsing `src`, as the text they point at along with their
+/// hint.
+fn errors(src: &str) -> Vec<(&str, Option<String>)> {
+    parse_commands(src)
+        .expect_err("the program should not parse")
+        .errors
+        .iter()
+        .map(|e| {
+            let span = e.span();
+            (
+                &src[span.offset()..span.end()],
+                e.hint().map(str::to_string),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn all_syntax_errors_are_reported() {
+    let src = "x = 1; y := 2; z = 3";
+    let found = errors(src);
+    assert_eq!(found.len(), 2);
+    assert!(found.iter().all(|(token, _)| *token == "="));
+
+    let err = parse_commands(src).unwrap_err();
+    assert!(err.to_string().starts_with("found 2 syntax errors"));
+    assert!(err.to_string().contains("\n  1:3: Unrecognized Token"));
+    assert!(err.to_string().contains("\n  1:18: Unrecognized Token"));
+}
+
+#[test]
+fn recovery_resumes_at_the_next_command() {
+    let tokens = |src| errors(src).into_iter().map(|(t, _)| t).collect::<Vec<_>>();
+    // After a `;`
+    assert_eq!(tokens("x := 1 +; y := * 2; z := 3"), [";", "*"]);
+    // After a `fi`
+    assert_eq!(tokens("if x > 0 -> x := * fi; y := / 2"), ["*", "/"]);
+    // After an `od`, where the rest of the body is still parsed
+    assert_eq!(
+        tokens("do x > 0 -> x := x - ; y := * od; z := ) 3"),
+        [";", "*", ")"]
+    );
+    // In the condition of a guard, skipping to the `->`
+    assert_eq!(tokens("if x > * -> y := * fi"), ["*", "*"]);
+}
+
+#[test]
+fn missing_fi_or_od_at_end_of_input() {
+    let [err] = parse_commands("if x > 0 -> x := 1")
+        .unwrap_err()
+        .errors
+        .try_into()
+        .unwrap();
+    assert!(matches!(err, ParseError::UnrecognizedEof { .. }));
+    assert_eq!(err.hint(), Some("Are you missing a `fi` to close an `if`?"));
+
+    let [err] = parse_commands("x := 0; do x < 3 -> x := x + 1")
+        .unwrap_err()
+        .errors
+        .try_into()
+        .unwrap();
+    assert!(matches!(err, ParseError::UnrecognizedEof { .. }));
+    assert_eq!(err.hint(), Some("Are you missing an `od` to close a `do`?"));
+}
+
+#[test]
+fn hints_for_common_mistakes() {
+    let hint = |src| {
+        let [(_, hint)] = errors(src).try_into().unwrap();
+        hint.expect("a hint should be given")
+    };
+    assert!(hint("x = 1").starts_with("Did you mean `:=`?"));
+    assert_eq!(
+        hint("if x == 1 -> skip fi"),
+        "Equality is written with a single `=`"
+    );
+    assert!(hint("if x > 0 -> skip od").starts_with("Did you mean `fi`?"));
+    assert!(hint("do x > 0 -> skip fi").starts_with("Did you mean `od`?"));
+    assert_eq!(
+        hint("x := 1 y := 2"),
+        "Did you forget a `;` between two commands?"
+    );
+}
+
+#[test]
+fn parsing_does_not_check_the_program() {
+    let cmds = parse_commands("x := 1; x[0] := 2; p(x)").unwrap();
+    let errors = cmds.check(None).unwrap_err().errors;
+    assert!(matches!(errors[0], CheckError::Procedure(_)), "{errors:?}");
+    assert!(
+        errors
+            .iter()
+            .any(|e| matches!(e, CheckError::VariableAndArray { .. })),
+        "{errors:?}"
+    );
+}
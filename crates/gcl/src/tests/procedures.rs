@@ -0,0 +1,72 @@
+use itertools::Itertools;
+
+use crate::{
+    ast::{Commands, Target, Variable},
+    procedures::MAX_CALL_DEPTH,
+};
+
+fn inline(src: &str) -> Vec<String> {
+    let cmds: Commands = src.parse().unwrap();
+    let inlined = cmds.inline_procedures().to_string();
+    inlined.lines().map(|l| l.trim().to_string()).collect()
+}
+
+#[test]
+fn parameters_are_local_to_the_activation() {
+    let src = "proc inc(in a, out b) { b := a + 1 }; a := 2; inc(x, y)";
+    assert_eq!(
+        inline(src),
+        [
+            "a := 2 ;",
+            "a@inc/1 := x ;",
+            "b@inc/1 := (a@inc/1 + 1) ;",
+            "y := b@inc/1",
+        ]
+    );
+
+    // The locals are not variables of the program, unlike the global `a`
+    let cmds: Commands = src.parse().unwrap();
+    assert_eq!(
+        cmds.fv().into_iter().sorted().collect_vec(),
+        ["a", "x", "y"].map(|v| Target::Variable(Variable(v.to_string())))
+    );
+}
+
+#[test]
+fn nested_calls_are_inlined() {
+    let src = "
+        proc inc(in a, out b) { b := a + 1 };
+        proc twice(in a, out b) { inc(a, b); inc(b, b) };
+        twice(x, y)
+    ";
+    // Both calls of `inc` are the first activation of it, as the first has
+    // returned by the time of the second
+    assert_eq!(
+        inline(src),
+        [
+            "a@twice/1 := x ;",
+            "a@inc/1 := a@twice/1 ;",
+            "b@inc/1 := (a@inc/1 + 1) ;",
+            "b@twice/1 := b@inc/1 ;",
+            "a@inc/1 := b@twice/1 ;",
+            "b@inc/1 := (a@inc/1 + 1) ;",
+            "b@twice/1 := b@inc/1 ;",
+            "y := b@twice/1",
+        ]
+    );
+}
+
+#[test]
+fn recursive_calls_are_numbered_by_depth() {
+    let lines = inline("proc down(in n) { if n > 0 -> down(n - 1) [] n <= 0 -> skip fi }; down(2)");
+    assert_eq!(lines[0], "n@down/1 := 2 ;");
+    assert_eq!(lines[2], "n@down/2 := (n@down/1 - 1) ;");
+
+    // Each activation gets its own local, until the call beyond the limit
+    // aborts
+    let last = format!("n@down/{MAX_CALL_DEPTH}");
+    assert!(lines.iter().any(|l| l.starts_with(&format!("{last} :="))));
+    let beyond = format!("n@down/{}", MAX_CALL_DEPTH + 1);
+    assert!(!lines.iter().any(|l| l.contains(&beyond)));
+    assert_eq!(lines.iter().filter(|l| *l == "abort").count(), 1);
+}
@@ -0,0 +1,97 @@
+use crate::{
+    ast::{Commands, Variable},
+    interpreter::InterpreterMemory,
+    pg::{Determinism, Edge, Node, ProgramGraph},
+    reachability::{OutOfFuel, StateSpace},
+};
+
+fn pg(src: &str) -> ProgramGraph {
+    let cmds: Commands = src.parse().unwrap();
+    ProgramGraph::new(Determinism::NonDeterministic, &cmds)
+}
+
+fn memory(x: i32) -> InterpreterMemory {
+    InterpreterMemory {
+        variables: [("x", x), ("y", 0)]
+            .into_iter()
+            .map(|(name, v)| (Variable(name.to_string()), v))
+            .collect(),
+        ..InterpreterMemory::default()
+    }
+}
+
+/// The node which the edge doing `action` leads to.
+fn target(pg: &ProgramGraph, action: &str) -> Node {
+    pg.edges()
+        .iter()
+        .find(|Edge(_, a, _)| a.to_string() == action)
+        .map(|Edge(_, _, to)| *to)
+        .unwrap()
+}
+
+fn reaches(space: &StateSpace, node: Node) -> bool {
+    space.configurations().iter().any(|c| c.node == node)
+}
+
+const SRC: &str = "if x > 0 -> x := x - 1 ; y := 1
+                   [] x > 0 & x < 0 -> y := 2
+                   [] true -> y := 3
+                   fi";
+
+#[test]
+fn reachable_nodes_are_explored() {
+    let pg = pg(SRC);
+    let mut space = StateSpace::new(&pg, memory(1));
+    space.explore(100).unwrap();
+    assert!(space.is_complete());
+
+    assert!(reaches(&space, target(&pg, "(x > 0)")));
+    assert!(reaches(&space, target(&pg, "x := (x - 1)")));
+    assert!(reaches(&space, target(&pg, "true")));
+    assert!(reaches(&space, Node::End));
+    assert_eq!(space.terminal().count(), 2);
+    assert_eq!(space.stuck().count(), 0);
+}
+
+#[test]
+fn nodes_behind_infeasible_guards_are_unreachable() {
+    let pg = pg(SRC);
+    for x in [-1, 0, 1] {
+        let mut space = StateSpace::new(&pg, memory(x));
+        space.explore(100).unwrap();
+        assert!(!reaches(&space, target(&pg, "((x > 0) & (x < 0))")));
+        assert_eq!(reaches(&space, target(&pg, "(x > 0)")), x > 0);
+    }
+}
+
+#[test]
+fn paths_witness_how_a_node_is_reached() {
+    let pg = pg(SRC);
+    let mut space = StateSpace::new(&pg, memory(5));
+    space.explore(100).unwrap();
+
+    let node = target(&pg, "x := (x - 1)");
+    let id = space
+        .configurations()
+        .iter()
+        .position(|c| c.node == node)
+        .unwrap();
+    let path = space.path_to(id);
+    assert_eq!(path.first(), Some(&StateSpace::INITIAL));
+    assert_eq!(path.last(), Some(&id));
+    assert_eq!(space.configuration(path[0]).memory, memory(5));
+    for step in path.windows(2) {
+        assert!(space.successors(step[0]).iter().any(|t| t.to == step[1]));
+    }
+    assert_eq!(path.len(), 3);
+    assert_eq!(space.configuration(id).memory, memory(4));
+}
+
+#[test]
+fn exploration_runs_out_of_fuel() {
+    let pg = pg("do true -> x := x + 1 od");
+    let mut space = StateSpace::new(&pg, memory(0));
+    assert_eq!(space.explore(10), Err(OutOfFuel));
+    assert!(!space.is_complete());
+    assert!(space.cycle().is_none());
+}
@@ -0,0 +1,128 @@
+use std::{
+    collections::BTreeMap,
+    hash::{BuildHasher, RandomState},
+};
+
+use crate::{
+    ast::{AExprKind, Array, CommandKind, Commands, Variable},
+    interpreter::{Execution, InterpreterMemory},
+    parse::SourceSpan,
+    pg::{Determinism, ProgramGraph},
+    semantics::SemanticsError,
+};
+
+fn parse(src: &str) -> Commands {
+    src.parse().unwrap()
+}
+
+#[test]
+fn layout_does_not_affect_equality() {
+    let compact = parse("if x > 0 -> y := 1 [] x <= 0 -> skip fi");
+    let spread = parse("if x > 0 ->\n    y := 1\n[] x <= 0 ->\n    skip\nfi");
+    assert_ne!(compact.0[0].span, spread.0[0].span);
+    assert_eq!(compact, spread);
+    assert_eq!(compact.cmp(&spread), std::cmp::Ordering::Equal);
+
+    let state = RandomState::new();
+    assert_eq!(state.hash_one(&compact), state.hash_one(&spread));
+}
+
+#[test]
+fn semantics_errors_point_at_the_command() {
+    let src = "x := 1;\nA[x + 4] := 2";
+    let pg = ProgramGraph::new(Determinism::Deterministic, &parse(src));
+    let memory = InterpreterMemory {
+        variables: BTreeMap::from([(Variable("x".to_string()), 0)]),
+        arrays: BTreeMap::from([(Array("A".to_string()), vec![0, 0])]),
+        ..Default::default()
+    };
+    let exe = Execution::new(memory).run(&pg, 10, |_| 0);
+    let [err] = exe.errors(&pg).try_into().unwrap();
+    assert_eq!(
+        err.error,
+        SemanticsError::IndexOutOfBound {
+            name: "A".to_string(),
+            index: 5
+        }
+    );
+    let span = err.span.unwrap();
+    assert_eq!(&src[span.offset()..span.end()], "A[x + 4] := 2");
+    assert_eq!(span.line_col(src), (2, 1));
+}
+
+fn text(src: &str, span: SourceSpan) -> &str {
+    &src[span.offset()..span.end()]
+}
+
+#[test]
+fn expressions_have_spans() {
+    let src = "x := 1 + y * (2 - z)";
+    let cmds = parse(src);
+    let CommandKind::Assignment(_, a) = &cmds.0[0].kind else {
+        panic!("expected an assignment")
+    };
+    assert_eq!(text(src, a.span), "1 + y * (2 - z)");
+    let AExprKind::Binary(l, _, r) = &a.kind else {
+        panic!("expected a binary operation")
+    };
+    assert_eq!(text(src, l.span), "1");
+    assert_eq!(text(src, r.span), "y * (2 - z)");
+    let AExprKind::Binary(_, _, r) = &r.kind else {
+        panic!("expected a binary operation")
+    };
+    assert_eq!(text(src, r.span), "2 - z");
+
+    // Expressions are compared without their spans as well
+    assert_eq!(cmds, parse("x := 1 + y * (2 - z)"));
+}
+
+fn first_error(src: &str, memory: InterpreterMemory) -> (SemanticsError, SourceSpan) {
+    let pg = ProgramGraph::new(Determinism::Deterministic, &parse(src));
+    let exe = Execution::new(memory).run(&pg, 10, |_| 0);
+    // A failing guard is reported by every edge it is evaluated on
+    let err = exe.errors(&pg).into_iter().next().unwrap();
+    (err.error, err.span.unwrap())
+}
+
+#[test]
+fn semantics_errors_point_at_the_expression() {
+    let memory = InterpreterMemory {
+        variables: BTreeMap::from([
+            (Variable("x".to_string()), 0),
+            (Variable("y".to_string()), 0),
+        ]),
+        arrays: BTreeMap::from([(Array("A".to_string()), vec![0, 0])]),
+        ..Default::default()
+    };
+
+    let src = "y := 1 + A[x + 4] * 2";
+    let (error, span) = first_error(src, memory.clone());
+    assert!(matches!(
+        error,
+        SemanticsError::IndexOutOfBound { index: 4, .. }
+    ));
+    assert_eq!(text(src, span), "A[x + 4]");
+
+    let src = "if 3 / x > 1 && true -> skip [] true -> skip fi";
+    let (error, span) = first_error(src, memory.clone());
+    assert_eq!(error, SemanticsError::DivisionByZero);
+    assert_eq!(text(src, span), "3 / x");
+
+    // A division is only evaluated if the left operand does not decide the
+    // result
+    let src = "if x = 1 && 1 / x = 1 -> skip [] x = 0 || 2 / x = 1 -> y := 2 / x fi";
+    let (error, span) = first_error(src, memory);
+    assert_eq!(error, SemanticsError::DivisionByZero);
+    assert_eq!(text(src, span), "2 / x");
+}
+
+#[test]
+fn type_errors_point_at_the_reference() {
+    let src = "x := 1;\nif x && true -> skip fi";
+    let errors = parse(src).check(None).unwrap_err().errors;
+    let [err] = errors.as_slice() else {
+        panic!("expected a single type error, found {errors:?}")
+    };
+    assert_eq!(text(src, err.span()), "x");
+    assert_eq!(err.span().line_col(src), (2, 4));
+}
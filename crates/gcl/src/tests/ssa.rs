@@ -0,0 +1,82 @@
+use std::collections::BTreeSet;
+
+use itertools::Itertools;
+
+use super::dominators::{diamond, nested_loops, node};
+use crate::{
+    ast::{Target, Variable},
+    pg::{Action, Node, ProgramGraph, ssa::Ssa},
+};
+
+fn phis(ssa: &Ssa, n: &str) -> Vec<String> {
+    ssa.phis(node(n)).iter().map(|p| p.to_string()).collect()
+}
+
+/// Every version is defined once, either by an edge or by a phi.
+fn assert_unique_definitions(pg: &ProgramGraph, ssa: &Ssa) {
+    let defs = ssa
+        .edges()
+        .iter()
+        .filter_map(|e| match e.action() {
+            Action::Assignment(Target::Variable(x), _)
+            | Action::BoolAssignment(Target::Variable(x), _) => Some(x.clone()),
+            _ => None,
+        })
+        .chain(
+            pg.nodes()
+                .iter()
+                .flat_map(|&n| ssa.phis(n).iter().map(|p| p.variable.clone())),
+        )
+        .collect_vec();
+    let unique: BTreeSet<&Variable> = defs.iter().collect();
+    assert_eq!(unique.len(), defs.len(), "{defs:?}");
+}
+
+#[test]
+fn phis_at_join_points() {
+    let pg = diamond();
+    let ssa = pg.ssa();
+    // Only `y` is assigned differently on the two paths
+    assert_eq!(phis(&ssa, "q3"), ["y₃ := φ(y₁, y₂)"]);
+    for n in ["qStart", "q1", "q2", "qFinal"] {
+        assert!(phis(&ssa, n).is_empty(), "{n}");
+    }
+    // The phi is used after the join
+    let last = ssa.edges().last().unwrap();
+    assert_eq!(last.action().to_string(), "z₁ := y₃");
+    assert_unique_definitions(&pg, &ssa);
+}
+
+#[test]
+fn phis_at_loop_heads() {
+    let pg = nested_loops();
+    let ssa = pg.ssa();
+    assert_eq!(phis(&ssa, "q1"), ["i₂ := φ(i₁, i₃)", "j₁ := φ(j₀, j₃)"]);
+    assert_eq!(phis(&ssa, "q3"), ["j₃ := φ(j₂, j₄)"]);
+    for n in ["q2", "q4", "q5", "qFinal"] {
+        assert!(phis(&ssa, n).is_empty(), "{n}");
+    }
+
+    // Each source of a phi is the version on the edge it is entered by
+    let [inner] = ssa.phis(node("q3")) else {
+        panic!()
+    };
+    let sources = inner
+        .sources
+        .iter()
+        .map(|s| (s.from, s.value.to_string()))
+        .collect_vec();
+    assert_eq!(
+        sources,
+        [
+            (Some(node("q2")), "j₂".to_string()),
+            (Some(node("q4")), "j₄".to_string())
+        ]
+    );
+    // The initial value of `j` enters the outer loop from the start
+    let [_, outer_j] = ssa.phis(node("q1")) else {
+        panic!()
+    };
+    assert_eq!(outer_j.sources[0].from, Some(Node::Start));
+    assert_unique_definitions(&pg, &ssa);
+}
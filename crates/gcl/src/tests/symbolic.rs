@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    ast::Commands,
+    interpreter::TerminationState,
+    pg::{Determinism, ProgramGraph},
+    symbolic::{Path, SymbolicError, SymbolicExecutor},
+};
+
+fn pg(src: &str) -> ProgramGraph {
+    let cmds: Commands = src.parse().unwrap();
+    ProgramGraph::new(Determinism::Deterministic, &cmds)
+}
+
+/// The final value of each variable on `path`, as written.
+fn variables(path: &Path) -> BTreeMap<String, String> {
+    path.memory
+        .variables
+        .iter()
+        .map(|(x, v)| (x.to_string(), v.to_string()))
+        .collect()
+}
+
+fn input(path: &Path, x: &str) -> i32 {
+    let input = path.input.as_ref().expect("paths to be feasible");
+    input
+        .variables
+        .iter()
+        .find(|(y, _)| y.to_string() == x)
+        .map(|(_, v)| *v)
+        .unwrap()
+}
+
+#[test]
+fn branches_split_the_path_condition() {
+    if !super::z3_available() {
+        return;
+    }
+    let paths = SymbolicExecutor::default()
+        .paths(&pg("if x > 0 -> y := x [] x <= 0 -> y := 0 - x fi"))
+        .unwrap();
+    let terminated = paths
+        .iter()
+        .filter(|p| p.state == TerminationState::Terminated)
+        .collect::<Vec<_>>();
+    assert_eq!(terminated.len(), 2);
+
+    let [positive, negative] = terminated[..] else {
+        unreachable!()
+    };
+    assert_eq!(variables(positive)["y"], "x");
+    assert!(positive.condition.to_string().contains("(x > 0)"));
+    assert!(input(positive, "x") > 0);
+
+    assert_eq!(variables(negative)["y"], "(0 - x)");
+    assert!(negative.condition.to_string().contains("(x <= 0)"));
+    assert!(input(negative, "x") <= 0);
+
+    // Negating the smallest integer overflows
+    let stuck = paths
+        .iter()
+        .find(|p| p.state == TerminationState::Stuck)
+        .unwrap();
+    assert_eq!(input(stuck, "x"), i32::MIN);
+}
+
+#[test]
+fn loops_are_cut_off_after_the_unrollings() {
+    if !super::z3_available() {
+        return;
+    }
+    let exe = SymbolicExecutor {
+        unrollings: 2,
+        ..SymbolicExecutor::default()
+    };
+    let paths = exe.paths(&pg("do x > 0 -> x := x - 1 od")).unwrap();
+
+    let mut terminated = paths
+        .iter()
+        .filter(|p| p.state == TerminationState::Terminated)
+        .map(|p| input(p, "x"))
+        .collect::<Vec<_>>();
+    terminated.sort();
+    assert_eq!(terminated, [0, 1, 2]);
+
+    let running = paths
+        .iter()
+        .filter(|p| p.state == TerminationState::Running)
+        .collect::<Vec<_>>();
+    assert_eq!(running.len(), 1);
+    assert_eq!(variables(running[0])["x"], "(((x - 1) - 1) - 1)");
+    assert!(input(running[0], "x") >= 3);
+}
+
+#[test]
+fn too_many_paths_is_an_error() {
+    if !super::z3_available() {
+        return;
+    }
+    let exe = SymbolicExecutor {
+        max_paths: 4,
+        ..SymbolicExecutor::default()
+    };
+    let src = "if x > 0 -> a := 1 [] x <= 0 -> a := 2 fi ;
+               if y > 0 -> b := 1 [] y <= 0 -> b := 2 fi ;
+               if z > 0 -> c := 1 [] z <= 0 -> c := 2 fi";
+    assert!(matches!(
+        exe.paths(&pg(src)),
+        Err(SymbolicError::TooManyPaths(4))
+    ));
+}
+
+#[test]
+fn exponents_must_be_constant() {
+    if !super::z3_available() {
+        return;
+    }
+    assert!(matches!(
+        SymbolicExecutor::default().paths(&pg("y := 2 ^ x")),
+        Err(SymbolicError::UnsupportedExponent(_))
+    ));
+    assert!(SymbolicExecutor::default().paths(&pg("y := x ^ 2")).is_ok());
+}
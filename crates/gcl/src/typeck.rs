@@ -0,0 +1,432 @@
+//! Type and scope checking of programs.
+//!
+//! Targets do not have declared types. Instead, the type of a target is
+//! determined by the positions it is used in: a target used in an arithmetic
+//! expression or assigned an arithmetic expression is an integer, while one
+//! used as a condition or assigned a boolean expression is a boolean. A copy
+//! `x := y` requires `x` and `y` to have the same type, and targets which are
+//! only ever copied, or arrays only ever measured by `len`, default to
+//! integers.
+//!
+//! A name may refer to either a variable or an array, but not both. When the
+//! inputs of a program are declared up front, every target of the program
+//! must be among them with the kind it is used as.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use itertools::Itertools;
+use thiserror::Error;
+
+use crate::{
+    ast::{
+        AExpr, AExprKind, BExpr, BExprKind, Command, CommandKind, Commands, Function, Guard,
+        Target, TargetDef, TargetKind, Type, Variable,
+    },
+    parse::SourceSpan,
+    procedures::ProcedureError,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum CheckError {
+    #[error("'{target}' has type {expected}, but is used as {found}")]
+    Mismatch {
+        target: Target,
+        expected: Type,
+        found: Type,
+        span: SourceSpan,
+    },
+    #[error("'{name}' is used both as a variable and as an array")]
+    VariableAndArray { name: String, span: SourceSpan },
+    #[error("'{target}' is used as {kind}, but is not in the given input")]
+    Undeclared {
+        target: Target,
+        kind: TargetKind,
+        span: SourceSpan,
+    },
+    #[error("'{target}' is used as {used}, but is given as {declared} in the input")]
+    DeclaredAs {
+        target: Target,
+        declared: TargetKind,
+        used: TargetKind,
+        span: SourceSpan,
+    },
+    #[error(transparent)]
+    Procedure(#[from] ProcedureError),
+}
+
+impl CheckError {
+    pub fn span(&self) -> SourceSpan {
+        match self {
+            CheckError::Mismatch { span, .. }
+            | CheckError::VariableAndArray { span, .. }
+            | CheckError::Undeclared { span, .. }
+            | CheckError::DeclaredAs { span, .. } => *span,
+            CheckError::Procedure(err) => err.span(),
+        }
+    }
+}
+
+/// All of the errors found while checking a program.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{}", errors.iter().format("\n"))]
+pub struct CheckErrors {
+    pub errors: Vec<CheckError>,
+}
+
+/// The inferred types of the targets of a program.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Types(BTreeMap<Target, Type>);
+
+impl Types {
+    /// The type of `target`, defaulting to [`Type::Int`] for targets not in
+    /// the program.
+    pub fn get(&self, target: &Target) -> Type {
+        self.0.get(target).copied().unwrap_or(Type::Int)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&Target, Type)> {
+        self.0.iter().map(|(t, ty)| (t, *ty))
+    }
+    /// The definitions of the targets, leaving out the parameters of
+    /// procedures.
+    pub fn defs(&self) -> BTreeSet<TargetDef> {
+        self.iter()
+            .filter(|(t, _)| !t.is_local())
+            .map(|(t, ty)| t.typed_def(ty))
+            .collect()
+    }
+}
+
+impl Commands {
+    /// Infers the types of all targets of the program, along with any
+    /// conflicting uses of targets. Procedures are inlined prior to inference.
+    pub fn infer_types(&self) -> (Types, Vec<CheckError>) {
+        Inference::infer(&self.inline_procedures(), None)
+    }
+
+    /// Checks that the [procedures](Commands::check_procedures) of the program
+    /// are well-formed, and that every target is used consistently with a
+    /// single type and kind, returning the variables and arrays of the program along with
+    /// their types.
+    ///
+    /// If `inputs` are given, every target must furthermore be declared
+    /// among them with the same kind. The declared types are also used for
+    /// targets which are only ever copied.
+    pub fn check(
+        &self,
+        inputs: Option<&BTreeSet<TargetDef>>,
+    ) -> Result<BTreeSet<TargetDef>, CheckErrors> {
+        let mut errors = self
+            .check_procedures()
+            .into_iter()
+            .map(CheckError::from)
+            .collect::<Vec<_>>();
+        let (types, type_errors) = Inference::infer(&self.inline_procedures(), inputs);
+        errors.extend(type_errors);
+        if errors.is_empty() {
+            Ok(types.defs())
+        } else {
+            Err(CheckErrors { errors })
+        }
+    }
+
+    /// The variables and arrays of the program along with their types.
+    pub fn target_defs(&self) -> BTreeSet<TargetDef> {
+        self.infer_types().0.defs()
+    }
+
+    /// Inlines procedures and turns copies between boolean targets into
+    /// boolean assignments, such that every command of the result is typed
+    /// by its constructor.
+    pub fn resolve_types(&self) -> Commands {
+        let cmds = self.inline_procedures();
+        let (types, _) = Inference::infer(&cmds, None);
+        cmds.resolve(&types)
+    }
+
+    fn resolve(&self, types: &Types) -> Commands {
+        Commands(
+            self.0
+                .iter()
+                .map(|cmd| Command::new(cmd.kind.resolve(types), cmd.span))
+                .collect(),
+        )
+    }
+}
+
+impl CommandKind {
+    fn resolve(&self, types: &Types) -> CommandKind {
+        let guards = |guards: &[Guard]| {
+            guards
+                .iter()
+                .map(|g| Guard {
+                    guard_span: g.guard_span,
+                    guard: g.guard.clone(),
+                    cmds: g.cmds.resolve(types),
+                })
+                .collect()
+        };
+        match self {
+            CommandKind::Assignment(
+                t,
+                AExpr {
+                    kind: AExprKind::Reference(src),
+                    span,
+                },
+            ) if types.get(&t.clone().unit()) == Type::Bool => CommandKind::BoolAssignment(
+                t.clone(),
+                BExpr::new(BExprKind::Reference(src.clone()), *span),
+            ),
+            CommandKind::If(gs) => CommandKind::If(guards(gs)),
+            CommandKind::Loop(gs) => CommandKind::Loop(guards(gs)),
+            CommandKind::Par(components) => {
+                CommandKind::Par(components.iter().map(|c| c.resolve(types)).collect())
+            }
+            kind => kind.clone(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Inference {
+    types: BTreeMap<Target, Type>,
+    /// The span of the first use of each target
+    uses: BTreeMap<Target, SourceSpan>,
+    copies: Vec<(Target, Target, SourceSpan)>,
+    errors: Vec<CheckError>,
+}
+
+impl Inference {
+    fn infer(cmds: &Commands, inputs: Option<&BTreeSet<TargetDef>>) -> (Types, Vec<CheckError>) {
+        let mut inference = Inference::default();
+        inference.commands(cmds);
+        inference.finish(inputs)
+    }
+
+    fn use_target(&mut self, target: &Target, span: SourceSpan) {
+        self.uses.entry(target.clone()).or_insert(span);
+    }
+
+    fn constrain(&mut self, target: &Target<Box<AExpr>>, ty: Type, span: SourceSpan) {
+        if let Target::Array(_, idx) = target {
+            self.aexpr(idx, span);
+        }
+        self.constrain_unit(target.clone().unit(), ty, span);
+    }
+
+    fn constrain_unit(&mut self, target: Target, ty: Type, span: SourceSpan) {
+        self.use_target(&target, span);
+        match self.types.get(&target) {
+            Some(&expected) if expected != ty => self.errors.push(CheckError::Mismatch {
+                target,
+                expected,
+                found: ty,
+                span,
+            }),
+            Some(_) => {}
+            None => {
+                self.types.insert(target, ty);
+            }
+        }
+    }
+
+    fn commands(&mut self, cmds: &Commands) {
+        for cmd in &cmds.0 {
+            self.command(cmd);
+        }
+    }
+
+    fn command(&mut self, cmd: &Command) {
+        let span = cmd.span;
+        match &cmd.kind {
+            CommandKind::Assignment(
+                t,
+                AExpr {
+                    kind: AExprKind::Reference(src),
+                    ..
+                },
+            ) => {
+                for target in [t, src] {
+                    if let Target::Array(_, idx) = target {
+                        self.aexpr(idx, span);
+                    }
+                }
+                let (t, src) = (t.clone().unit(), src.clone().unit());
+                self.use_target(&t, span);
+                self.use_target(&src, span);
+                self.copies.push((t, src, span));
+            }
+            CommandKind::Assignment(t, a) => {
+                self.constrain(t, Type::Int, span);
+                self.aexpr(a, span);
+            }
+            CommandKind::BoolAssignment(t, b) => {
+                self.constrain(t, Type::Bool, span);
+                self.bexpr(b, span);
+            }
+            CommandKind::ArrayAssignment(arr, elems) => {
+                self.constrain_unit(Target::Array(arr.clone(), ()), Type::Int, span);
+                for a in elems {
+                    self.aexpr(a, span);
+                }
+            }
+            CommandKind::Skip | CommandKind::Abort => {}
+            CommandKind::Assert(b) => self.bexpr(b, span),
+            CommandKind::If(guards) | CommandKind::Loop(guards) => {
+                for g in guards {
+                    self.bexpr(&g.guard, g.guard_span);
+                    self.commands(&g.cmds);
+                }
+            }
+            CommandKind::Par(components) => {
+                for cmds in components {
+                    self.commands(cmds);
+                }
+            }
+            CommandKind::Proc(_) | CommandKind::Call(_, _) => {
+                unreachable!("procedures are inlined before type inference")
+            }
+        }
+    }
+
+    /// Constrains the targets of `a`, locating conflicts by the span of the
+    /// expression they are found in, or by `span` if it has none.
+    fn aexpr(&mut self, a: &AExpr, span: SourceSpan) {
+        let span = located(a.span, span);
+        match &a.kind {
+            AExprKind::Number(_) => {}
+            AExprKind::Reference(t) => self.constrain(t, Type::Int, span),
+            AExprKind::Binary(l, _, r) => {
+                self.aexpr(l, span);
+                self.aexpr(r, span);
+            }
+            AExprKind::Minus(x) => self.aexpr(x, span),
+            // The length of an array does not depend on the type of its
+            // elements
+            AExprKind::Function(Function::Length(arr)) => {
+                self.use_target(&Target::Array(arr.clone(), ()), span)
+            }
+            AExprKind::Function(f @ Function::Count(arr, _)) => {
+                self.constrain_unit(Target::Array(arr.clone(), ()), Type::Int, span);
+                for a in f.args() {
+                    self.aexpr(a, span);
+                }
+            }
+            AExprKind::Function(f) => {
+                for a in f.args() {
+                    self.aexpr(a, span);
+                }
+            }
+        }
+    }
+
+    fn bexpr(&mut self, b: &BExpr, span: SourceSpan) {
+        let span = located(b.span, span);
+        match &b.kind {
+            BExprKind::Bool(_) => {}
+            BExprKind::Reference(t) => self.constrain(t, Type::Bool, span),
+            BExprKind::Rel(l, _, r) => {
+                self.aexpr(l, span);
+                self.aexpr(r, span);
+            }
+            BExprKind::Logic(l, _, r) => {
+                self.bexpr(l, span);
+                self.bexpr(r, span);
+            }
+            BExprKind::Not(x) => self.bexpr(x, span),
+        }
+    }
+
+    /// Propagates types along copies until a fixed point is reached, and
+    /// reports copies between targets of different types along with targets
+    /// not agreeing with the declared inputs.
+    fn finish(mut self, inputs: Option<&BTreeSet<TargetDef>>) -> (Types, Vec<CheckError>) {
+        self.propagate();
+        if let Some(inputs) = inputs {
+            for def in inputs {
+                if self.uses.contains_key(&def.name) && !self.types.contains_key(&def.name) {
+                    self.types.insert(def.name.clone(), def.kind.ty());
+                }
+            }
+            self.propagate();
+        }
+
+        for (a, b, span) in &self.copies {
+            for t in [a, b] {
+                self.types.entry(t.clone()).or_insert(Type::Int);
+            }
+            if self.types[a] != self.types[b] {
+                self.errors.push(CheckError::Mismatch {
+                    target: b.clone(),
+                    expected: self.types[b],
+                    found: self.types[a],
+                    span: *span,
+                });
+            }
+        }
+        // Arrays whose only use is their length default to integers as well
+        for target in self.uses.keys() {
+            self.types.entry(target.clone()).or_insert(Type::Int);
+        }
+
+        for (target, span) in &self.uses {
+            if let Target::Array(arr, ()) = target
+                && let Some(var_span) = self.uses.get(&Target::Variable(Variable(arr.0.clone())))
+            {
+                self.errors.push(CheckError::VariableAndArray {
+                    name: arr.to_string(),
+                    span: *span.max(var_span),
+                });
+            }
+        }
+
+        if let Some(inputs) = inputs {
+            for (target, span) in self.uses.iter().filter(|(t, _)| !t.is_local()) {
+                let used = target.typed_def(self.types[target]).kind;
+                match inputs.iter().find(|def| def.name.name() == target.name()) {
+                    None => self.errors.push(CheckError::Undeclared {
+                        target: target.clone(),
+                        kind: used,
+                        span: *span,
+                    }),
+                    Some(def) if def.kind != used => self.errors.push(CheckError::DeclaredAs {
+                        target: target.clone(),
+                        declared: def.kind.clone(),
+                        used,
+                        span: *span,
+                    }),
+                    Some(_) => {}
+                }
+            }
+        }
+
+        (Types(self.types), self.errors)
+    }
+
+    fn propagate(&mut self) {
+        loop {
+            let mut changed = false;
+            for (a, b, _) in &self.copies {
+                match (self.types.get(a), self.types.get(b)) {
+                    (Some(&ty), None) => {
+                        self.types.insert(b.clone(), ty);
+                        changed = true;
+                    }
+                    (None, Some(&ty)) => {
+                        self.types.insert(a.clone(), ty);
+                        changed = true;
+                    }
+                    _ => {}
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+}
+
+/// The span of an expression, falling back to `span` for expressions which
+/// were not parsed.
+fn located(expr: SourceSpan, span: SourceSpan) -> SourceSpan {
+    if expr.is_empty() { span } else { expr }
+}
@@ -2,6 +2,7 @@
 
 use std::{collections::BTreeMap, fs, path::Path, sync::Arc};
 
+use ce_core::Coverage;
 use ce_shell::{Analysis, Input};
 use color_eyre::{Result, eyre::Context};
 use indexmap::IndexMap;
@@ -40,6 +41,10 @@ pub struct ProgramConfig {
     pub input: Option<String>,
     #[serde(default)]
     pub shown: bool,
+    /// Generate the input from the seed such that it covers behaviour which
+    /// the programs before it in the env do not
+    #[serde(default)]
+    pub guided: bool,
 }
 
 #[derive(tapi::Tapi, Debug, Default, Clone, Serialize, Deserialize)]
@@ -62,6 +67,39 @@ pub struct CanonicalProgramConfig {
     pub shown: bool,
 }
 impl ProgramsConfig {
+    /// Fixes the inputs of guided programs, which depend on the coverage of
+    /// the programs before them, such that they are only generated once.
+    fn generate_guided(&mut self) {
+        for (&analysis, env) in &mut self.envs {
+            let mut seen = Coverage::default();
+            for p in &mut env.programs {
+                match p {
+                    ProgramConfig {
+                        seed: Some(seed),
+                        input: None,
+                        guided: true,
+                        ..
+                    } => {
+                        let input = analysis.gen_input_guided_seeded(&mut seen, Some(*seed));
+                        p.input = Some(input.to_string());
+                    }
+                    ProgramConfig {
+                        seed: None,
+                        input: None,
+                        ..
+                    } => {}
+                    _ => {
+                        if let Ok(c) = p.canonicalize(analysis)
+                            && let Ok(input) = analysis.input_from_str(&c.input)
+                        {
+                            seen.extend(input.coverage());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
     pub fn canonicalize(&self) -> Result<CanonicalProgramsConfig> {
         let envs = self
             .envs
@@ -174,8 +212,9 @@ pub fn read_programs(programs: impl AsRef<Path>) -> Result<ProgramsConfig> {
     let p = programs.as_ref();
     let src =
         fs::read_to_string(p).wrap_err_with(|| format!("could not read programs at {p:?}"))?;
-    let parsed =
+    let mut parsed: ProgramsConfig =
         toml::from_str(&src).wrap_err_with(|| format!("error parsing programs from file {p:?}"))?;
+    parsed.generate_guided();
     Ok(parsed)
 }
 pub fn read_groups(groups: impl AsRef<Path>) -> Result<GroupsConfig> {
@@ -1,9 +1,11 @@
 use std::{sync::Arc, time::Duration};
 
 use axum::{Json, extract::State};
-use ce_core::ValidationResult;
+use ce_core::{ValidationResult, shrink::Minimizer};
 use ce_shell::{Analysis, Hash, Input};
 use driver::{HubEvent, JobId, JobState};
+use indexmap::IndexMap;
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
 use crate::checko::{self, config::GroupName, scoreboard::PublicState};
@@ -19,6 +21,7 @@ pub struct AppState {
     pub driver: Option<driver::Driver<InspectifyJobMeta>>,
     pub checko: Option<Arc<checko::Checko>>,
     pub public_state: Arc<std::sync::RwLock<Option<PublicState>>>,
+    pub debug_sessions: Arc<std::sync::Mutex<DebugSessions>>,
 }
 
 pub fn endpoints() -> tapi::endpoints::Endpoints<'static, AppState> {
@@ -31,6 +34,10 @@ pub fn endpoints() -> tapi::endpoints::Endpoints<'static, AppState> {
         &jobs_cancel::endpoint as E,
         &exec_analysis::endpoint as E,
         &exec_reference::endpoint as E,
+        &minimize::endpoint as E,
+        &debug_start::endpoint as E,
+        &debug_command::endpoint as E,
+        &debug_stop::endpoint as E,
     ])
 }
 
@@ -383,7 +390,11 @@ struct ReferenceExecution {
 #[tapi::tapi(path = "/reference", method = Post)]
 async fn exec_reference(Json(input): Json<ce_shell::Input>) -> Json<ReferenceExecution> {
     let output = input.reference_output();
-    let error = output.as_ref().err().map(|e| e.to_string());
+    // NOTE: include the sources of the error, such that all parse
+    // diagnostics reach the user, and not just "failed to parse commands"
+    let error = output.as_ref().err().map(|e| {
+        std::iter::successors(Some(e as &dyn std::error::Error), |e| e.source()).join("\n")
+    });
     let output = output.ok();
     let annotation = output
         .as_ref()
@@ -397,6 +408,64 @@ async fn exec_reference(Json(input): Json<ce_shell::Input>) -> Json<ReferenceExe
     })
 }
 
+/// The number of smaller inputs tried when minimising an input, each of which
+/// is a run of the analysis being tested.
+const MINIMIZE_RUNS: usize = 100;
+
+#[derive(tapi::Tapi, Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct Minimization {
+    input: ce_shell::Input,
+    validation: ValidationResult,
+    runs: usize,
+}
+
+/// Shrinks an input on which the analysis being tested produces a mismatch for
+/// as long as it still does, like [`ce_core::shrink::minimize`], but running
+/// the analysis for every candidate.
+#[tapi::tapi(path = "/minimize", method = Post)]
+async fn minimize(
+    State(state): State<AppState>,
+    Json(input): Json<ce_shell::Input>,
+) -> Json<Option<Minimization>> {
+    let Some(driver) = state.driver.as_ref() else {
+        tracing::warn!("driver is not available");
+        return Json(None);
+    };
+    let Some(mut validation) = mismatch(driver, &input).await else {
+        return Json(None);
+    };
+    let mut minimizer = Minimizer::new(input, MINIMIZE_RUNS - 1);
+    while let Some(candidate) = minimizer.candidate() {
+        if let Some(v) = mismatch(driver, &candidate).await {
+            validation = v;
+            minimizer.accept(candidate);
+        }
+    }
+    let runs = 1 + minimizer.tries();
+    let input = minimizer.into_value();
+    Json(Some(Minimization {
+        input,
+        validation,
+        runs,
+    }))
+}
+
+/// The mismatch in the output of the analysis being tested on `input`, if any.
+async fn mismatch(
+    driver: &driver::Driver<InspectifyJobMeta>,
+    input: &Input,
+) -> Option<ValidationResult> {
+    let job = driver.exec_job(input, InspectifyJobMeta::default());
+    if job.wait().await != JobState::Succeeded {
+        return None;
+    }
+    let output = input.analysis().output_from_str(&job.stdout()).ok()?;
+    match input.validate_output(&output) {
+        Ok((validation @ ValidationResult::Mismatch { .. }, _)) => Some(validation),
+        _ => None,
+    }
+}
+
 #[tapi::tapi(path = "/jobs/cancel", method = Post)]
 async fn jobs_cancel(State(state): State<AppState>, Json(id): Json<JobId>) {
     if let Some(job) = state.hub.get_job(id) {
@@ -469,3 +538,289 @@ async fn checko_csv(State(state): State<AppState>) -> String {
         String::new()
     }
 }
+
+/// The number of debug sessions kept, after which the oldest session is
+/// dropped when a new one is started
+const MAX_DEBUG_SESSIONS: usize = 64;
+
+/// The number of steps after which running a debug session gives up
+const DEBUG_STEP_LIMIT: usize = 10_000;
+
+/// A random id, such that clients can not guess the sessions of others.
+type DebugSessionId = String;
+
+#[derive(Debug, Default)]
+pub struct DebugSessions {
+    /// The sessions in the order they were started. Each is locked on its
+    /// own, so commands to different sessions do not wait for each other.
+    sessions: IndexMap<DebugSessionId, Arc<std::sync::Mutex<DebugSession>>>,
+}
+
+#[derive(Debug)]
+struct DebugSession {
+    session: gcl::debugger::Session,
+    /// The watches as written, as the session only keeps them parsed
+    watches: Vec<String>,
+}
+
+#[derive(tapi::Tapi, Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct DebugStart {
+    commands: String,
+    determinism: gcl::pg::Determinism,
+    assignment: gcl::interpreter::InterpreterMemory,
+}
+
+#[derive(tapi::Tapi, Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct DebugBreakpoint {
+    node: Option<String>,
+    condition: Option<String>,
+}
+
+#[derive(tapi::Tapi, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", content = "value")]
+enum DebugCommand {
+    Inspect,
+    Step { choice: usize },
+    StepBack,
+    Continue,
+    ReverseContinue,
+    RunTo { node: String },
+    Restart,
+    SetBreakpoints { breakpoints: Vec<DebugBreakpoint> },
+    SetWatches { watches: Vec<String> },
+}
+
+#[derive(tapi::Tapi, Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct DebugCommandParams {
+    session: DebugSessionId,
+    command: DebugCommand,
+}
+
+#[derive(tapi::Tapi, Debug, Clone, PartialEq, serde::Serialize)]
+struct DebugChoice {
+    action: String,
+    node: String,
+}
+
+#[derive(tapi::Tapi, Debug, Clone, PartialEq, serde::Serialize)]
+struct DebugWatch {
+    expression: String,
+    value: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(tapi::Tapi, Debug, Clone, PartialEq, serde::Serialize)]
+struct DebugState {
+    session: DebugSessionId,
+    node: String,
+    memory: gcl::interpreter::InterpreterMemory,
+    trace: Vec<gcl::interpreter::Step>,
+    termination: gcl::interpreter::TerminationState,
+    choices: Vec<DebugChoice>,
+    watches: Vec<DebugWatch>,
+    /// The index of the breakpoint hit at the current step, if any
+    breakpoint: Option<usize>,
+    /// Why the last command stopped running, if it ran
+    stop: Option<String>,
+    dot: String,
+}
+
+#[derive(tapi::Tapi, Debug, Clone, PartialEq, serde::Serialize)]
+struct DebugResponse {
+    state: Option<DebugState>,
+    error: Option<String>,
+}
+
+impl DebugResponse {
+    fn error(error: impl std::fmt::Display) -> Json<DebugResponse> {
+        Json(DebugResponse {
+            state: None,
+            error: Some(error.to_string()),
+        })
+    }
+}
+
+fn parse_node(node: &str) -> Result<gcl::pg::Node, String> {
+    node.trim()
+        .parse()
+        .map_err(|()| format!("'{node}' is not the name of a node"))
+}
+
+impl DebugSessions {
+    fn insert(
+        &mut self,
+        session: DebugSession,
+    ) -> (DebugSessionId, Arc<std::sync::Mutex<DebugSession>>) {
+        let id = format!("{:032x}", rand::random::<u128>());
+        let session = Arc::new(std::sync::Mutex::new(session));
+        self.sessions.insert(id.clone(), session.clone());
+        while self.sessions.len() > MAX_DEBUG_SESSIONS {
+            self.sessions.shift_remove_index(0);
+        }
+        (id, session)
+    }
+}
+
+impl DebugSession {
+    fn execute(&mut self, command: DebugCommand) -> Result<Option<gcl::debugger::Stop>, String> {
+        let session = &mut self.session;
+        let first = |_: &[(gcl::pg::Action, gcl::pg::Node)]| 0;
+        Ok(match command {
+            DebugCommand::Inspect => None,
+            DebugCommand::Step { choice } => {
+                if !session.step(choice) {
+                    return Err("no step can be taken".to_string());
+                }
+                None
+            }
+            DebugCommand::StepBack => {
+                if !session.step_back() {
+                    return Err("no step has been taken".to_string());
+                }
+                None
+            }
+            DebugCommand::Continue => Some(session.resume(DEBUG_STEP_LIMIT, first)),
+            DebugCommand::ReverseContinue => Some(session.reverse(DEBUG_STEP_LIMIT)),
+            DebugCommand::RunTo { node } => {
+                Some(session.run_to(parse_node(&node)?, DEBUG_STEP_LIMIT, first))
+            }
+            DebugCommand::Restart => {
+                session.restart();
+                None
+            }
+            DebugCommand::SetBreakpoints { breakpoints } => {
+                session.breakpoints = breakpoints
+                    .iter()
+                    .map(|b| {
+                        Ok(gcl::debugger::Breakpoint {
+                            node: b.node.as_deref().map(parse_node).transpose()?,
+                            condition: b
+                                .condition
+                                .as_deref()
+                                .map(|c| c.parse().map_err(|err| format!("{c}: {err}")))
+                                .transpose()?,
+                        })
+                    })
+                    .collect::<Result<_, String>>()?;
+                None
+            }
+            DebugCommand::SetWatches { watches } => {
+                session.watches = watches
+                    .iter()
+                    .map(|w| gcl::debugger::Watch::parse(w).map_err(|err| format!("{w}: {err}")))
+                    .collect::<Result<_, _>>()?;
+                self.watches = watches;
+                None
+            }
+        })
+    }
+
+    fn state(&self, id: &str, stop: Option<gcl::debugger::Stop>) -> DebugState {
+        let session = &self.session;
+        DebugState {
+            session: id.to_string(),
+            node: session.node().to_string(),
+            memory: session.memory().clone(),
+            trace: session
+                .execution()
+                .trace()
+                .iter()
+                .map(|(step, _)| step.clone())
+                .collect(),
+            termination: session.state(),
+            choices: session
+                .choices()
+                .into_iter()
+                .map(|(action, node)| DebugChoice {
+                    action: action.to_string(),
+                    node: node.to_string(),
+                })
+                .collect(),
+            watches: self
+                .watches
+                .iter()
+                .zip(session.watch_values())
+                .map(|(expression, value)| DebugWatch {
+                    expression: expression.clone(),
+                    value: value.as_ref().ok().map(|v| v.to_string()),
+                    error: value.err().map(|err| err.to_string()),
+                })
+                .collect(),
+            breakpoint: session.breakpoint(),
+            stop: stop.map(|stop| stop.to_string()),
+            dot: session.pg().dot(),
+        }
+    }
+}
+
+#[tapi::tapi(path = "/debug/start", method = Post)]
+async fn debug_start(
+    State(state): State<AppState>,
+    Json(params): Json<DebugStart>,
+) -> Json<DebugResponse> {
+    let commands = match gcl::parse::parse_commands(&params.commands) {
+        Ok(commands) => commands,
+        Err(err) => return DebugResponse::error(err),
+    };
+    if let Err(err) = commands.check(Some(&params.assignment.target_defs())) {
+        return DebugResponse::error(err);
+    }
+    let pg = gcl::pg::ProgramGraph::new(params.determinism, &commands);
+
+    let session = DebugSession {
+        session: gcl::debugger::Session::new(pg, params.assignment),
+        watches: vec![],
+    };
+    let (id, session) = state.debug_sessions.lock().unwrap().insert(session);
+    let debug_state = session.lock().unwrap().state(&id, None);
+    Json(DebugResponse {
+        state: Some(debug_state),
+        error: None,
+    })
+}
+
+#[tapi::tapi(path = "/debug/command", method = Post)]
+async fn debug_command(
+    State(state): State<AppState>,
+    Json(params): Json<DebugCommandParams>,
+) -> Json<DebugResponse> {
+    let session = state
+        .debug_sessions
+        .lock()
+        .unwrap()
+        .sessions
+        .get(&params.session)
+        .cloned();
+    let Some(session) = session else {
+        return DebugResponse::error("the debug session has ended");
+    };
+    // A command may run up to `DEBUG_STEP_LIMIT` steps, so it runs off the
+    // async workers
+    let response = tokio::task::spawn_blocking(move || {
+        let mut session = session.lock().unwrap();
+        // Errors are reported along with the state, which they leave unchanged
+        let (stop, error) = match session.execute(params.command) {
+            Ok(stop) => (stop, None),
+            Err(err) => (None, Some(err)),
+        };
+        DebugResponse {
+            state: Some(session.state(&params.session, stop)),
+            error,
+        }
+    })
+    .await;
+    match response {
+        Ok(response) => Json(response),
+        Err(err) => DebugResponse::error(err),
+    }
+}
+
+#[tapi::tapi(path = "/debug/stop", method = Post)]
+async fn debug_stop(State(state): State<AppState>, Json(session): Json<DebugSessionId>) {
+    state
+        .debug_sessions
+        .lock()
+        .unwrap()
+        .sessions
+        .shift_remove(&session);
+}
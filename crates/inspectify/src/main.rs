@@ -122,6 +122,7 @@ async fn run() -> color_eyre::Result<()> {
             driver,
             checko,
             public_state,
+            debug_sessions: Default::default(),
         });
     let app = Router::new().nest("/api", api).fallback(static_dir);
 